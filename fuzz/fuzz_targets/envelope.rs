@@ -0,0 +1,38 @@
+// Frames around the messages
+//
+// What the HTTP server reads from a frame before deciding it is a message
+// for an actor: a keepalive, an auth frame, or a hello to negotiate, and the
+// ids it pulls out of a frame that failed for its error reply. Whatever the
+// text, anything the server answers must be a frame a client can decode.
+
+#![no_main]
+
+#[path = "../../ping-http-server/src/hello.rs"]
+mod hello;
+#[path = "../../ping-http-server/src/routes.rs"]
+#[allow(dead_code)]
+mod routes;
+
+use libfuzzer_sys::fuzz_target;
+use ping_common::{Auth, Error, ErrorReply, HelloAck, KeepAlive};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = serde_json::from_str::<KeepAlive>(text);
+    let _ = serde_json::from_str::<Auth>(text);
+    for codec in ["json", "msgpack"] {
+        match hello::answer(text, codec) {
+            Some(Ok(ack)) => {
+                serde_json::from_str::<HelloAck>(&ack).expect("acks decode");
+            }
+            Some(Err(error)) => {
+                serde_json::from_str::<ErrorReply>(&error).expect("refusals decode");
+            }
+            None => {}
+        }
+    }
+    let error = routes::error_frame(&Error::Codec("fuzzed".to_string()), text);
+    serde_json::from_str::<ErrorReply>(&error).expect("error frames decode");
+});
@@ -0,0 +1,46 @@
+// JSON frames
+//
+// Arbitrary bytes decoded as every message a JSON client or server reads.
+// Decoding may fail, but must not panic, and whatever decodes must encode,
+// and decode again to the same message.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ping_common::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(data: &[u8]) -> Option<T> {
+    let msg = serde_json::from_slice::<T>(data).ok()?;
+    let json = serde_json::to_vec(&msg).expect("a decoded message encodes");
+    assert_eq!(serde_json::from_slice::<T>(&json).expect("an encoded message decodes"), msg);
+    Some(msg)
+}
+
+// For the messages without PartialEq
+fn decodes<T: Serialize + DeserializeOwned>(data: &[u8]) {
+    if let Ok(msg) = serde_json::from_slice::<T>(data) {
+        serde_json::to_vec(&msg).expect("a decoded message encodes");
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(ping) = round_trip::<Ping>(data) {
+        let _ = ping.validate();
+    }
+    if let Some(pong) = round_trip::<Pong>(data) {
+        let _ = pong.validate();
+    }
+    round_trip::<ErrorReply>(data);
+    round_trip::<Hello>(data);
+    round_trip::<HelloAck>(data);
+    decodes::<Auth>(data);
+    decodes::<KeepAlive>(data);
+    decodes::<Broadcast>(data);
+    decodes::<Heartbeat>(data);
+    decodes::<SessionInfo>(data);
+    decodes::<Echo>(data);
+    decodes::<KvRequest>(data);
+    decodes::<CounterRequest>(data);
+});
@@ -0,0 +1,43 @@
+// MessagePack frames
+//
+// Arbitrary bytes decoded as every message that travels as MessagePack, over
+// the `ping.msgpack` WebSocket subprotocol or kameo's libp2p messages, and
+// through the HTTP server's transcoding to JSON. Decoding may fail, but must
+// not panic; a decoded message must encode and decode to the same message,
+// and JSON transcoded from a frame must transcode back.
+
+#![no_main]
+
+#[path = "../../ping-http-server/src/msgpack.rs"]
+#[allow(dead_code)]
+mod msgpack;
+
+use libfuzzer_sys::fuzz_target;
+use ping_common::codec::{msgpack::Msgpack, Codec};
+use ping_common::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(data: &[u8]) -> Option<T> {
+    let msg = Msgpack::decode::<T>(data).ok()?;
+    let frame = Msgpack::encode(&msg).expect("a decoded message encodes");
+    assert_eq!(Msgpack::decode::<T>(&frame).expect("an encoded message decodes"), msg);
+    Some(msg)
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(ping) = round_trip::<Ping>(data) {
+        let _ = ping.validate();
+    }
+    round_trip::<Pong>(data);
+    round_trip::<ErrorReply>(data);
+    round_trip::<Hello>(data);
+    round_trip::<HelloAck>(data);
+    // MessagePack nests deeper than serde_json reads (128 levels); such a
+    // frame transcodes to JSON the routes refuse as a bad request
+    if let Ok(json) = msgpack::decode(data) {
+        if serde_json::from_str::<serde_json::Value>(&json).is_ok() {
+            msgpack::encode(&json).expect("transcoded JSON transcodes back");
+        }
+    }
+});
@@ -0,0 +1,90 @@
+// WebSocket frames
+//
+// A frame through what ping-http-server's reader does with it: a binary
+// frame is transcoded from MessagePack or inflated, a keepalive or a hello
+// is answered, and anything else is routed to a real PingActor, failures
+// turning into error frames. The first byte picks the kind of frame (text,
+// MessagePack or deflate). Whatever the frame, the server must answer with
+// JSON a client can read, which a MessagePack client must get transcoded.
+
+#![no_main]
+
+#[path = "../../ping-http-server/src/compression.rs"]
+#[allow(dead_code)]
+mod compression;
+#[path = "../../ping-http-server/src/hello.rs"]
+mod hello;
+#[path = "../../ping-http-server/src/msgpack.rs"]
+#[allow(dead_code)]
+mod msgpack;
+#[path = "../../ping-http-server/src/routes.rs"]
+#[allow(dead_code)]
+mod routes;
+
+use compression::Compression;
+use kameo::prelude::*;
+use libfuzzer_sys::fuzz_target;
+use ping_common::{Error, KeepAlive, Ping, PingActor};
+use routes::{ActorRoute, JsonRoute};
+use serde_json::Value;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+struct Server {
+    runtime: Runtime,
+    route: JsonRoute<PingActor, Ping>,
+    compression: Compression,
+}
+
+fn server() -> &'static Server {
+    static SERVER: OnceLock<Server> = OnceLock::new();
+    SERVER.get_or_init(|| {
+        let runtime = Runtime::new().unwrap();
+        let actor = runtime.block_on(async { PingActor::spawn(PingActor { ping_count: 0 }) });
+        Server { runtime, route: JsonRoute::new(actor), compression: Compression::default() }
+    })
+}
+
+// The server's answer to a frame, as ws.rs gives it
+fn answer(server: &Server, kind: u8, payload: &[u8]) -> String {
+    let codec_error = |e: &dyn std::fmt::Display| routes::error_frame(&Error::Codec(e.to_string()), "");
+    let text = match kind % 3 {
+        0 => match std::str::from_utf8(payload) {
+            Ok(text) => text.to_string(),
+            Err(e) => return codec_error(&e),
+        },
+        1 => match msgpack::decode(payload) {
+            Ok(text) => text,
+            Err(e) => return codec_error(&e),
+        },
+        _ => match server.compression.decompress(payload).map(String::from_utf8) {
+            Ok(Ok(text)) => text,
+            Ok(Err(e)) => return codec_error(&e),
+            Err(e) => return codec_error(&e),
+        },
+    };
+    if serde_json::from_str::<KeepAlive>(&text).is_ok() {
+        return serde_json::to_string(&KeepAlive::KeepAlive).unwrap();
+    }
+    let codec = if kind % 3 == 1 { "msgpack" } else { "json" };
+    if let Some(answer) = hello::answer(&text, codec) {
+        return answer.unwrap_or_else(|error| error);
+    }
+    match server.runtime.block_on(server.route.handle(&text)) {
+        Ok(json) => json,
+        Err(e) => routes::error_frame(&e.into(), &text),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&kind, payload)) = data.split_first() else {
+        return;
+    };
+    let server = server();
+    let reply = answer(server, kind, payload);
+    let value: Value = serde_json::from_str(&reply).expect("replies are JSON");
+    assert!(value.is_object(), "replies are JSON objects");
+    if kind % 3 == 1 {
+        msgpack::encode(&reply).expect("replies transcode to MessagePack");
+    }
+});
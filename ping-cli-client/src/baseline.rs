@@ -0,0 +1,86 @@
+// Saved runs and baselines
+//
+// `--save` stores a run's summary as JSON; `--compare` loads such a file and
+// diffs the current run against it metric by metric. A latency percentile
+// that grew, or a throughput that shrank, by more than the threshold counts
+// as a regression. The max is shown but not judged, one slow reply is noise.
+
+use crate::output::Summary;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedRun {
+    /// "sequence" or "load"; comparing across modes is allowed but flagged
+    pub mode: String,
+    /// Unix timestamp in milliseconds when the run was saved
+    pub saved_at_ms: u64,
+    pub summary: Summary,
+}
+
+pub fn save(path: &Path, mode: &str, summary: &Summary) -> io::Result<()> {
+    let saved_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let run = SavedRun { mode: mode.to_string(), saved_at_ms, summary: summary.clone() };
+    fs::write(path, serde_json::to_vec_pretty(&run)?)
+}
+
+pub fn load(path: &Path) -> io::Result<SavedRun> {
+    let bytes = fs::read(path)?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+    })
+}
+
+/// Log the current run next to the baseline; returns whether anything
+/// regressed by more than `threshold` percent
+pub fn compare(baseline: &SavedRun, mode: &str, current: &Summary, threshold: f64) -> bool {
+    if baseline.mode != mode {
+        warn!("Baseline is a {} run, this is a {} run", baseline.mode, mode);
+    }
+    let base = &baseline.summary;
+    let latencies = [
+        ("p50", base.p50_us, current.p50_us),
+        ("p90", base.p90_us, current.p90_us),
+        ("p99", base.p99_us, current.p99_us),
+        ("mean", base.mean_us, current.mean_us),
+        ("max", base.max_us, current.max_us),
+    ];
+
+    let mut regressed = false;
+    for (metric, before, after) in latencies {
+        let (Some(before), Some(after)) = (before, after) else {
+            info!("{:>10}: no data to compare", metric);
+            continue;
+        };
+        let change = percent_change(before as f64, after as f64);
+        if metric != "max" && change > threshold {
+            regressed = true;
+            warn!("{:>10}: {}µs -> {}µs ({:+.1}%) REGRESSION", metric, before, after, change);
+        } else {
+            info!("{:>10}: {}µs -> {}µs ({:+.1}%)", metric, before, after, change);
+        }
+    }
+
+    let change = percent_change(base.throughput, current.throughput);
+    if -change > threshold {
+        regressed = true;
+        warn!(
+            "{:>10}: {:.1}/s -> {:.1}/s ({:+.1}%) REGRESSION",
+            "throughput", base.throughput, current.throughput, change
+        );
+    } else {
+        info!("{:>10}: {:.1}/s -> {:.1}/s ({:+.1}%)", "throughput", base.throughput, current.throughput, change);
+    }
+    regressed
+}
+
+fn percent_change(before: f64, after: f64) -> f64 {
+    (after - before) * 100.0 / before.max(f64::EPSILON)
+}
@@ -0,0 +1,29 @@
+// Exit codes
+//
+// Failures scripts and CI pipelines typically need to react to get a code of
+// their own, so they don't have to parse the logs. Any other error exits
+// with 1, as a failing `main` always does.
+
+use std::process::ExitCode;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Failure {
+    /// Neither --server nor --ws was given (clap's own code for bad usage)
+    Usage = 2,
+    /// The server could not be connected to
+    DialFailed = 3,
+    /// The PingActor was not found in the registry before the deadline
+    ActorNotFound = 4,
+    /// More pings failed than --max-errors allows
+    TooManyErrors = 5,
+    /// The run was slower than the --compare baseline
+    Regression = 6,
+    /// The server refused this client's protocol version
+    Incompatible = 7,
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        ExitCode::from(failure as u8)
+    }
+}
@@ -0,0 +1,146 @@
+// Load-test mode
+//
+// Drives `concurrency` independent ask pipelines against the actor for a
+// fixed duration. Each pipeline sends its next ping as soon as the
+// previous reply arrives, so the total rate is bounded only by the actor and
+// the transport. With `no_reply` the pings are told instead of asked: nothing
+// waits for the actor, so the run measures how fast pings can be sent, and
+// the recorded latency is only the time it took to hand each one off. With
+// several targets the pings are spread evenly over them.
+
+use crate::output::{Output, PingRecord};
+use crate::payload::Payload;
+use crate::retry::RetryPolicy;
+use crate::stats::Jitter;
+use ping_common::latency::LatencyStats;
+use crate::transport::Transport;
+use ping_common::Ping;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+/// Aggregate results of a load run
+#[derive(Default)]
+pub struct LoadReport {
+    pub completed: u64,
+    pub errors: u64,
+    /// Errors that were timeouts, i.e. pings lost rather than refused
+    pub timeouts: u64,
+    pub retries: u64,
+    pub elapsed: Duration,
+    pub latency: LatencyStats,
+    pub jitter: Jitter,
+    /// Latency of each target, when there are several
+    pub by_target: Vec<LatencyStats>,
+}
+
+impl LoadReport {
+    pub fn throughput(&self) -> f64 {
+        self.completed as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+pub async fn run(
+    transport: Transport,
+    concurrency: usize,
+    duration: Duration,
+    retry: RetryPolicy,
+    no_reply: bool,
+    payload: Payload,
+    output: Arc<Output>,
+) -> LoadReport {
+    info!("Load test: {} concurrent pipeline(s) for {:?}", concurrency, duration);
+    let targets = transport.targets();
+
+    // Connect every pipeline before the clock starts; the first one reuses
+    // the transport it was given
+    let mut transports = Vec::with_capacity(concurrency);
+    for worker in 1..concurrency {
+        match transport.fork().await {
+            Ok(transport) => transports.push((worker, transport)),
+            Err(e) => warn!("Pipeline #{} could not connect: {}", worker, e),
+        }
+    }
+
+    transports.insert(0, (0, transport));
+
+    let sequence = Arc::new(AtomicU64::new(0));
+    let start = Instant::now();
+    let deadline = start + duration;
+    let mut pipelines = JoinSet::new();
+
+    for (worker, transport) in transports {
+        let sequence = sequence.clone();
+        let output = output.clone();
+        let payload = payload.clone();
+        pipelines.spawn(async move {
+            let mut report = LoadReport {
+                by_target: (0..targets).map(|_| LatencyStats::default()).collect(),
+                ..LoadReport::default()
+            };
+            while Instant::now() < deadline {
+                let seq = sequence.fetch_add(1, Ordering::Relaxed) + 1;
+                let ping = Ping::builder()
+                    .message(payload.pad(format!("Load ping from pipeline #{}", worker)))
+                    .sequence(seq)
+                    .build();
+                let target = seq as usize % targets;
+                let sent_at = Instant::now();
+                let reply = if no_reply {
+                    transport.tell(target, &ping).await.map(|()| None)
+                } else {
+                    let (reply, retries) = retry.ask(&transport, target, &ping).await;
+                    report.retries += u64::from(retries);
+                    reply.map(Some)
+                };
+                let record = match reply {
+                    Ok(pong) => {
+                        let rtt = sent_at.elapsed();
+                        report.completed += 1;
+                        report.latency.record(rtt);
+                        report.jitter.record(rtt);
+                        report.by_target[target].record(rtt);
+                        PingRecord {
+                            sequence: seq,
+                            rtt_us: Some(rtt.as_micros() as u64),
+                            total_pings: pong.map(|pong| pong.total_pings),
+                            error: None,
+                        }
+                    }
+                    Err(e) => {
+                        debug!("Send failed: {}", e);
+                        report.errors += 1;
+                        report.timeouts += u64::from(e.is_timeout());
+                        PingRecord { sequence: seq, rtt_us: None, total_pings: None, error: Some(e.to_string()) }
+                    }
+                };
+                output.ping(&record);
+            }
+            report
+        });
+    }
+
+    let mut total = LoadReport {
+        by_target: (0..targets).map(|_| LatencyStats::default()).collect(),
+        ..LoadReport::default()
+    };
+    while let Some(result) = pipelines.join_next().await {
+        if let Ok(report) = result {
+            total.completed += report.completed;
+            total.errors += report.errors;
+            total.timeouts += report.timeouts;
+            total.retries += report.retries;
+            total.latency.merge(&report.latency);
+            total.jitter.merge(&report.jitter);
+            for (total, report) in total.by_target.iter_mut().zip(&report.by_target) {
+                total.merge(report);
+            }
+        }
+    }
+    total.elapsed = start.elapsed();
+    total
+}
@@ -0,0 +1,84 @@
+// Actor lookup
+//
+// With one server the actor is simply looked up by name. With several, each
+// server registers its own PingActor under the same name, so all the
+// registrations are collected and matched back to the servers by peer id.
+// Every actor found is then greeted with a `Hello`, so a server speaking
+// another protocol version is refused before the first ping.
+
+use futures::StreamExt;
+use kameo::error::RegistryError;
+use kameo::prelude::*;
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use ping_common::{Error, Hello, HelloReply, PingActor, PingActorClient};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Peer id in the `/p2p/...` part of a server address
+pub fn peer_id(server: &Multiaddr) -> Option<PeerId> {
+    server.iter().find_map(|protocol| match protocol {
+        Protocol::P2p(peer_id) => Some(peer_id),
+        _ => None,
+    })
+}
+
+/// The actor registered as `name` on each server, in the order given
+pub async fn find(
+    servers: &[Multiaddr],
+    name: &str,
+) -> Result<Vec<Option<RemoteActorRef<PingActor>>>, RegistryError> {
+    if let [_] = servers {
+        return Ok(vec![RemoteActorRef::<PingActor>::lookup(name).await?]);
+    }
+
+    let mut by_peer = HashMap::new();
+    let mut registrations = RemoteActorRef::<PingActor>::lookup_all(name);
+    while let Some(actor) = registrations.next().await {
+        let actor = actor?;
+        if let Some(peer_id) = actor.id().peer_id() {
+            by_peer.insert(*peer_id, actor);
+        }
+    }
+    Ok(servers
+        .iter()
+        .map(|server| peer_id(server).and_then(|peer_id| by_peer.remove(&peer_id)))
+        .collect())
+}
+
+/// Why `find_all` came back without every actor
+#[derive(Debug)]
+pub enum LookupError {
+    Registry(RegistryError),
+    /// Indices of the servers whose actor is not registered (yet)
+    NotFound(Vec<usize>),
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::Registry(e) => write!(f, "lookup failed: {}", e),
+            LookupError::NotFound(missing) => write!(f, "actor not found on {} server(s)", missing.len()),
+        }
+    }
+}
+
+/// The actor of every server, or an error unless all were found
+pub async fn find_all(servers: &[Multiaddr], name: &str) -> Result<Vec<RemoteActorRef<PingActor>>, LookupError> {
+    let found = find(servers, name).await.map_err(LookupError::Registry)?;
+    let missing: Vec<usize> = found.iter().enumerate().filter(|(_, actor)| actor.is_none()).map(|(i, _)| i).collect();
+    if !missing.is_empty() {
+        return Err(LookupError::NotFound(missing));
+    }
+    Ok(found.into_iter().flatten().collect())
+}
+
+/// Exchange the protocol handshake with `actor`; `Error::Version` if it
+/// refuses this client's version
+pub async fn greet(actor: &RemoteActorRef<PingActor>) -> Result<(), Error> {
+    let hello = Hello::new(&["msgpack"], concat!("ping-cli-client/", env!("CARGO_PKG_VERSION")));
+    match PingActorClient::new(actor).hello(hello).await {
+        Ok(HelloReply(Ok(_))) => Ok(()),
+        Ok(HelloReply(Err(reply))) => Err(Error::from(reply)),
+        Err(e) => Err(Error::Actor(e.to_string())),
+    }
+}
@@ -1,140 +1,488 @@
-use kameo::prelude::*;
-use kameo::remote;
-use libp2p::{
-    noise, tcp, yamux,
-    swarm::{NetworkBehaviour, SwarmEvent},
-    Multiaddr,
-};
-use ping_common::{PingActor, Ping};
-use std::time::{Duration, Instant};
-use tracing::{info, warn, error};
-use tracing_subscriber::EnvFilter;
+mod baseline;
+mod exit;
+mod load;
+mod lookup;
+mod output;
+mod payload;
+mod progress;
+mod reconnect;
+mod repl;
+mod retry;
+mod script;
+mod sequence;
+mod stats;
+mod stream;
+mod swarm;
+mod transport;
+
+use exit::Failure;
+use libp2p::Multiaddr;
+use output::{Output, OutputFormat, Summary};
+use payload::{Fill, Payload};
+use progress::Progress;
+use reconnect::Reconnect;
+use lookup::LookupError;
+use retry::RetryPolicy;
+use sequence::Step;
+use stats::Jitter;
+use ping_common::identity;
+use ping_common::latency::LatencyStats;
+use ping_common::transport::TcpTarget;
+use ping_common::tuning::{Tuning, MIN_YAMUX_WINDOW};
+use ping_common::validate::MAX_MESSAGE_BYTES;
+use ping_common::Error;
+use stream::StreamClient;
+use transport::Transport;
+use std::{path::PathBuf, process::ExitCode, sync::Arc, time::{Duration, Instant}};
+use tracing::{info, info_span, warn, error, Instrument};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 use clap::Parser;
-use futures::StreamExt;
+
+/// How long the server connection may take before the dial counts as failed
+const DIAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delays between registry lookups until `--lookup-timeout`
+const LOOKUP_RETRY: ping_common::retry::RetryPolicy =
+    ping_common::retry::RetryPolicy::new(Duration::from_millis(100), Duration::from_secs(2));
+
+/// Least time left for the lookup after waiting for the DHT
+const LOOKUP_GRACE: Duration = Duration::from_secs(2);
 
 // Command-line argument parser
 #[derive(Parser, Debug)]
 #[command(name = "ping-cli-client")]
 struct Args {
+    /// libp2p address of a ping-cli-server; repeat it to spread the pings
+    /// over several servers
     #[arg(short, long)]
-    server: Option<String>,
-}
+    server: Vec<String>,
+
+    /// Ping through ping-http-server's WebSocket bridge instead of libp2p
+    /// (e.g. ws://127.0.0.1:8080/ws)
+    #[arg(long, conflicts_with_all = ["server", "interactive"])]
+    ws: Option<String>,
+
+    /// Ping through ping-http-server's plain TCP endpoint (its --tcp-port)
+    /// instead of libp2p (e.g. 127.0.0.1:9000)
+    #[arg(long, conflicts_with_all = ["server", "ws", "interactive"])]
+    tcp: Option<String>,
+
+    /// Token for --tcp, when the HTTP server requires one
+    #[arg(long, requires = "tcp")]
+    token: Option<String>,
+
+    /// Registry name of the PingActor to talk to
+    #[arg(long, default_value = "ping_actor")]
+    actor_name: String,
+
+    /// Keypair file giving the client a stable peer id (created if missing)
+    #[arg(long, conflicts_with_all = ["ws", "tcp"])]
+    identity: Option<PathBuf>,
+
+    /// How long to wait for the DHT and the actor lookup before giving up
+    #[arg(long, default_value = "30s", value_parser = humantime::parse_duration)]
+    lookup_timeout: Duration,
+
+    /// Number of pings to send
+    #[arg(short, long, default_value_t = 10, conflicts_with = "infinite")]
+    count: u64,
+
+    /// Pause between pings (e.g. 1s, 250ms)
+    #[arg(short, long, default_value = "1s", value_parser = humantime::parse_duration)]
+    interval: Duration,
+
+    /// Send at a fixed rate in pings per second instead of pausing
+    /// --interval after each reply
+    #[arg(long, value_parser = parse_rate, conflicts_with_all = ["interval", "script", "load", "interactive"])]
+    rate: Option<f64>,
+
+    /// Keep pinging until the process is stopped
+    #[arg(long)]
+    infinite: bool,
+
+    /// Replay pings from a JSON-lines file ({"message": ..., "delay": "1s"})
+    #[arg(long, conflicts_with_all = ["count", "infinite", "load", "interactive"])]
+    script: Option<PathBuf>,
+
+    /// Stress-test the actor instead of the paced ping sequence
+    #[arg(long, conflicts_with = "interactive")]
+    load: bool,
+
+    /// Type messages and commands (stats, reset, lookup) at a prompt
+    #[arg(long)]
+    interactive: bool,
+
+    /// Concurrent ask pipelines in load mode
+    #[arg(long, default_value_t = 8, requires = "load", value_parser = clap::value_parser!(u64).range(1..))]
+    concurrency: u64,
+
+    /// Unmeasured pings to send before the run starts
+    #[arg(long, default_value_t = 0)]
+    warmup: u64,
+
+    /// Tell pings instead of asking in load mode, measuring send throughput only
+    #[arg(long, requires = "load")]
+    no_reply: bool,
+
+    /// Pad each ping's message to this many bytes (0 leaves it as is, at most 16 MiB)
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u64).range(..=MAX_MESSAGE_BYTES as u64))]
+    payload_bytes: u64,
+
+    /// What the padding is made of
+    #[arg(long, value_enum, default_value_t = Fill::Pattern)]
+    payload_fill: Fill,
+
+    /// How long the load test runs (e.g. 30s, 2m)
+    #[arg(long, default_value = "30s", requires = "load", value_parser = humantime::parse_duration)]
+    duration: Duration,
 
-// Custom network behavior wrapping Kameo's remote messaging
-#[derive(NetworkBehaviour)]
-struct MyBehaviour {
-    kameo: remote::Behaviour,
+    /// How long to wait for each reply (e.g. 5s, 500ms)
+    #[arg(long, default_value = "120s", value_parser = humantime::parse_duration)]
+    timeout: Duration,
+
+    /// How long an unused server connection is kept open
+    #[arg(long, default_value = "10m", value_parser = humantime::parse_duration)]
+    idle_timeout: Duration,
+
+    /// Disable Nagle's algorithm on TCP connections
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    tcp_nodelay: bool,
+
+    /// How long a new connection may take over its noise and yamux handshakes
+    #[arg(long, default_value = "10s", value_parser = humantime::parse_duration)]
+    handshake_timeout: Duration,
+
+    /// yamux receive window per substream, in bytes, at least 256 KiB
+    /// [default: yamux's own]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(MIN_YAMUX_WINDOW as i64..))]
+    yamux_window: Option<u32>,
+
+    /// yamux receive buffer per substream, in bytes [default: yamux's own]
+    #[arg(long)]
+    yamux_buffer: Option<usize>,
+
+    /// Times a failed ping is retried, with backoff, before counting as an error
+    #[arg(long, default_value_t = 0)]
+    retries: u32,
+
+    /// Failed pings tolerated before the client exits with an error code
+    #[arg(long, default_value_t = 0)]
+    max_errors: u64,
+
+    /// Show a live progress line instead of logging every ping
+    #[arg(short, long, conflicts_with = "interactive")]
+    quiet: bool,
+
+    /// Save this run's summary to a JSON file, to compare later runs against
+    #[arg(long)]
+    save: Option<PathBuf>,
+
+    /// Compare this run against a summary saved with --save
+    #[arg(long)]
+    compare: Option<PathBuf>,
+
+    /// Change, in percent, beyond which --compare reports a regression
+    #[arg(long, default_value_t = 10.0, requires = "compare")]
+    regression_threshold: f64,
+
+    /// Also emit per-ping records and the summary as structured data
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Write structured output to this file instead of stdout
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+
+    /// Export tracing spans to the OpenTelemetry collector at this URL over
+    /// OTLP/HTTP (e.g. http://localhost:4318)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
 }
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     let args = Args::parse();
     
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
-        .init();
+    // Initialize logging, on stderr when stdout carries structured output,
+    // and span export if configured. --quiet leaves only the errors of the
+    // per-ping lines
+    let mut filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    if args.quiet {
+        filter = filter.add_directive("ping_cli_client::sequence=error".parse()?);
+    }
+    let (otel, _telemetry) = match &args.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, guard) = ping_common::telemetry::layer("ping-cli-client", endpoint)?;
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(otel);
+    let logs = tracing_subscriber::fmt::layer();
+    if args.output != OutputFormat::Text && args.output_file.is_none() {
+        registry.with(logs.with_writer(std::io::stderr)).init();
+    } else {
+        registry.with(logs).init();
+    }
+
+    let script = args.script.as_deref().map(|path| script::load(path, args.interval)).transpose()?;
+    let baseline = args.compare.as_deref().map(baseline::load).transpose()?;
+    let mut output = Output::new(args.output, args.output_file.as_deref())?;
+    if args.quiet {
+        let total = match &script {
+            Some(steps) => Some(steps.len() as u64),
+            None => (!args.load && !args.infinite).then_some(args.count),
+        };
+        output = output.with_progress(Progress::new(total));
+    }
+    let output = Arc::new(output);
 
     info!("Starting CLI Ping Client...");
 
-    if let Some(server_addr) = args.server {
+    let retry = RetryPolicy { retries: args.retries };
+    let payload = Payload::new(args.payload_bytes as usize, args.payload_fill);
+
+    // Reach the PingActor over libp2p, or through the HTTP server's WebSocket
+    // or TCP bridge; everything after this point is the same for all of them
+    let (transport, swarm) = if let Some(url) = &args.ws {
+        info!("WebSocket mode");
+        info!("Server: {}", url);
+        match StreamClient::connect(url.clone(), args.timeout).instrument(info_span!("connect", transport = "websocket")).await {
+            Ok(client) => (Transport::WebSocket(Box::new(client)), None),
+            Err(e) if e.is_incompatible() => {
+                error!("{} refused the handshake: {}", url, e);
+                return Ok(Failure::Incompatible.into());
+            }
+            Err(e) => {
+                error!("Could not connect to {}: {}", url, e);
+                return Ok(Failure::DialFailed.into());
+            }
+        }
+    } else if let Some(addr) = &args.tcp {
+        info!("TCP mode");
+        info!("Server: {}", addr);
+        let target = TcpTarget { addr: addr.clone(), token: args.token.clone() };
+        match StreamClient::connect(target, args.timeout).instrument(info_span!("connect", transport = "tcp")).await {
+            Ok(client) => (Transport::Tcp(Box::new(client)), None),
+            Err(e) if e.is_incompatible() => {
+                error!("{} refused the handshake: {}", addr, e);
+                return Ok(Failure::Incompatible.into());
+            }
+            Err(e) => {
+                error!("Could not connect to {}: {}", addr, e);
+                return Ok(Failure::DialFailed.into());
+            }
+        }
+    } else if !args.server.is_empty() {
         info!("Custom swarm mode");
-        info!("Server: {}", server_addr);
-        
-        let server_multiaddr: Multiaddr = server_addr.parse()?;
-        
-        // Build libp2p swarm with TCP transport and Kameo behavior
-        let mut swarm = libp2p::SwarmBuilder::with_new_identity()
-            .with_tokio()
-            .with_tcp(tcp::Config::default(), noise::Config::new, || yamux::Config::default())?
-            .with_behaviour(|key| {
-                let peer_id = key.public().to_peer_id();
-                let messaging_config = remote::messaging::Config::default()
-                    .with_request_timeout(Duration::from_secs(120));
-                let kameo = remote::Behaviour::new(peer_id, messaging_config);
-                Ok(MyBehaviour { kameo })
-            })?
-            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(600)))
-            .build();
-
-        // Initialize Kameo's global actor registry
-        swarm.behaviour().kameo.init_global();
-        info!("Client Peer ID: {}", swarm.local_peer_id());
-
-        // Listen on random port and dial the server
-        swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
-        swarm.dial(server_multiaddr.clone())?;
-
-        // Spawn swarm event handler
-        let swarm_handle = tokio::spawn(async move {
-            loop {
-                match swarm.select_next_some().await {
-                    SwarmEvent::Behaviour(MyBehaviourEvent::Kameo(event)) => {
-                        info!("Kameo event: {:?}", event);
-                    }
-                    SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
-                        info!("Connected to {} via {}", peer_id, endpoint.get_remote_address());
-                    }
-                    SwarmEvent::NewListenAddr { address, .. } => {
-                        info!("Listening on {}", address);
-                    }
-                    _ => {}
-                }
+        let mut servers = Vec::new();
+        for server_addr in &args.server {
+            info!("Server: {}", server_addr);
+            let server: Multiaddr = server_addr.parse()?;
+            // Several servers register under the same name, so they are told
+            // apart by peer id
+            if args.server.len() > 1 && lookup::peer_id(&server).is_none() {
+                error!("{} has no /p2p/PEER_ID part, needed with several servers", server_addr);
+                return Ok(Failure::Usage.into());
             }
-        });
+            servers.push(server);
+        }
 
-        // Wait for DHT to propagate
-        info!("Waiting for DHT propagation (15s)...");
-        tokio::time::sleep(Duration::from_secs(15)).await;
-
-        // Look up the remote PingActor in the distributed registry
-        info!("Looking for PingActor in DHT...");
-        let remote_actor = loop {
-            match RemoteActorRef::<PingActor>::lookup("ping_actor").await? {
-                Some(actor) => {
-                    info!("Found PingActor!");
-                    break actor;
-                }
-                None => {
-                    warn!("Actor not found, retrying...");
-                    tokio::time::sleep(Duration::from_secs(3)).await;
-                }
-            }
+        let keypair = match &args.identity {
+            Some(path) => identity::load_or_create(path)?,
+            None => libp2p::identity::Keypair::generate_ed25519(),
+        };
+        let tuning = Tuning {
+            nodelay: args.tcp_nodelay,
+            handshake_timeout: args.handshake_timeout,
+            yamux_window: args.yamux_window,
+            yamux_buffer: args.yamux_buffer,
         };
+        let swarm = swarm::spawn(keypair, &servers, &tuning, args.timeout, args.idle_timeout)?;
+        if !swarm.wait_connected(DIAL_TIMEOUT).await {
+            error!("Could not connect to any server within {:?}", DIAL_TIMEOUT);
+            swarm.shutdown().await;
+            return Ok(Failure::DialFailed.into());
+        }
 
-        // Send 10 ping messages to the remote actor
-        info!("Starting ping-pong sequence...");
-        let start = Instant::now();
-
-        for i in 1..=10 {
-            let ping = Ping {
-                message: format!("Hello from CLI client #{}", i),
-                sequence: i,
-            };
-
-            info!("Sending PING #{}", i);
-            match remote_actor.ask(&ping).await {
-                Ok(pong_reply) => {
-                    let pong = pong_reply.0;
-                    info!("Received PONG #{} (total: {})", pong.sequence, pong.total_pings);
-                }
-                Err(e) => {
-                    error!("Error: {}", e);
+        // Wait until the DHT can route to the servers, then look up the
+        // remote PingActor of every server in the distributed registry
+        let deadline = Instant::now() + args.lookup_timeout;
+        let peers: Vec<_> = servers.iter().filter_map(lookup::peer_id).collect();
+        info!("Waiting for the DHT to reach the server(s)...");
+        if !swarm.wait_routable(&peers, args.lookup_timeout).await {
+            warn!("DHT routing not confirmed within {:?}, trying the lookup anyway", args.lookup_timeout);
+        }
+        info!("Looking for PingActor '{}' in DHT...", args.actor_name);
+        // If waiting for the DHT used up the deadline, the lookup still gets
+        // a short try
+        let deadline = deadline.max(Instant::now() + LOOKUP_GRACE);
+        let found = ping_common::retry::retry(
+            &LOOKUP_RETRY,
+            tokio::time::sleep_until(deadline.into()),
+            |_| lookup::find_all(&servers, &args.actor_name),
+            |e, _, delay| warn!("{}, retrying in {:?}...", e, delay),
+        )
+        .instrument(info_span!("connect", transport = "libp2p"))
+        .await;
+        let actors = match found {
+            Ok(actors) => {
+                info!("Found PingActor!");
+                actors
+            }
+            Err(e) => {
+                match e.into_last() {
+                    Some(LookupError::NotFound(missing)) => {
+                        for server in missing.iter().map(|&i| &args.server[i]) {
+                            error!("PingActor '{}' of {} not found within {:?}", args.actor_name, server, args.lookup_timeout);
+                        }
+                    }
+                    Some(e) => error!("{} within {:?}", e, args.lookup_timeout),
+                    None => error!("PingActor '{}' not found within {:?}", args.actor_name, args.lookup_timeout),
                 }
+                swarm.shutdown().await;
+                return Ok(Failure::ActorNotFound.into());
             }
-
-            if i < 10 {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+        };
+        for (server, actor) in args.server.iter().zip(&actors) {
+            if let Err(e) = lookup::greet(actor).await {
+                error!("Handshake with the PingActor of {} failed: {}", server, e);
+                swarm.shutdown().await;
+                let failure = if matches!(e, Error::Version(_)) { Failure::Incompatible } else { Failure::DialFailed };
+                return Ok(failure.into());
             }
         }
 
-        let duration = start.elapsed();
-        info!("Complete! Total: {:?}, Avg: {:?}", duration, duration / 10);
-        swarm_handle.abort();
-        
+        if args.interactive {
+            repl::run(actors[0].clone()).await?;
+            swarm.shutdown().await;
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        (Transport::Libp2p(actors), Some((swarm, servers)))
+    } else {
+        error!("Usage: --server \"/ip4/IP/tcp/PORT/p2p/PEER_ID\", --ws \"ws://HOST:PORT/ws\" or --tcp HOST:PORT");
+        return Ok(Failure::Usage.into());
+    };
+
+    transport.warmup(args.warmup).await;
+
+    let summary = if args.load {
+        let report = load::run(
+            transport,
+            args.concurrency as usize,
+            args.duration,
+            retry,
+            args.no_reply,
+            payload,
+            output.clone(),
+        )
+        .await;
+        info!(
+            "Load test complete! {} pings in {:?} ({:.1} pings/s), {} errors ({} timeouts), {} retries",
+            report.completed,
+            report.elapsed,
+            report.throughput(),
+            report.errors,
+            report.timeouts,
+            report.retries
+        );
+        stats::log_report(&report.latency);
+        log_jitter(&report.jitter);
+        log_targets(&args.server, &report.by_target);
+        Summary::new(
+            report.completed + report.errors,
+            report.completed,
+            report.errors,
+            report.retries,
+            report.elapsed,
+            &report.latency,
+        )
+        .with_loss(report.timeouts, &report.jitter)
     } else {
-        error!("Usage: --server \"/ip4/IP/tcp/PORT/p2p/PEER_ID\"");
+        // Send the scripted pings, or the requested number of them
+        let steps: Box<dyn Iterator<Item = Step>> = match script {
+            Some(steps) => Box::new(steps.into_iter()),
+            None => Box::new(sequence::paced((!args.infinite).then_some(args.count), args.interval)),
+        };
+        let reconnect = swarm.as_ref().map(|(swarm, servers)| Reconnect {
+            swarm,
+            servers,
+            actor_name: &args.actor_name,
+        });
+        let steps = steps.map(|step| Step { message: payload.pad(step.message), ..step });
+        let report =
+            sequence::run(transport, reconnect.as_ref(), steps, args.rate, retry, &output).await;
+        info!(
+            "Complete! {} sent, {} received, {:.1}% loss ({} timeouts), {} retries, {} reconnect(s), Total: {:?}, Avg: {:?}",
+            report.sent,
+            report.received,
+            report.loss(),
+            report.timeouts,
+            report.retries,
+            report.reconnects,
+            report.elapsed,
+            report.elapsed / report.sent.max(1) as u32
+        );
+        stats::log_report(&report.latency);
+        log_jitter(&report.jitter);
+        log_targets(&args.server, &report.by_target);
+        Summary::new(
+            report.sent,
+            report.received,
+            report.errors,
+            report.retries,
+            report.elapsed,
+            &report.latency,
+        )
+        .with_loss(report.timeouts, &report.jitter)
+    };
+    output.summary(&summary);
+
+    if let Some((swarm, _)) = swarm {
+        swarm.shutdown().await;
+    }
+
+    let mode = if args.load { "load" } else { "sequence" };
+    if let Some(path) = &args.save {
+        baseline::save(path, mode, &summary)?;
+        info!("Saved results to {}", path.display());
     }
 
-    Ok(())
-}
\ No newline at end of file
+    if summary.errors > args.max_errors {
+        error!("{} ping(s) failed, more than --max-errors {}", summary.errors, args.max_errors);
+        return Ok(Failure::TooManyErrors.into());
+    }
+    if let (Some(baseline), Some(path)) = (&baseline, &args.compare) {
+        info!("Compared with {}:", path.display());
+        if baseline::compare(baseline, mode, &summary, args.regression_threshold) {
+            error!("Regressed by more than {}% against the baseline", args.regression_threshold);
+            return Ok(Failure::Regression.into());
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// A --rate must be a positive number of pings per second
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        Ok(_) => Err("must be greater than 0".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn log_jitter(jitter: &Jitter) {
+    if let Some(mean) = jitter.mean() {
+        info!("Jitter: {:?} (mean change between consecutive round trips)", mean);
+    }
+}
+
+// Per-server latency, when the pings were spread over several servers
+fn log_targets(servers: &[String], by_target: &[LatencyStats]) {
+    if servers.len() > 1 {
+        for (server, latency) in servers.iter().zip(by_target) {
+            stats::log_target(server, latency);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Machine-readable results
+//
+// With `--output json` every ping and the final summary are written as JSON
+// lines tagged with `"type"`; with `--output csv` they share one table whose
+// `record` column tells them apart. Records go to stdout, or to
+// `--output-file` when given, and the log lines move to stderr so they never
+// mix with the data. The live progress line of `--quiet` is fed from here
+// too, since every ping passes through.
+
+use crate::progress::Progress;
+use crate::stats::Jitter;
+use ping_common::latency::LatencyStats;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable log lines only
+    Text,
+    /// One JSON object per line
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// Outcome of a single ping
+#[derive(Serialize, Debug)]
+pub struct PingRecord {
+    pub sequence: u64,
+    pub rtt_us: Option<u64>,
+    pub total_pings: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Totals and latency percentiles for a whole run
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Summary {
+    pub sent: u64,
+    pub received: u64,
+    pub errors: u64,
+    #[serde(default)]
+    pub timeouts: u64,
+    pub retries: u64,
+    pub elapsed_ms: u64,
+    pub throughput: f64,
+    pub p50_us: Option<u64>,
+    pub p90_us: Option<u64>,
+    pub p99_us: Option<u64>,
+    pub max_us: Option<u64>,
+    pub mean_us: Option<u64>,
+    #[serde(default)]
+    pub jitter_us: Option<u64>,
+}
+
+impl Summary {
+    pub fn new(
+        sent: u64,
+        received: u64,
+        errors: u64,
+        retries: u64,
+        elapsed: Duration,
+        latency: &LatencyStats,
+    ) -> Self {
+        let micros = |d: Option<Duration>| d.map(|d| d.as_micros() as u64);
+        Summary {
+            sent,
+            received,
+            errors,
+            timeouts: 0,
+            retries,
+            elapsed_ms: elapsed.as_millis() as u64,
+            throughput: received as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            p50_us: micros(latency.percentile(0.50)),
+            p90_us: micros(latency.percentile(0.90)),
+            p99_us: micros(latency.percentile(0.99)),
+            max_us: micros(latency.max()),
+            mean_us: micros(latency.mean()),
+            jitter_us: None,
+        }
+    }
+
+    /// Add the loss and jitter figures
+    pub fn with_loss(self, timeouts: u64, jitter: &Jitter) -> Self {
+        Summary {
+            timeouts,
+            jitter_us: jitter.mean().map(|d| d.as_micros() as u64),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonRecord<'a> {
+    Ping(&'a PingRecord),
+    Summary(&'a Summary),
+}
+
+// One CSV schema for both record kinds; columns that don't apply stay empty
+#[derive(Serialize, Default)]
+struct CsvRow<'a> {
+    record: &'a str,
+    sequence: Option<u64>,
+    rtt_us: Option<u64>,
+    total_pings: Option<u64>,
+    error: Option<&'a str>,
+    sent: Option<u64>,
+    received: Option<u64>,
+    errors: Option<u64>,
+    timeouts: Option<u64>,
+    retries: Option<u64>,
+    elapsed_ms: Option<u64>,
+    throughput: Option<f64>,
+    p50_us: Option<u64>,
+    p90_us: Option<u64>,
+    p99_us: Option<u64>,
+    max_us: Option<u64>,
+    mean_us: Option<u64>,
+    jitter_us: Option<u64>,
+}
+
+enum Sink {
+    Text,
+    Json(Mutex<Box<dyn Write + Send>>),
+    Csv(Box<Mutex<csv::Writer<Box<dyn Write + Send>>>>),
+}
+
+/// Where per-ping records and the summary are written
+pub struct Output {
+    sink: Sink,
+    progress: Option<Progress>,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, path: Option<&Path>) -> io::Result<Self> {
+        let writer = || -> io::Result<Box<dyn Write + Send>> {
+            Ok(match path {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(io::stdout()),
+            })
+        };
+        let sink = match format {
+            OutputFormat::Text => Sink::Text,
+            OutputFormat::Json => Sink::Json(Mutex::new(writer()?)),
+            OutputFormat::Csv => Sink::Csv(Box::new(Mutex::new(csv::Writer::from_writer(writer()?)))),
+        };
+        Ok(Output { sink, progress: None })
+    }
+
+    /// Also show the records on a live progress line
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn ping(&self, record: &PingRecord) {
+        if let Some(progress) = &self.progress {
+            progress.record(record);
+        }
+        match &self.sink {
+            Sink::Text => {}
+            Sink::Json(writer) => write_json(writer, &JsonRecord::Ping(record), false),
+            Sink::Csv(writer) => write_csv(
+                writer,
+                &CsvRow {
+                    record: "ping",
+                    sequence: Some(record.sequence),
+                    rtt_us: record.rtt_us,
+                    total_pings: record.total_pings,
+                    error: record.error.as_deref(),
+                    ..CsvRow::default()
+                },
+                false,
+            ),
+        }
+    }
+
+    pub fn summary(&self, summary: &Summary) {
+        if let Some(progress) = &self.progress {
+            progress.finish();
+        }
+        match &self.sink {
+            Sink::Text => {}
+            Sink::Json(writer) => write_json(writer, &JsonRecord::Summary(summary), true),
+            Sink::Csv(writer) => write_csv(
+                writer,
+                &CsvRow {
+                    record: "summary",
+                    sent: Some(summary.sent),
+                    received: Some(summary.received),
+                    errors: Some(summary.errors),
+                    timeouts: Some(summary.timeouts),
+                    retries: Some(summary.retries),
+                    elapsed_ms: Some(summary.elapsed_ms),
+                    throughput: Some(summary.throughput),
+                    p50_us: summary.p50_us,
+                    p90_us: summary.p90_us,
+                    p99_us: summary.p99_us,
+                    max_us: summary.max_us,
+                    mean_us: summary.mean_us,
+                    jitter_us: summary.jitter_us,
+                    ..CsvRow::default()
+                },
+                true,
+            ),
+        }
+    }
+}
+
+// Output errors are not worth aborting a run over; the log lines still have
+// everything. Files are only flushed with the summary to keep load runs fast.
+fn write_json(writer: &Mutex<Box<dyn Write + Send>>, record: &JsonRecord, flush: bool) {
+    let mut writer = writer.lock().unwrap();
+    let _ = serde_json::to_writer(&mut *writer, record);
+    let _ = writer.write_all(b"\n");
+    if flush {
+        let _ = writer.flush();
+    }
+}
+
+fn write_csv(writer: &Mutex<csv::Writer<Box<dyn Write + Send>>>, row: &CsvRow, flush: bool) {
+    let mut writer = writer.lock().unwrap();
+    let _ = writer.serialize(row);
+    if flush {
+        let _ = writer.flush();
+    }
+}
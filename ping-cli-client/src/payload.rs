@@ -0,0 +1,48 @@
+// Payload size
+//
+// `--payload-bytes N` pads each Ping's message up to N bytes, to see how the
+// transports and codecs cope with larger messages. The padding is built once
+// and appended to every message, so producing it costs nothing during the
+// run. It is either a repeated pattern, which compresses well, or random
+// alphanumerics, which don't; both keep the message valid UTF-8.
+
+use clap::ValueEnum;
+use rand::{distributions::Alphanumeric, Rng};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Fill {
+    /// The alphabet over and over
+    Pattern,
+    /// Random letters and digits
+    Random,
+}
+
+#[derive(Clone)]
+pub struct Payload {
+    bytes: usize,
+    padding: Arc<str>,
+}
+
+impl Payload {
+    /// Pad messages to `bytes`; 0 leaves them as they are
+    pub fn new(bytes: usize, fill: Fill) -> Self {
+        let padding: String = match fill {
+            Fill::Pattern => ('a'..='z').cycle().take(bytes).collect(),
+            Fill::Random => rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(bytes)
+                .map(char::from)
+                .collect(),
+        };
+        Self { bytes, padding: padding.into() }
+    }
+
+    /// `message` grown to the payload size; longer messages are left alone
+    pub fn pad(&self, mut message: String) -> String {
+        if let Some(missing) = self.bytes.checked_sub(message.len()) {
+            message.push_str(&self.padding[..missing]);
+        }
+        message
+    }
+}
@@ -0,0 +1,85 @@
+// Live progress
+//
+// With `--quiet` the per-ping log lines are silenced and a single status line
+// on stderr keeps count instead: pings done, replies, errors and the p99 so
+// far. indicatif hides it by itself when stderr is not a terminal.
+
+use crate::output::PingRecord;
+use ping_common::latency::LatencyStats;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the stats in the status line are recomputed
+const REFRESH: Duration = Duration::from_millis(200);
+
+pub struct Progress {
+    bar: ProgressBar,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    sent: u64,
+    received: u64,
+    errors: u64,
+    latency: LatencyStats,
+    refreshed: Option<Instant>,
+}
+
+impl State {
+    fn message(&self) -> String {
+        let p99 = match self.latency.percentile(0.99) {
+            Some(p99) => format!("{:?}", p99),
+            None => "-".to_string(),
+        };
+        format!(
+            "sent {} | received {} | errors {} | p99 {}",
+            self.sent, self.received, self.errors, p99
+        )
+    }
+}
+
+impl Progress {
+    /// A bar when the number of pings is known up front, a spinner otherwise
+    pub fn new(total: Option<u64>) -> Self {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total).with_style(
+                ProgressStyle::with_template("[{elapsed_precise}] {bar:30} {pos}/{len} {msg}")
+                    .expect("valid template"),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template("{spinner} [{elapsed_precise}] {msg}").expect("valid template"),
+            ),
+        };
+        Self { bar, state: Mutex::new(State::default()) }
+    }
+
+    pub fn record(&self, record: &PingRecord) {
+        let mut state = self.state.lock().unwrap();
+        // The run may start long after the client does (DHT wait, warmup)
+        if state.sent == 0 {
+            self.bar.reset_elapsed();
+            self.bar.enable_steady_tick(Duration::from_millis(100));
+        }
+        state.sent += 1;
+        match record.rtt_us {
+            Some(rtt_us) => {
+                state.received += 1;
+                state.latency.record(Duration::from_micros(rtt_us));
+            }
+            None => state.errors += 1,
+        }
+        self.bar.inc(1);
+        if state.refreshed.is_none_or(|at| at.elapsed() >= REFRESH) {
+            self.bar.set_message(state.message());
+            state.refreshed = Some(Instant::now());
+        }
+    }
+
+    /// Leave the final counts on screen
+    pub fn finish(&self) {
+        let state = self.state.lock().unwrap();
+        self.bar.finish_with_message(state.message());
+    }
+}
@@ -0,0 +1,61 @@
+// Reconnection
+//
+// When the server connections drop mid-run, the client re-dials the servers
+// and looks the actors up again, with ping-common's backoff between attempts
+// (jittered, so a fleet of clients doesn't retry in lockstep) until it is
+// back.
+
+use crate::lookup;
+use crate::swarm::SwarmHandle;
+use kameo::prelude::*;
+use libp2p::Multiaddr;
+use ping_common::retry::{self, RetryPolicy};
+use ping_common::PingActor;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long each attempt waits for the connection before backing off again
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delays between attempts; there is no giving up
+const RETRY: RetryPolicy = RetryPolicy::new(Duration::from_millis(500), Duration::from_secs(30));
+
+/// What is needed to get the actors back after the connections drop
+pub struct Reconnect<'a> {
+    pub swarm: &'a SwarmHandle,
+    pub servers: &'a [Multiaddr],
+    pub actor_name: &'a str,
+}
+
+impl Reconnect<'_> {
+    /// Re-dial and re-lookup until every server's actor is reachable again
+    pub async fn run(&self) -> Vec<RemoteActorRef<PingActor>> {
+        let found = retry::retry(
+            &RETRY,
+            std::future::pending(),
+            |retries| self.attempt(retries + 1),
+            |e, retry, delay| warn!("Reconnect attempt #{} failed: {}, next in {:?}", retry, e, delay),
+        )
+        .await;
+        let Ok(actors) = found else { unreachable!("reconnecting is retried forever") };
+        actors
+    }
+
+    async fn attempt(&self, attempt: u32) -> Result<Vec<RemoteActorRef<PingActor>>, String> {
+        if !self.swarm.is_connected() {
+            for server in self.servers {
+                self.swarm.dial(server.clone());
+            }
+            if !self.swarm.wait_connected(CONNECT_TIMEOUT).await {
+                return Err("server unreachable".to_string());
+            }
+        }
+        match lookup::find_all(self.servers, self.actor_name).await {
+            Ok(actors) => {
+                info!("Reconnected to {} after {} attempt(s)", self.actor_name, attempt);
+                Ok(actors)
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
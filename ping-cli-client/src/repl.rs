@@ -0,0 +1,80 @@
+// Interactive mode
+//
+// Reads lines from stdin: plain text is sent to the actor as a Ping payload,
+// and a few commands inspect or switch the actor. Replies are printed inline
+// so the session reads like a conversation with the actor.
+
+use kameo::prelude::*;
+use ping_common::{Ping, PingActor, PingActorClient, Validate};
+use std::io::Write;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+const HELP: &str = "\
+Type a message to send it as a Ping, or one of:
+  stats          show the actor's ping counter
+  reset          reset the actor's ping counter
+  lookup <name>  switch to the PingActor registered under <name>
+  help           show this help
+  quit           leave interactive mode";
+
+pub async fn run(actor: RemoteActorRef<PingActor>) -> std::io::Result<()> {
+    let mut actor = PingActorClient::new(actor);
+    println!("{}", HELP);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut sequence = 0;
+
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let line = line.trim();
+
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => {}
+            ("quit" | "exit", "") => break,
+            ("help", "") => println!("{}", HELP),
+            ("stats", "") => match actor.get_stats().await {
+                Ok(stats) => println!("total pings: {}", stats.total_pings),
+                Err(e) => println!("error: {}", e),
+            },
+            ("reset", "") => match actor.reset_stats().await {
+                Ok(stats) => println!("counter reset (was {})", stats.total_pings),
+                Err(e) => println!("error: {}", e),
+            },
+            ("lookup", name) if !name.trim().is_empty() => {
+                match RemoteActorRef::<PingActor>::lookup(name.trim()).await {
+                    Ok(Some(found)) => {
+                        actor = PingActorClient::new(found);
+                        println!("now talking to {}", name.trim());
+                    }
+                    Ok(None) => println!("no actor registered as {}", name.trim()),
+                    Err(e) => println!("lookup failed: {}", e),
+                }
+            }
+            ("lookup", _) => println!("usage: lookup <name>"),
+            _ => {
+                sequence += 1;
+                let ping = Ping::builder().message(line).sequence(sequence).build();
+                if let Err(e) = ping.validate() {
+                    println!("invalid ping: {}", e);
+                    continue;
+                }
+                let sent_at = Instant::now();
+                match actor.ping(ping).await {
+                    Ok(pong) => println!(
+                        "{} (seq {}, total {}, rtt {:?})",
+                        pong.message,
+                        pong.sequence,
+                        pong.total_pings,
+                        sent_at.elapsed()
+                    ),
+                    Err(e) => println!("error: {}", e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// Retries
+//
+// A failed ask is retried a bounded number of times with ping-common's
+// backoff before it counts as an error. Note that a ping whose reply was lost
+// may reach the actor twice, so retries can bump the actor's counter.
+
+use crate::transport::{Transport, TransportError};
+use ping_common::retry::{self, RetryError};
+use ping_common::{Ping, Pong};
+use std::time::Duration;
+use tracing::debug;
+
+/// How many times a failed ask is repeated
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub retries: u32,
+}
+
+impl RetryPolicy {
+    /// Send `ping` to `target`, retrying failures; also returns how many
+    /// retries it took
+    pub async fn ask(
+        &self,
+        transport: &Transport,
+        target: usize,
+        ping: &Ping,
+    ) -> (Result<Pong, TransportError>, u32) {
+        let policy = retry::RetryPolicy::new(Duration::from_millis(100), Duration::from_secs(2))
+            .with_max_attempts(self.retries.saturating_add(1));
+        let mut retries = 0;
+        let result = retry::retry(
+            &policy,
+            std::future::pending(),
+            |_| transport.ask(target, ping),
+            |e, retry, delay| {
+                debug!("PING #{} failed ({}), retrying in {:?}", ping.sequence, e, delay);
+                retries = retry;
+            },
+        )
+        .await;
+        (result.map_err(|e| RetryError::into_last(e).expect("never cancelled")), retries)
+    }
+}
@@ -0,0 +1,45 @@
+// Script mode
+//
+// Replays pings from a JSON-lines file, one object per line:
+//
+//     {"message": "hello"}
+//     {"message": "after a pause", "delay": "2s"}
+//
+// `delay` is the pause before that ping (humantime syntax) and defaults to
+// `--interval`, except for the first ping which goes out right away. Blank
+// lines are skipped, so scripts can be grouped into paragraphs.
+
+use crate::sequence::Step;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptLine {
+    message: String,
+    delay: Option<String>,
+}
+
+pub fn load(path: &Path, interval: Duration) -> io::Result<Vec<Step>> {
+    let invalid = |line: usize, e: &dyn std::fmt::Display| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path.display(), line, e))
+    };
+
+    let mut steps = Vec::new();
+    for (index, text) in fs::read_to_string(path)?.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let line: ScriptLine = serde_json::from_str(text).map_err(|e| invalid(index + 1, &e))?;
+        let delay = match line.delay {
+            Some(delay) => humantime::parse_duration(&delay).map_err(|e| invalid(index + 1, &e))?,
+            None if steps.is_empty() => Duration::ZERO,
+            None => interval,
+        };
+        steps.push(Step { delay, message: line.message });
+    }
+    Ok(steps)
+}
@@ -0,0 +1,197 @@
+// Paced ping sequence
+//
+// Sends pings one at a time with a pause in between, like the classic `ping`
+// tool: a fixed number of them, until Ctrl-C when no count is given, or the
+// steps of a script.
+// Ctrl-C always ends the run cleanly, and whatever was collected up to then
+// is still reported. If the libp2p connection drops, the run reconnects and
+// carries on with the next sequence number (a WebSocket re-opens by itself).
+// With several targets the pings take turns going to each of them.
+//
+// With a rate the pauses are replaced by a fixed schedule: a ping is due
+// every 1/rate seconds from the start, however long the replies take. A ping
+// that falls behind schedule is sent as soon as the previous one is
+// answered, so a slow reply is made up for by a short burst and the average
+// rate holds.
+//
+// Numbering the pings, matching each Pong to its ping and counting them is
+// left to ping-common's `PingClientCore`, as in the wasm client.
+
+use crate::output::{Output, PingRecord};
+use crate::reconnect::Reconnect;
+use crate::retry::RetryPolicy;
+use crate::stats::Jitter;
+use ping_common::latency::LatencyStats;
+use crate::transport::Transport;
+use ping_common::client::PingClientCore;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+/// One ping of a sequence: the pause before it, then its payload
+pub struct Step {
+    pub delay: Duration,
+    pub message: String,
+}
+
+/// `count` pings (or endless ones) `interval` apart
+pub fn paced(count: Option<u64>, interval: Duration) -> impl Iterator<Item = Step> {
+    (1..)
+        .take_while(move |&i| count.is_none_or(|count| i <= count))
+        .map(move |i: u64| Step {
+            delay: if i == 1 { Duration::ZERO } else { interval },
+            message: format!("Hello from CLI client #{}", i),
+        })
+}
+
+/// Results of a sequence run
+#[derive(Default)]
+pub struct SequenceReport {
+    pub sent: u64,
+    pub received: u64,
+    pub errors: u64,
+    /// Errors that were timeouts, i.e. pings lost rather than refused
+    pub timeouts: u64,
+    pub retries: u64,
+    pub reconnects: u64,
+    pub elapsed: Duration,
+    pub latency: LatencyStats,
+    pub jitter: Jitter,
+    /// Latency of each target, when there are several
+    pub by_target: Vec<LatencyStats>,
+}
+
+impl SequenceReport {
+    /// Share of pings that never got a pong, in percent
+    pub fn loss(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        (self.sent - self.received) as f64 * 100.0 / self.sent as f64
+    }
+}
+
+pub async fn run(
+    mut transport: Transport,
+    reconnect: Option<&Reconnect<'_>>,
+    steps: impl Iterator<Item = Step>,
+    rate: Option<f64>,
+    retry: RetryPolicy,
+    output: &Output,
+) -> SequenceReport {
+    info!("Starting ping-pong sequence...");
+    let start = Instant::now();
+    let mut schedule = rate.map(|rate| {
+        info!("Sending at {} pings/s", rate);
+        let mut schedule = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+        schedule.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        schedule
+    });
+    let mut report = SequenceReport {
+        by_target: (0..transport.targets()).map(|_| LatencyStats::default()).collect(),
+        ..SequenceReport::default()
+    };
+
+    // The core keeps each waiting ping's target, and times them in
+    // milliseconds since the start
+    let mut core = PingClientCore::<usize>::new(1, None);
+    let now_ms = || start.elapsed().as_secs_f64() * 1000.0;
+
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+
+    for step in steps {
+        if let Some(schedule) = &mut schedule {
+            tokio::select! {
+                _ = schedule.tick() => {}
+                _ = &mut interrupted => {
+                    info!("Interrupted");
+                    break;
+                }
+            }
+        } else if !step.delay.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(step.delay) => {}
+                _ = &mut interrupted => {
+                    info!("Interrupted");
+                    break;
+                }
+            }
+        }
+        let ping = core.next_ping(step.message, unix_ms());
+        let i = ping.sequence;
+        let target = (i - 1) as usize % transport.targets();
+
+        info!("Sending PING #{}", i);
+        core.track(&ping, now_ms(), target);
+        core.record_sent();
+        let reply = tokio::select! {
+            (reply, retries) = retry.ask(&transport, target, &ping) => {
+                report.retries += u64::from(retries);
+                reply
+            }
+            _ = &mut interrupted => {
+                info!("Interrupted, PING #{} left unanswered", i);
+                break;
+            }
+        };
+        let reply = reply.map(|pong| {
+            let resolved = core.resolve(&pong, now_ms());
+            (pong, resolved)
+        });
+        let record = match reply {
+            Ok((pong, Some((rtt_ms, target)))) => {
+                let rtt = Duration::from_secs_f64(rtt_ms / 1000.0);
+                report.latency.record(rtt);
+                report.jitter.record(rtt);
+                report.by_target[target].record(rtt);
+                info!("Received PONG #{} (total: {}, rtt: {:?})", pong.sequence, pong.total_pings, rtt);
+                PingRecord {
+                    sequence: i,
+                    rtt_us: Some(rtt.as_micros() as u64),
+                    total_pings: Some(pong.total_pings),
+                    error: None,
+                }
+            }
+            Ok((pong, None)) => {
+                error!("Error: PONG #{} doesn't answer PING #{}", pong.sequence, i);
+                core.cancel(i);
+                core.record_error();
+                let error = format!("PONG #{} doesn't answer PING #{}", pong.sequence, i);
+                PingRecord { sequence: i, rtt_us: None, total_pings: None, error: Some(error) }
+            }
+            Err(e) => {
+                error!("Error: {}", e);
+                core.cancel(i);
+                core.record_error();
+                report.timeouts += u64::from(e.is_timeout());
+                PingRecord { sequence: i, rtt_us: None, total_pings: None, error: Some(e.to_string()) }
+            }
+        };
+        output.ping(&record);
+
+        let lost = reconnect.filter(|reconnect| record.error.is_some() && !reconnect.swarm.is_connected());
+        if let Some(reconnect) = lost {
+            warn!("Connection to the server lost, reconnecting...");
+            tokio::select! {
+                found = reconnect.run() => transport = Transport::Libp2p(found),
+                _ = &mut interrupted => {
+                    info!("Interrupted while reconnecting");
+                    break;
+                }
+            }
+            report.reconnects += 1;
+        }
+    }
+
+    let counts = core.counts();
+    report.sent = counts.sent;
+    report.received = counts.received;
+    report.errors = counts.errors;
+    report.elapsed = start.elapsed();
+    report
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64)
+}
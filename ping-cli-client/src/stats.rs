@@ -0,0 +1,122 @@
+// Round-trip latency statistics
+//
+// Every ask's round-trip time goes into ping-common's `LatencyStats`
+// histogram so the end-of-run report can show the tail, not just the
+// average, computed the way the wasm client and the server compute theirs.
+// Jitter is tracked next to it, since it depends on the order of the
+// replies, which a histogram forgets.
+
+use ping_common::latency::LatencyStats;
+use std::time::Duration;
+use tracing::info;
+
+/// Width of the bars in the text histogram
+const BAR_WIDTH: u64 = 40;
+
+/// Inter-reply jitter: the plain mean of the absolute differences between
+/// consecutive round trips. Unlike RFC 3550's smoothed estimate it weighs
+/// every pair the same, so runs can be merged, and unlike `ping`'s mdev it
+/// isn't a standard deviation.
+#[derive(Default)]
+pub struct Jitter {
+    last: Option<Duration>,
+    total: Duration,
+    samples: u64,
+}
+
+impl Jitter {
+    pub fn record(&mut self, rtt: Duration) {
+        if let Some(last) = self.last {
+            self.total += rtt.abs_diff(last);
+            self.samples += 1;
+        }
+        self.last = Some(rtt);
+    }
+
+    /// Fold in another pipeline's jitter; consecutive only within each one
+    pub fn merge(&mut self, other: &Jitter) {
+        self.total += other.total;
+        self.samples += other.samples;
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.samples > 0).then(|| Duration::from_nanos((self.total.as_nanos() / self.samples as u128) as u64))
+    }
+}
+
+/// One line per target of a multi-server run
+pub fn log_target(target: &str, latency: &LatencyStats) {
+    let (Some(p50), Some(p99), Some(max), Some(mean)) =
+        (latency.percentile(0.50), latency.percentile(0.99), latency.max(), latency.mean())
+    else {
+        info!("{}: no replies", target);
+        return;
+    };
+    info!(
+        "{}: {} replies, p50: {:?}, p99: {:?}, max: {:?}, mean: {:?}",
+        target,
+        latency.count(),
+        p50,
+        p99,
+        max,
+        mean
+    );
+}
+
+/// Log the percentiles followed by a power-of-two bucketed histogram
+pub fn log_report(latency: &LatencyStats) {
+    let (Some(p50), Some(p90), Some(p99), Some(max), Some(mean)) = (
+        latency.percentile(0.50),
+        latency.percentile(0.90),
+        latency.percentile(0.99),
+        latency.max(),
+        latency.mean(),
+    ) else {
+        info!("No replies received, no latency data");
+        return;
+    };
+    info!("RTT p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}, mean: {:?}", p50, p90, p99, max, mean);
+
+    let buckets = latency.log2_buckets();
+    let tallest = buckets.iter().map(|&(_, count)| count).max().unwrap_or(1).max(1);
+    for (upper, count) in buckets {
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(tallest) as usize);
+        info!(
+            "  <= {:>10} | {:<width$} | {}",
+            format!("{:?}", upper),
+            bar,
+            count,
+            width = BAR_WIDTH as usize
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn of(rtts_ms: &[u64]) -> Jitter {
+        let mut jitter = Jitter::default();
+        for &ms in rtts_ms {
+            jitter.record(Duration::from_millis(ms));
+        }
+        jitter
+    }
+
+    #[test]
+    fn mean_of_consecutive_differences() {
+        assert_eq!(of(&[]).mean(), None);
+        assert_eq!(of(&[10]).mean(), None);
+        assert_eq!(of(&[10, 20, 15]).mean(), Some(Duration::from_micros(7_500)));
+    }
+
+    #[test]
+    fn merge_keeps_pipelines_apart() {
+        let mut jitter = of(&[10, 20, 15]);
+        jitter.merge(&of(&[100, 103]));
+        // 10, 5 and 3 ms; 15 to 100 is no difference, as they ran side by side
+        assert_eq!(jitter.mean(), Some(Duration::from_millis(6)));
+        jitter.merge(&of(&[50]));
+        assert_eq!(jitter.mean(), Some(Duration::from_millis(6)));
+    }
+}
@@ -0,0 +1,90 @@
+// Stream clients
+//
+// Talks to ping-http-server's `/ws` endpoint the way the browser client does,
+// or to its `--tcp-port` endpoint, with ping-common's `PingActorClient` over a
+// `PingTransport`: a Ping goes out and the Pong with the same sequence number
+// comes back. Server
+// pushes arriving in between are skipped by the transport. The server answers
+// every Ping, so the Pong of a told ping is simply discarded when it arrives.
+// A connection that fails is dropped and re-opened on the next ping, so a
+// restarted server is picked up without extra bookkeeping.
+
+use futures::FutureExt;
+use ping_common::transport::{PingTransport, Reconnecting, TransportError};
+use ping_common::{Ping, PingActorClient, Pong};
+use std::fmt;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[derive(Debug)]
+pub enum StreamError {
+    Transport(TransportError),
+    Timeout(Duration),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Transport(e) => write!(f, "{}", e),
+            StreamError::Timeout(timeout) => write!(f, "no reply within {:?}", timeout),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+impl StreamError {
+    /// The server refused the handshake: it speaks another protocol version
+    pub fn is_incompatible(&self) -> bool {
+        matches!(self, StreamError::Transport(TransportError::Server(e)) if e.code == "unsupported_version")
+    }
+}
+
+impl From<TransportError> for StreamError {
+    fn from(e: TransportError) -> Self {
+        StreamError::Transport(e)
+    }
+}
+
+/// One connection to the HTTP server, carrying one request at a time
+pub struct StreamClient<T: PingTransport> {
+    timeout: Duration,
+    client: PingActorClient<Mutex<Reconnecting<T>>>,
+}
+
+impl<T: PingTransport> StreamClient<T> {
+    /// Open the connection up front so a wrong target fails before the run
+    /// starts
+    pub async fn connect(target: T::Target, timeout: Duration) -> Result<Self, StreamError> {
+        Ok(Self {
+            timeout,
+            client: PingActorClient::new(Mutex::new(Reconnecting::connect(target).await?)),
+        })
+    }
+
+    /// A second, independent connection to the same endpoint
+    pub async fn reopen(&self) -> Result<Self, StreamError> {
+        let target = self.client.get_ref().lock().await.target().clone();
+        Self::connect(target, self.timeout).await
+    }
+
+    pub async fn ask(&self, ping: &Ping) -> Result<Pong, StreamError> {
+        match tokio::time::timeout(self.timeout, self.client.ping(ping.clone())).await {
+            Ok(result) => result.map_err(StreamError::Transport),
+            // A late reply is harmless (its sequence number won't match)
+            Err(_) => Err(StreamError::Timeout(self.timeout)),
+        }
+    }
+
+    pub async fn tell(&self, ping: &Ping) -> Result<(), StreamError> {
+        let mut connection = self.client.get_ref().lock().await;
+        // Drop whatever replies have already arrived
+        while let Some(reply) = connection.recv().now_or_never() {
+            match reply {
+                Ok(_) | Err(TransportError::Server(_)) => {}
+                Err(_) => break,
+            }
+        }
+        Ok(connection.send(ping).await?)
+    }
+}
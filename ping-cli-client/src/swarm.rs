@@ -0,0 +1,179 @@
+// libp2p swarm
+//
+// The swarm lives in its own task so it keeps being polled while the client
+// waits on replies. The rest of the client talks to it through a
+// `SwarmHandle`: it can watch whether the server is connected and whether
+// the DHT can route to it yet, ask for a re-dial, and shut the task down
+// cleanly at the end of a run.
+
+use crate::lookup;
+use ping_common::tuning::Tuning;
+use futures::StreamExt;
+use kameo::remote;
+use libp2p::{
+    identity::Keypair,
+    swarm::{NetworkBehaviour, SwarmEvent},
+    Multiaddr, PeerId,
+};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use tracing::{info, warn};
+
+// Largest Pong accepted; it echoes the Ping's message, which --payload-bytes
+// can make bigger than kameo's default limits
+const MAX_MESSAGE_BYTES: u64 = 16 * 1024 * 1024;
+
+// Custom network behavior wrapping Kameo's remote messaging
+#[derive(NetworkBehaviour)]
+struct MyBehaviour {
+    kameo: remote::Behaviour,
+}
+
+enum Command {
+    Dial(Multiaddr),
+    Shutdown,
+}
+
+pub struct SwarmHandle {
+    commands: mpsc::UnboundedSender<Command>,
+    connected: watch::Receiver<bool>,
+    routable: watch::Receiver<HashSet<PeerId>>,
+    task: JoinHandle<()>,
+}
+
+// Build the swarm under `identity`, dial the servers and start driving it in
+// the background. Remote asks give up after `request_timeout`, and unused
+// connections are closed after `idle_timeout`.
+pub fn spawn(
+    identity: Keypair,
+    servers: &[Multiaddr],
+    tuning: &Tuning,
+    request_timeout: Duration,
+    idle_timeout: Duration,
+) -> Result<SwarmHandle, Box<dyn std::error::Error>> {
+    // Build libp2p swarm with TCP transport and Kameo behavior
+    let mut swarm = libp2p::SwarmBuilder::with_existing_identity(identity)
+        .with_tokio()
+        .with_other_transport(|key| tuning.transport(key))?
+        .with_behaviour(|key| {
+            let peer_id = key.public().to_peer_id();
+            let messaging_config = remote::messaging::Config::default()
+                .with_request_timeout(request_timeout)
+                .with_request_size_maximum(MAX_MESSAGE_BYTES)
+                .with_response_size_maximum(MAX_MESSAGE_BYTES);
+            let kameo = remote::Behaviour::new(peer_id, messaging_config);
+            Ok(MyBehaviour { kameo })
+        })?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(idle_timeout))
+        .build();
+
+    // Initialize Kameo's global actor registry
+    swarm.behaviour().kameo.init_global();
+    info!("Client Peer ID: {}", swarm.local_peer_id());
+
+    // Listen on random port and dial the servers. Known server addresses go
+    // straight into the routing table: a server that has seen this peer id
+    // before won't probe it again, so the DHT would otherwise never confirm it
+    swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
+    for server in servers {
+        if let Some(peer_id) = lookup::peer_id(server) {
+            swarm.add_peer_address(peer_id, server.clone());
+        }
+        swarm.dial(server.clone())?;
+    }
+
+    let (commands, mut command_rx) = mpsc::unbounded_channel();
+    let (connected_tx, connected) = watch::channel(false);
+    let (routable_tx, routable) = watch::channel(HashSet::new());
+
+    // Spawn swarm event handler
+    let task = tokio::spawn(async move {
+        let mut connections = 0usize;
+        loop {
+            let event = tokio::select! {
+                event = swarm.select_next_some() => event,
+                command = command_rx.recv() => match command {
+                    Some(Command::Dial(addr)) => {
+                        if let Err(e) = swarm.dial(addr) {
+                            warn!("Dial failed: {}", e);
+                        }
+                        continue;
+                    }
+                    Some(Command::Shutdown) | None => break,
+                },
+            };
+            match event {
+                SwarmEvent::Behaviour(MyBehaviourEvent::Kameo(event)) => {
+                    // Once a peer is in the routing table, registry lookups
+                    // can reach it
+                    if let remote::Event::Registry(remote::registry::Event::RoutingUpdated { peer, .. }) = &event {
+                        routable_tx.send_modify(|peers| {
+                            peers.insert(*peer);
+                        });
+                    }
+                    info!("Kameo event: {:?}", event);
+                }
+                SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                    info!("Connected to {} via {}", peer_id, endpoint.get_remote_address());
+                    connections += 1;
+                    connected_tx.send_replace(true);
+                }
+                SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
+                    warn!("Connection to {} closed: {:?}", peer_id, cause);
+                    connections = connections.saturating_sub(1);
+                    connected_tx.send_replace(connections > 0);
+                }
+                SwarmEvent::OutgoingConnectionError { error, .. } => {
+                    warn!("Outgoing connection failed: {}", error);
+                }
+                SwarmEvent::NewListenAddr { address, .. } => {
+                    info!("Listening on {}", address);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(SwarmHandle { commands, connected, routable, task })
+}
+
+impl SwarmHandle {
+    pub fn is_connected(&self) -> bool {
+        *self.connected.borrow()
+    }
+
+    pub fn dial(&self, addr: Multiaddr) {
+        let _ = self.commands.send(Command::Dial(addr));
+    }
+
+    /// Wait up to `timeout` for a connection to be established
+    pub async fn wait_connected(&self, timeout: Duration) -> bool {
+        let mut connected = self.connected.clone();
+        tokio::time::timeout(timeout, connected.wait_for(|&up| up))
+            .await
+            .is_ok_and(|result| result.is_ok())
+    }
+
+    /// Wait up to `timeout` until the DHT can route to all of `peers` (to any
+    /// peer when the list is empty)
+    pub async fn wait_routable(&self, peers: &[PeerId], timeout: Duration) -> bool {
+        let mut routable = self.routable.clone();
+        let ready = |known: &HashSet<PeerId>| match peers {
+            [] => !known.is_empty(),
+            peers => peers.iter().all(|peer| known.contains(peer)),
+        };
+        tokio::time::timeout(timeout, routable.wait_for(ready))
+            .await
+            .is_ok_and(|result| result.is_ok())
+    }
+
+    /// Stop the swarm task, closing its connections
+    pub async fn shutdown(self) {
+        let _ = self.commands.send(Command::Shutdown);
+        let _ = self.task.await;
+    }
+}
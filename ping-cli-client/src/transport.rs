@@ -0,0 +1,147 @@
+// Transports
+//
+// The same PingActor can be reached directly over libp2p or through the HTTP
+// server's WebSocket or plain TCP bridge. All of them sit behind `Transport`
+// so the sequence and load runs measure them with identical code. Over libp2p there can be
+// several targets, one actor per server; callers pick the target of every
+// ping, so they can spread pings over the servers and tell the results apart.
+// Every ping is checked against its `Validate` rules before it goes out, and
+// goes out in a `ping` span, which is where the client's part of a trace
+// begins. When the span is exported the ping carries its trace context, for
+// the servers to continue the trace.
+
+use crate::stream::{StreamClient, StreamError};
+use kameo::error::RemoteSendError;
+use kameo::prelude::*;
+use ping_common::builder::TRACEPARENT;
+use ping_common::transport::{Tcp, WebSocket};
+use ping_common::{telemetry, Ping, PingActor, Pong, Validate, ValidationError};
+use std::borrow::Cow;
+use std::fmt;
+use tracing::{info, info_span, warn, Instrument};
+
+pub enum Transport {
+    Libp2p(Vec<RemoteActorRef<PingActor>>),
+    WebSocket(Box<StreamClient<WebSocket>>),
+    Tcp(Box<StreamClient<Tcp>>),
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    Libp2p(RemoteSendError),
+    Stream(StreamError),
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Libp2p(e) => write!(f, "{}", e),
+            TransportError::Stream(e) => write!(f, "{}", e),
+            TransportError::Invalid(e) => write!(f, "invalid ping: {}", e),
+        }
+    }
+}
+
+impl TransportError {
+    /// The ping went unanswered rather than failing outright
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            TransportError::Libp2p(RemoteSendError::ReplyTimeout | RemoteSendError::NetworkTimeout)
+                | TransportError::Stream(StreamError::Timeout(_))
+        )
+    }
+}
+
+// `ping` with the trace context of the current span, if it is exported
+fn traced(ping: &Ping) -> Cow<'_, Ping> {
+    match telemetry::traceparent() {
+        Some(traceparent) => {
+            let mut ping = ping.clone();
+            ping.metadata.insert(TRACEPARENT.to_string(), traceparent);
+            Cow::Owned(ping)
+        }
+        None => Cow::Borrowed(ping),
+    }
+}
+
+impl Transport {
+    pub fn targets(&self) -> usize {
+        match self {
+            Transport::Libp2p(actors) => actors.len(),
+            Transport::WebSocket(_) | Transport::Tcp(_) => 1,
+        }
+    }
+
+    pub async fn ask(&self, target: usize, ping: &Ping) -> Result<Pong, TransportError> {
+        ping.validate().map_err(TransportError::Invalid)?;
+        let asked = async {
+            let ping = traced(ping);
+            let ping = ping.as_ref();
+            match self {
+                Transport::Libp2p(actors) => {
+                    actors[target].ask(ping).await.map(|reply| reply.0).map_err(TransportError::Libp2p)
+                }
+                Transport::WebSocket(client) => client.ask(ping).await.map_err(TransportError::Stream),
+                Transport::Tcp(client) => client.ask(ping).await.map_err(TransportError::Stream),
+            }
+        };
+        asked.instrument(info_span!("ping", sequence = ping.sequence, target)).await
+    }
+
+    /// Send `ping` without waiting for a reply
+    pub async fn tell(&self, target: usize, ping: &Ping) -> Result<(), TransportError> {
+        ping.validate().map_err(TransportError::Invalid)?;
+        let told = async {
+            let ping = traced(ping);
+            let ping = ping.as_ref();
+            match self {
+                Transport::Libp2p(actors) => {
+                    let sent = actors[target].tell(ping).send().map_err(TransportError::Libp2p);
+                    // `send` only queues the ping for the swarm task; give it a
+                    // chance to run before queueing the next one
+                    tokio::task::yield_now().await;
+                    sent
+                }
+                Transport::WebSocket(client) => client.tell(ping).await.map_err(TransportError::Stream),
+                Transport::Tcp(client) => client.tell(ping).await.map_err(TransportError::Stream),
+            }
+        };
+        told.instrument(info_span!("ping", sequence = ping.sequence, target)).await
+    }
+
+    /// Send `count` pings, in turn to each target, whose results are thrown
+    /// away, so connection setup and a cold actor don't skew the measured run
+    pub async fn warmup(&self, count: u64) {
+        if count == 0 {
+            return;
+        }
+        info!("Warming up with {} ping(s)...", count);
+        let mut failed = 0;
+        for sequence in 1..=count {
+            let ping = Ping::builder().message(format!("Warmup ping #{}", sequence)).sequence(sequence).build();
+            if self.ask(sequence as usize % self.targets(), &ping).await.is_err() {
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            warn!("{} of {} warmup ping(s) failed", failed, count);
+        }
+    }
+
+    /// A handle for another concurrent pipeline: libp2p multiplexes asks over
+    /// the existing connection, while each WebSocket or TCP pipeline gets its
+    /// own connection since a connection carries one request at a time
+    pub async fn fork(&self) -> Result<Transport, TransportError> {
+        match self {
+            Transport::Libp2p(actors) => Ok(Transport::Libp2p(actors.clone())),
+            Transport::WebSocket(client) => {
+                client.reopen().await.map(|client| Transport::WebSocket(Box::new(client))).map_err(TransportError::Stream)
+            }
+            Transport::Tcp(client) => {
+                client.reopen().await.map(|client| Transport::Tcp(Box::new(client))).map_err(TransportError::Stream)
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// Peer access control
+//
+// `--deny-peer` refuses the listed peers, and `--allow-peer` refuses every
+// peer that isn't listed. kameo hands remote messages to the actors without
+// saying which peer sent them, so the decision is made per connection while
+// it is being set up: a peer that may not ask the actors never gets a
+// connection to ask them over. Every refusal is logged and counted.
+
+use libp2p::allow_block_list::{self, AllowedPeers, Blocked, BlockedPeers, NotAllowed};
+use libp2p::swarm::{behaviour::toggle::Toggle, ListenError};
+use libp2p::PeerId;
+
+/// Refuses every peer not in `peers`; does nothing when the list is empty
+pub fn allowlist(peers: &[PeerId]) -> Toggle<allow_block_list::Behaviour<AllowedPeers>> {
+    if peers.is_empty() {
+        return None.into();
+    }
+    let mut behaviour = allow_block_list::Behaviour::<AllowedPeers>::default();
+    for peer in peers {
+        behaviour.allow_peer(*peer);
+    }
+    Some(behaviour).into()
+}
+
+/// Refuses the peers in `peers`
+pub fn denylist(peers: &[PeerId]) -> allow_block_list::Behaviour<BlockedPeers> {
+    let mut behaviour = allow_block_list::Behaviour::<BlockedPeers>::default();
+    for peer in peers {
+        behaviour.block_peer(*peer);
+    }
+    behaviour
+}
+
+/// Incoming connections refused so far
+#[derive(Default)]
+pub struct Refusals {
+    /// Peers on the denylist
+    pub denied: u64,
+    /// Peers missing from the allowlist
+    pub not_allowed: u64,
+}
+
+impl Refusals {
+    /// Count `error` if it is a refusal, returning the reason for it
+    pub fn record(&mut self, error: &ListenError) -> Option<&'static str> {
+        let ListenError::Denied { cause } = error else {
+            return None;
+        };
+        if cause.downcast_ref::<Blocked>().is_some() {
+            self.denied += 1;
+            Some("denied")
+        } else if cause.downcast_ref::<NotAllowed>().is_some() {
+            self.not_allowed += 1;
+            Some("not allowed")
+        } else {
+            None
+        }
+    }
+}
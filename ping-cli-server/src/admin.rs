@@ -0,0 +1,178 @@
+// Admin socket
+//
+// With `--admin-socket <path>` the server listens on a Unix socket for
+// one-line commands, so an operator on the same machine can look into a
+// running server without a libp2p client:
+//
+//   stats     the PingActor's counter, the number of connected peers and
+//             when the actors were last registered in the DHT
+//   peers     each connected peer, how long it has been quiet, the replies
+//             it got and the bytes exchanged with it
+//   reset     reset the PingActor's counter, printing the old value
+//   shutdown  shut the server down gracefully
+//
+// e.g. `echo stats | socat - UNIX-CONNECT:/run/ping.sock`. The socket is
+// created owner-only. Peer questions and shutdown go to the event loop,
+// which owns that state, as `Request`s. Off Unix there is no socket and so no
+// requests.
+
+use libp2p::PeerId;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+#[cfg_attr(not(unix), allow(dead_code))]
+pub enum Request {
+    /// Connected peers and how long each has been quiet
+    Peers(oneshot::Sender<Vec<(PeerId, Duration)>>),
+    Shutdown,
+}
+
+#[cfg(unix)]
+pub use socket::serve;
+
+#[cfg(unix)]
+mod socket {
+    use super::Request;
+    use crate::metrics::Metrics;
+    use kameo::prelude::*;
+    use libp2p::PeerId;
+    use ping_common::{GetPeerStats, PeerStats, PingActor, PingActorClient, StatsActor};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::{mpsc, oneshot};
+    use tracing::{error, info, warn};
+
+    // Accept admin connections on `path` until the process exits
+    pub async fn serve(
+        path: PathBuf,
+        ping_actor: ActorRef<PingActor>,
+        stats_actor: ActorRef<StatsActor>,
+        metrics: Arc<Metrics>,
+        requests: mpsc::Sender<Request>,
+    ) {
+        // A socket left behind by an earlier run would make the bind fail
+        let _ = fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind the admin socket {}: {}", path.display(), e);
+                return;
+            }
+        };
+        if let Err(e) = fs::set_permissions(&path, fs::Permissions::from_mode(0o600)) {
+            warn!("Failed to restrict the admin socket: {}", e);
+        }
+        info!("Admin socket on {}", path.display());
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(session(stream, ping_actor.clone(), stats_actor.clone(), metrics.clone(), requests.clone()));
+                }
+                Err(e) => warn!("Admin socket accept failed: {}", e),
+            }
+        }
+    }
+
+    // Answer commands on one admin connection until it closes
+    async fn session(
+        stream: UnixStream,
+        ping_actor: ActorRef<PingActor>,
+        stats_actor: ActorRef<StatsActor>,
+        metrics: Arc<Metrics>,
+        requests: mpsc::Sender<Request>,
+    ) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let command = line.trim();
+            if command.is_empty() {
+                continue;
+            }
+            info!("Admin command: {}", command);
+            let reply = execute(command, &ping_actor, &stats_actor, &metrics, &requests).await;
+            if writer.write_all(reply.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn execute(
+        command: &str,
+        ping_actor: &ActorRef<PingActor>,
+        stats_actor: &ActorRef<StatsActor>,
+        metrics: &Metrics,
+        requests: &mpsc::Sender<Request>,
+    ) -> String {
+        match command {
+            "stats" => {
+                let total_pings = match PingActorClient::new(ping_actor).get_stats().await {
+                    Ok(stats) => stats.total_pings.to_string(),
+                    Err(e) => return format!("error: {}\n", e),
+                };
+                let refreshed = match metrics.registry_refreshed.get() {
+                    0 => "never".to_string(),
+                    secs => {
+                        let at = UNIX_EPOCH + Duration::from_secs(secs as u64);
+                        humantime::format_rfc3339_seconds(at).to_string()
+                    }
+                };
+                match peers(requests).await {
+                    Some(peers) => format!(
+                        "total_pings {}\nconnected_peers {}\nregistry_refreshed {}\n",
+                        total_pings,
+                        peers.len(),
+                        refreshed
+                    ),
+                    None => "error: server is shutting down\n".to_string(),
+                }
+            }
+            "peers" => match peers(requests).await {
+                Some(peers) if peers.is_empty() => "no peers connected\n".to_string(),
+                Some(peers) => {
+                    let stats = stats_actor.ask(GetPeerStats).await.map(|r| r.0).unwrap_or_default();
+                    peers
+                        .iter()
+                        .map(|(peer, idle)| {
+                            let peer = peer.to_string();
+                            let stats = stats.iter().find(|s| s.peer == peer).cloned().unwrap_or_default();
+                            format_peer(&peer, *idle, &stats)
+                        })
+                        .collect()
+                }
+                None => "error: server is shutting down\n".to_string(),
+            },
+            "reset" => match PingActorClient::new(ping_actor).reset_stats().await {
+                Ok(previous) => format!("reset, total_pings was {}\n", previous.total_pings),
+                Err(e) => format!("error: {}\n", e),
+            },
+            "shutdown" => {
+                let _ = requests.send(Request::Shutdown).await;
+                "shutting down\n".to_string()
+            }
+            other => format!("error: unknown command '{}' (stats, peers, reset, shutdown)\n", other),
+        }
+    }
+
+    fn format_peer(peer: &str, idle: Duration, stats: &PeerStats) -> String {
+        let handling = match stats.avg_handling_us {
+            Some(us) => format!(" avg handling {:?}", Duration::from_micros(us)),
+            None => String::new(),
+        };
+        format!(
+            "{} idle {:?} requests {} received {}B sent {}B{}\n",
+            peer, idle, stats.requests, stats.bytes_received, stats.bytes_sent, handling
+        )
+    }
+
+    async fn peers(requests: &mpsc::Sender<Request>) -> Option<Vec<(PeerId, Duration)>> {
+        let (reply, peers) = oneshot::channel();
+        requests.send(Request::Peers(reply)).await.ok()?;
+        peers.await.ok()
+    }
+}
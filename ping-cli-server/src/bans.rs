@@ -0,0 +1,160 @@
+// Misbehaving peers
+//
+// Data from a peer that fails to decrypt or decode, whether it breaks the
+// connection or a single messaging stream, counts as a strike against it.
+// Connections that merely drop, and requests that time out, don't: they
+// happen to well-behaved peers too. (Requests the codec can't read at all,
+// e.g. ones over --max-message-bytes, are dropped inside libp2p without
+// naming the peer, so they can't be counted.) Strikes decay: each one is
+// forgotten after STRIKE_DECAY, so only a peer that keeps at it reaches the
+// threshold. It is then banned for a while through the denylist, which
+// closes its connections and refuses new ones like a denied peer's until
+// the ban is lifted.
+
+use libp2p::{request_response::InboundFailure, swarm::ConnectionError, PeerId};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How long a strike counts towards the threshold
+const STRIKE_DECAY: Duration = Duration::from_secs(60);
+
+pub struct BanList {
+    threshold: usize,
+    duration: Duration,
+    strikes: HashMap<PeerId, VecDeque<Instant>>,
+    banned: HashMap<PeerId, Instant>,
+}
+
+impl BanList {
+    /// Ban peers reaching `threshold` strikes for `duration`; a threshold of
+    /// 0 never bans anyone
+    pub fn new(threshold: usize, duration: Duration) -> Self {
+        Self { threshold, duration, strikes: HashMap::new(), banned: HashMap::new() }
+    }
+
+    /// Count a strike against `peer` at `now`; returns the strikes it has so
+    /// far and whether that got it banned
+    pub fn strike(&mut self, peer: PeerId, now: Instant) -> (usize, bool) {
+        let strikes = self.strikes.entry(peer).or_default();
+        strikes.retain(|at| now.duration_since(*at) < STRIKE_DECAY);
+        strikes.push_back(now);
+        let count = strikes.len();
+        if self.threshold == 0 || count < self.threshold || self.banned.contains_key(&peer) {
+            return (count, false);
+        }
+        self.strikes.remove(&peer);
+        self.banned.insert(peer, now + self.duration);
+        (count, true)
+    }
+
+    /// Remove and return the peers whose ban is over at `now`, and forget
+    /// strikes that have decayed
+    pub fn expired(&mut self, now: Instant) -> Vec<PeerId> {
+        self.strikes.retain(|_, strikes| {
+            strikes.retain(|at| now.duration_since(*at) < STRIKE_DECAY);
+            !strikes.is_empty()
+        });
+        let expired: Vec<PeerId> =
+            self.banned.iter().filter(|(_, &until)| until <= now).map(|(&peer, _)| peer).collect();
+        for peer in &expired {
+            self.banned.remove(peer);
+        }
+        expired
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn len(&self) -> usize {
+        self.banned.len()
+    }
+}
+
+/// Whether a failed inbound request was the peer's fault
+pub fn bad_request(error: &InboundFailure) -> bool {
+    match error {
+        InboundFailure::Io(e) => e.kind() == io::ErrorKind::InvalidData,
+        InboundFailure::UnsupportedProtocols => true,
+        _ => false,
+    }
+}
+
+/// Whether a connection was closed because of the peer's data
+pub fn bad_connection(cause: &ConnectionError) -> bool {
+    matches!(cause, ConnectionError::IO(e) if e.kind() == io::ErrorKind::InvalidData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAN: Duration = Duration::from_secs(300);
+
+    #[test]
+    fn bans_at_the_threshold() {
+        let mut bans = BanList::new(3, BAN);
+        let peer = PeerId::random();
+        let now = Instant::now();
+        assert_eq!(bans.strike(peer, now), (1, false));
+        assert_eq!(bans.strike(peer, now), (2, false));
+        assert_eq!(bans.strike(peer, now), (3, true));
+        assert_eq!(bans.len(), 1);
+    }
+
+    #[test]
+    fn strikes_decay() {
+        let mut bans = BanList::new(3, BAN);
+        let peer = PeerId::random();
+        let start = Instant::now();
+        bans.strike(peer, start);
+        bans.strike(peer, start + Duration::from_secs(30));
+        // The first strike is forgotten by now, the second still counts
+        assert_eq!(bans.strike(peer, start + STRIKE_DECAY), (2, false));
+        assert_eq!(bans.strike(peer, start + STRIKE_DECAY), (3, true));
+    }
+
+    #[test]
+    fn threshold_zero_never_bans() {
+        let mut bans = BanList::new(0, BAN);
+        let peer = PeerId::random();
+        let now = Instant::now();
+        for n in 1..=10 {
+            assert_eq!(bans.strike(peer, now), (n, false));
+        }
+        assert_eq!(bans.len(), 0);
+    }
+
+    #[test]
+    fn banned_peer_is_not_banned_again() {
+        let mut bans = BanList::new(1, BAN);
+        let peer = PeerId::random();
+        let now = Instant::now();
+        assert_eq!(bans.strike(peer, now), (1, true));
+        assert_eq!(bans.strike(peer, now + Duration::from_secs(1)), (1, false));
+        assert_eq!(bans.len(), 1);
+        // The ban still ends when the first one said
+        assert!(bans.expired(now + BAN - Duration::from_secs(1)).is_empty());
+        assert_eq!(bans.expired(now + BAN), vec![peer]);
+    }
+
+    #[test]
+    fn expired_lifts_bans_and_forgets_decayed_strikes() {
+        let mut bans = BanList::new(2, BAN);
+        let (banned, struck) = (PeerId::random(), PeerId::random());
+        let start = Instant::now();
+        bans.strike(banned, start);
+        bans.strike(banned, start);
+        bans.strike(struck, start);
+
+        assert!(bans.expired(start + STRIKE_DECAY).is_empty());
+        assert!(bans.strikes.is_empty());
+        // The decayed strike no longer counts towards a ban
+        assert_eq!(bans.strike(struck, start + STRIKE_DECAY), (1, false));
+
+        assert_eq!(bans.expired(start + BAN), vec![banned]);
+        assert_eq!(bans.len(), 0);
+        assert!(bans.expired(start + BAN).is_empty());
+    }
+}
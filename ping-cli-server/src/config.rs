@@ -0,0 +1,112 @@
+// Configuration file
+//
+// `--config server.toml` holds the settings a deployment would otherwise
+// spell out as flags. Every key is optional; a flag given on the command
+// line wins over the file, and the file wins over the built-in default.
+// Unknown keys are rejected so a typo doesn't go unnoticed.
+
+use ping_common::log_file::Rotation;
+use ping_common::logging::LogFormat;
+use libp2p::{Multiaddr, PeerId};
+use serde::{de, Deserialize, Deserializer};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// "text" or "json"
+    pub log_format: Option<LogFormat>,
+    /// Also write the logs here
+    pub log_file: Option<PathBuf>,
+    /// "minutely", "hourly", "daily" or "never"
+    pub log_rotation: Option<Rotation>,
+    /// Rotate the log file by size instead
+    pub log_max_bytes: Option<u64>,
+    /// Rotated log files to keep
+    pub log_keep: Option<usize>,
+    /// OpenTelemetry collector to export spans to (e.g. "http://localhost:4318")
+    pub otlp_endpoint: Option<String>,
+    /// File holding the process id while the server runs
+    pub pid_file: Option<PathBuf>,
+    /// Multiaddrs to listen on; when empty the server listens on `port`
+    #[serde(deserialize_with = "parsed")]
+    pub listen: Vec<Multiaddr>,
+    /// TCP port on all interfaces, 0 for a random one
+    pub port: Option<u16>,
+    /// Keypair file for a stable peer id
+    pub identity: Option<PathBuf>,
+    /// Registry name of the PingActor
+    pub actor_name: Option<String>,
+    /// Extra registry names for the same PingActor
+    pub aliases: Vec<String>,
+    /// How long a remote ask may take (e.g. "2m")
+    #[serde(deserialize_with = "duration")]
+    pub request_timeout: Option<Duration>,
+    /// How long an unused connection is kept open (e.g. "10m")
+    #[serde(deserialize_with = "duration")]
+    pub idle_timeout: Option<Duration>,
+    /// Largest message accepted, in bytes
+    pub max_message_bytes: Option<u64>,
+    /// Disable Nagle's algorithm on TCP connections
+    pub tcp_nodelay: Option<bool>,
+    /// How long a new connection may take over its handshakes (e.g. "10s")
+    #[serde(deserialize_with = "duration")]
+    pub handshake_timeout: Option<Duration>,
+    /// yamux receive window per substream, in bytes
+    pub yamux_window: Option<u32>,
+    /// yamux receive buffer per substream, in bytes
+    pub yamux_buffer: Option<usize>,
+    /// Most peers connected at once
+    pub max_peers: Option<usize>,
+    /// Disconnect peers quiet for this long (e.g. "5m")
+    #[serde(deserialize_with = "duration")]
+    pub peer_idle_timeout: Option<Duration>,
+    /// Port serving Prometheus metrics at /metrics
+    pub metrics_port: Option<u16>,
+    /// Unix socket taking admin commands
+    pub admin_socket: Option<PathBuf>,
+    /// Only these peers may connect, when not empty
+    #[serde(deserialize_with = "parsed")]
+    pub allow_peers: Vec<PeerId>,
+    /// These peers may not connect
+    #[serde(deserialize_with = "parsed")]
+    pub deny_peers: Vec<PeerId>,
+    /// How often the actors are registered again (e.g. "30m")
+    #[serde(deserialize_with = "duration")]
+    pub registry_refresh: Option<Duration>,
+    /// Decode failures within a minute that get a peer banned, 0 to never ban
+    pub ban_threshold: Option<usize>,
+    /// How long a ban lasts (e.g. "10m")
+    #[serde(deserialize_with = "duration")]
+    pub ban_duration: Option<Duration>,
+}
+
+pub fn load(path: &Path) -> io::Result<ConfigFile> {
+    let text = fs::read_to_string(path)?;
+    toml::from_str(&text).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+    })
+}
+
+// Durations are written the way the flags take them, e.g. "30s" or "2m"
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    humantime::parse_duration(&text).map(Some).map_err(de::Error::custom)
+}
+
+// Multiaddrs and peer ids are written as strings
+fn parsed<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|addr| addr.parse().map_err(de::Error::custom))
+        .collect()
+}
@@ -0,0 +1,41 @@
+// Daemon mode and pid file
+//
+// `--daemon` detaches the server from its terminal the classic way, for init
+// scripts that expect a server to background itself: fork, new session,
+// fork again, with stdin, stdout and stderr on /dev/null: its logs go to
+// `--log-file` only, or nowhere without one. The working directory is
+// kept, so relative paths in flags and the config file still work. It has
+// to happen before the tokio runtime starts any threads. Under systemd,
+// leave it off and use Type=notify instead.
+//
+// `--pid-file` records the server's process id, in the background or not,
+// and the file is removed again when the server exits.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Continue in a detached child process; the original process exits
+#[cfg(unix)]
+pub fn detach() -> io::Result<()> {
+    let daemon = daemonize::Daemonize::new().working_directory(std::env::current_dir()?);
+    daemon.start().map_err(io::Error::other)
+}
+
+/// Removes the pid file when dropped
+pub struct PidFile(PathBuf);
+
+impl PidFile {
+    pub fn create(path: PathBuf) -> io::Result<Self> {
+        fs::write(&path, format!("{}\n", std::process::id())).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+        })?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
@@ -1,42 +1,300 @@
 use kameo::prelude::*;
 use kameo::remote;
 use libp2p::{
-    noise, tcp, yamux,
-    swarm::{NetworkBehaviour, SwarmEvent},
+    allow_block_list::{self, AllowedPeers, BlockedPeers},
+    identity::Keypair,
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent},
+    Multiaddr, PeerId, Swarm, Transport,
 };
-use ping_common::PingActor;
-use std::time::Duration;
-use tracing::info;
-use tracing_subscriber::EnvFilter;
+use ping_common::identity;
+use ping_common::log_file::{LogFile, Rotation};
+use ping_common::logging::{self, LogFormat};
+use ping_common::tuning::{Tuning, MIN_YAMUX_WINDOW};
+use ping_common::{CounterActor, EchoActor, PeerActivity, PeerGone, PingActor, StatsActor};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 use futures::StreamExt;
+use clap::Parser;
 
-// Custom network behavior wrapping Kameo's remote messaging
+mod access;
+mod admin;
+mod bans;
+mod config;
+mod daemon;
+mod metrics;
+mod peers;
+mod shutdown;
+mod systemd;
+mod traffic;
+
+const DEFAULT_ACTOR_NAME: &str = "ping_actor";
+const ECHO_ACTOR_NAME: &str = "echo_actor";
+const COUNTER_ACTOR_NAME: &str = "counter_actor";
+const STATS_ACTOR_NAME: &str = "stats_actor";
+const DEFAULT_PORT: u16 = 36341;
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_REGISTRY_REFRESH: Duration = Duration::from_secs(3600);
+const DEFAULT_LOG_KEEP: usize = 7;
+const DEFAULT_BAN_THRESHOLD: usize = 5;
+const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(600);
+
+// How often peers are checked against --peer-idle-timeout and bans expire
+const IDLE_SWEEP: Duration = Duration::from_secs(1);
+
+// Largest Ping accepted by default, well above kameo's 1 MiB so clients can
+// test with large --payload-bytes
+const DEFAULT_MAX_MESSAGE_BYTES: u64 = 16 * 1024 * 1024;
+
+// Custom network behavior wrapping Kameo's remote messaging, behind the
+// peer access lists
 #[derive(NetworkBehaviour)]
 struct MyBehaviour {
+    allowed: Toggle<allow_block_list::Behaviour<AllowedPeers>>,
+    denied: allow_block_list::Behaviour<BlockedPeers>,
     kameo: remote::Behaviour,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
-        .init();
+// Command-line argument parser
+#[derive(Parser, Debug)]
+#[command(name = "ping-cli-server")]
+struct Args {
+    /// TOML file with any of the settings below; flags override it
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Log format: text, or json for one JSON object per line [default: text]
+    #[arg(long)]
+    log_format: Option<LogFormat>,
+
+    /// Detach from the terminal and run in the background (Unix only)
+    #[arg(long)]
+    daemon: bool,
+
+    /// Also write the logs to this file, rotated daily unless --log-rotation
+    /// or --log-max-bytes say otherwise; a --daemon's only log
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// When the log file starts afresh: minutely, hourly, daily or never
+    /// [default: daily]
+    #[arg(long)]
+    log_rotation: Option<Rotation>,
+
+    /// Rotate the log file once it reaches this many bytes instead
+    #[arg(long, conflicts_with = "log_rotation")]
+    log_max_bytes: Option<u64>,
+
+    /// Rotated log files to keep [default: 7]
+    #[arg(long)]
+    log_keep: Option<usize>,
+
+    /// Export tracing spans to the OpenTelemetry collector at this URL over
+    /// OTLP/HTTP (e.g. http://localhost:4318)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
+    /// Serve the runtime's tasks to tokio-console on 127.0.0.1:6669
+    /// (TOKIO_CONSOLE_BIND picks another address); needs a build with
+    /// `--cfg tokio_unstable` and the tokio-console feature
+    #[arg(long)]
+    tokio_console: bool,
+
+    /// Write the process id to this file, removed again on exit
+    #[arg(long)]
+    pid_file: Option<PathBuf>,
+
+    /// Registry name to publish the PingActor under [default: ping_actor]
+    #[arg(long)]
+    actor_name: Option<String>,
+
+    /// Extra registry name for the same PingActor, e.g. one unique to this
+    /// server so clients can pick it out; repeat it for several
+    #[arg(long)]
+    alias: Vec<String>,
+
+    /// TCP port to listen on, on all interfaces [default: 36341]
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    /// Let the OS pick a free port instead of --port
+    #[arg(long, conflicts_with = "port")]
+    random_port: bool,
+
+    /// Listen on this multiaddr instead (e.g. /ip4/127.0.0.1/tcp/4001);
+    /// repeat it for several
+    #[arg(long, conflicts_with_all = ["port", "random_port"])]
+    listen: Vec<Multiaddr>,
+
+    /// Keypair file giving the server a stable peer id (created if missing)
+    #[arg(long)]
+    identity: Option<PathBuf>,
+
+    /// How long a remote ask may take (e.g. 2m) [default: 120s]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    request_timeout: Option<Duration>,
+
+    /// How long an unused connection is kept open [default: 10m]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    idle_timeout: Option<Duration>,
+
+    /// Largest message accepted, in bytes [default: 16 MiB]
+    #[arg(long)]
+    max_message_bytes: Option<u64>,
+
+    /// Disable Nagle's algorithm on TCP connections [default: true]
+    #[arg(long)]
+    tcp_nodelay: Option<bool>,
+
+    /// How long a new connection may take over its noise and yamux
+    /// handshakes [default: 10s]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    handshake_timeout: Option<Duration>,
+
+    /// yamux receive window per substream, in bytes, at least 256 KiB
+    /// [default: yamux's own]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(MIN_YAMUX_WINDOW as i64..))]
+    yamux_window: Option<u32>,
+
+    /// yamux receive buffer per substream, in bytes [default: yamux's own]
+    #[arg(long)]
+    yamux_buffer: Option<usize>,
+
+    /// Most peers connected at once; beyond it the least recently active
+    /// peer is disconnected
+    #[arg(long)]
+    max_peers: Option<usize>,
+
+    /// Disconnect peers that haven't sent an actor a message for this long
+    /// (e.g. 5m)
+    #[arg(long, value_parser = humantime::parse_duration)]
+    peer_idle_timeout: Option<Duration>,
+
+    /// Serve Prometheus metrics at http://0.0.0.0:<port>/metrics
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    /// Unix socket taking admin commands (stats, peers, reset, shutdown)
+    #[arg(long)]
+    admin_socket: Option<PathBuf>,
+
+    /// Only accept connections from this peer; repeat it for several
+    #[arg(long)]
+    allow_peer: Vec<PeerId>,
+
+    /// Refuse connections from this peer; repeat it for several
+    #[arg(long)]
+    deny_peer: Vec<PeerId>,
+
+    /// Register the actors again this often, so the DHT records don't
+    /// expire on a long-running server (e.g. 30m) [default: 1h]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    registry_refresh: Option<Duration>,
+
+    /// Ban a peer whose data fails to decode this many times within a
+    /// minute; 0 never bans [default: 5]
+    #[arg(long)]
+    ban_threshold: Option<usize>,
+
+    /// How long a ban lasts (e.g. 1h) [default: 10m]
+    #[arg(long, value_parser = humantime::parse_duration)]
+    ban_duration: Option<Duration>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    // Flags win over the config file, which wins over the defaults
+    let file = match &args.config {
+        Some(path) => config::load(path)?,
+        None => config::ConfigFile::default(),
+    };
+
+    // Detach before the runtime starts any threads
+    if args.daemon {
+        #[cfg(unix)]
+        daemon::detach()?;
+        #[cfg(not(unix))]
+        return Err("--daemon is only available on Unix".into());
+    }
+    let _pid_file = args.pid_file.clone().or(file.pid_file.clone()).map(daemon::PidFile::create).transpose()?;
+
+    tokio::runtime::Runtime::new()?.block_on(run(args, file))
+}
+
+async fn run(args: Args, file: config::ConfigFile) -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging, without colours when detached, and the log file,
+    // span export and tokio-console if configured. A --log-rotation flag
+    // overrides a log_max_bytes in the file. The peer an event is about is a
+    // field of its own (`peer`); kameo decodes and encodes remote messages
+    // inside its swarm, so the spans exported are the PingActor's `handle`
+    // span of each ping.
+    let otlp_endpoint = args.otlp_endpoint.clone().or(file.otlp_endpoint.clone());
+    let log_file = args.log_file.clone().or(file.log_file.clone()).map(|path| LogFile {
+        path,
+        rotation: args.log_rotation.or(file.log_rotation).unwrap_or_default(),
+        max_bytes: args.log_max_bytes.or(file.log_max_bytes.filter(|_| args.log_rotation.is_none())),
+        keep: args.log_keep.or(file.log_keep).unwrap_or(DEFAULT_LOG_KEEP),
+    });
+    let _logging = logging::init(
+        "ping-cli-server",
+        args.log_format.or(file.log_format).unwrap_or_default(),
+        !args.daemon,
+        otlp_endpoint.as_deref(),
+        log_file.as_ref(),
+        args.tokio_console,
+    )?;
 
     info!("Starting CLI Ping Server...");
+    let request_timeout = args.request_timeout.or(file.request_timeout).unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    let idle_timeout = args.idle_timeout.or(file.idle_timeout).unwrap_or(DEFAULT_IDLE_TIMEOUT);
+    let max_message_bytes = args.max_message_bytes.or(file.max_message_bytes).unwrap_or(DEFAULT_MAX_MESSAGE_BYTES);
+    let allow_peers = if args.allow_peer.is_empty() { file.allow_peers } else { args.allow_peer };
+    let deny_peers = if args.deny_peer.is_empty() { file.deny_peers } else { args.deny_peer };
+    let tuning = Tuning {
+        nodelay: args.tcp_nodelay.or(file.tcp_nodelay).unwrap_or(true),
+        handshake_timeout: args.handshake_timeout.or(file.handshake_timeout).unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT),
+        yamux_window: args.yamux_window.or(file.yamux_window),
+        yamux_buffer: args.yamux_buffer.or(file.yamux_buffer),
+    };
+    if tuning.yamux_window.is_some_and(|window| window < MIN_YAMUX_WINDOW) {
+        return Err(format!("yamux_window must be at least {} bytes", MIN_YAMUX_WINDOW).into());
+    }
+    let keypair = match args.identity.or(file.identity) {
+        Some(path) => identity::load_or_create(&path)?,
+        None => Keypair::generate_ed25519(),
+    };
 
-    // Build libp2p swarm with TCP transport and Kameo behavior
-    let mut swarm = libp2p::SwarmBuilder::with_new_identity()
+    // Build libp2p swarm with TCP transport and Kameo behavior. The transport
+    // is put together by hand, to apply the tuning and count each peer's
+    // traffic
+    let traffic = traffic::Traffic::default();
+    let mut swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
-        .with_tcp(tcp::Config::default(), noise::Config::new, || yamux::Config::default())?
+        .with_other_transport(|key| {
+            let traffic = traffic.clone();
+            let transport = tuning.transport(key)?;
+            Ok(Transport::map(transport, move |(peer, muxer), _| (peer, traffic.counted(peer, muxer))))
+        })?
         .with_behaviour(|key| {
             let peer_id = key.public().to_peer_id();
             let messaging_config = remote::messaging::Config::default()
-                .with_request_timeout(Duration::from_secs(120));
+                .with_request_timeout(request_timeout)
+                .with_request_size_maximum(max_message_bytes)
+                .with_response_size_maximum(max_message_bytes);
             let kameo = remote::Behaviour::new(peer_id, messaging_config);
-            Ok(MyBehaviour { kameo })
+            Ok(MyBehaviour {
+                allowed: access::allowlist(&allow_peers),
+                denied: access::denylist(&deny_peers),
+                kameo,
+            })
         })?
-        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(600)))
+        .with_swarm_config(|c| c.with_idle_connection_timeout(idle_timeout))
         .build();
 
     // Initialize Kameo's global actor registry
@@ -45,19 +303,106 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let peer_id = *swarm.local_peer_id();
     info!("Server Peer ID: {}", peer_id);
 
-    // Listen on TCP port 36341
-    swarm.listen_on("/ip4/0.0.0.0/tcp/36341".parse()?)?;
+    // Listen on the given addresses, or on all interfaces at the port. Any
+    // listen flag replaces the file's addresses as a whole
+    let port = if args.random_port { Some(0) } else { args.port };
+    let listen = if !args.listen.is_empty() {
+        args.listen
+    } else if port.is_none() && !file.listen.is_empty() {
+        file.listen
+    } else {
+        let port = port.or(file.port).unwrap_or(DEFAULT_PORT);
+        vec![format!("/ip4/0.0.0.0/tcp/{}", port).parse()?]
+    };
+    for addr in listen {
+        swarm.listen_on(addr)?;
+    }
 
-    // Spawn and register the PingActor in the distributed registry
-    tokio::spawn(async move {
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        let ping_actor = PingActor::spawn(PingActor { ping_count: 0 });
-        match ping_actor.register("ping_actor").await {
-            Ok(_) => info!("PingActor registered successfully"),
-            Err(e) => info!("Failed to register PingActor: {}", e),
+    // Spawn the PingActor and register it in the distributed registry, under
+    // its name and then each alias. The other actors share the same swarm and
+    // registry, each under a name of its own
+    let actor_name = args.actor_name.or(file.actor_name).unwrap_or_else(|| DEFAULT_ACTOR_NAME.to_string());
+    let aliases = if args.alias.is_empty() { file.aliases } else { args.alias };
+    let names: Vec<String> = std::iter::once(actor_name).chain(aliases).collect();
+    let ping_actor = PingActor::spawn(PingActor { ping_count: 0 });
+    let echo_actor = EchoActor::spawn(EchoActor { echo_count: 0 });
+    let counter_actor = CounterActor::spawn(CounterActor::default());
+    // Unbounded, so updates from the swarm loop never wait or get dropped
+    let stats_actor = StatsActor::spawn_with_mailbox(StatsActor::default(), kameo::mailbox::unbounded());
+    let metrics = Arc::new(metrics::Metrics::new());
+    if let Some(port) = args.metrics_port.or(file.metrics_port) {
+        tokio::spawn(metrics::serve(port, metrics.clone(), ping_actor.clone(), stats_actor.clone()));
+    }
+    // Admin requests that need the event loop's state come back over a channel
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    let (admin_tx, mut admin_rx) = tokio::sync::mpsc::channel(16);
+    let admin_socket = args.admin_socket.or(file.admin_socket);
+    if let Some(path) = &admin_socket {
+        #[cfg(unix)]
+        tokio::spawn(admin::serve(path.clone(), ping_actor.clone(), stats_actor.clone(), metrics.clone(), admin_tx));
+        #[cfg(not(unix))]
+        warn!("--admin-socket {} ignored: Unix sockets are not available here", path.display());
+    }
+
+    // Register once the swarm is being polled, then again every refresh
+    // interval so the records don't expire from the DHT
+    let registry_refresh = args.registry_refresh.or(file.registry_refresh).unwrap_or(DEFAULT_REGISTRY_REFRESH);
+    let (registered_tx, mut registered_rx) = tokio::sync::oneshot::channel();
+    let registration = tokio::spawn({
+        let (names, metrics) = (names.clone(), metrics.clone());
+        let (ping_actor, echo_actor, counter_actor, stats_actor) =
+            (ping_actor.clone(), echo_actor.clone(), counter_actor.clone(), stats_actor.clone());
+        async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut registered_tx = Some(registered_tx);
+            let mut refresh = tokio::time::interval(registry_refresh);
+            loop {
+                refresh.tick().await;
+                let mut all = true;
+                for name in &names {
+                    all &= register(&ping_actor, name).await;
+                }
+                all &= register(&echo_actor, ECHO_ACTOR_NAME).await;
+                all &= register(&counter_actor, COUNTER_ACTOR_NAME).await;
+                all &= register(&stats_actor, STATS_ACTOR_NAME).await;
+                match registered_tx.take() {
+                    Some(registered_tx) => {
+                        let _ = registered_tx.send(());
+                    }
+                    None => info!("Refreshed {} registration(s) in the DHT", names.len() + 3),
+                }
+                if all {
+                    metrics.registry_refreshed.set(unix_seconds());
+                }
+            }
         }
     });
 
+    if !allow_peers.is_empty() {
+        info!("Accepting only {} allowed peer(s)", allow_peers.len());
+    }
+    if !deny_peers.is_empty() {
+        info!("Refusing {} denied peer(s)", deny_peers.len());
+    }
+    let mut refusals = access::Refusals::default();
+    let mut peers = peers::PeerTracker::new(
+        args.max_peers.or(file.max_peers),
+        args.peer_idle_timeout.or(file.peer_idle_timeout),
+    );
+    let mut bans = bans::BanList::new(
+        args.ban_threshold.or(file.ban_threshold).unwrap_or(DEFAULT_BAN_THRESHOLD),
+        args.ban_duration.or(file.ban_duration).unwrap_or(DEFAULT_BAN_DURATION),
+    );
+    let mut idle_sweep = tokio::time::interval(IDLE_SWEEP);
+
+    // systemd hears about readiness once the server is both listening and
+    // registered, whichever comes last
+    let systemd = systemd::Notifier::from_env();
+    let (mut listening, mut registered) = (false, false);
+    let mut watchdog = tokio::time::interval(systemd.watchdog_interval().unwrap_or(IDLE_SWEEP));
+    let stop = shutdown::signal();
+    tokio::pin!(stop);
+
     info!("Waiting for connections...");
 
     // Main event loop - handle swarm events
@@ -66,26 +411,164 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             event = swarm.select_next_some() => {
                 match event {
                     SwarmEvent::Behaviour(MyBehaviourEvent::Kameo(event)) => {
+                        match &event {
+                            remote::Event::Messaging(remote::messaging::Event::ResponseSent { peer, .. }) => {
+                                peers.active(*peer);
+                                metrics.response_sent(peer);
+                                let (bytes_received, bytes_sent) = traffic.totals(peer);
+                                let _ = stats_actor
+                                    .tell(PeerActivity { peer: peer.to_string(), bytes_received, bytes_sent, handled_in: None })
+                                    .try_send();
+                            }
+                            remote::Event::Messaging(remote::messaging::Event::InboundFailure { peer, error, .. })
+                                if bans::bad_request(error) =>
+                            {
+                                strike(&mut swarm, &mut bans, &metrics, *peer, error);
+                            }
+                            _ => {}
+                        }
                         info!("Kameo event: {:?}", event);
                     }
                     SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
-                        info!("Client connected: {}", peer_id);
+                        info!(peer = %peer_id, "Client connected");
                         let remote_addr = endpoint.get_remote_address().clone();
                         swarm.add_peer_address(peer_id, remote_addr);
+                        metrics.connections.inc();
+                        if let Some(evicted) = peers.connected(peer_id) {
+                            warn!(peer = %evicted, "Peer limit reached, disconnecting the least recently active peer");
+                            metrics.evicted.get_or_create(&ping_metrics::ReasonLabels { reason: "limit" }).inc();
+                            let _ = swarm.disconnect_peer_id(evicted);
+                        }
+                        metrics.connected_peers.set(peers.len() as i64);
+                    }
+                    SwarmEvent::ConnectionClosed { peer_id, num_established, cause, .. } => {
+                        if let Some(cause) = cause.filter(bans::bad_connection) {
+                            strike(&mut swarm, &mut bans, &metrics, peer_id, &cause);
+                        }
+                        if num_established == 0 {
+                            peers.disconnected(peer_id);
+                            traffic.forget(&peer_id);
+                            let _ = stats_actor.tell(PeerGone { peer: peer_id.to_string() }).try_send();
+                            metrics.peer_gone(&peer_id);
+                            metrics.connected_peers.set(peers.len() as i64);
+                            info!(peer = %peer_id, connected = peers.len(), "Client disconnected");
+                        }
+                    }
+                    SwarmEvent::IncomingConnectionError { peer_id, error, send_back_addr, .. } => {
+                        if let Some(reason) = refusals.record(&error) {
+                            metrics.refused.get_or_create(&ping_metrics::ReasonLabels { reason }).inc();
+                            warn!(
+                                peer = peer_id.map(tracing::field::display),
+                                address = %send_back_addr,
+                                reason,
+                                denied = refusals.denied,
+                                not_allowed = refusals.not_allowed,
+                                "Refused connection"
+                            );
+                        }
                     }
                     SwarmEvent::NewListenAddr { address, .. } => {
                         info!("Listening on {}", address);
-                        info!("Connection string: /ip4/.../tcp/36341/p2p/{}", peer_id);
+                        info!("Connection string: {}/p2p/{}", address, peer_id);
+                        if !listening && registered {
+                            systemd.ready();
+                        }
+                        listening = true;
                     }
                     _ => {}
                 }
             }
-            _ = tokio::signal::ctrl_c() => {
+            _ = idle_sweep.tick() => {
+                for peer in peers.evict_idle() {
+                    info!(%peer, "Disconnecting idle peer");
+                    metrics.evicted.get_or_create(&ping_metrics::ReasonLabels { reason: "idle" }).inc();
+                    let _ = swarm.disconnect_peer_id(peer);
+                }
+                for peer in bans.expired(Instant::now()) {
+                    info!(%peer, "Ban lifted");
+                    swarm.behaviour_mut().denied.unblock_peer(peer);
+                    metrics.banned_peers.set(bans.len() as i64);
+                }
+            }
+            _ = &mut registered_rx, if !registered => {
+                if listening {
+                    systemd.ready();
+                }
+                registered = true;
+            }
+            _ = watchdog.tick(), if systemd.watchdog_interval().is_some() => systemd.watchdog(),
+            Some(request) = admin_rx.recv() => match request {
+                admin::Request::Peers(reply) => {
+                    let _ = reply.send(peers.idle_times());
+                }
+                admin::Request::Shutdown => {
+                    info!("Shutting down on admin request...");
+                    systemd.stopping();
+                    break;
+                }
+            },
+            _ = &mut stop => {
                 info!("Shutting down...");
+                systemd.stopping();
                 break;
             }
         }
     }
 
+    let all_names: Vec<String> = names
+        .into_iter()
+        .chain([ECHO_ACTOR_NAME, COUNTER_ACTOR_NAME, STATS_ACTOR_NAME].map(String::from))
+        .collect();
+    let drain = async {
+        shutdown::drain(&ping_actor).await;
+        shutdown::drain(&echo_actor).await;
+        shutdown::drain(&counter_actor).await;
+        shutdown::drain(&stats_actor).await;
+    };
+    // No re-registering what shutdown unregisters
+    registration.abort();
+    shutdown::run(&mut swarm, &all_names, drain).await;
+    if let Some(path) = admin_socket {
+        let _ = std::fs::remove_file(path);
+    }
+    info!("Server stopped");
+
     Ok(())
-}
\ No newline at end of file
+}
+
+// Publish `actor` in the registry as `name`; returns whether that worked
+async fn register<A: Actor + RemoteActor>(actor: &ActorRef<A>, name: &str) -> bool {
+    match actor.register(name).await {
+        Ok(_) => {
+            info!("{} registered as '{}'", A::name(), name);
+            true
+        }
+        Err(e) => {
+            info!("Failed to register {} as '{}': {}", A::name(), name, e);
+            false
+        }
+    }
+}
+
+fn unix_seconds() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+// Count a strike against `peer`, banning it once it reaches the threshold
+fn strike(
+    swarm: &mut Swarm<MyBehaviour>,
+    bans: &mut bans::BanList,
+    metrics: &metrics::Metrics,
+    peer: PeerId,
+    error: &dyn Display,
+) {
+    let (strikes, banned) = bans.strike(peer, Instant::now());
+    warn!(%peer, strikes, %error, "Misbehaving peer");
+    if banned {
+        warn!(%peer, duration = %humantime::format_duration(bans.duration()), "Banning peer");
+        // Blocking the peer also closes its connections
+        swarm.behaviour_mut().denied.block_peer(peer);
+        metrics.evicted.get_or_create(&ping_metrics::ReasonLabels { reason: "ban" }).inc();
+        metrics.banned_peers.set(bans.len() as i64);
+    }
+}
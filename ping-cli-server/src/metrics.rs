@@ -0,0 +1,130 @@
+// Prometheus metrics
+//
+// With `--metrics-port` a small HTTP listener serves /metrics in the
+// Prometheus text format: connection, peer and ban counts from the swarm loop,
+// replies sent to and bytes exchanged with each connected peer (dropped when
+// the peer leaves, so the series don't pile up), and the PingActor's own
+// counter, read from the actor on every scrape like the byte counts are from
+// the StatsActor. The swarm loop updates the metrics whether or not they are
+// served. Names and labels come from ping-metrics, shared with the HTTP
+// server; peers are its clients, and libp2p their transport.
+
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use kameo::prelude::*;
+use libp2p::PeerId;
+use ping_common::{GetPeerStats, PingActor, PingActorClient, StatsActor};
+use ping_metrics::prometheus_client::encoding::text::encode;
+use ping_metrics::prometheus_client::metrics::{counter::Counter, family::Family, gauge::Gauge};
+use ping_metrics::prometheus_client::registry::Registry;
+use ping_metrics::{ClientLabels, ReasonLabels, Transport, TransportLabels};
+use std::sync::Arc;
+use tracing::{error, info};
+
+pub struct Metrics {
+    registry: Registry,
+    pub connected_peers: Gauge,
+    pub banned_peers: Gauge,
+    pub connections: Counter,
+    pub refused: Family<ReasonLabels, Counter>,
+    pub evicted: Family<ReasonLabels, Counter>,
+    pub responses: Family<ClientLabels, Counter>,
+    received_bytes: Family<ClientLabels, Counter>,
+    sent_bytes: Family<ClientLabels, Counter>,
+    /// Unix time of the last registration that went through, 0 before it
+    pub registry_refreshed: Gauge,
+    actor_pings: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = ping_metrics::registry("ping-cli-server");
+        let libp2p = TransportLabels { transport: Transport::Libp2p };
+        let connected_peers = ping_metrics::CONNECTED_CLIENTS
+            .register::<Family<TransportLabels, Gauge>>(&mut registry)
+            .get_or_create(&libp2p)
+            .clone();
+        let banned_peers = ping_metrics::BANNED_CLIENTS.register(&mut registry);
+        let connections = ping_metrics::CONNECTIONS
+            .register::<Family<TransportLabels, Counter>>(&mut registry)
+            .get_or_create(&libp2p)
+            .clone();
+        let refused = ping_metrics::REFUSED_CONNECTIONS.register(&mut registry);
+        let evicted = ping_metrics::EVICTED_CLIENTS.register(&mut registry);
+        let responses = ping_metrics::CLIENT_REQUESTS.register(&mut registry);
+        let received_bytes = ping_metrics::RECEIVED_BYTES.register(&mut registry);
+        let sent_bytes = ping_metrics::SENT_BYTES.register(&mut registry);
+        let registry_refreshed = ping_metrics::REGISTRY_REFRESHED.register(&mut registry);
+        let actor_pings = ping_metrics::ACTOR_PINGS.register(&mut registry);
+        Self {
+            registry,
+            connected_peers,
+            banned_peers,
+            connections,
+            refused,
+            evicted,
+            responses,
+            received_bytes,
+            sent_bytes,
+            registry_refreshed,
+            actor_pings,
+        }
+    }
+
+    pub fn response_sent(&self, peer: &PeerId) {
+        self.responses.get_or_create(&ClientLabels { client: peer.to_string() }).inc();
+    }
+
+    pub fn peer_gone(&self, peer: &PeerId) {
+        self.responses.remove(&ClientLabels { client: peer.to_string() });
+    }
+}
+
+#[derive(Clone)]
+struct MetricsState {
+    metrics: Arc<Metrics>,
+    ping_actor: ActorRef<PingActor>,
+    stats_actor: ActorRef<StatsActor>,
+}
+
+// Serve /metrics on `port` until the process exits
+pub async fn serve(
+    port: u16,
+    metrics: Arc<Metrics>,
+    ping_actor: ActorRef<PingActor>,
+    stats_actor: ActorRef<StatsActor>,
+) {
+    let app = Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(MetricsState { metrics, ping_actor, stats_actor });
+    let listener = match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind the metrics port {}: {}", port, e);
+            return;
+        }
+    };
+    info!("Metrics on http://0.0.0.0:{}/metrics", port);
+    if let Err(e) = axum::serve(listener, app).await {
+        error!("Metrics server failed: {}", e);
+    }
+}
+
+async fn scrape(State(state): State<MetricsState>) -> impl IntoResponse {
+    if let Ok(stats) = PingActorClient::new(&state.ping_actor).get_stats().await {
+        state.metrics.actor_pings.set(stats.total_pings as i64);
+    }
+    // The byte totals are rebuilt from the StatsActor on every scrape
+    if let Ok(peers) = state.stats_actor.ask(GetPeerStats).await {
+        let metrics = &state.metrics;
+        metrics.received_bytes.clear();
+        metrics.sent_bytes.clear();
+        for stats in peers.0 {
+            let label = ClientLabels { client: stats.peer };
+            metrics.received_bytes.get_or_create(&label).inc_by(stats.bytes_received);
+            metrics.sent_bytes.get_or_create(&label).inc_by(stats.bytes_sent);
+        }
+    }
+    let mut body = String::new();
+    let _ = encode(&mut body, &state.metrics.registry);
+    ([(header::CONTENT_TYPE, "application/openmetrics-text; version=1.0.0; charset=utf-8")], body)
+}
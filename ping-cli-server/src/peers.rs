@@ -0,0 +1,78 @@
+// Peer limits
+//
+// Keeps track of when each connected peer last did something (connected, or
+// got a reply from an actor). With a peer limit, a new peer beyond it pushes
+// out the peer that has been quiet the longest, so a flood of clients can't
+// use up the server's file descriptors. With an idle timeout, peers that
+// have been quiet for longer are disconnected even below the limit; unlike
+// the swarm's own idle timeout, DHT traffic doesn't count as activity.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub struct PeerTracker {
+    max_peers: Option<usize>,
+    idle_timeout: Option<Duration>,
+    last_active: HashMap<PeerId, Instant>,
+}
+
+impl PeerTracker {
+    pub fn new(max_peers: Option<usize>, idle_timeout: Option<Duration>) -> Self {
+        Self { max_peers, idle_timeout, last_active: HashMap::new() }
+    }
+
+    /// Note a new connection from `peer`; returns the peer to evict if that
+    /// took the server over its limit
+    pub fn connected(&mut self, peer: PeerId) -> Option<PeerId> {
+        self.last_active.insert(peer, Instant::now());
+        let max_peers = self.max_peers?;
+        if self.last_active.len() <= max_peers {
+            return None;
+        }
+        let (&quietest, _) = self
+            .last_active
+            .iter()
+            .filter(|(&other, _)| other != peer)
+            .min_by_key(|(_, &at)| at)?;
+        self.last_active.remove(&quietest);
+        Some(quietest)
+    }
+
+    pub fn active(&mut self, peer: PeerId) {
+        if let Some(at) = self.last_active.get_mut(&peer) {
+            *at = Instant::now();
+        }
+    }
+
+    /// Forget `peer` once its last connection is closed
+    pub fn disconnected(&mut self, peer: PeerId) {
+        self.last_active.remove(&peer);
+    }
+
+    /// Remove and return the peers quiet for longer than the idle timeout
+    pub fn evict_idle(&mut self) -> Vec<PeerId> {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return Vec::new();
+        };
+        let idle: Vec<PeerId> = self
+            .last_active
+            .iter()
+            .filter(|(_, at)| at.elapsed() > idle_timeout)
+            .map(|(&peer, _)| peer)
+            .collect();
+        for peer in &idle {
+            self.last_active.remove(peer);
+        }
+        idle
+    }
+
+    /// Each connected peer and how long it has been quiet
+    pub fn idle_times(&self) -> Vec<(PeerId, Duration)> {
+        self.last_active.iter().map(|(&peer, at)| (peer, at.elapsed())).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_active.len()
+    }
+}
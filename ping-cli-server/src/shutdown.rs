@@ -0,0 +1,90 @@
+// Graceful shutdown
+//
+// On Ctrl-C or SIGTERM the server takes itself out of service in order: its
+// names are removed from the DHT registry so new lookups stop finding it, the
+// actors finish the messages already in their mailboxes (and the replies go
+// out), and only then are the peers' connections closed, which tells every
+// client the server is gone. The swarm keeps being polled throughout, since
+// both the unregistering and the replies go through it.
+
+use crate::MyBehaviour;
+use futures::StreamExt;
+use kameo::prelude::*;
+use kameo::remote;
+use libp2p::{PeerId, Swarm};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// How long unregistering and draining may take before the server gives up
+/// on them
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the peers' connections get to close
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub async fn run(swarm: &mut Swarm<MyBehaviour>, names: &[String], drain: impl Future<Output = ()>) {
+    let steps = async {
+        for name in names {
+            match remote::unregister(name.as_str()).await {
+                Ok(()) => info!("Unregistered '{}'", name),
+                Err(e) => warn!("Failed to unregister '{}': {}", name, e),
+            }
+        }
+        drain.await;
+        info!("Actor mailboxes drained");
+    };
+    tokio::pin!(steps);
+    let deadline = tokio::time::sleep(DRAIN_TIMEOUT);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut steps => break,
+            _ = swarm.select_next_some() => {}
+            _ = &mut deadline => {
+                warn!("Shutdown steps did not finish within {:?}", DRAIN_TIMEOUT);
+                break;
+            }
+        }
+    }
+
+    let peers: Vec<PeerId> = swarm.connected_peers().copied().collect();
+    info!("Closing connections to {} peer(s)", peers.len());
+    for peer in peers {
+        let _ = swarm.disconnect_peer_id(peer);
+    }
+    let deadline = Instant::now() + CLOSE_TIMEOUT;
+    while swarm.network_info().num_peers() > 0 {
+        if tokio::time::timeout_at(deadline, swarm.select_next_some()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM, which is how systemd and most other
+/// supervisors stop a service
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Cannot listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Stop `actor` once it has handled the messages already queued
+pub async fn drain<A: Actor>(actor: &ActorRef<A>) {
+    if actor.stop_gracefully().await.is_ok() {
+        actor.wait_for_shutdown().await;
+    }
+}
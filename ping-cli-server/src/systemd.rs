@@ -0,0 +1,83 @@
+// systemd integration
+//
+// Under a `Type=notify` unit, systemd passes a socket in NOTIFY_SOCKET. The
+// server reports READY=1 on it once it is listening and its actors are
+// registered, STOPPING=1 when it starts shutting down, and, when the unit
+// sets WatchdogSec=, WATCHDOG=1 from the event loop at half the watchdog
+// period, so a stuck loop gets the service restarted. The messages are the
+// plain datagrams described in sd_notify(3). Outside systemd, or off Linux,
+// every call does nothing.
+
+use std::time::Duration;
+
+pub struct Notifier {
+    #[cfg(target_os = "linux")]
+    socket: Option<(std::os::unix::net::UnixDatagram, std::os::unix::net::SocketAddr)>,
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    #[cfg(target_os = "linux")]
+    pub fn from_env() -> Self {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let socket = std::env::var("NOTIFY_SOCKET").ok().and_then(|path| {
+            // A leading '@' names a socket in the abstract namespace
+            let addr = match path.strip_prefix('@') {
+                Some(name) => SocketAddr::from_abstract_name(name),
+                None => SocketAddr::from_pathname(&path),
+            };
+            match (UnixDatagram::unbound(), addr) {
+                (Ok(socket), Ok(addr)) => Some((socket, addr)),
+                (Err(e), _) | (_, Err(e)) => {
+                    tracing::warn!("Ignoring NOTIFY_SOCKET {}: {}", path, e);
+                    None
+                }
+            }
+        });
+        // The watchdog is ours only if systemd meant it for this process
+        let for_us = std::env::var("WATCHDOG_PID")
+            .map_or(true, |pid| pid.parse() == Ok(std::process::id()));
+        let watchdog = std::env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse().ok())
+            .filter(|_| for_us && socket.is_some())
+            .map(|usec: u64| Duration::from_micros(usec) / 2);
+        Self { socket, watchdog }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn from_env() -> Self {
+        Self { watchdog: None }
+    }
+
+    /// How often the event loop should call `watchdog`, if at all
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    pub fn ready(&self) {
+        self.notify("READY=1");
+    }
+
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    pub fn watchdog(&self) {
+        self.notify("WATCHDOG=1");
+    }
+
+    #[cfg(target_os = "linux")]
+    fn notify(&self, state: &str) {
+        if let Some((socket, addr)) = &self.socket {
+            if let Err(e) = socket.send_to_addr(state.as_bytes(), addr) {
+                tracing::warn!("Failed to notify systemd ({}): {}", state, e);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn notify(&self, _state: &str) {}
+}
@@ -0,0 +1,118 @@
+// Per-peer traffic
+//
+// Counts the bytes that go over each peer's connections, for the per-peer
+// stats. Every connection's muxer is wrapped once the peer is known, and
+// every substream it opens counts into the peer's totals: kameo requests and
+// replies as well as the DHT traffic, after decryption and framing. A
+// peer's totals are dropped once its last connection is gone.
+
+use futures::io::{AsyncRead, AsyncWrite};
+use libp2p::core::muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent, StreamMuxerExt, SubstreamBox};
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+#[derive(Default)]
+struct Bytes {
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+/// Byte totals of the connected peers
+#[derive(Clone, Default)]
+pub struct Traffic {
+    peers: Arc<Mutex<HashMap<PeerId, Arc<Bytes>>>>,
+}
+
+impl Traffic {
+    /// `muxer` for a new connection to `peer`, counting into its totals
+    pub fn counted(&self, peer: PeerId, muxer: StreamMuxerBox) -> StreamMuxerBox {
+        let bytes = self.peers.lock().unwrap().entry(peer).or_default().clone();
+        StreamMuxerBox::new(Counted { inner: muxer, bytes })
+    }
+
+    /// Bytes received from and sent to `peer` so far
+    pub fn totals(&self, peer: &PeerId) -> (u64, u64) {
+        match self.peers.lock().unwrap().get(peer) {
+            Some(bytes) => (bytes.received.load(Ordering::Relaxed), bytes.sent.load(Ordering::Relaxed)),
+            None => (0, 0),
+        }
+    }
+
+    pub fn forget(&self, peer: &PeerId) {
+        self.peers.lock().unwrap().remove(peer);
+    }
+}
+
+struct Counted {
+    inner: StreamMuxerBox,
+    bytes: Arc<Bytes>,
+}
+
+impl Counted {
+    fn wrap(&self, substream: SubstreamBox) -> CountedSubstream {
+        CountedSubstream { inner: substream, bytes: self.bytes.clone() }
+    }
+}
+
+impl StreamMuxer for Counted {
+    type Substream = CountedSubstream;
+    type Error = io::Error;
+
+    fn poll_inbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner.poll_inbound_unpin(cx).map_ok(|substream| this.wrap(substream))
+    }
+
+    fn poll_outbound(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Self::Substream, Self::Error>> {
+        let this = self.get_mut();
+        this.inner.poll_outbound_unpin(cx).map_ok(|substream| this.wrap(substream))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_close_unpin(cx)
+    }
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+        self.get_mut().inner.poll_unpin(cx)
+    }
+}
+
+struct CountedSubstream {
+    inner: SubstreamBox,
+    bytes: Arc<Bytes>,
+}
+
+impl AsyncRead for CountedSubstream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let read = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(n)) = read {
+            this.bytes.received.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        read
+    }
+}
+
+impl AsyncWrite for CountedSubstream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = written {
+            this.bytes.sent.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        written
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
@@ -0,0 +1,323 @@
+// Actor implementation
+//
+// The PingActor and the messages it handles, with kameo underneath. Only
+// compiled with the `actor` feature, which keeps the Wasm client lightweight
+// (no Kameo dependency); every server bridge hands its frames to this same
+// actor.
+
+use crate::*;
+use kameo::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// PingActor - core business logic, completely transport-agnostic
+/// This same code handles messages from WebSocket and TCP clients
+#[derive(Actor)]
+pub struct PingActor {
+    pub ping_count: u64,
+}
+
+impl RemoteActor for PingActor {
+    const REMOTE_ID: &'static str = "ping_pong_app::PingActor";
+}
+
+/// Reply wrapper implementing Kameo's Reply trait
+/// Required for remote messaging with Serialize/Deserialize
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct PongReply(pub Pong);
+
+impl From<PongReply> for Pong {
+    fn from(reply: PongReply) -> Self {
+        reply.0
+    }
+}
+
+crate::typed_client! {
+    /// Typed client of a PingActor, over an `ActorRef`, a
+    /// `RemoteActorRef` or (for pings only) a `PingTransport` connection
+    pub struct PingActorClient {
+        fn ping(Ping) -> Pong;
+        /// The actor's counters
+        fn get_stats() -> PingStats = GetStats;
+        /// Reset the counters, returning the values before the reset
+        fn reset_stats() -> PingStats = ResetStats;
+        /// The protocol handshake; a refusal is the reply's Err
+        fn hello(Hello) -> HelloReply;
+    }
+}
+
+/// Message handler - THE SAME CODE FOR ALL TRANSPORTS
+/// Handles Ping messages from WebSocket clients (browser) and TCP clients (CLI)
+#[remote_message("a1b2c3d4-e5f6-7890-abcd-ef1234567890")]
+impl Message<Ping> for PingActor {
+    type Reply = PongReply;
+
+    async fn handle(
+        &mut self,
+        msg: Ping,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        // One span per ping handled, whichever transport brought it, in the
+        // trace of the client that sent it
+        let span = tracing::info_span!("handle", actor = "PingActor", sequence = msg.sequence);
+        #[cfg(feature = "otel")]
+        if let Some(traceparent) = msg.traceparent() {
+            crate::telemetry::set_parent(&span, traceparent);
+        }
+        span.in_scope(|| {
+            // Increment ping counter
+            self.ping_count += 1;
+
+            // Create response with current state
+            let pong = Pong::answering(&msg)
+                .message(format!("Pong! Responding to: {}", msg.message))
+                .total_pings(self.ping_count)
+                .build();
+
+            PongReply(pong)
+        })
+    }
+}
+
+/// Reply to a `Hello` over libp2p, where kameo's own MessagePack is the
+/// only codec
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct HelloReply(pub Result<HelloAck, ErrorReply>);
+
+#[remote_message("3e7a1c58-0d92-4f6b-8c25-b1f4e9a06d37")]
+impl Message<Hello> for PingActor {
+    type Reply = HelloReply;
+
+    async fn handle(
+        &mut self,
+        msg: Hello,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let server_info = concat!("ping-common/", env!("CARGO_PKG_VERSION"));
+        HelloReply(msg.negotiate(&["msgpack"], server_info).map_err(|e| e.to_reply()))
+    }
+}
+
+/// Reply wrapper for the control messages
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct PingStatsReply(pub PingStats);
+
+impl From<PingStatsReply> for PingStats {
+    fn from(reply: PingStatsReply) -> Self {
+        reply.0
+    }
+}
+
+#[remote_message("d7e3a9f1-2b64-4c8e-a5d0-3f9b1c7e6a42")]
+impl Message<GetStats> for PingActor {
+    type Reply = PingStatsReply;
+
+    async fn handle(
+        &mut self,
+        _msg: GetStats,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        PingStatsReply(PingStats { total_pings: self.ping_count })
+    }
+}
+
+#[remote_message("e2c8b5d4-9a17-4f3e-b6c1-8d4a0f2e7b95")]
+impl Message<ResetStats> for PingActor {
+    type Reply = PingStatsReply;
+
+    async fn handle(
+        &mut self,
+        _msg: ResetStats,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let previous = PingStats { total_pings: self.ping_count };
+        self.ping_count = 0;
+        PingStatsReply(previous)
+    }
+}
+
+/// EchoActor - replies with whatever it receives
+#[derive(Actor)]
+pub struct EchoActor {
+    pub echo_count: u64,
+}
+
+impl RemoteActor for EchoActor {
+    const REMOTE_ID: &'static str = "ping_pong_app::EchoActor";
+}
+
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct EchoReply(pub Echo);
+
+#[remote_message("5c0e8d7a-3f42-4b6e-9a1d-2e7f0b9c4d13")]
+impl Message<Echo> for EchoActor {
+    type Reply = EchoReply;
+
+    async fn handle(
+        &mut self,
+        msg: Echo,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.echo_count += 1;
+        EchoReply(msg)
+    }
+}
+
+/// KvActor - a tiny in-memory key-value store
+#[derive(Actor, Default)]
+pub struct KvActor {
+    pub store: HashMap<String, String>,
+}
+
+impl RemoteActor for KvActor {
+    const REMOTE_ID: &'static str = "ping_pong_app::KvActor";
+}
+
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct KvReply(pub KvResponse);
+
+#[remote_message("9b4f6a21-7c3d-4e58-b0a2-61d8e5f3c7a9")]
+impl Message<KvRequest> for KvActor {
+    type Reply = KvReply;
+
+    async fn handle(
+        &mut self,
+        msg: KvRequest,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let (key, value) = match msg {
+            KvRequest::Get { key } => {
+                let value = self.store.get(&key).cloned();
+                (key, value)
+            }
+            KvRequest::Set { key, value } => {
+                self.store.insert(key.clone(), value.clone());
+                (key, Some(value))
+            }
+            KvRequest::Delete { key } => {
+                self.store.remove(&key);
+                (key, None)
+            }
+        };
+        KvReply(KvResponse { key, value })
+    }
+}
+
+/// StatsActor - per-peer counters, fed by the server's swarm loop since
+/// actors aren't told who sent a message
+#[derive(Actor, Default)]
+pub struct StatsActor {
+    pub peers: HashMap<String, PeerStats>,
+    // Total handling time and the number of requests it covers, by peer
+    handling: HashMap<String, (Duration, u32)>,
+}
+
+impl RemoteActor for StatsActor {
+    const REMOTE_ID: &'static str = "ping_pong_app::StatsActor";
+}
+
+/// Local update - one more reply sent to `peer`, with the traffic over
+/// its connections so far
+#[derive(Clone, Debug)]
+pub struct PeerActivity {
+    pub peer: String,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    /// How long the actor took to answer, when the server saw the request
+    /// arrive; kameo doesn't tell a libp2p server when it did
+    pub handled_in: Option<Duration>,
+}
+
+/// Local update - `peer` has disconnected, drop its counters
+#[derive(Clone, Debug)]
+pub struct PeerGone {
+    pub peer: String,
+}
+
+impl Message<PeerActivity> for StatsActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        msg: PeerActivity,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let stats = self.peers.entry(msg.peer.clone()).or_insert_with(|| PeerStats {
+            peer: msg.peer,
+            ..PeerStats::default()
+        });
+        stats.requests += 1;
+        stats.bytes_received = msg.bytes_received;
+        stats.bytes_sent = msg.bytes_sent;
+        if let Some(handled_in) = msg.handled_in {
+            let (total, count) = self.handling.entry(stats.peer.clone()).or_default();
+            *total += handled_in;
+            *count += 1;
+            stats.avg_handling_us = Some((*total / *count).as_micros() as u64);
+        }
+        stats.last_active_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+    }
+}
+
+impl Message<PeerGone> for StatsActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        msg: PeerGone,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.peers.remove(&msg.peer);
+        self.handling.remove(&msg.peer);
+    }
+}
+
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct PeerStatsReply(pub Vec<PeerStats>);
+
+#[remote_message("0c6f2d8e-5a91-4b37-9e14-c8d2a7f05b63")]
+impl Message<GetPeerStats> for StatsActor {
+    type Reply = PeerStatsReply;
+
+    async fn handle(
+        &mut self,
+        _msg: GetPeerStats,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        PeerStatsReply(self.peers.values().cloned().collect())
+    }
+}
+
+/// CounterActor - a single counter shared by everyone who asks
+#[derive(Actor, Default)]
+pub struct CounterActor {
+    pub value: i64,
+}
+
+impl RemoteActor for CounterActor {
+    const REMOTE_ID: &'static str = "ping_pong_app::CounterActor";
+}
+
+#[derive(Reply, Serialize, Deserialize, Clone, Debug)]
+pub struct CounterReply(pub CounterValue);
+
+#[remote_message("4918b01c-4904-4eae-ba74-7a4bfd5c9c95")]
+impl Message<CounterRequest> for CounterActor {
+    type Reply = CounterReply;
+
+    async fn handle(
+        &mut self,
+        msg: CounterRequest,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        match msg {
+            CounterRequest::Increment { by } => self.value = self.value.saturating_add(by),
+            CounterRequest::Get => {}
+            CounterRequest::Reset => self.value = 0,
+        }
+        CounterReply(CounterValue { value: self.value })
+    }
+}
@@ -0,0 +1,137 @@
+// Message builders
+//
+// Ping and Pong keep gaining optional fields, and every struct literal has to
+// name them all. `Ping::builder()` starts from an empty ping and sets only
+// what the caller asks for, so a new field is one more builder method rather
+// than an edit to every caller. `Pong::answering(&ping)` starts from the
+// fields a Pong echoes, so a server can't forget one. The builders check
+// nothing; `Validate` does that where the message is sent or received.
+
+use crate::{Ping, Pong, Uuid};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// Metadata key of a W3C trace context (`00-<trace id>-<span id>-<flags>`)
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Builds a `Ping`; see `Ping::builder`
+#[derive(Clone, Debug)]
+pub struct PingBuilder {
+    ping: Ping,
+}
+
+impl Ping {
+    /// A ping with sequence number 1 and nothing else set
+    pub fn builder() -> PingBuilder {
+        PingBuilder {
+            ping: Ping {
+                message: String::new(),
+                sequence: 1,
+                client_id: None,
+                correlation_id: None,
+                sent_at_ms: None,
+                metadata: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// The W3C trace context the ping carries in its metadata, if any
+    pub fn traceparent(&self) -> Option<&str> {
+        self.metadata.get(TRACEPARENT).map(String::as_str)
+    }
+}
+
+impl PingBuilder {
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.ping.message = message.into();
+        self
+    }
+
+    pub fn sequence(mut self, sequence: u64) -> Self {
+        self.ping.sequence = sequence;
+        self
+    }
+
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.ping.client_id = Some(client_id.into());
+        self
+    }
+
+    pub fn correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.ping.correlation_id = Some(correlation_id);
+        self
+    }
+
+    /// A fresh random correlation id
+    #[cfg(feature = "std")]
+    pub fn new_correlation_id(self) -> Self {
+        self.correlation_id(Uuid::new_v4())
+    }
+
+    pub fn sent_at_ms(mut self, sent_at_ms: u64) -> Self {
+        self.ping.sent_at_ms = Some(sent_at_ms);
+        self
+    }
+
+    /// Add a metadata entry, replacing any under the same key
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.ping.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Carry a W3C trace context, so the servers can continue the trace
+    pub fn trace_context(self, traceparent: impl Into<String>) -> Self {
+        self.metadata(TRACEPARENT, traceparent)
+    }
+
+    pub fn build(self) -> Ping {
+        self.ping
+    }
+}
+
+/// Builds a `Pong`; see `Pong::answering`
+#[derive(Clone, Debug)]
+pub struct PongBuilder {
+    pong: Pong,
+}
+
+impl Pong {
+    /// A Pong answering `ping`: its sequence number, and its client id,
+    /// correlation id, timestamp and metadata echoed
+    pub fn answering(ping: &Ping) -> PongBuilder {
+        PongBuilder {
+            pong: Pong {
+                message: String::new(),
+                sequence: ping.sequence,
+                total_pings: 0,
+                client_id: ping.client_id.clone(),
+                correlation_id: ping.correlation_id,
+                sent_at_ms: ping.sent_at_ms,
+                metadata: ping.metadata.clone(),
+            },
+        }
+    }
+}
+
+impl PongBuilder {
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.pong.message = message.into();
+        self
+    }
+
+    pub fn total_pings(mut self, total_pings: u64) -> Self {
+        self.pong.total_pings = total_pings;
+        self
+    }
+
+    /// Add a metadata entry, replacing any the ping carried under the same
+    /// key
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.pong.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Pong {
+        self.pong
+    }
+}
@@ -0,0 +1,290 @@
+// Client core
+//
+// What every Ping client does whatever carries its pings: number them, keep
+// the ones still waiting for a Pong, match the Pongs that come back to them
+// and time the round trips, and count what was sent, received and lost. A
+// client keeps a `PingClientCore` next to its connection and tells it what
+// happened; the core never does I/O, sleeps or reads a clock itself, so the
+// same code runs in the CLI and in the browser. Times are in milliseconds
+// from whatever origin the caller picks. Each waiting ping carries a value of
+// the caller's, say a Promise to settle or the server it went to, returned
+// when the ping is settled. Every ping gets a fresh correlation id, so a Pong
+// carrying its sequence number but not its id (meant for another client, or
+// for an earlier run numbered the same) isn't taken for its answer.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::latency::LatencyStats;
+use crate::{ErrorReply, Ping, Pong, Uuid};
+
+/// Pings sent, Pongs received and pings that failed
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    pub sent: u64,
+    /// Pongs that answered a ping still waiting for one
+    pub received: u64,
+    /// Pings that couldn't be sent, or got no Pong in time
+    pub errors: u64,
+}
+
+impl Counts {
+    pub fn add(&mut self, other: &Counts) {
+        self.sent += other.sent;
+        self.received += other.received;
+        self.errors += other.errors;
+    }
+}
+
+/// A ping waiting for its Pong
+struct Outstanding<T> {
+    correlation_id: Option<Uuid>,
+    sent_at_ms: f64,
+    context: T,
+}
+
+/// Sequencing, correlation and stats of one client, generic over what the
+/// client keeps with each waiting ping
+pub struct PingClientCore<T> {
+    client_id: Option<String>,
+    metadata: BTreeMap<String, String>,
+    next_sequence: u64,
+    outstanding: HashMap<u64, Outstanding<T>>,
+    counts: Counts,
+    rtt: LatencyStats,
+}
+
+impl<T> PingClientCore<T> {
+    /// Number the pings from `first_sequence` (at least 1), each carrying
+    /// `client_id`
+    pub fn new(first_sequence: u64, client_id: Option<String>) -> Self {
+        Self {
+            client_id,
+            metadata: BTreeMap::new(),
+            next_sequence: first_sequence.max(1),
+            outstanding: HashMap::new(),
+            counts: Counts::default(),
+            rtt: LatencyStats::default(),
+        }
+    }
+
+    /// Carry on from stats kept from an earlier run
+    pub fn restore(&mut self, counts: Counts, rtt: LatencyStats) {
+        self.counts = counts;
+        self.rtt = rtt;
+    }
+
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    /// Carry these metadata entries in every ping
+    pub fn set_metadata(&mut self, metadata: BTreeMap<String, String>) {
+        self.metadata = metadata;
+    }
+
+    /// Sequence number of the next ping
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// The next ping, carrying `message` and sent at `sent_at_ms` (Unix
+    /// time). Its sequence number is only used up by `track`, so a ping
+    /// refused before it goes out doesn't leave a gap.
+    pub fn next_ping(&self, message: String, sent_at_ms: u64) -> Ping {
+        let mut ping = Ping::builder()
+            .message(message)
+            .sequence(self.next_sequence)
+            .new_correlation_id()
+            .sent_at_ms(sent_at_ms);
+        if let Some(client_id) = &self.client_id {
+            ping = ping.client_id(client_id.clone());
+        }
+        for (key, value) in &self.metadata {
+            ping = ping.metadata(key.clone(), value.clone());
+        }
+        ping.build()
+    }
+
+    /// Wait for the Pong to `ping`, sent (or queued) at `sent_at_ms`
+    pub fn track(&mut self, ping: &Ping, sent_at_ms: f64, context: T) {
+        self.next_sequence = self.next_sequence.max(ping.sequence + 1);
+        let correlation_id = ping.correlation_id;
+        self.outstanding.insert(ping.sequence, Outstanding { correlation_id, sent_at_ms, context });
+    }
+
+    /// Count the round trip of the ping with `sequence` from `sent_at_ms`
+    /// instead, as it only goes out now
+    pub fn restart(&mut self, sequence: u64, sent_at_ms: f64) {
+        if let Some(outstanding) = self.outstanding.get_mut(&sequence) {
+            outstanding.sent_at_ms = sent_at_ms;
+        }
+    }
+
+    /// A ping went out
+    pub fn record_sent(&mut self) {
+        self.counts.sent += 1;
+    }
+
+    /// A ping failed without being tracked, or failed to go out
+    pub fn record_error(&mut self) {
+        self.counts.errors += 1;
+    }
+
+    /// A Pong arrived at `now_ms`. If the ping it answers is still waiting,
+    /// it is counted as received and its round trip is recorded and returned
+    /// with the ping's context. A Pong to a ping that timed out or failed,
+    /// or that another client sent, isn't counted, as its ping already was.
+    pub fn resolve(&mut self, pong: &Pong, now_ms: f64) -> Option<(f64, T)> {
+        let outstanding = self.outstanding.get(&pong.sequence)?;
+        if pong.correlation_id.is_some() && pong.correlation_id != outstanding.correlation_id {
+            return None;
+        }
+        let outstanding = self.outstanding.remove(&pong.sequence)?;
+        self.counts.received += 1;
+        let rtt_ms = now_ms - outstanding.sent_at_ms;
+        self.rtt.record_ms(rtt_ms);
+        Some((rtt_ms, outstanding.context))
+    }
+
+    /// The server answered a ping with `error`; counted as an error if the
+    /// reply names a ping that is still waiting
+    pub fn fail(&mut self, error: &ErrorReply) -> Option<T> {
+        let sequence = error.sequence?;
+        let outstanding = self.outstanding.get(&sequence)?;
+        if error.correlation_id.is_some() && error.correlation_id != outstanding.correlation_id {
+            return None;
+        }
+        self.time_out(sequence)
+    }
+
+    /// The ping with `sequence` got no Pong in time; counted as an error if
+    /// it was still waiting
+    pub fn time_out(&mut self, sequence: u64) -> Option<T> {
+        let context = self.cancel(sequence)?;
+        self.counts.errors += 1;
+        Some(context)
+    }
+
+    /// Stop waiting for the ping with `sequence`, without counting it
+    pub fn cancel(&mut self, sequence: u64) -> Option<T> {
+        self.outstanding.remove(&sequence).map(|outstanding| outstanding.context)
+    }
+
+    /// Stop waiting for every ping
+    pub fn cancel_all(&mut self) -> Vec<T> {
+        self.outstanding.drain().map(|(_, outstanding)| outstanding.context).collect()
+    }
+
+    /// How many pings are waiting for their Pong
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    pub fn counts(&self) -> &Counts {
+        &self.counts
+    }
+
+    pub fn rtt(&self) -> &LatencyStats {
+        &self.rtt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(core: &mut PingClientCore<&'static str>, sent_at_ms: f64, context: &'static str) -> Ping {
+        let ping = core.next_ping("ping".into(), 0);
+        core.track(&ping, sent_at_ms, context);
+        core.record_sent();
+        ping
+    }
+
+    fn counts(sent: u64, received: u64, errors: u64) -> Counts {
+        Counts { sent, received, errors }
+    }
+
+    #[test]
+    fn numbers_and_resolves_pings() {
+        let mut core = PingClientCore::new(0, Some("me".into()));
+        let first = core.next_ping("hi".into(), 1_000);
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.client_id.as_deref(), Some("me"));
+        assert!(first.correlation_id.is_some());
+        // Not used up until tracked
+        assert_eq!(core.next_ping("hi".into(), 1_000).sequence, 1);
+
+        core.track(&first, 10.0, "first");
+        core.record_sent();
+        let second = sent(&mut core, 20.0, "second");
+        assert_eq!(second.sequence, 2);
+        assert_ne!(second.correlation_id, first.correlation_id);
+        assert_eq!(core.outstanding(), 2);
+
+        assert_eq!(core.resolve(&Pong::answering(&second).build(), 25.0), Some((5.0, "second")));
+        assert_eq!(core.resolve(&Pong::answering(&first).build(), 40.0), Some((30.0, "first")));
+        assert_eq!(core.outstanding(), 0);
+        assert_eq!(*core.counts(), counts(2, 2, 0));
+        assert_eq!(core.rtt().count(), 2);
+    }
+
+    #[test]
+    fn ignores_pongs_with_another_correlation_id() {
+        let mut core = PingClientCore::new(1, None);
+        let ping = sent(&mut core, 0.0, "ping");
+        let mut stranger = Pong::answering(&ping).build();
+        stranger.correlation_id = Some(Uuid::new_v4());
+        assert_eq!(core.resolve(&stranger, 5.0), None);
+        assert_eq!(core.outstanding(), 1);
+
+        // A server that leaves the id out is matched by sequence alone
+        let mut bare = Pong::answering(&ping).build();
+        bare.correlation_id = None;
+        assert_eq!(core.resolve(&bare, 5.0), Some((5.0, "ping")));
+        assert_eq!(*core.counts(), counts(1, 1, 0));
+    }
+
+    #[test]
+    fn fails_pings_named_by_an_error_reply() {
+        let mut core = PingClientCore::new(1, None);
+        let ping = sent(&mut core, 0.0, "ping");
+        let error = |sequence, correlation_id| ErrorReply {
+            code: "overloaded".into(),
+            message: "busy".into(),
+            sequence,
+            correlation_id,
+        };
+        assert_eq!(core.fail(&error(None, None)), None);
+        assert_eq!(core.fail(&error(Some(ping.sequence), Some(Uuid::new_v4()))), None);
+        assert_eq!(core.fail(&error(Some(ping.sequence), ping.correlation_id)), Some("ping"));
+        assert_eq!(core.fail(&error(Some(ping.sequence), ping.correlation_id)), None);
+        assert_eq!(*core.counts(), counts(1, 0, 1));
+    }
+
+    #[test]
+    fn a_late_pong_is_not_counted() {
+        let mut core = PingClientCore::new(1, None);
+        let ping = sent(&mut core, 0.0, "ping");
+        assert_eq!(core.time_out(ping.sequence), Some("ping"));
+        assert_eq!(core.time_out(ping.sequence), None);
+        assert_eq!(core.resolve(&Pong::answering(&ping).build(), 5_000.0), None);
+        assert_eq!(*core.counts(), counts(1, 0, 1));
+        assert!(core.rtt().is_empty());
+    }
+
+    #[test]
+    fn cancelled_pings_are_not_counted() {
+        let mut core = PingClientCore::new(1, None);
+        let first = sent(&mut core, 0.0, "first");
+        sent(&mut core, 0.0, "second");
+        sent(&mut core, 0.0, "third");
+        assert_eq!(core.cancel(first.sequence), Some("first"));
+        assert_eq!(core.resolve(&Pong::answering(&first).build(), 5.0), None);
+        let mut rest = core.cancel_all();
+        rest.sort();
+        assert_eq!(rest, ["second", "third"]);
+        assert_eq!(core.outstanding(), 0);
+        assert_eq!(*core.counts(), counts(3, 0, 0));
+    }
+}
@@ -0,0 +1,100 @@
+// Codecs
+//
+// How messages become frames, one module per wire format, each behind a
+// feature of its name so a client builds in only what it speaks. Every
+// codec implements `Codec`, turning any message into bytes and back, with
+// failures as `Error::Codec`. Both formats describe themselves, which the
+// messages need: their optional fields are left out when empty, and the
+// correlation ids read either a string or bytes. That rules out bincode and
+// postcard, which can't skip a field or tell what comes next.
+//
+// - `json` (feature `json`): JSON text, as WebSocket text frames and
+//   WebTransport and TCP lines carry it
+// - `msgpack` (feature `msgpack`): MessagePack with named fields, as the
+//   `ping.msgpack` WebSocket subprotocol and kameo's libp2p messages carry it,
+//   and transcoding to and from JSON for servers whose routes speak JSON
+
+use crate::Error;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// A wire format
+pub trait Codec {
+    /// Name of the format, as negotiated in a `Hello`
+    const NAME: &'static str;
+
+    fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, Error>;
+
+    fn decode<T: DeserializeOwned>(frame: &[u8]) -> Result<T, Error>;
+}
+
+fn codec_error(e: impl std::fmt::Display) -> Error {
+    Error::Codec(e.to_string())
+}
+
+#[cfg(feature = "json")]
+pub mod json {
+    use super::{codec_error, Codec};
+    use crate::Error;
+    use serde::{de::DeserializeOwned, Serialize};
+
+    pub struct Json;
+
+    impl Codec for Json {
+        const NAME: &'static str = "json";
+
+        fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, Error> {
+            serde_json::to_vec(msg).map_err(codec_error)
+        }
+
+        fn decode<T: DeserializeOwned>(frame: &[u8]) -> Result<T, Error> {
+            serde_json::from_slice(frame).map_err(codec_error)
+        }
+    }
+
+    /// `msg` as a text frame
+    pub fn to_string<T: Serialize>(msg: &T) -> Result<String, Error> {
+        serde_json::to_string(msg).map_err(codec_error)
+    }
+
+    pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, Error> {
+        serde_json::from_str(text).map_err(codec_error)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+pub mod msgpack {
+    use super::{codec_error, Codec};
+    use crate::Error;
+    use serde::{de::DeserializeOwned, Serialize};
+
+    pub struct Msgpack;
+
+    impl Codec for Msgpack {
+        const NAME: &'static str = "msgpack";
+
+        fn encode<T: Serialize>(msg: &T) -> Result<Vec<u8>, Error> {
+            rmp_serde::to_vec_named(msg).map_err(codec_error)
+        }
+
+        fn decode<T: DeserializeOwned>(frame: &[u8]) -> Result<T, Error> {
+            rmp_serde::from_slice(frame).map_err(codec_error)
+        }
+    }
+
+    /// The JSON text of a MessagePack frame, without knowing its message
+    /// type. A frame nested deeper than JSON parsers read (128 levels for
+    /// serde_json) comes out as JSON that won't parse back.
+    #[cfg(feature = "json")]
+    pub fn to_json(frame: &[u8]) -> Result<String, Error> {
+        let value: serde_json::Value = rmp_serde::from_slice(frame).map_err(codec_error)?;
+        Ok(value.to_string())
+    }
+
+    /// The MessagePack frame of some JSON text, without knowing its message
+    /// type
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<Vec<u8>, Error> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(codec_error)?;
+        rmp_serde::to_vec_named(&value).map_err(codec_error)
+    }
+}
@@ -0,0 +1,93 @@
+// Errors
+//
+// What can go wrong between a client and an actor, on any transport: a frame
+// that doesn't decode, a connection that fails, a reply that doesn't come, a
+// token that isn't accepted, a message breaking a `Validate` rule, an actor
+// that fails to answer, or a peer speaking another version of the protocol.
+// Servers turn these into an `ErrorReply` for the client instead of only
+// logging them, and clients turn a received `ErrorReply` back into the same
+// variant, so both sides agree on what the `code` of each means.
+
+use crate::{ErrorReply, ValidationError};
+use ::core::time::Duration;
+use alloc::string::{String, ToString};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A frame that couldn't be encoded or decoded
+    #[error("codec error: {0}")]
+    Codec(String),
+    /// The connection failed or was closed
+    #[error("transport error: {0}")]
+    Transport(String),
+    #[error("no reply within {0:?}")]
+    Timeout(Duration),
+    /// A missing or refused token
+    #[error("unauthorized: {0}")]
+    Auth(String),
+    /// A message that broke one of its `Validate` rules
+    #[error("invalid message: {0}")]
+    Invalid(String),
+    /// The actor failed to handle the message, or couldn't be reached
+    #[error("actor error: {0}")]
+    Actor(String),
+    /// The server is too busy to take the message
+    #[error("overloaded: {0}")]
+    Overloaded(String),
+    /// The peers' protocol versions or codecs don't match
+    #[error("incompatible: {0}")]
+    Version(String),
+}
+
+impl Error {
+    /// The `code` of this error's `ErrorReply`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Codec(_) => "bad_request",
+            Error::Transport(_) => "transport",
+            Error::Timeout(_) => "timeout",
+            Error::Auth(_) => "unauthorized",
+            Error::Invalid(_) => "invalid_message",
+            Error::Actor(_) => "actor_error",
+            Error::Overloaded(_) => "overloaded",
+            Error::Version(_) => "unsupported_version",
+        }
+    }
+
+    /// The reply telling a client about this error
+    pub fn to_reply(&self) -> ErrorReply {
+        let message = match self {
+            Error::Codec(message)
+            | Error::Transport(message)
+            | Error::Auth(message)
+            | Error::Invalid(message)
+            | Error::Actor(message)
+            | Error::Overloaded(message)
+            | Error::Version(message) => message.clone(),
+            Error::Timeout(_) => self.to_string(),
+        };
+        ErrorReply { code: self.code().to_string(), message, sequence: None, correlation_id: None }
+    }
+}
+
+impl From<ErrorReply> for Error {
+    fn from(reply: ErrorReply) -> Self {
+        match reply.code.as_str() {
+            "bad_request" => Error::Codec(reply.message),
+            "unauthorized" => Error::Auth(reply.message),
+            "invalid_message" => Error::Invalid(reply.message),
+            "overloaded" => Error::Overloaded(reply.message),
+            "unsupported_version" => Error::Version(reply.message),
+            // A timeout's duration doesn't survive the trip; the message
+            // still says it
+            "transport" | "timeout" => Error::Transport(reply.message),
+            _ => Error::Actor(reply.message),
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Self {
+        Error::Invalid(e.to_string())
+    }
+}
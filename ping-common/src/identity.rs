@@ -0,0 +1,50 @@
+// Peer identity
+//
+// By default every start of the CLI server or client gets a fresh keypair,
+// and so a new peer id: the server's connection string changes on each
+// restart, and the server sees a new peer on each client run. With an
+// identity file the keypair (protobuf-encoded, as libp2p writes it) is kept
+// and reused, so the connection string stays valid and allowlists can name
+// the client. The file is created on first use.
+
+use libp2p::identity::Keypair;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::info;
+
+/// The keypair kept in `path`, or a new one written there if the file
+/// doesn't exist yet
+pub fn load_or_create(path: &Path) -> io::Result<Keypair> {
+    match fs::read(path) {
+        Ok(bytes) => Keypair::from_protobuf_encoding(&bytes).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = Keypair::generate_ed25519();
+            let bytes = keypair.to_protobuf_encoding().map_err(io::Error::other)?;
+            write_private(path, &bytes)?;
+            info!("Created new identity in {}", path.display());
+            Ok(keypair)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// The file holds a private key, so keep it to the owner where that's possible
+#[cfg(unix)]
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(bytes)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    fs::write(path, bytes)
+}
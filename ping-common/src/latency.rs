@@ -0,0 +1,273 @@
+// Latency statistics
+//
+// One histogram type for every place that reports round trips: the CLI's
+// end-of-run report, the wasm client's stats getters and the HTTP server's
+// latency endpoint, so a p99 means the same thing everywhere. Samples are
+// kept in microseconds, in log-linear buckets: exact below 128µs, then 64
+// buckets per power of two, so a percentile is within 1.6% of the true
+// value (and never outside the recorded min and max). Only the buckets in use
+// are stored, so memory stays flat however long a soak test runs, two
+// histograms merge by adding their buckets, and the whole thing serializes
+// for the wasm client's saved stats. The count, sum, min, max and the last
+// sample are kept exactly.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// Values below this are bucketed exactly; above it, each power of two is
+// split into HALF buckets
+const SUB_BITS: u32 = 7;
+const SUB: u64 = 1 << SUB_BITS;
+const HALF: u64 = SUB / 2;
+
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LatencyStats {
+    buckets: BTreeMap<u32, u64>,
+    count: u64,
+    sum_us: u64,
+    min_us: u64,
+    max_us: u64,
+    last_us: u64,
+}
+
+/// The usual figures of a histogram, in microseconds, for JSON output; None
+/// while it is empty
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub min_us: Option<u64>,
+    pub mean_us: Option<u64>,
+    pub p50_us: Option<u64>,
+    pub p90_us: Option<u64>,
+    pub p99_us: Option<u64>,
+    pub max_us: Option<u64>,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, rtt: Duration) {
+        self.record_us(u64::try_from(rtt.as_micros()).unwrap_or(u64::MAX));
+    }
+
+    /// Record a round trip in milliseconds, as browsers time them
+    pub fn record_ms(&mut self, rtt_ms: f64) {
+        self.record_us((rtt_ms * 1000.0).round().max(0.0) as u64);
+    }
+
+    fn record_us(&mut self, us: u64) {
+        if self.count == 0 || us < self.min_us {
+            self.min_us = us;
+        }
+        self.max_us = self.max_us.max(us);
+        self.last_us = us;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.count += 1;
+        *self.buckets.entry(bucket(us)).or_default() += 1;
+    }
+
+    /// Fold another histogram's samples into this one; its last sample
+    /// becomes the last
+    pub fn merge(&mut self, other: &LatencyStats) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 || other.min_us < self.min_us {
+            self.min_us = other.min_us;
+        }
+        self.max_us = self.max_us.max(other.max_us);
+        self.last_us = other.last_us;
+        self.sum_us = self.sum_us.saturating_add(other.sum_us);
+        self.count += other.count;
+        for (&bucket, &count) in &other.buckets {
+            *self.buckets.entry(bucket).or_default() += count;
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // The figures are None before the first sample
+    fn some(&self, us: u64) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_micros(us))
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.some(self.min_us)
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        self.some(self.sum_us / self.count.max(1))
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.some(self.max_us)
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.some(self.last_us)
+    }
+
+    /// The sample at `quantile` (0.0 to 1.0) by nearest rank, to the
+    /// precision of its bucket
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let (&index, _) = self.buckets.iter().find(|(_, &count)| {
+            seen += count;
+            seen >= rank
+        })?;
+        self.some(highest(index).clamp(self.min_us, self.max_us))
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        let us = |d: Option<Duration>| d.map(|d| d.as_micros() as u64);
+        LatencySummary {
+            count: self.count,
+            min_us: us(self.min()),
+            mean_us: us(self.mean()),
+            p50_us: us(self.percentile(0.50)),
+            p90_us: us(self.percentile(0.90)),
+            p99_us: us(self.percentile(0.99)),
+            max_us: us(self.max()),
+        }
+    }
+
+    /// The samples counted per power of two of microseconds: the upper
+    /// bound of each range and its count, from the first range in use to the
+    /// last, empty ones in between included
+    pub fn log2_buckets(&self) -> Vec<(Duration, u64)> {
+        let mut ranges: BTreeMap<u32, u64> = BTreeMap::new();
+        for (&index, &count) in &self.buckets {
+            let high = highest(index);
+            *ranges.entry(u64::BITS - high.leading_zeros()).or_default() += count;
+        }
+        let (Some((&first, _)), Some((&last, _))) = (ranges.first_key_value(), ranges.last_key_value()) else {
+            return Vec::new();
+        };
+        (first..=last)
+            .map(|bits| {
+                let upper = 1u64.checked_shl(bits).map_or(u64::MAX, |bound| bound - 1);
+                (Duration::from_micros(upper), ranges.get(&bits).copied().unwrap_or(0))
+            })
+            .collect()
+    }
+}
+
+// Index of the bucket holding `us`
+fn bucket(us: u64) -> u32 {
+    if us < SUB {
+        return us as u32;
+    }
+    let shift = u64::BITS - us.leading_zeros() - SUB_BITS;
+    (u64::from(shift) * HALF + (us >> shift)) as u32
+}
+
+// Largest value that falls in bucket `index`
+fn highest(index: u32) -> u64 {
+    let index = u64::from(index);
+    if index < SUB {
+        return index;
+    }
+    let shift = index / HALF - 1;
+    let mantissa = index - shift * HALF;
+    // The last bucket ends at 2^64, which wraps to 0: one less is u64::MAX
+    ((mantissa + 1) << shift).wrapping_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn of(samples: impl IntoIterator<Item = u64>) -> LatencyStats {
+        let mut stats = LatencyStats::default();
+        for us in samples {
+            stats.record_us(us);
+        }
+        stats
+    }
+
+    #[test]
+    fn bucket_boundaries() {
+        // Exact up to 127µs, then two values to a bucket up to 255µs, four
+        // from 256µs
+        assert_eq!((bucket(127), highest(127)), (127, 127));
+        assert_eq!((bucket(128), highest(bucket(128))), (128, 129));
+        assert_eq!(bucket(129), bucket(128));
+        assert_eq!(highest(bucket(255)), 255);
+        assert_ne!(bucket(256), bucket(255));
+        assert_eq!(highest(bucket(256)), 259);
+        assert_eq!(highest(bucket(u64::MAX)), u64::MAX);
+        assert_eq!(highest(bucket(u64::MAX) - 1), u64::MAX - (1 << 57));
+    }
+
+    #[test]
+    fn every_value_is_in_its_bucket() {
+        for us in (0..4096).chain([u64::MAX / 2, u64::MAX - 1, u64::MAX]) {
+            let index = bucket(us);
+            assert!(highest(index) >= us, "{} above its bucket", us);
+            if index > 0 {
+                assert!(highest(index - 1) < us, "{} below its bucket", us);
+            }
+        }
+    }
+
+    #[test]
+    fn percentiles_of_known_samples() {
+        let exact = of(1..=100);
+        assert_eq!(exact.percentile(0.50), Some(Duration::from_micros(50)));
+        assert_eq!(exact.percentile(0.99), Some(Duration::from_micros(99)));
+        assert_eq!(exact.percentile(0.0), Some(Duration::from_micros(1)));
+        assert_eq!(exact.percentile(1.0), Some(Duration::from_micros(100)));
+
+        let stats = of(1..=100_000);
+        for (quantile, expected) in [(0.50, 50_000.0), (0.90, 90_000.0), (0.99, 99_000.0)] {
+            let us = stats.percentile(quantile).unwrap().as_micros() as f64;
+            assert!(us >= expected && us <= expected * 1.016, "p{} is {}µs", quantile * 100.0, us);
+        }
+        assert_eq!(stats.percentile(1.0), Some(Duration::from_micros(100_000)));
+        assert_eq!(stats.mean(), Some(Duration::from_micros(50_000)));
+    }
+
+    #[test]
+    fn percentiles_stay_within_min_and_max() {
+        let stats = of([130, 130, 130]);
+        assert_eq!(stats.percentile(0.5), Some(Duration::from_micros(130)));
+        assert_eq!(of([u64::MAX]).percentile(0.5), Some(Duration::from_micros(u64::MAX)));
+        assert_eq!(LatencyStats::default().percentile(0.5), None);
+    }
+
+    #[test]
+    fn merging_equals_recording_into_one() {
+        let (left, right): (Vec<u64>, Vec<u64>) = (0..5000u64).map(|n| n * n % 100_003).partition(|us| us % 3 == 0);
+        let mut merged = of(left.iter().copied());
+        merged.merge(&of(right.iter().copied()));
+        let one = of(left.into_iter().chain(right));
+
+        assert_eq!(merged.buckets, one.buckets);
+        assert_eq!(
+            (merged.count, merged.sum_us, merged.min_us, merged.max_us, merged.last_us),
+            (one.count, one.sum_us, one.min_us, one.max_us, one.last_us)
+        );
+        assert_eq!(merged.log2_buckets(), one.log2_buckets());
+    }
+
+    #[test]
+    fn merging_an_empty_histogram() {
+        let stats = of([10, 20]);
+        let mut merged = stats.clone();
+        merged.merge(&LatencyStats::default());
+        assert_eq!(merged.buckets, stats.buckets);
+        assert_eq!(merged.last(), Some(Duration::from_micros(20)));
+
+        let mut empty = LatencyStats::default();
+        empty.merge(&stats);
+        assert_eq!(empty.min(), Some(Duration::from_micros(10)));
+        assert_eq!(empty.count(), 2);
+    }
+}
@@ -1,69 +1,79 @@
-use serde::{Deserialize, Serialize};
-
-/// Ping message - used across all transports (WebSocket, TCP, etc.)
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Ping {
-    pub message: String,
-    pub sequence: u64,
-}
-
-/// Pong response - used across all transports (WebSocket, TCP, etc.)
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Pong {
-    pub message: String,
-    pub sequence: u64,
-    pub total_pings: u64,
-}
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use uuid::Uuid;
+
+// Message types, without std
+pub mod messages;
+pub use messages::*;
+
+// Wire formats, each behind a feature
+#[cfg(any(feature = "json", feature = "msgpack"))]
+pub mod codec;
+
+// `Ping::builder()` and `Pong::answering()`
+pub mod builder;
+
+// Sequencing, correlation and stats shared by the CLI and wasm clients
+#[cfg(feature = "std")]
+pub mod client;
+
+// Round-trip histograms, the same for every client and server
+#[cfg(feature = "std")]
+pub mod latency;
+
+mod error;
+pub use error::Error;
+
+// Correlation ids as strings in every codec
+mod uuid_text;
+
+// Backoff for every retry loop, and (with tokio) an async retry
+#[cfg(feature = "std")]
+pub mod retry;
+
+// Typed clients generated from message lists (`typed_client!`)
+#[cfg(feature = "std")]
+pub mod typed;
+
+// Rules checked by bridges and clients before a message reaches an actor
+pub mod validate;
+pub use validate::{Validate, ValidationError};
+
+// Exporting spans over OTLP
+#[cfg(feature = "otel")]
+pub mod telemetry;
+
+// Rotating log files for the servers
+#[cfg(feature = "log-file")]
+pub mod log_file;
+
+// The libp2p TCP transport of the CLI server and client, tuned
+#[cfg(feature = "libp2p")]
+pub mod tuning;
+
+// The CLI server's and client's keypair, kept in a file
+#[cfg(feature = "libp2p")]
+pub mod identity;
+
+// The servers' tracing subscriber: format, log file, OTLP and tokio-console
+#[cfg(feature = "logging")]
+pub mod logging;
+
+// proptest strategies for the message types
+#[cfg(feature = "testing")]
+pub mod testing;
 
 // Actor implementation - only compiled when "actor" feature is enabled
 // This keeps the Wasm client lightweight (no Kameo dependency)
 #[cfg(feature = "actor")]
-pub mod actor {
-    use super::*;
-    use kameo::prelude::*;
-
-    /// PingActor - core business logic, completely transport-agnostic
-    /// This same code handles messages from WebSocket and TCP clients
-    #[derive(Actor)]
-    pub struct PingActor {
-        pub ping_count: u64,
-    }
-
-    impl RemoteActor for PingActor {
-        const REMOTE_ID: &'static str = "ping_pong_app::PingActor";
-    }
-
-    /// Reply wrapper implementing Kameo's Reply trait
-    /// Required for remote messaging with Serialize/Deserialize
-    #[derive(Reply, Serialize, Deserialize, Clone, Debug)]
-    pub struct PongReply(pub Pong);
-
-    /// Message handler - THE SAME CODE FOR ALL TRANSPORTS
-    /// Handles Ping messages from WebSocket clients (browser) and TCP clients (CLI)
-    #[remote_message("a1b2c3d4-e5f6-7890-abcd-ef1234567890")]
-    impl Message<Ping> for PingActor {
-        type Reply = PongReply;
-
-        async fn handle(
-            &mut self,
-            msg: Ping,
-            _ctx: &mut Context<Self, Self::Reply>,
-        ) -> Self::Reply {
-            // Increment ping counter
-            self.ping_count += 1;
-
-            // Create response with current state
-            let pong = Pong {
-                message: format!("Pong! Responding to: {}", msg.message),
-                sequence: msg.sequence,
-                total_pings: self.ping_count,
-            };
-            
-            PongReply(pong)
-        }
-    }
-}
+pub mod actor;
 
 // Re-export actor types when feature is enabled
 #[cfg(feature = "actor")]
-pub use actor::*;
\ No newline at end of file
+pub use actor::*;
+
+// Transports for Rust clients - only compiled with the "transport" feature
+#[cfg(feature = "transport")]
+pub mod transport;
@@ -0,0 +1,164 @@
+// Log files
+//
+// `--log-file <path>` has a server write its logs to a file as well as the
+// console, so a long-running demo keeps its history without a log shipper.
+// The file rotates by time, with tracing-appender's rolling appender: each
+// minute, hour or day gets a file of its own named after it
+// (`server.log.2026-10-15`), or by size like the audit log: once the file
+// would grow past `max_bytes` it becomes `<path>.1`, shifting older ones to
+// `.2`, `.3`, ... Either way at most `keep` old files are kept. Lines are
+// written from a thread of their own, so a slow disk never holds up a
+// request; the guard returned with the writer flushes what is left when the
+// process ends.
+
+use serde::Deserialize;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing_appender::rolling::{self, RollingFileAppender};
+
+pub use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+
+/// How often a time-rotated log file starts afresh
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minutely" => Ok(Rotation::Minutely),
+            "hourly" => Ok(Rotation::Hourly),
+            "daily" => Ok(Rotation::Daily),
+            "never" => Ok(Rotation::Never),
+            _ => Err(format!("expected minutely, hourly, daily or never, not {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rotation::Minutely => "minutely",
+            Rotation::Hourly => "hourly",
+            Rotation::Daily => "daily",
+            Rotation::Never => "never",
+        })
+    }
+}
+
+/// Where logs go besides the console, and how the file rotates
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    pub path: PathBuf,
+    pub rotation: Rotation,
+    /// Rotate by size instead of by time
+    pub max_bytes: Option<u64>,
+    /// Old files to keep
+    pub keep: usize,
+}
+
+impl LogFile {
+    /// A writer appending to the file from a thread of its own, and the
+    /// guard that flushes it
+    pub fn open(&self) -> io::Result<(NonBlocking, WorkerGuard)> {
+        match self.max_bytes {
+            Some(max_bytes) => Ok(tracing_appender::non_blocking(SizeRotating::open(&self.path, max_bytes, self.keep)?)),
+            None => Ok(tracing_appender::non_blocking(self.rolling()?)),
+        }
+    }
+
+    fn rolling(&self) -> io::Result<RollingFileAppender> {
+        let name = self.path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{}: not a file name", self.path.display()))
+        })?;
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let rotation = match self.rotation {
+            Rotation::Minutely => rolling::Rotation::MINUTELY,
+            Rotation::Hourly => rolling::Rotation::HOURLY,
+            Rotation::Daily => rolling::Rotation::DAILY,
+            Rotation::Never => rolling::Rotation::NEVER,
+        };
+        RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(name.to_string_lossy())
+            .max_log_files(self.keep + 1)
+            .build(directory)
+            .map_err(|e| io::Error::other(format!("{}: {}", self.path.display(), e)))
+    }
+}
+
+// A file renamed to `<path>.1` once it would grow past `max_bytes`
+struct SizeRotating {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl SizeRotating {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = append(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), max_bytes, keep, file, size })
+    }
+
+    // Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and the current
+    // file to `<path>.1`
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(&self.path, self.keep));
+            for n in (1..self.keep).rev() {
+                let from = rotated(&self.path, n);
+                if from.exists() {
+                    fs::rename(from, rotated(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+        self.file = append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotating {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
@@ -0,0 +1,122 @@
+// Server logging
+//
+// Both servers log the same way, so they install their subscriber from here
+// with one call. `--log-format json` writes one JSON object per line instead
+// of the usual text, so Loki or ELK can ingest the logs without a regex.
+// Event fields sit at the top level next to the message; the fields of the
+// enclosing spans come along under "span" and "spans". With an OTLP
+// endpoint the spans are exported as well (see `telemetry`), and with a log
+// file the same lines go to a rotating file (see `log_file`).
+//
+// With the `tokio-console` feature the runtime's tasks can be served to
+// tokio-console too. tokio only emits the spans it needs when built with
+// `--cfg tokio_unstable`; without both, asking for it is an error.
+
+use crate::log_file::{LogFile, WorkerGuard};
+use crate::telemetry::{self, Telemetry};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, EnvFilter, Layer};
+#[cfg(not(all(feature = "tokio-console", tokio_unstable)))]
+use tracing_subscriber::layer::Identity;
+
+/// How log lines are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("expected text or json, not {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// Flushes the exported spans and the log file's last lines when dropped
+pub struct Guard {
+    _telemetry: Option<Telemetry>,
+    _log_file: Option<WorkerGuard>,
+}
+
+/// Install the global subscriber for `service`; RUST_LOG picks the levels,
+/// info by default. The console gets colours when `ansi` is set,
+/// `log_file` the same lines without them, and `tokio_console` serves the
+/// runtime's tasks to tokio-console.
+pub fn init(
+    service: &'static str,
+    format: LogFormat,
+    ansi: bool,
+    otlp_endpoint: Option<&str>,
+    log_file: Option<&LogFile>,
+    tokio_console: bool,
+) -> Result<Guard, Box<dyn std::error::Error>> {
+    let (otel, guard) = match otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, guard) = telemetry::layer(service, endpoint)?;
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry().with(console(tokio_console)?);
+    let logs = tracing_subscriber::fmt::layer().with_ansi(ansi);
+    let (file, file_guard) = log_file.map(LogFile::open).transpose()?.unzip();
+    let to_file = file.map(|writer| tracing_subscriber::fmt::layer().with_ansi(false).with_writer(writer));
+    match format {
+        LogFormat::Text => registry.with(logs.and_then(to_file).and_then(otel).with_filter(filter)).init(),
+        LogFormat::Json => registry
+            .with(
+                logs.json()
+                    .flatten_event(true)
+                    .and_then(to_file.map(|layer| layer.json().flatten_event(true)))
+                    .and_then(otel)
+                    .with_filter(filter),
+            )
+            .init(),
+    }
+    Ok(Guard { _telemetry: guard, _log_file: file_guard })
+}
+
+// The tokio-console layer, when asked for. It sees the runtime's own task
+// and resource spans, which RUST_LOG must not filter out, so it sits beside
+// the filtered layers rather than under them.
+#[cfg(all(feature = "tokio-console", tokio_unstable))]
+fn console<S>(enabled: bool) -> Result<Option<impl Layer<S>>, Box<dyn std::error::Error>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    Ok(enabled.then(|| console_subscriber::ConsoleLayer::builder().with_default_env().spawn()))
+}
+
+#[cfg(not(all(feature = "tokio-console", tokio_unstable)))]
+fn console<S>(enabled: bool) -> Result<Option<impl Layer<S>>, Box<dyn std::error::Error>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    if enabled {
+        return Err("--tokio-console needs a build with RUSTFLAGS=\"--cfg tokio_unstable\" and --features tokio-console".into());
+    }
+    Ok(None::<Identity>)
+}
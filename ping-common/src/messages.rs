@@ -0,0 +1,281 @@
+// Message types
+//
+// Every message and frame that crosses the wire, and the protocol constants,
+// with nothing but serde, uuid and alloc underneath, so they compile without
+// std (`default-features = false`). A client on a microcontroller can depend
+// on ping-common for these instead of copying the structs, and encode them
+// with whatever self-describing serde format it has. Everything here is
+// re-exported at the crate root; what needs std (the client core, histograms,
+// retries, typed clients, codecs, actors and transports) lives in the other
+// modules.
+
+use crate::Error;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Largest frame the HTTP server takes from a client, as a WebSocket message
+/// or a WebTransport line; the wasm client checks its pings against it
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+/// Version of the messages in this crate, exchanged in the `Hello`
+/// handshake; raised whenever a change would break an older peer
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest client version the servers still accept
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Ping message - used across all transports (WebSocket, TCP, etc.)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Ping {
+    pub message: String,
+    pub sequence: u64,
+    /// Name the client gives itself, the same across its connections, so a
+    /// server can tell a returning client from a new one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Unique to this ping and echoed in its Pong, so replies can be matched
+    /// however they are ordered, and a ping followed through the logs of
+    /// every hop
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::uuid_text")]
+    pub correlation_id: Option<Uuid>,
+    /// Unix timestamp in milliseconds when the client sent it, echoed in the
+    /// Pong
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at_ms: Option<u64>,
+    /// Free-form entries for whatever cuts across the protocol, such as a
+    /// trace context (see `builder::TRACEPARENT`), an auth subject or an
+    /// experiment flag. Every bridge carries them unchanged, and the Pong
+    /// echoes them, so a new one needs no schema change.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Pong response - used across all transports (WebSocket, TCP, etc.)
+/// The optional fields echo the Ping's; they are missing when the Ping had
+/// none, as from clients that predate them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Pong {
+    pub message: String,
+    pub sequence: u64,
+    pub total_pings: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::uuid_text")]
+    pub correlation_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Pong {
+    /// Whether this is the reply to `ping`: the sequence numbers match, and
+    /// so do the correlation ids unless the server left them out
+    pub fn answers(&self, ping: &Ping) -> bool {
+        self.sequence == ping.sequence
+            && (self.correlation_id.is_none() || self.correlation_id == ping.correlation_id)
+    }
+}
+
+/// Control message - asks the PingActor for its counters
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct GetStats;
+
+/// Control message - resets the PingActor's counters, replying with the
+/// values they held before the reset
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ResetStats;
+
+/// PingActor counters
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PingStats {
+    pub total_pings: u64,
+}
+
+/// Control message - asks a server's StatsActor for its per-peer counters
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetPeerStats;
+
+/// Counters the server keeps for one connected peer
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PeerStats {
+    pub peer: String,
+    /// Replies sent to the peer, by any of the server's actors
+    pub requests: u64,
+    /// Bytes over the peer's connections, DHT traffic included
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    /// Unix timestamp in milliseconds of the last reply
+    pub last_active_ms: u64,
+    /// Mean time the actors took to answer the peer, in microseconds; only
+    /// known where the server sees requests arrive, as the HTTP bridges do
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_handling_us: Option<u64>,
+}
+
+/// Echo request - the EchoActor replies with the same message
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Echo {
+    pub message: String,
+}
+
+/// Key-value store request handled by the KvActor
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum KvRequest {
+    Get { key: String },
+    Set { key: String, value: String },
+    Delete { key: String },
+}
+
+/// Key-value store response - the value after the operation was applied
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvResponse {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// Counter request handled by the CounterActor
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CounterRequest {
+    Increment { by: i64 },
+    Get,
+    Reset,
+}
+
+/// Counter response - the value after the operation was applied
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CounterValue {
+    pub value: i64,
+}
+
+/// Session status - sent by the HTTP server when a client connects with a
+/// session id, before any missed server-pushed frames are replayed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub resumed: bool,
+    pub messages: u64,
+    pub missed: usize,
+}
+
+/// First frame of a WebSocket client presenting its token, when the HTTP
+/// server requires one and it isn't in the URL
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename = "auth")]
+pub struct Auth {
+    pub token: String,
+}
+
+/// Gossipsub topic the HTTP server publishes its `Broadcast`s on, when it
+/// is in a libp2p swarm
+pub const BROADCAST_TOPIC: &str = "ping/broadcast";
+
+/// Server-originated notice pushed to every connected client
+/// Tagged with `"type": "broadcast"` so clients can tell it apart from Pongs
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename = "broadcast")]
+pub struct Broadcast {
+    pub message: String,
+    /// Unix timestamp in milliseconds when the server sent it
+    pub sent_at_ms: u64,
+}
+
+/// Error reported to a client in place of a reply; `Error::to_reply` makes
+/// one, and `Error::from` reads it back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename = "error")]
+pub struct ErrorReply {
+    pub code: String,
+    pub message: String,
+    /// The sequence number and correlation id of the Ping that failed, when
+    /// the frame was read far enough to have them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::uuid_text")]
+    pub correlation_id: Option<Uuid>,
+}
+
+/// First frame of a client after connecting (after its auth frame, if it
+/// sends one), so a version mismatch is refused up front rather than showing
+/// up as parse errors later. Clients that don't send one are served as if
+/// they spoke the current version.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename = "hello")]
+pub struct Hello {
+    pub protocol_version: u32,
+    /// Codecs the client can use, e.g. "json", "msgpack"
+    #[serde(default)]
+    pub supported_codecs: Vec<String>,
+    /// Name and version of the client, for the server's logs
+    #[serde(default)]
+    pub client_info: String,
+}
+
+/// The server's answer to an accepted `Hello`; a refused one gets an
+/// `ErrorReply` with code `unsupported_version` and the connection is closed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename = "hello_ack")]
+pub struct HelloAck {
+    pub protocol_version: u32,
+    /// Codec of the rest of the connection
+    pub codec: String,
+    pub server_info: String,
+}
+
+impl Hello {
+    /// A hello for the current version
+    pub fn new(supported_codecs: &[&str], client_info: impl Into<String>) -> Self {
+        Hello {
+            protocol_version: PROTOCOL_VERSION,
+            supported_codecs: supported_codecs.iter().map(|codec| codec.to_string()).collect(),
+            client_info: client_info.into(),
+        }
+    }
+
+    /// Accept the hello if its version is supported and it can use one of
+    /// `codecs`, the server's for this connection, best first. A client
+    /// listing no codecs takes the server's first.
+    pub fn negotiate(&self, codecs: &[&str], server_info: &str) -> Result<HelloAck, Error> {
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&self.protocol_version) {
+            return Err(Error::Version(format!(
+                "protocol version {} is not supported, only {} to {}",
+                self.protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            )));
+        }
+        let codec = codecs
+            .iter()
+            .find(|codec| self.supported_codecs.is_empty() || self.supported_codecs.iter().any(|c| c == *codec))
+            .ok_or_else(|| Error::Version(format!("no codec in common, the server offers {}", codecs.join(", "))))?;
+        Ok(HelloAck {
+            protocol_version: self.protocol_version,
+            codec: codec.to_string(),
+            server_info: server_info.to_string(),
+        })
+    }
+}
+
+/// Probe a browser client sends over an idle connection, as JavaScript can't
+/// send WebSocket pings; the HTTP server sends it straight back
+/// An enum so the `type` is checked: a tagged struct without fields would
+/// accept any frame
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum KeepAlive {
+    KeepAlive,
+}
+
+/// Periodic server push carrying the PingActor's counter, so passive
+/// clients can follow the actor without sending pings
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename = "heartbeat")]
+pub struct Heartbeat {
+    pub total_pings: u64,
+    /// Unix timestamp in milliseconds when the server sent it
+    pub sent_at_ms: u64,
+}
@@ -0,0 +1,150 @@
+// Retries and backoff
+//
+// One way of trying again for every client and bridge. `Backoff` hands out
+// the delays of a `RetryPolicy`: doubling from `base` up to `max_delay`,
+// randomized into the upper half of each so a fleet of clients doesn't retry
+// in lockstep, and running out after `max_attempts`. It reads no clock and
+// does no I/O, so the wasm client uses it with its own timers. With tokio,
+// `retry` runs an async operation under a policy, sleeping in between, and
+// gives up as soon as a cancellation future completes, even mid-attempt.
+
+use std::fmt;
+use std::time::Duration;
+
+/// How long to wait between attempts, and how many to make
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Delay before the first retry, doubled for each one after it
+    pub base: Duration,
+    pub max_delay: Duration,
+    /// Attempts in all, the first one included; None to retry forever
+    pub max_attempts: Option<u32>,
+    /// Randomize each delay into the upper half of its value
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Retry forever, with jitter
+    pub const fn new(base: Duration, max_delay: Duration) -> Self {
+        RetryPolicy { base, max_delay, max_attempts: None, jitter: true }
+    }
+
+    pub const fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff { policy: *self, retries: 0 }
+    }
+}
+
+/// The delays of one run of a policy
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    retries: u32,
+}
+
+impl Backoff {
+    /// The delay before retrying after a failed attempt, or None once the
+    /// policy's attempts are used up
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.policy.max_attempts.is_some_and(|max| self.retries.saturating_add(1) >= max) {
+            return None;
+        }
+        let delay = self
+            .policy
+            .base
+            .saturating_mul(2u32.saturating_pow(self.retries))
+            .min(self.policy.max_delay);
+        self.retries = self.retries.saturating_add(1);
+        Some(if self.policy.jitter { delay.mul_f64(0.5 + random() / 2.0) } else { delay })
+    }
+
+    /// Delays handed out so far
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Start over, after a success
+    pub fn reset(&mut self) {
+        self.retries = 0;
+    }
+}
+
+// Uniform in [0, 1). A v4 UUID is random everywhere ping-common builds,
+// browsers included, without another dependency.
+fn random() -> f64 {
+    let (bits, _) = uuid::Uuid::new_v4().as_u64_pair();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Why `retry` gave up
+#[derive(Debug)]
+pub enum RetryError<E> {
+    /// Every attempt failed; the error of the last one
+    Exhausted(E),
+    /// Cancelled first; the error of the last attempt, if one had failed
+    Cancelled(Option<E>),
+}
+
+impl<E> RetryError<E> {
+    /// The last attempt's error, if there was one
+    pub fn into_last(self) -> Option<E> {
+        match self {
+            RetryError::Exhausted(e) => Some(e),
+            RetryError::Cancelled(e) => e,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::Exhausted(e) => write!(f, "gave up retrying: {}", e),
+            RetryError::Cancelled(Some(e)) => write!(f, "cancelled while retrying: {}", e),
+            RetryError::Cancelled(None) => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RetryError<E> {}
+
+/// Run `attempt` until it succeeds, the policy runs out or `cancel`
+/// completes. `attempt` is given the number of retries so far, and
+/// `on_retry` is told each failure with the retry number and delay before
+/// the next attempt.
+#[cfg(feature = "tokio")]
+pub async fn retry<T, E, Fut>(
+    policy: &RetryPolicy,
+    cancel: impl std::future::Future<Output = ()>,
+    mut attempt: impl FnMut(u32) -> Fut,
+    mut on_retry: impl FnMut(&E, u32, Duration),
+) -> Result<T, RetryError<E>>
+where
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    tokio::pin!(cancel);
+    let mut backoff = policy.backoff();
+    let mut last = None;
+    loop {
+        let result = tokio::select! {
+            result = attempt(backoff.retries()) => result,
+            _ = &mut cancel => return Err(RetryError::Cancelled(last)),
+        };
+        let e = match result {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let Some(delay) = backoff.next_delay() else {
+            return Err(RetryError::Exhausted(e));
+        };
+        on_retry(&e, backoff.retries(), delay);
+        last = Some(e);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = &mut cancel => return Err(RetryError::Cancelled(last)),
+        }
+    }
+}
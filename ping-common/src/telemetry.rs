@@ -0,0 +1,76 @@
+// OpenTelemetry export
+//
+// The servers and the CLI client already describe their work as tracing
+// spans: a connection, and inside it each message decoded, handled by an
+// actor and encoded. With an OTLP endpoint configured (`--otlp-endpoint`, or
+// the standard `OTEL_EXPORTER_OTLP_ENDPOINT`) the same spans are exported to
+// an OpenTelemetry collector over OTLP/HTTP, next to the logs, so Jaeger,
+// Tempo or Honeycomb can show where a ping spent its time. Spans go out in
+// batches from a thread of the exporter's own; the guard returned with the
+// layer flushes what is left when the process ends.
+//
+// A trace crosses processes in the Ping itself: a client puts the W3C trace
+// context of its span in the metadata (`builder::TRACEPARENT`), and the
+// bridges and the PingActor open their spans as its children, so a ping is
+// one trace from the client through every hop.
+
+use crate::builder::TRACEPARENT;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Flushes the spans still waiting to be exported when dropped
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        let _ = self.provider.shutdown();
+    }
+}
+
+/// A tracing layer exporting spans as `service` to the collector at
+/// `endpoint` (say `http://localhost:4318`, where `/v1/traces` is added),
+/// and the guard that flushes them
+pub fn layer<S>(service: &'static str, endpoint: &str) -> Result<(OpenTelemetryLayer<S, Tracer>, Telemetry), ExporterBuildError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service).build())
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(service));
+    Ok((layer, Telemetry { provider }))
+}
+
+/// The W3C trace context of the current span, for a Ping to carry; None
+/// when no span is being exported
+pub fn traceparent() -> Option<String> {
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&Span::current().context(), &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// Make `span` a child of the span `traceparent` came from, in another task
+/// or process. Only a span not yet entered takes a parent; a malformed
+/// context leaves it where it was.
+pub fn set_parent(span: &Span, traceparent: &str) {
+    let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.to_string())]);
+    let cx = TraceContextPropagator::new().extract(&carrier);
+    if cx.span().span_context().is_valid() {
+        let _ = span.set_parent(cx);
+    }
+}
@@ -0,0 +1,130 @@
+// Property-test strategies
+//
+// `proptest::arbitrary::Arbitrary` for the messages that cross the wire:
+// Ping and Pong, and the frames around them (hellos and error replies).
+// Optional fields are as often missing as present, strings run through all
+// of Unicode, and numbers cover their whole range, except where `Validate`
+// has a rule: the generated Pings and Pongs pass it, so a test sending them
+// isn't refused before it gets to what it tests. Only with the `testing`
+// feature, so proptest stays out of every other build.
+
+use crate::validate::{MAX_TIMESTAMP_MS, MIN_TIMESTAMP_MS};
+use crate::{ErrorReply, Hello, HelloAck, Ping, Pong, Uuid};
+use proptest::collection::{btree_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+/// Any UUID, not only v4 ones
+pub fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+/// A sequence number that passes `Validate`
+pub fn sequence() -> impl Strategy<Value = u64> {
+    1..=u64::MAX
+}
+
+/// A timestamp that passes `Validate`
+pub fn timestamp_ms() -> impl Strategy<Value = u64> {
+    MIN_TIMESTAMP_MS..=MAX_TIMESTAMP_MS
+}
+
+/// A few metadata entries, often none
+pub fn metadata() -> impl Strategy<Value = BTreeMap<String, String>> {
+    btree_map(any::<String>(), any::<String>(), 0..4)
+}
+
+impl Arbitrary for Ping {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Ping>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<String>(),
+            sequence(),
+            option::of(any::<String>()),
+            option::of(uuid()),
+            option::of(timestamp_ms()),
+            metadata(),
+        )
+            .prop_map(|(message, sequence, client_id, correlation_id, sent_at_ms, metadata)| Ping {
+                message,
+                sequence,
+                client_id,
+                correlation_id,
+                sent_at_ms,
+                metadata,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Pong {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Pong>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<String>(),
+            sequence(),
+            any::<u64>(),
+            option::of(any::<String>()),
+            option::of(uuid()),
+            option::of(timestamp_ms()),
+            metadata(),
+        )
+            .prop_map(|(message, sequence, total_pings, client_id, correlation_id, sent_at_ms, metadata)| Pong {
+                message,
+                sequence,
+                total_pings,
+                client_id,
+                correlation_id,
+                sent_at_ms,
+                metadata,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for ErrorReply {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<ErrorReply>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<String>(), any::<String>(), option::of(any::<u64>()), option::of(uuid()))
+            .prop_map(|(code, message, sequence, correlation_id)| ErrorReply {
+                code,
+                message,
+                sequence,
+                correlation_id,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Hello {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Hello>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<u32>(), vec(any::<String>(), 0..4), any::<String>())
+            .prop_map(|(protocol_version, supported_codecs, client_info)| Hello {
+                protocol_version,
+                supported_codecs,
+                client_info,
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for HelloAck {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<HelloAck>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<u32>(), any::<String>(), any::<String>())
+            .prop_map(|(protocol_version, codec, server_info)| HelloAck { protocol_version, codec, server_info })
+            .boxed()
+    }
+}
@@ -0,0 +1,421 @@
+// Client transports
+//
+// Every way of reaching a PingActor from Rust sits behind `PingTransport`:
+// connect to a target, send Pings, receive Pongs, close. Framing, decoding
+// the server's error replies and skipping its pushes (heartbeats, broadcasts,
+// keepalives) are done here once, so a client or a bridge written against the
+// trait works over any of them, and `Reconnecting` adds re-opening a broken
+// connection to any of them, and refuses pings that fail `Validate`; behind
+// a mutex, a `Reconnecting` connection is also a transport for typed clients
+// (see `typed`). Replies come back in the order the server sends them, which
+// need not be the order of the Pings; callers match them up by sequence
+// number. Every transport opens with the `Hello` handshake, so
+// connecting to a server that speaks another protocol version fails with its
+// `unsupported_version` error.
+//
+// - `WebSocket`: ping-http-server's `/ws` endpoint, one JSON text frame each
+// - `Tcp`: ping-http-server's `--tcp-port` endpoint, one JSON frame per line
+// - `Libp2p` (with the `actor` feature): a PingActor looked up over kameo
+
+use crate::{Auth, Error, ErrorReply, Hello, HelloAck, Ping, Pong, Validate, ValidationError};
+use futures::{SinkExt, StreamExt};
+use crate::typed::Ask;
+use std::fmt;
+use std::future::Future;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::Mutex,
+};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+/// A connection to a PingActor
+pub trait PingTransport: Sized + Send {
+    /// What `connect` is given: an address, a URL, an actor reference
+    type Target: Clone + Send + Sync;
+
+    fn connect(target: &Self::Target) -> impl Future<Output = Result<Self, TransportError>> + Send;
+
+    /// Send one Ping; its Pong comes from `recv`
+    fn send(&mut self, ping: &Ping) -> impl Future<Output = Result<(), TransportError>> + Send;
+
+    /// The next Pong, or the server's error in place of one (for any ping;
+    /// its `sequence` tells which, if it is known). Cancel-safe: a
+    /// reply that was being received when the future was dropped is returned
+    /// by the next call.
+    fn recv(&mut self) -> impl Future<Output = Result<Pong, TransportError>> + Send;
+
+    fn close(self) -> impl Future<Output = Result<(), TransportError>> + Send;
+}
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(std::io::Error),
+    // Boxed, as it would make every Result of this module large
+    WebSocket(Box<tungstenite::Error>),
+    /// The other side ended the connection
+    Closed,
+    /// The server answered with an error instead of a Pong
+    Server(ErrorReply),
+    Libp2p(String),
+    /// The ping broke a `Validate` rule, so it wasn't sent
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Io(e) => write!(f, "connection error: {}", e),
+            TransportError::WebSocket(e) => write!(f, "websocket error: {}", e),
+            TransportError::Closed => write!(f, "connection closed by the server"),
+            TransportError::Server(e) => write!(f, "server error ({}): {}", e.code, e.message),
+            TransportError::Libp2p(e) => write!(f, "{}", e),
+            TransportError::Invalid(e) => write!(f, "invalid ping: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<std::io::Error> for TransportError {
+    fn from(e: std::io::Error) -> Self {
+        TransportError::Io(e)
+    }
+}
+
+impl From<tungstenite::Error> for TransportError {
+    fn from(e: tungstenite::Error) -> Self {
+        TransportError::WebSocket(Box::new(e))
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(e: TransportError) -> Self {
+        match e {
+            TransportError::Server(reply) => Error::from(reply),
+            TransportError::Libp2p(e) => Error::Actor(e),
+            TransportError::Invalid(e) => Error::from(e),
+            e => Error::Transport(e.to_string()),
+        }
+    }
+}
+
+impl TransportError {
+    /// The connection is unusable and has to be opened again; a server error
+    /// leaves it working
+    pub fn is_broken(&self) -> bool {
+        matches!(self, TransportError::Io(_) | TransportError::WebSocket(_) | TransportError::Closed)
+    }
+}
+
+// A frame from the server: a Pong, an error in its place, or a push to skip
+fn decode(text: &str) -> Option<Result<Pong, TransportError>> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    match value.get("type").and_then(serde_json::Value::as_str) {
+        Some("error") => serde_json::from_value(value).ok().map(|e| Err(TransportError::Server(e))),
+        Some(_) => None,
+        None => serde_json::from_value(value).ok().map(Ok),
+    }
+}
+
+// The Hello every transport opens with
+fn hello(codecs: &[&str]) -> Hello {
+    Hello::new(codecs, concat!("ping-common/", env!("CARGO_PKG_VERSION")))
+}
+
+// A frame from the server while waiting for the HelloAck: the ack, a refusal,
+// or anything else to skip
+fn decode_ack(text: &str) -> Option<Result<HelloAck, TransportError>> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    match value.get("type").and_then(serde_json::Value::as_str)? {
+        "hello_ack" => serde_json::from_value(value).ok().map(Ok),
+        "error" => serde_json::from_value(value).ok().map(|e| Err(TransportError::Server(e))),
+        _ => None,
+    }
+}
+
+/// ping-http-server's WebSocket endpoint; the target is its URL, with
+/// `?token=` when the server wants one
+pub struct WebSocket {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl PingTransport for WebSocket {
+    type Target = String;
+
+    async fn connect(url: &String) -> Result<Self, TransportError> {
+        let (mut socket, _) = connect_async(url.as_str()).await?;
+        let hello = serde_json::to_string(&hello(&["json"])).expect("Hello serializes");
+        socket.send(Message::Text(hello)).await?;
+        while let Some(message) = socket.next().await {
+            match message? {
+                Message::Text(text) => match decode_ack(&text) {
+                    Some(Ok(_)) => return Ok(WebSocket { socket }),
+                    Some(Err(e)) => return Err(e),
+                    None => {}
+                },
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        Err(TransportError::Closed)
+    }
+
+    async fn send(&mut self, ping: &Ping) -> Result<(), TransportError> {
+        let text = serde_json::to_string(ping).expect("Ping serializes");
+        self.socket.send(Message::Text(text)).await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Pong, TransportError> {
+        while let Some(message) = self.socket.next().await {
+            match message? {
+                Message::Text(text) => {
+                    if let Some(reply) = decode(&text) {
+                        return reply;
+                    }
+                }
+                Message::Close(_) => return Err(TransportError::Closed),
+                _ => {}
+            }
+        }
+        Err(TransportError::Closed)
+    }
+
+    async fn close(mut self) -> Result<(), TransportError> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
+
+/// Where a `Tcp` connection goes
+#[derive(Clone, Debug)]
+pub struct TcpTarget {
+    /// `host:port` of ping-http-server's `--tcp-port` endpoint
+    pub addr: String,
+    /// Sent as the first line when the server requires a token
+    pub token: Option<String>,
+}
+
+/// ping-http-server's plain TCP endpoint, one JSON frame per line
+pub struct Tcp {
+    stream: TcpStream,
+    // Bytes read but not yet returned as a line; kept here rather than in a
+    // BufReader future so `recv` is cancel-safe
+    buffer: Vec<u8>,
+}
+
+impl Tcp {
+    // Write one frame as a line
+    async fn write_line(&mut self, json: String) -> Result<(), TransportError> {
+        let mut line = json.into_bytes();
+        line.push(b'\n');
+        self.stream.write_all(&line).await?;
+        Ok(())
+    }
+
+    // The next non-empty line
+    async fn read_line(&mut self) -> Result<String, TransportError> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let text = String::from_utf8_lossy(&line);
+                if !text.trim().is_empty() {
+                    return Ok(text.trim_end().to_string());
+                }
+                continue;
+            }
+            if self.buffer.len() > crate::MAX_FRAME_BYTES {
+                return Err(TransportError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "line longer than MAX_FRAME_BYTES",
+                )));
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(TransportError::Closed);
+            }
+        }
+    }
+}
+
+impl PingTransport for Tcp {
+    type Target = TcpTarget;
+
+    async fn connect(target: &TcpTarget) -> Result<Self, TransportError> {
+        let stream = TcpStream::connect(target.addr.as_str()).await?;
+        stream.set_nodelay(true)?;
+        let mut tcp = Tcp { stream, buffer: Vec::new() };
+        if let Some(token) = &target.token {
+            let auth = Auth { token: token.clone() };
+            tcp.write_line(serde_json::to_string(&auth).expect("Auth serializes")).await?;
+        }
+        tcp.write_line(serde_json::to_string(&hello(&["json"])).expect("Hello serializes")).await?;
+        loop {
+            if let Some(ack) = decode_ack(&tcp.read_line().await?) {
+                return ack.map(|_| tcp);
+            }
+        }
+    }
+
+    async fn send(&mut self, ping: &Ping) -> Result<(), TransportError> {
+        self.write_line(serde_json::to_string(ping).expect("Ping serializes")).await
+    }
+
+    async fn recv(&mut self) -> Result<Pong, TransportError> {
+        loop {
+            if let Some(reply) = decode(&self.read_line().await?) {
+                return reply;
+            }
+        }
+    }
+
+    async fn close(mut self) -> Result<(), TransportError> {
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "actor")]
+pub use self::libp2p::Libp2p;
+
+#[cfg(feature = "actor")]
+mod libp2p {
+    use super::{PingTransport, TransportError};
+    use crate::{HelloReply, Ping, PingActor, PingActorClient, Pong};
+    use kameo::prelude::*;
+    use tokio::sync::mpsc;
+
+    /// A PingActor reached over kameo's libp2p remoting; the target is the
+    /// actor reference its lookup returned, and kameo keeps the connection
+    pub struct Libp2p {
+        actor: RemoteActorRef<PingActor>,
+        // Each Ping is asked in its own task, whose reply lands here
+        replies_tx: mpsc::UnboundedSender<Result<Pong, TransportError>>,
+        replies: mpsc::UnboundedReceiver<Result<Pong, TransportError>>,
+    }
+
+    impl PingTransport for Libp2p {
+        type Target = RemoteActorRef<PingActor>;
+
+        async fn connect(actor: &RemoteActorRef<PingActor>) -> Result<Self, TransportError> {
+            let HelloReply(ack) = PingActorClient::new(actor)
+                .hello(super::hello(&["msgpack"]))
+                .await
+                .map_err(|e| TransportError::Libp2p(e.to_string()))?;
+            ack.map_err(TransportError::Server)?;
+            let (replies_tx, replies) = mpsc::unbounded_channel();
+            Ok(Libp2p { actor: actor.clone(), replies_tx, replies })
+        }
+
+        async fn send(&mut self, ping: &Ping) -> Result<(), TransportError> {
+            let actor = PingActorClient::new(self.actor.clone());
+            let ping = ping.clone();
+            let replies = self.replies_tx.clone();
+            tokio::spawn(async move {
+                let reply = actor.ping(ping).await;
+                let _ = replies.send(reply.map_err(|e| TransportError::Libp2p(e.to_string())));
+            });
+            Ok(())
+        }
+
+        async fn recv(&mut self) -> Result<Pong, TransportError> {
+            // The sender half is kept in self, so the channel never ends
+            self.replies.recv().await.unwrap_or(Err(TransportError::Closed))
+        }
+
+        async fn close(self) -> Result<(), TransportError> {
+            Ok(())
+        }
+    }
+}
+
+/// Any transport, opened again on the next `send` after an error that broke
+/// it. Replies still on their way over the broken connection are lost.
+pub struct Reconnecting<T: PingTransport> {
+    target: T::Target,
+    connection: Option<T>,
+}
+
+impl<T: PingTransport> Reconnecting<T> {
+    /// Connect up front, so a wrong target fails straight away
+    pub async fn connect(target: T::Target) -> Result<Self, TransportError> {
+        let connection = T::connect(&target).await?;
+        Ok(Reconnecting { target, connection: Some(connection) })
+    }
+
+    pub fn target(&self) -> &T::Target {
+        &self.target
+    }
+
+    /// Send `ping` if it passes `Validate`
+    pub async fn send(&mut self, ping: &Ping) -> Result<(), TransportError> {
+        ping.validate().map_err(TransportError::Invalid)?;
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => self.connection.insert(T::connect(&self.target).await?),
+        };
+        let result = connection.send(ping).await;
+        self.check(result)
+    }
+
+    /// Cancel-safe, as `PingTransport::recv`; fails with `Closed` while there
+    /// is no connection
+    pub async fn recv(&mut self) -> Result<Pong, TransportError> {
+        let Some(connection) = self.connection.as_mut() else {
+            return Err(TransportError::Closed);
+        };
+        let result = connection.recv().await;
+        self.check(result)
+    }
+
+    /// Give up on the connection, so the next `send` opens a new one
+    pub fn reset(&mut self) {
+        self.connection = None;
+    }
+
+    pub async fn close(self) -> Result<(), TransportError> {
+        match self.connection {
+            Some(connection) => connection.close().await,
+            None => Ok(()),
+        }
+    }
+
+    // Drop the connection if the error broke it
+    fn check<R>(&mut self, result: Result<R, TransportError>) -> Result<R, TransportError> {
+        if result.as_ref().is_err_and(TransportError::is_broken) {
+            self.connection = None;
+        }
+        result
+    }
+
+    // Send one Ping and wait for its Pong, skipping the replies and errors
+    // of earlier pings
+    async fn exchange(&mut self, ping: &Ping) -> Result<Pong, TransportError> {
+        self.send(ping).await?;
+        loop {
+            match self.recv().await {
+                Ok(pong) if pong.answers(ping) => return Ok(pong),
+                Ok(_) => {}
+                Err(TransportError::Server(e)) if e.sequence.is_some_and(|sequence| sequence != ping.sequence) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// One ping at a time over the connection, for `typed_client!` clients
+impl<T: PingTransport> Ask<Ping> for Mutex<Reconnecting<T>>
+where
+    T::Target: Send,
+{
+    type Reply = Pong;
+    type Error = TransportError;
+
+    async fn ask(&self, ping: Ping) -> Result<Pong, TransportError> {
+        self.lock().await.exchange(&ping).await
+    }
+}
@@ -0,0 +1,56 @@
+// Transport tuning
+//
+// The connection settings SwarmBuilder::with_tcp would otherwise fix, the
+// same for ping-cli-server and ping-cli-client: TCP_NODELAY, how long a new
+// connection may take over its noise and yamux handshakes, and yamux's
+// per-substream receive window and buffer. Setting either yamux size switches
+// to the older yamux implementation, the only one libp2p lets tune them; the
+// newer one sizes its windows by itself. A window can't be smaller than the
+// 256 KiB every yamux substream starts with.
+
+use libp2p::{
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade},
+    identity::Keypair,
+    noise, tcp, yamux, PeerId, Transport,
+};
+use std::time::Duration;
+
+/// Smallest yamux receive window
+pub const MIN_YAMUX_WINDOW: u32 = 256 * 1024;
+
+/// How the libp2p TCP transport is set up
+pub struct Tuning {
+    pub nodelay: bool,
+    pub handshake_timeout: Duration,
+    pub yamux_window: Option<u32>,
+    pub yamux_buffer: Option<usize>,
+}
+
+impl Tuning {
+    /// TCP with noise and yamux as configured, for SwarmBuilder's
+    /// with_other_transport
+    pub fn transport(
+        &self,
+        key: &Keypair,
+    ) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(tcp::tokio::Transport::new(tcp::Config::new().nodelay(self.nodelay))
+            .upgrade(upgrade::Version::V1Lazy)
+            .authenticate(noise::Config::new(key)?)
+            .multiplex(self.yamux())
+            .timeout(self.handshake_timeout)
+            .map(|(peer, muxer), _| (peer, StreamMuxerBox::new(muxer)))
+            .boxed())
+    }
+
+    #[allow(deprecated)]
+    fn yamux(&self) -> yamux::Config {
+        let mut config = yamux::Config::default();
+        if let Some(window) = self.yamux_window {
+            config.set_receive_window_size(window);
+        }
+        if let Some(buffer) = self.yamux_buffer {
+            config.set_max_buffer_size(buffer);
+        }
+        config
+    }
+}
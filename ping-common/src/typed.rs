@@ -0,0 +1,136 @@
+// Typed clients
+//
+// `typed_client!` turns a list of an actor's messages and replies into a
+// client struct with one async method per message, e.g. `client.ping(ping)`
+// returning a `Pong` and `client.get_stats()` returning `PingStats`. The
+// client is generic over whatever carries the messages: anything that
+// implements `Ask` for a message gets that message's method. `Ask` is
+// implemented here for kameo's `ActorRef` and `RemoteActorRef` (with the
+// `actor` feature), and for a `PingTransport` connection behind a mutex (with
+// the `transport` feature), which only carries pings. Reply wrappers such as
+// `PongReply` are unwrapped through their `From` impls, so callers never
+// deal with them.
+
+use std::future::Future;
+
+/// Delivers message `M` to an actor and brings back its reply
+pub trait Ask<M>: Sync {
+    type Reply;
+    type Error;
+
+    fn ask(&self, msg: M) -> impl Future<Output = Result<Self::Reply, Self::Error>> + Send;
+}
+
+impl<M, T: Ask<M>> Ask<M> for &T {
+    type Reply = T::Reply;
+    type Error = T::Error;
+
+    fn ask(&self, msg: M) -> impl Future<Output = Result<Self::Reply, Self::Error>> + Send {
+        (**self).ask(msg)
+    }
+}
+
+/// Declare a typed client: a struct wrapping a transport, with one method
+/// per message. Each method is available when the transport implements
+/// `Ask` for its message, and returns the reply converted with `Into`.
+/// A message without fields can be named after the reply instead, for a
+/// method that takes no argument and sends the message's `Default`.
+///
+/// ```ignore
+/// typed_client! {
+///     pub struct PingActorClient {
+///         fn ping(Ping) -> Pong;
+///         fn get_stats() -> PingStats = GetStats;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! typed_client {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $client:ident {
+            $( $(#[$method_meta:meta])* fn $method:ident($($message:ty)?) -> $reply:ty $(= $unit:ty)?; )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $client<T> {
+            transport: T,
+        }
+
+        impl<T> $client<T> {
+            pub fn new(transport: T) -> Self {
+                $client { transport }
+            }
+
+            pub fn get_ref(&self) -> &T {
+                &self.transport
+            }
+
+            pub fn into_inner(self) -> T {
+                self.transport
+            }
+
+            $(
+                $crate::typed_client!(@method [$(#[$method_meta])*] $method [$($message)?] [$($unit)?] $reply);
+            )*
+        }
+    };
+    (@method [$(#[$meta:meta])*] $method:ident [] [$message:ty] $reply:ty) => {
+        $(#[$meta])*
+        pub async fn $method(&self) -> Result<$reply, <T as $crate::typed::Ask<$message>>::Error>
+        where
+            T: $crate::typed::Ask<$message>,
+            <T as $crate::typed::Ask<$message>>::Reply: Into<$reply>,
+        {
+            self.transport.ask(<$message>::default()).await.map(Into::into)
+        }
+    };
+    (@method [$(#[$meta:meta])*] $method:ident [$message:ty] [] $reply:ty) => {
+        $(#[$meta])*
+        pub async fn $method(&self, msg: $message) -> Result<$reply, <T as $crate::typed::Ask<$message>>::Error>
+        where
+            T: $crate::typed::Ask<$message>,
+            <T as $crate::typed::Ask<$message>>::Reply: Into<$reply>,
+        {
+            self.transport.ask(msg).await.map(Into::into)
+        }
+    };
+}
+
+#[cfg(feature = "actor")]
+mod kameo_refs {
+    use super::Ask;
+    use kameo::error::{RemoteSendError, SendError};
+    use kameo::prelude::*;
+    use kameo::remote::RemoteMessage;
+    use kameo::reply::Reply;
+    use serde::{de::DeserializeOwned, Serialize};
+
+    impl<A, M> Ask<M> for ActorRef<A>
+    where
+        A: Actor + Message<M>,
+        M: Send + 'static,
+    {
+        type Reply = <A::Reply as Reply>::Ok;
+        type Error = SendError<M, <A::Reply as Reply>::Error>;
+
+        async fn ask(&self, msg: M) -> Result<Self::Reply, Self::Error> {
+            ActorRef::ask(self, msg).await
+        }
+    }
+
+    impl<A, M> Ask<M> for RemoteActorRef<A>
+    where
+        A: RemoteActor + Message<M> + RemoteMessage<M>,
+        M: Serialize + Send + Sync + 'static,
+        <A::Reply as Reply>::Ok: DeserializeOwned,
+        <A::Reply as Reply>::Error: DeserializeOwned,
+    {
+        type Reply = <A::Reply as Reply>::Ok;
+        type Error = RemoteSendError<<A::Reply as Reply>::Error>;
+
+        async fn ask(&self, msg: M) -> Result<Self::Reply, Self::Error> {
+            RemoteActorRef::ask(self, &msg).await
+        }
+    }
+}
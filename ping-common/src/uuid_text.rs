@@ -0,0 +1,54 @@
+// Correlation ids as text
+//
+// `Uuid` serializes as a string in JSON but as 16 raw bytes in MessagePack,
+// and the HTTP server transcodes MessagePack frames through JSON, which has
+// no bytes: a MessagePack ping with a correlation id couldn't be read. With
+// `#[serde(with = "uuid_text")]` an `Option<Uuid>` is a hyphenated string in
+// every format, and is read back from a string or, as older MessagePack
+// peers send it, from bytes.
+
+use crate::Uuid;
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+use ::core::fmt;
+
+pub fn serialize<S: Serializer>(id: &Option<Uuid>, serializer: S) -> Result<S::Ok, S::Error> {
+    match id {
+        Some(id) => serializer.serialize_some(&id.hyphenated()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Uuid>, D::Error> {
+    deserializer.deserialize_option(IdVisitor)
+}
+
+struct IdVisitor;
+
+impl<'de> Visitor<'de> for IdVisitor {
+    type Value = Option<Uuid>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a UUID as a string or 16 bytes")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(IdVisitor)
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<Self::Value, E> {
+        Uuid::parse_str(text).map(Some).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Uuid::from_slice(bytes).map(Some).map_err(E::custom)
+    }
+}
@@ -0,0 +1,103 @@
+// Message validation
+//
+// Rules a message must pass before it is worth an actor's time, checked in
+// the same place by both sides: every bridge checks a frame once it decodes
+// and before the actor sees it, and the clients check their pings before
+// sending them, so a bad ping fails on the spot instead of after a round trip.
+// A message breaking a rule is answered with an `ErrorReply` coded
+// `invalid_message`, naming the rule. The checks read no clock, so they run
+// the same in the browser.
+
+use crate::{Echo, KvRequest, Ping, Pong};
+use ::core::fmt;
+
+/// Longest `message` of a Ping or Pong, in bytes: the CLI server's default
+/// limit on a libp2p message
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Earliest `sent_at_ms` taken as genuine: 2020-01-01
+pub const MIN_TIMESTAMP_MS: u64 = 1_577_836_800_000;
+
+/// Latest `sent_at_ms` taken as genuine: 2100-01-01. Anything outside these
+/// bounds is a broken clock, or a timestamp in seconds or microseconds.
+pub const MAX_TIMESTAMP_MS: u64 = 4_102_444_800_000;
+
+/// A rule a message broke
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    MessageTooLong { bytes: usize, max: usize },
+    /// Sequence numbers start at 1
+    ZeroSequence,
+    Timestamp(u64),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MessageTooLong { bytes, max } => {
+                write!(f, "message is {} bytes, more than {}", bytes, max)
+            }
+            ValidationError::ZeroSequence => write!(f, "sequence must not be 0"),
+            ValidationError::Timestamp(ms) => write!(
+                f,
+                "sent_at_ms {} is not between {} and {}",
+                ms, MIN_TIMESTAMP_MS, MAX_TIMESTAMP_MS
+            ),
+        }
+    }
+}
+
+impl ::core::error::Error for ValidationError {}
+
+/// A message that can check itself. Messages without rules of their own
+/// keep the default, which accepts everything.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+fn check_message(message: &str) -> Result<(), ValidationError> {
+    if message.len() > MAX_MESSAGE_BYTES {
+        return Err(ValidationError::MessageTooLong { bytes: message.len(), max: MAX_MESSAGE_BYTES });
+    }
+    Ok(())
+}
+
+fn check_sequence(sequence: u64) -> Result<(), ValidationError> {
+    if sequence == 0 {
+        return Err(ValidationError::ZeroSequence);
+    }
+    Ok(())
+}
+
+fn check_timestamp(sent_at_ms: Option<u64>) -> Result<(), ValidationError> {
+    match sent_at_ms {
+        Some(ms) if !(MIN_TIMESTAMP_MS..=MAX_TIMESTAMP_MS).contains(&ms) => Err(ValidationError::Timestamp(ms)),
+        _ => Ok(()),
+    }
+}
+
+impl Validate for Ping {
+    fn validate(&self) -> Result<(), ValidationError> {
+        check_message(&self.message)?;
+        check_sequence(self.sequence)?;
+        check_timestamp(self.sent_at_ms)
+    }
+}
+
+impl Validate for Pong {
+    fn validate(&self) -> Result<(), ValidationError> {
+        check_message(&self.message)?;
+        check_sequence(self.sequence)?;
+        check_timestamp(self.sent_at_ms)
+    }
+}
+
+impl Validate for Echo {
+    fn validate(&self) -> Result<(), ValidationError> {
+        check_message(&self.message)
+    }
+}
+
+impl Validate for KvRequest {}
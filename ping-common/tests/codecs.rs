@@ -0,0 +1,64 @@
+// Codec round trips
+//
+// Every message must come back unchanged from each way it is encoded: JSON
+// (WebSocket text frames, WebTransport and TCP lines), MessagePack with named
+// fields (the `ping.msgpack` subprotocol, and kameo over libp2p), and the HTTP
+// server's transcoding between the two, which goes through a
+// `serde_json::Value` without knowing the message type.
+
+use ping_common::codec::{self, json::Json, msgpack::Msgpack, Codec};
+use ping_common::{ErrorReply, Hello, HelloAck, Ping, Pong};
+use proptest::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+fn round_trip<C: Codec, T: Serialize + DeserializeOwned>(msg: &T) -> T {
+    C::decode(&C::encode(msg).unwrap()).unwrap()
+}
+
+// A MessagePack frame read by a JSON route, as ping-http-server does
+fn msgpack_to_json<T: Serialize + DeserializeOwned>(msg: &T) -> T {
+    let json = codec::msgpack::to_json(&Msgpack::encode(msg).unwrap()).unwrap();
+    codec::json::from_str(&json).unwrap()
+}
+
+// A JSON reply sent to a MessagePack client, as ping-http-server does
+fn json_to_msgpack<T: Serialize + DeserializeOwned>(msg: &T) -> T {
+    let frame = codec::msgpack::from_json(&codec::json::to_string(msg).unwrap()).unwrap();
+    Msgpack::decode(&frame).unwrap()
+}
+
+fn round_trips<T: Serialize + DeserializeOwned + PartialEq + Debug>(msg: T) -> Result<(), TestCaseError> {
+    prop_assert_eq!(&round_trip::<Json, _>(&msg), &msg, "JSON");
+    prop_assert_eq!(&round_trip::<Msgpack, _>(&msg), &msg, "MessagePack");
+    prop_assert_eq!(&msgpack_to_json(&msg), &msg, "MessagePack read as JSON");
+    prop_assert_eq!(&json_to_msgpack(&msg), &msg, "JSON sent as MessagePack");
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn ping(ping: Ping) {
+        round_trips(ping)?;
+    }
+
+    #[test]
+    fn pong(pong: Pong) {
+        round_trips(pong)?;
+    }
+
+    #[test]
+    fn error_reply(reply: ErrorReply) {
+        round_trips(reply)?;
+    }
+
+    #[test]
+    fn hello(hello: Hello) {
+        round_trips(hello)?;
+    }
+
+    #[test]
+    fn hello_ack(ack: HelloAck) {
+        round_trips(ack)?;
+    }
+}
@@ -0,0 +1,5 @@
+// Rebuild when anything under static/ changes, including files added by
+// buildwasm.sh, so the embedded assets are never stale
+fn main() {
+    println!("cargo:rerun-if-changed=static");
+}
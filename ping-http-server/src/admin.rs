@@ -0,0 +1,162 @@
+// Admin endpoints
+//
+// Server-side controls that are not part of the actor protocol. Every route
+// under /admin requires `Authorization: Bearer <token>` matching
+// `--admin-token`; without a configured token the admin API is disabled.
+
+use crate::client::ClientIp;
+use crate::connections::{ConnectionId, ConnectionSummary};
+use crate::dashboard;
+use crate::sessions::SessionSummary;
+use crate::{unix_millis, AppState};
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use ping_common::latency::LatencySummary;
+use ping_common::{Broadcast, GetPeerStats, PingStats};
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+type AdminResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+// Build the /admin router, guarded by the admin token
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/actor", get(actor_stats))
+        .route("/actor/reset", post(reset_actor))
+        .route("/latency", get(latency))
+        .route("/sessions", get(list_sessions))
+        .route("/connections", get(list_connections))
+        .route("/connections/:id", delete(kick_connection))
+        .route("/broadcast", post(broadcast))
+        .route_layer(middleware::from_fn_with_state(state, require_token))
+}
+
+// Reject requests without the configured bearer token
+async fn require_token(
+    State(state): State<AppState>,
+    ClientIp(client): ClientIp,
+    request: Request,
+    next: Next,
+) -> Response {
+    let settings = state.config.current();
+    let Some(expected) = settings.admin_token.as_deref() else {
+        return (StatusCode::FORBIDDEN, "Admin API disabled: start the server with --admin-token")
+            .into_response();
+    };
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            warn!("Rejected admin request to {} from {}", request.uri().path(), client);
+            (StatusCode::UNAUTHORIZED, "Invalid admin token").into_response()
+        }
+    }
+}
+
+// Compare tokens without leaking the position of the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn actor_error(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("Actor error: {}", e))
+}
+
+// Inspect the PingActor's counters
+async fn actor_stats(State(state): State<AppState>) -> AdminResult<PingStats> {
+    let stats = state.ping.stats().await.map_err(actor_error)?;
+    Ok(Json(stats))
+}
+
+// Reset the PingActor's counters, returning the values before the reset
+async fn reset_actor(State(state): State<AppState>) -> AdminResult<PingStats> {
+    let previous = state.ping.reset().await.map_err(actor_error)?;
+    info!("PingActor counters reset (was {} pings)", previous.total_pings);
+    Ok(Json(previous))
+}
+
+// Percentiles of the time the actors took to answer bridged frames
+async fn latency(State(state): State<AppState>) -> Json<LatencySummary> {
+    Json(state.latency.lock().unwrap().summary())
+}
+
+async fn list_sessions(State(state): State<AppState>) -> Json<Vec<SessionSummary>> {
+    Json(state.sessions.list())
+}
+
+// List the live WebSocket connections, with the mean handling time the
+// StatsActor has for each
+async fn list_connections(State(state): State<AppState>) -> Json<Vec<ConnectionSummary>> {
+    let stats = state.stats.ask(GetPeerStats).await.map(|reply| reply.0).unwrap_or_default();
+    let handling: HashMap<_, _> = stats.into_iter().map(|peer| (peer.peer, peer.avg_handling_us)).collect();
+    let mut connections = state.connections.list();
+    for conn in &mut connections {
+        conn.avg_handling_us = handling.get(&dashboard::peer_name(conn.id, conn.client)).copied().flatten();
+    }
+    Json(connections)
+}
+
+// Close one client connection
+async fn kick_connection(
+    State(state): State<AppState>,
+    Path(id): Path<ConnectionId>,
+) -> StatusCode {
+    if state.connections.close(id, "kicked by admin") {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BroadcastRequest {
+    message: String,
+}
+
+#[derive(Serialize)]
+pub struct BroadcastResponse {
+    delivered: usize,
+    queued: usize,
+    /// Handed to the libp2p swarm for gossipsub
+    published: bool,
+}
+
+// Push a server-originated message to every connected WebSocket client,
+// queueing it for disconnected sessions, and publish it on gossipsub
+async fn broadcast(
+    State(state): State<AppState>,
+    Json(request): Json<BroadcastRequest>,
+) -> AdminResult<BroadcastResponse> {
+    let broadcast = Broadcast {
+        message: request.message,
+        sent_at_ms: unix_millis(),
+    };
+    let frame = serde_json::to_string(&broadcast)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let delivered = state.connections.broadcast(&frame);
+    let queued = state.sessions.queue_missed(&frame);
+    let published = state.gossip.as_ref().is_some_and(|gossip| gossip.publish(&frame));
+    info!(
+        "Broadcast delivered to {} client(s), queued for {} session(s){}",
+        delivered,
+        queued,
+        if published { ", published on gossipsub" } else { "" }
+    );
+
+    Ok(Json(BroadcastResponse { delivered, queued, published }))
+}
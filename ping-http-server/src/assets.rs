@@ -0,0 +1,53 @@
+// Static assets embedded in the binary
+//
+// Everything under `ping-http-server/static` (the JavaScript client page,
+// the dashboard, and the wasm page plus the bundle `buildwasm.sh` builds into
+// `static/wasm`) is compiled into the executable, so the server works from
+// any working directory.
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Assets;
+
+// Serve the main HTML page with embedded JavaScript client
+pub async fn serve_index() -> Response {
+    asset("index.html")
+}
+
+// Serve the live dashboard
+pub async fn serve_dashboard() -> Response {
+    asset("dashboard.html")
+}
+
+// Serve the page driving the compiled WasmPingClient
+pub async fn serve_wasm_index() -> Response {
+    asset("wasm/index.html")
+}
+
+// Serve the wasm-bindgen bundle under /wasm
+pub async fn serve_wasm(Path(path): Path<String>) -> Response {
+    asset(&format!("wasm/{}", path))
+}
+
+// Serve files under /static
+pub async fn serve_static(Path(path): Path<String>) -> Response {
+    asset(&path)
+}
+
+fn asset(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.metadata.mimetype().to_string())],
+            file.data,
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "Not found").into_response(),
+    }
+}
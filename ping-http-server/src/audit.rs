@@ -0,0 +1,118 @@
+// Ping audit log
+//
+// With `--audit-log <path>` every Ping the server answers is appended to a
+// file as one JSON line: when it was handled, who sent it (client address,
+// connection, session and the client id it gave, if any) over which
+// transport, its sequence number and the size of its message. Sorting by
+// client or session then shows what each of several clients did in a session,
+// after the fact. Once the file would grow past `--audit-max-bytes` it is
+// renamed to `<path>.1` (shifting older ones to `.2`, `.3`, ...) and a fresh
+// file is started; at most `--audit-keep` rotated files are kept.
+//
+// Only the WebSocket, WebTransport and TCP side can be audited this way: the
+// libp2p server's actors aren't told which peer sent a message.
+
+use crate::connections::ConnectionId;
+use ping_common::{Ping, Uuid};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+// One line of the log
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    // Unix timestamp in milliseconds
+    timestamp_ms: u64,
+    transport: &'static str,
+    client: IpAddr,
+    conn: ConnectionId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<Uuid>,
+    sequence: u64,
+    payload_bytes: usize,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Mutex<(File, u64)>,
+}
+
+impl AuditLog {
+    /// Append to `path`, rotating it at `max_bytes` and keeping `keep`
+    /// rotated files
+    pub fn open(path: PathBuf, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let file = append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, max_bytes, keep, file: Mutex::new((file, size)) })
+    }
+
+    /// Record `ping` as handled for `client` over `transport`; failures are
+    /// logged, the ping has been answered either way
+    pub fn record(&self, ping: &Ping, transport: &'static str, client: IpAddr, conn: ConnectionId, session: Option<&str>) {
+        let record = AuditRecord {
+            timestamp_ms: crate::unix_millis(),
+            transport,
+            client,
+            conn,
+            session,
+            client_id: ping.client_id.as_deref(),
+            correlation_id: ping.correlation_id,
+            sequence: ping.sequence,
+            payload_bytes: ping.message.len(),
+        };
+        let mut line = serde_json::to_vec(&record).expect("audit records serialize");
+        line.push(b'\n');
+        if let Err(e) = self.write(&line) {
+            warn!("Failed to write the audit log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn write(&self, line: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let (current, size) = &mut *file;
+        if *size > 0 && *size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+            *current = append(&self.path)?;
+            *size = 0;
+        }
+        current.write_all(line)?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+
+    // Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and the current
+    // file to `<path>.1`
+    fn rotate(&self) -> io::Result<()> {
+        if self.keep == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let _ = fs::remove_file(rotated(&self.path, self.keep));
+        for n in (1..self.keep).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                fs::rename(from, rotated(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(&self.path, 1))
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
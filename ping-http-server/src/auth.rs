@@ -0,0 +1,68 @@
+// WebSocket authentication
+//
+// With `--ws-token` (or `auth.ws_tokens` in the config file) a WebSocket
+// client has to present one of the tokens before any of its frames reach an
+// actor: as `?token=` on the URL, or in an `{"type": "auth", "token": ...}`
+// frame sent first thing after connecting, which keeps it out of access
+// logs. A client that doesn't is closed with code 4401. Browsers hand a close
+// code to the page, whereas a refused upgrade looks like any network error.
+// Without tokens configured every client gets in, as before. WebTransport
+// sessions are held to the same tokens.
+
+use crate::config::Settings;
+use crate::msgpack;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use ping_common::Auth;
+use std::time::Duration;
+
+/// Close code for a missing or invalid token
+pub const UNAUTHORIZED: u16 = 4401;
+
+/// How long a client gets to send its auth frame
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Let the client in if no token is required or it presents a valid one: in
+/// the URL, or else in its first frame. Otherwise close the socket with
+/// UNAUTHORIZED and return the reason.
+pub async fn authenticate(
+    socket: &mut WebSocket,
+    settings: &Settings,
+    url_token: Option<&str>,
+    msgpack: bool,
+) -> Result<(), &'static str> {
+    if settings.ws_tokens.is_empty() {
+        return Ok(());
+    }
+    let token = match url_token {
+        Some(token) => Ok(token.to_string()),
+        None => auth_frame(socket, msgpack).await,
+    };
+    let result = token.and_then(|token| {
+        if settings.accepts_token(&token) {
+            Ok(())
+        } else {
+            Err("invalid token")
+        }
+    });
+    if let Err(reason) = result {
+        let close = CloseFrame { code: UNAUTHORIZED, reason: reason.into() };
+        let _ = socket.send(Message::Close(Some(close))).await;
+    }
+    result
+}
+
+// The token in the client's first frame
+async fn auth_frame(socket: &mut WebSocket, msgpack: bool) -> Result<String, &'static str> {
+    let frame = match tokio::time::timeout(AUTH_TIMEOUT, socket.recv()).await {
+        Ok(Some(Ok(frame))) => frame,
+        Ok(_) => return Err("closed before authenticating"),
+        Err(_) => return Err("no token in time"),
+    };
+    let text = match frame {
+        Message::Text(text) => text,
+        Message::Binary(data) if msgpack => msgpack::decode(&data).map_err(|_| "token required")?,
+        _ => return Err("token required"),
+    };
+    let auth: Auth = serde_json::from_str(&text).map_err(|_| "token required")?;
+    Ok(auth.token)
+}
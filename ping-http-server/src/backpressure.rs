@@ -0,0 +1,122 @@
+// Backpressure between sockets and actors
+//
+// Each connection buffers inbound frames in a bounded inbox that its
+// processing tasks drain. When the actor can't keep up and the inbox is full,
+// the configured overflow policy decides what happens to the new frame, and
+// every such decision is counted.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// What to do with a frame that arrives while the inbox is full
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued frame to make room
+    DropOldest,
+    /// Discard the new frame and tell the client with an error frame
+    Reject,
+    /// Close the connection
+    Close,
+}
+
+/// Outcome of pushing a frame into an inbox
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission {
+    Queued,
+    DroppedOldest,
+    Rejected,
+    Close,
+}
+
+/// Server-wide backpressure counters
+#[derive(Default)]
+pub struct Backpressure {
+    dropped: AtomicU64,
+    rejected: AtomicU64,
+    closed: AtomicU64,
+}
+
+/// Snapshot of the backpressure counters, served at `/stats/backpressure`
+#[derive(Serialize, Debug)]
+pub struct BackpressureStats {
+    pub policy: OverflowPolicy,
+    pub queue_size: usize,
+    pub dropped: u64,
+    pub rejected: u64,
+    pub closed: u64,
+}
+
+impl Backpressure {
+    /// Queue `frame`, applying the inbox's overflow policy if it is full
+    pub fn admit(&self, inbox: &Inbox, frame: String) -> Admission {
+        let mut queue = inbox.queue.lock().unwrap();
+        let admission = if queue.len() < inbox.capacity {
+            queue.push_back(frame);
+            Admission::Queued
+        } else {
+            match inbox.policy {
+                OverflowPolicy::DropOldest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    queue.pop_front();
+                    queue.push_back(frame);
+                    Admission::DroppedOldest
+                }
+                OverflowPolicy::Reject => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    Admission::Rejected
+                }
+                OverflowPolicy::Close => {
+                    self.closed.fetch_add(1, Ordering::Relaxed);
+                    Admission::Close
+                }
+            }
+        };
+        drop(queue);
+        inbox.notify.notify_one();
+        admission
+    }
+
+    pub fn stats(&self, policy: OverflowPolicy, queue_size: usize) -> BackpressureStats {
+        BackpressureStats {
+            policy,
+            queue_size,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            closed: self.closed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Bounded queue of frames waiting for a processing slot
+pub struct Inbox {
+    policy: OverflowPolicy,
+    capacity: usize,
+    queue: Mutex<VecDeque<String>>,
+    notify: Notify,
+}
+
+impl Inbox {
+    pub fn new(policy: OverflowPolicy, capacity: usize) -> Self {
+        Self {
+            policy,
+            capacity,
+            queue: Mutex::new(VecDeque::with_capacity(capacity)),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Wait for the next queued frame
+    pub async fn next(&self) -> String {
+        loop {
+            if let Some(frame) = self.queue.lock().unwrap().pop_front() {
+                return frame;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
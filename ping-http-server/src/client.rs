@@ -0,0 +1,194 @@
+// Client identification
+//
+// Clients are identified by IP address. Behind a reverse proxy the socket
+// peer is the proxy itself, so with `--trusted-proxies N` the address is
+// taken from the header the proxies write instead: `X-Forwarded-For` by
+// default, or `Forwarded` (RFC 7239) with `--forwarded-header forwarded`.
+// Only that one header is read. A proxy that appends to one passes the
+// other on as the client sent it, so falling back from one to the other
+// would let a client pick its own address. Each proxy appends the address it
+// saw, so the entry N from the right is the one written by the outermost
+// trusted proxy; anything further left was supplied by the client and
+// cannot be trusted.
+
+use crate::AppState;
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, HeaderMap, StatusCode},
+};
+use std::net::{IpAddr, SocketAddr};
+
+/// The header the trusted proxies write the client's address to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ForwardedHeader {
+    /// `X-Forwarded-For`, as nginx and most load balancers write it
+    #[default]
+    XForwardedFor,
+    /// `Forwarded` (RFC 7239)
+    Forwarded,
+}
+
+/// The originating client's IP address
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Missing connection info"))?;
+        Ok(ClientIp(client_ip(peer.ip(), &parts.headers, state.trusted_proxies, state.forwarded_header)))
+    }
+}
+
+// Resolve the client address, trusting `hops` proxies in front of the server
+// to write it to `header`
+fn client_ip(peer: IpAddr, headers: &HeaderMap, hops: usize, header: ForwardedHeader) -> IpAddr {
+    if hops == 0 {
+        return peer;
+    }
+    let chain = match header {
+        ForwardedHeader::XForwardedFor => x_forwarded_for_chain(headers),
+        ForwardedHeader::Forwarded => forwarded_chain(headers),
+    };
+
+    // Entry `hops` from the right; a missing or malformed entry means the
+    // proxy setup does not match the configuration, so fall back to the peer
+    chain
+        .len()
+        .checked_sub(hops)
+        .and_then(|i| chain.get(i).copied().flatten())
+        .unwrap_or(peer)
+}
+
+// `for=` addresses from every `Forwarded` header, in order
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all("forwarded")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                .and_then(|(_, node)| parse_node(node))
+        })
+        .collect()
+}
+
+// Addresses from every `X-Forwarded-For` header, in order
+fn x_forwarded_for_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(parse_node)
+        .collect()
+}
+
+// Parse a node such as `192.0.2.1`, `192.0.2.1:4711`, `"[2001:db8::1]:4711"`
+// or `2001:db8::1`; obfuscated identifiers and `unknown` yield None
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(ip) = node.parse() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    node.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .and_then(|(ip, _)| ip.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    const PEER: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for &(name, value) in pairs {
+            headers.append(name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_nodes() {
+        assert_eq!(parse_node("192.0.2.1"), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node(" 192.0.2.1:4711 "), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("\"[2001:db8::1]:4711\""), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("[2001:db8::1]"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("unknown"), None);
+        assert_eq!(parse_node("_hidden"), None);
+        assert_eq!(parse_node("\"[_hidden]:4711\""), None);
+        assert_eq!(parse_node(""), None);
+    }
+
+    #[test]
+    fn forwarded_chain_reads_every_header_in_order() {
+        let headers = headers(&[
+            ("forwarded", "for=192.0.2.1;proto=https, for=unknown"),
+            ("forwarded", "proto=http;For=\"[2001:db8::1]:4711\", by=203.0.113.9"),
+        ]);
+        assert_eq!(forwarded_chain(&headers), vec![Some(ip("192.0.2.1")), None, Some(ip("2001:db8::1")), None]);
+        assert!(forwarded_chain(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn takes_the_entry_of_the_outermost_trusted_proxy() {
+        let headers = headers(&[("x-forwarded-for", "198.51.100.7, 192.0.2.1, 203.0.113.5")]);
+        let client = |hops| client_ip(PEER, &headers, hops, ForwardedHeader::XForwardedFor);
+        assert_eq!(client(0), PEER);
+        assert_eq!(client(1), ip("203.0.113.5"));
+        assert_eq!(client(2), ip("192.0.2.1"));
+        assert_eq!(client(3), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn falls_back_to_the_peer() {
+        // A chain shorter than the proxies configured
+        let short = headers(&[("x-forwarded-for", "192.0.2.1")]);
+        assert_eq!(client_ip(PEER, &short, 2, ForwardedHeader::XForwardedFor), PEER);
+        assert_eq!(client_ip(PEER, &HeaderMap::new(), 1, ForwardedHeader::XForwardedFor), PEER);
+        // Nodes that name no address
+        let hidden = headers(&[("forwarded", "for=192.0.2.1, for=_hidden"), ("x-forwarded-for", "unknown")]);
+        assert_eq!(client_ip(PEER, &hidden, 1, ForwardedHeader::Forwarded), PEER);
+        assert_eq!(client_ip(PEER, &hidden, 1, ForwardedHeader::XForwardedFor), PEER);
+    }
+
+    #[test]
+    fn reads_forwarded_when_configured() {
+        let headers = headers(&[("forwarded", "for=192.0.2.1, for=\"[2001:db8::1]:4711\"")]);
+        let client = client_ip(PEER, &headers, 1, ForwardedHeader::Forwarded);
+        assert_eq!(client, IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+    }
+
+    #[test]
+    fn ignores_the_header_the_proxy_does_not_write() {
+        // nginx appends to X-Forwarded-For and passes the client's own
+        // Forwarded header on untouched
+        let headers = headers(&[("forwarded", "for=1.2.3.4"), ("x-forwarded-for", "198.51.100.7")]);
+        assert_eq!(client_ip(PEER, &headers, 1, ForwardedHeader::XForwardedFor), ip("198.51.100.7"));
+        // and the other way round
+        let headers = super::tests::headers(&[("forwarded", "for=198.51.100.7"), ("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(client_ip(PEER, &headers, 1, ForwardedHeader::Forwarded), ip("198.51.100.7"));
+    }
+}
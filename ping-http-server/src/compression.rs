@@ -0,0 +1,91 @@
+// WebSocket payload compression
+//
+// axum's WebSocket stack (tungstenite) does not implement the
+// permessage-deflate extension, so compression is negotiated at the
+// application level instead: a client connects with `?compression=deflate`
+// and every reply larger than the threshold is sent as a binary frame holding
+// raw deflate data (`DecompressionStream("deflate-raw")` in browsers).
+// Clients may send compressed pings the same way.
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use serde::Serialize;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Upper bound on an inflated inbound frame, guards against deflate bombs
+const MAX_INFLATED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Server-wide compression counters
+#[derive(Default)]
+pub struct Compression {
+    frames_compressed: AtomicU64,
+    frames_uncompressed: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+/// Snapshot of the compression counters, served at `/stats/compression`
+#[derive(Serialize, Debug)]
+pub struct CompressionStats {
+    pub enabled: bool,
+    pub threshold: usize,
+    pub frames_compressed: u64,
+    pub frames_uncompressed: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub ratio: f64,
+}
+
+impl Compression {
+    /// Whether a client asking for `requested` gets compressed frames
+    pub fn negotiate(enabled: bool, requested: Option<&str>) -> bool {
+        enabled && requested == Some("deflate")
+    }
+
+    /// Compress `payload` if it is at least `threshold` bytes, returning
+    /// `None` when it should go out as-is
+    pub fn compress(&self, payload: &[u8], threshold: usize) -> io::Result<Option<Vec<u8>>> {
+        if payload.len() < threshold {
+            self.frames_uncompressed.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(payload)?;
+        let compressed = encoder.finish()?;
+
+        self.frames_compressed.fetch_add(1, Ordering::Relaxed);
+        self.bytes_in.fetch_add(payload.len() as u64, Ordering::Relaxed);
+        self.bytes_out.fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        Ok(Some(compressed))
+    }
+
+    /// Inflate a compressed inbound frame
+    pub fn decompress(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        DeflateDecoder::new(payload)
+            .take(MAX_INFLATED_BYTES + 1)
+            .read_to_end(&mut out)?;
+        if out.len() as u64 > MAX_INFLATED_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "inflated frame exceeds size limit",
+            ));
+        }
+        Ok(out)
+    }
+
+    pub fn stats(&self, enabled: bool, threshold: usize) -> CompressionStats {
+        let bytes_in = self.bytes_in.load(Ordering::Relaxed);
+        let bytes_out = self.bytes_out.load(Ordering::Relaxed);
+        CompressionStats {
+            enabled,
+            threshold,
+            frames_compressed: self.frames_compressed.load(Ordering::Relaxed),
+            frames_uncompressed: self.frames_uncompressed.load(Ordering::Relaxed),
+            bytes_in,
+            bytes_out,
+            ratio: if bytes_in == 0 { 1.0 } else { bytes_out as f64 / bytes_in as f64 },
+        }
+    }
+}
@@ -0,0 +1,288 @@
+// Reloadable server settings
+//
+// Limits, the tokens, compression, codecs and allowed origins can come from
+// a TOML file (`--config`), with command-line flags taking precedence. The
+// file is re-read on SIGHUP and whenever its modification time changes.
+// Reloaded settings apply to requests and connections made afterwards;
+// existing WebSocket connections keep the settings they started with.
+
+use crate::admin::constant_time_eq;
+use crate::backpressure::OverflowPolicy;
+use serde::Deserialize;
+use std::{
+    fmt, fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
+};
+use tracing::{info, warn};
+
+/// How often the config file's modification time is checked
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Command-line flags for the reloadable settings (flattened into Args)
+#[derive(clap::Args, Debug)]
+pub struct SettingsArgs {
+    /// TOML configuration file, reloaded on SIGHUP or when it changes
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Disable deflate compression of WebSocket frames
+    #[arg(long)]
+    no_compression: bool,
+
+    /// Minimum reply size in bytes before compression kicks in [default: 1024]
+    #[arg(long)]
+    compression_threshold: Option<usize>,
+
+    /// Refuse the MessagePack WebSocket subprotocol, so clients speak JSON
+    #[arg(long)]
+    no_msgpack: bool,
+
+    /// Maximum messages processed concurrently per WebSocket connection [default: 16]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_inflight: Option<u64>,
+
+    /// Frames buffered per connection while waiting for the actor [default: 64]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    queue_size: Option<u64>,
+
+    /// What to do with frames that arrive while a connection's queue is full
+    /// [default: reject]
+    #[arg(long, value_enum)]
+    overflow_policy: Option<OverflowPolicy>,
+
+    /// Log and count requests the actors take longer than this many
+    /// milliseconds to answer; 0 turns it off [default: 500]
+    #[arg(long)]
+    slow_request_ms: Option<u64>,
+
+    /// Bearer token required by the /admin API (disabled when unset)
+    #[arg(long, env = "PING_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+
+    /// Token WebSocket clients have to present, as `?token=` or in an auth
+    /// frame; may be repeated (no authentication when none are configured)
+    #[arg(long = "ws-token", env = "PING_WS_TOKENS", value_delimiter = ',')]
+    ws_tokens: Vec<String>,
+
+    /// Browser origin allowed to use the WebSocket and HTTP APIs; may be
+    /// repeated (any origin when none are configured)
+    #[arg(long = "allowed-origin")]
+    allowed_origins: Vec<String>,
+}
+
+// Layout of the TOML file; every key is optional
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    auth: AuthSection,
+    compression: CompressionSection,
+    codecs: CodecsSection,
+    limits: LimitsSection,
+    cors: CorsSection,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct AuthSection {
+    admin_token: Option<String>,
+    ws_tokens: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CompressionSection {
+    enabled: Option<bool>,
+    threshold: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CodecsSection {
+    msgpack: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct LimitsSection {
+    max_inflight: Option<usize>,
+    queue_size: Option<usize>,
+    overflow_policy: Option<OverflowPolicy>,
+    slow_request_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CorsSection {
+    allowed_origins: Option<Vec<String>>,
+}
+
+/// Effective settings after merging defaults, the file and the flags
+#[derive(Debug)]
+pub struct Settings {
+    pub admin_token: Option<String>,
+    pub ws_tokens: Vec<String>,
+    pub compression: bool,
+    pub compression_threshold: usize,
+    /// Whether WebSocket clients may pick the MessagePack subprotocol
+    pub msgpack: bool,
+    pub max_inflight: usize,
+    pub queue_size: usize,
+    pub overflow_policy: OverflowPolicy,
+    /// Requests taking longer are slow; zero when none are
+    pub slow_request: Duration,
+    pub allowed_origins: Vec<String>,
+}
+
+impl Settings {
+    fn resolve(args: &SettingsArgs, file: FileConfig) -> Result<Self, ConfigError> {
+        let settings = Settings {
+            admin_token: args.admin_token.clone().or(file.auth.admin_token),
+            ws_tokens: if args.ws_tokens.is_empty() {
+                file.auth.ws_tokens.unwrap_or_default()
+            } else {
+                args.ws_tokens.clone()
+            },
+            compression: !args.no_compression && file.compression.enabled.unwrap_or(true),
+            compression_threshold: args
+                .compression_threshold
+                .or(file.compression.threshold)
+                .unwrap_or(1024),
+            msgpack: !args.no_msgpack && file.codecs.msgpack.unwrap_or(true),
+            max_inflight: args
+                .max_inflight
+                .map(|n| n as usize)
+                .or(file.limits.max_inflight)
+                .unwrap_or(16),
+            queue_size: args
+                .queue_size
+                .map(|n| n as usize)
+                .or(file.limits.queue_size)
+                .unwrap_or(64),
+            overflow_policy: args
+                .overflow_policy
+                .or(file.limits.overflow_policy)
+                .unwrap_or(OverflowPolicy::Reject),
+            slow_request: Duration::from_millis(
+                args.slow_request_ms.or(file.limits.slow_request_ms).unwrap_or(500),
+            ),
+            allowed_origins: if args.allowed_origins.is_empty() {
+                file.cors.allowed_origins.unwrap_or_default()
+            } else {
+                args.allowed_origins.clone()
+            },
+        };
+        if settings.max_inflight == 0 || settings.queue_size == 0 {
+            return Err(ConfigError("limits must be at least 1".to_string()));
+        }
+        Ok(settings)
+    }
+
+    /// Whether `token` is one of the WebSocket tokens
+    pub fn accepts_token(&self, token: &str) -> bool {
+        self.ws_tokens.iter().any(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+    }
+
+    /// Whether a browser page from `origin` may use the server
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins.is_empty() || self.allowed_origins.iter().any(|o| o == origin)
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Current settings plus what is needed to rebuild them
+pub struct Config {
+    args: SettingsArgs,
+    current: RwLock<Arc<Settings>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl Config {
+    pub fn load(args: SettingsArgs) -> Result<Self, ConfigError> {
+        let (file, modified) = read_file(&args)?;
+        let settings = Settings::resolve(&args, file)?;
+        Ok(Self {
+            args,
+            current: RwLock::new(Arc::new(settings)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    /// Snapshot of the settings in effect right now
+    pub fn current(&self) -> Arc<Settings> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Re-read the config file; on error the previous settings stay in effect
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        let (file, modified) = read_file(&self.args)?;
+        let settings = Settings::resolve(&self.args, file)?;
+        *self.modified.lock().unwrap() = modified;
+        *self.current.write().unwrap() = Arc::new(settings);
+        Ok(())
+    }
+
+    /// Reload on SIGHUP and whenever the config file changes
+    pub fn watch(self: Arc<Self>) {
+        let Some(path) = self.args.config.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            #[cfg(unix)]
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                .expect("failed to install SIGHUP handler");
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+            loop {
+                #[cfg(unix)]
+                let reason = tokio::select! {
+                    _ = hangup.recv() => "SIGHUP",
+                    _ = interval.tick() => "file change",
+                };
+                #[cfg(not(unix))]
+                let reason = {
+                    interval.tick().await;
+                    "file change"
+                };
+
+                if reason == "file change" {
+                    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                    let mut last = self.modified.lock().unwrap();
+                    if modified == *last {
+                        continue;
+                    }
+                    // Remember it even if the reload fails, so a broken file
+                    // is reported once rather than on every check
+                    *last = modified;
+                }
+                match self.reload() {
+                    Ok(()) => info!("Configuration reloaded from {} ({})", path.display(), reason),
+                    Err(e) => warn!("Keeping previous configuration, reload failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+// Parse the config file, if any, along with its modification time
+fn read_file(args: &SettingsArgs) -> Result<(FileConfig, Option<SystemTime>), ConfigError> {
+    let Some(path) = &args.config else {
+        return Ok((FileConfig::default(), None));
+    };
+    let error = |e: &dyn fmt::Display| ConfigError(format!("{}: {}", path.display(), e));
+    let modified = fs::metadata(path).and_then(|m| m.modified()).map_err(|e| error(&e))?;
+    let text = fs::read_to_string(path).map_err(|e| error(&e))?;
+    let file = toml::from_str(&text).map_err(|e| error(&e))?;
+    Ok((file, Some(modified)))
+}
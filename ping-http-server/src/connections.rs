@@ -0,0 +1,110 @@
+// Connection manager
+//
+// Tracks every live WebSocket connection so the server can push frames to
+// them (broadcasts) or close them, independently of their read loops.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+pub type ConnectionId = u64;
+
+/// Instructions delivered to a connection's socket loop
+#[derive(Debug)]
+pub enum Outbound {
+    Frame(String),
+    Close(String),
+}
+
+struct Connection {
+    client: IpAddr,
+    actor: String,
+    session: Option<String>,
+    connected_at: Instant,
+    tx: mpsc::UnboundedSender<Outbound>,
+}
+
+/// Description of a live connection, served by the admin API
+#[derive(Serialize, Debug)]
+pub struct ConnectionSummary {
+    pub id: ConnectionId,
+    pub client: IpAddr,
+    pub actor: String,
+    pub session: Option<String>,
+    pub connected_secs: u64,
+    /// Mean time the actors took to answer, once they have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_handling_us: Option<u64>,
+}
+
+/// All live WebSocket connections
+#[derive(Default)]
+pub struct ConnectionManager {
+    next_id: AtomicU64,
+    connections: Mutex<HashMap<ConnectionId, Connection>>,
+}
+
+impl ConnectionManager {
+    /// Register a new connection from `client` to `actor`, returning its id
+    /// and outbound queue
+    pub fn register(
+        &self,
+        client: IpAddr,
+        actor: &str,
+        session: Option<&str>,
+    ) -> (ConnectionId, mpsc::UnboundedReceiver<Outbound>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let connection = Connection {
+            client,
+            actor: actor.to_string(),
+            session: session.map(str::to_string),
+            connected_at: Instant::now(),
+            tx,
+        };
+        self.connections.lock().unwrap().insert(id, connection);
+        (id, rx)
+    }
+
+    pub fn unregister(&self, id: ConnectionId) {
+        self.connections.lock().unwrap().remove(&id);
+    }
+
+    /// Push a frame to every connection, returning how many received it
+    pub fn broadcast(&self, frame: &str) -> usize {
+        let connections = self.connections.lock().unwrap();
+        connections
+            .values()
+            .filter(|conn| conn.tx.send(Outbound::Frame(frame.to_string())).is_ok())
+            .count()
+    }
+
+    pub fn list(&self) -> Vec<ConnectionSummary> {
+        let connections = self.connections.lock().unwrap();
+        let mut list: Vec<_> = connections
+            .iter()
+            .map(|(&id, conn)| ConnectionSummary {
+                id,
+                client: conn.client,
+                actor: conn.actor.clone(),
+                session: conn.session.clone(),
+                connected_secs: conn.connected_at.elapsed().as_secs(),
+                avg_handling_us: None,
+            })
+            .collect();
+        list.sort_by_key(|conn| conn.id);
+        list
+    }
+
+    /// Ask a connection to close, returning false if it is not connected
+    pub fn close(&self, id: ConnectionId, reason: &str) -> bool {
+        let connections = self.connections.lock().unwrap();
+        connections
+            .get(&id)
+            .is_some_and(|conn| conn.tx.send(Outbound::Close(reason.to_string())).is_ok())
+    }
+}
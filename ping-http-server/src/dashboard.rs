@@ -0,0 +1,174 @@
+// Live dashboard
+//
+// /dashboard is a page charting the server as it runs: requests answered per
+// second, clients connected and the actors' latency percentiles, with a table
+// of the clients. It reads /dashboard/stream, which pushes a snapshot of the
+// server every second as Server-Sent Events; the page turns the request
+// count into a rate and keeps the last few minutes of each series. The
+// percentiles are over the last WINDOW only, so a slow spell shows up and
+// then passes rather than being averaged into everything since the start:
+// the bridges record each round trip into a histogram per INTERVAL as well,
+// and a snapshot merges the ones still in the window.
+//
+// The clients come from a StatsActor, as on ping-cli-server: the bridges
+// tell it about every reply with the bytes its connection has carried so far
+// (frames as the actors see them, before compression or MessagePack), and
+// forget a connection when it closes. A client here is one connection, so a
+// browser tab that reconnects shows up anew.
+
+use crate::connections::ConnectionId;
+use crate::AppState;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use kameo::prelude::*;
+use ping_common::latency::{LatencyStats, LatencySummary};
+use ping_common::{GetPeerStats, PeerActivity, PeerGone, PeerStats, StatsActor};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    convert::Infallible,
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::time::MissedTickBehavior;
+
+/// How often the stream pushes a snapshot
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// How far back the latency percentiles reach
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// How long a snapshot waits for the PingActor's count
+const STATS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// One snapshot of the server, as pushed to the page
+#[derive(Serialize)]
+struct Snapshot {
+    /// Unix timestamp in milliseconds
+    time_ms: u64,
+    /// Frames the actors have answered on any transport, since the start
+    requests: u64,
+    connections: usize,
+    /// The PingActor's own count; None when it can't be asked (a gateway's
+    /// ping-cli-server gone away, or too slow to answer)
+    total_pings: Option<u64>,
+    /// Round trips of the last WINDOW
+    latency: LatencySummary,
+    clients: Vec<PeerStats>,
+}
+
+/// The bytes a connection has carried, reported to the StatsActor with every
+/// reply
+pub struct Traffic {
+    peer: String,
+    received: AtomicU64,
+    sent: AtomicU64,
+}
+
+impl Traffic {
+    pub fn new(id: ConnectionId, client: IpAddr) -> Self {
+        Self { peer: peer_name(id, client), received: AtomicU64::new(0), sent: AtomicU64::new(0) }
+    }
+
+    /// Count a request of `received` bytes answered with `sent` after
+    /// `handled_in`
+    pub fn reply(&self, stats: &ActorRef<StatsActor>, received: usize, sent: usize, handled_in: Duration) {
+        let bytes_received = self.received.fetch_add(received as u64, Ordering::Relaxed) + received as u64;
+        let bytes_sent = self.sent.fetch_add(sent as u64, Ordering::Relaxed) + sent as u64;
+        let activity = PeerActivity { peer: self.peer.clone(), bytes_received, bytes_sent, handled_in: Some(handled_in) };
+        let _ = stats.tell(activity).try_send();
+    }
+
+    pub fn closed(&self, stats: &ActorRef<StatsActor>) {
+        let _ = stats.tell(PeerGone { peer: self.peer.clone() }).try_send();
+    }
+}
+
+/// The name connection `id` from `client` goes by in the StatsActor
+pub fn peer_name(id: ConnectionId, client: IpAddr) -> String {
+    format!("{} #{}", client, id)
+}
+
+/// Round trips of the last WINDOW, one histogram per INTERVAL
+pub struct RecentLatency {
+    start: Instant,
+    // Histograms by the number of INTERVALs since `start`, oldest first
+    slots: VecDeque<(u64, LatencyStats)>,
+}
+
+impl Default for RecentLatency {
+    fn default() -> Self {
+        Self { start: Instant::now(), slots: VecDeque::new() }
+    }
+}
+
+impl RecentLatency {
+    pub fn record(&mut self, rtt: Duration) {
+        let now = self.slot(Instant::now());
+        self.forget(now);
+        match self.slots.back_mut() {
+            Some((slot, stats)) if *slot == now => stats.record(rtt),
+            _ => {
+                let mut stats = LatencyStats::default();
+                stats.record(rtt);
+                self.slots.push_back((now, stats));
+            }
+        }
+    }
+
+    fn summary(&mut self) -> LatencySummary {
+        self.forget(self.slot(Instant::now()));
+        let mut window = LatencyStats::default();
+        for (_, stats) in &self.slots {
+            window.merge(stats);
+        }
+        window.summary()
+    }
+
+    fn slot(&self, at: Instant) -> u64 {
+        (at.duration_since(self.start).as_millis() / INTERVAL.as_millis()) as u64
+    }
+
+    // Drop the histograms that have left the window by slot `now`
+    fn forget(&mut self, now: u64) {
+        let kept = (WINDOW.as_millis() / INTERVAL.as_millis()) as u64;
+        while self.slots.front().is_some_and(|(slot, _)| slot + kept <= now) {
+            self.slots.pop_front();
+        }
+    }
+}
+
+pub async fn stream(State(state): State<AppState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut interval = tokio::time::interval(INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let snapshots = stream::unfold((state, interval), |(state, mut interval)| async move {
+        interval.tick().await;
+        let event = Event::default().json_data(snapshot(&state).await).unwrap_or_default();
+        Some((Ok(event), (state, interval)))
+    });
+    Sse::new(snapshots).keep_alive(KeepAlive::default())
+}
+
+async fn snapshot(state: &AppState) -> Snapshot {
+    let requests = state.latency.lock().unwrap().count();
+    let latency = state.recent_latency.lock().unwrap().summary();
+    let mut clients = state.stats.ask(GetPeerStats).await.map(|reply| reply.0).unwrap_or_default();
+    clients.sort_by_key(|client| Reverse(client.last_active_ms));
+    Snapshot {
+        time_ms: crate::unix_millis(),
+        requests,
+        connections: state.connections.list().len(),
+        total_pings: tokio::time::timeout(STATS_TIMEOUT, state.ping.stats())
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .map(|stats| stats.total_pings),
+        latency,
+        clients,
+    }
+}
@@ -0,0 +1,128 @@
+// Gateway mode
+//
+// With `--gateway <multiaddr>` the server spawns no PingActor of its own.
+// Instead it joins the libp2p swarm the same way ping-cli-client does, looks
+// up the CLI server's PingActor (`ping_actor` unless `--gateway-actor` says
+// otherwise), and forwards browser pings to it. The WebSocket side is
+// unchanged: the route simply holds a RemoteActorRef instead of a local
+// ActorRef. Admin broadcasts are published into the swarm over gossipsub
+// (see `gossip`).
+
+use crate::gossip::{self, Gossip};
+use futures::StreamExt;
+use kameo::prelude::*;
+use kameo::remote;
+use libp2p::{
+    noise, tcp, yamux,
+    swarm::{NetworkBehaviour, SwarmEvent},
+    Multiaddr,
+};
+use ping_common::retry::{self, RetryPolicy};
+use ping_common::{PingActor, PingActorClient, PingStats};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Delays between registry lookups while the remote actor is not yet visible
+const LOOKUP_RETRY: RetryPolicy = RetryPolicy::new(Duration::from_millis(500), Duration::from_secs(3));
+
+// Network behaviour wrapping Kameo's remote messaging, as in the CLI crates
+#[derive(NetworkBehaviour)]
+struct GatewayBehaviour {
+    kameo: remote::Behaviour,
+    gossipsub: libp2p::gossipsub::Behaviour,
+}
+
+/// The PingActor behind the server: spawned locally, or on a remote peer in
+/// gateway mode
+#[derive(Clone)]
+pub enum PingHandle {
+    Local(ActorRef<PingActor>),
+    Remote(RemoteActorRef<PingActor>),
+}
+
+impl PingHandle {
+    pub async fn stats(&self) -> Result<PingStats, String> {
+        match self {
+            PingHandle::Local(actor) => PingActorClient::new(actor).get_stats().await.map_err(|e| e.to_string()),
+            PingHandle::Remote(actor) => PingActorClient::new(actor).get_stats().await.map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Reset the counters, returning the values before the reset
+    pub async fn reset(&self) -> Result<PingStats, String> {
+        match self {
+            PingHandle::Local(actor) => PingActorClient::new(actor).reset_stats().await.map_err(|e| e.to_string()),
+            PingHandle::Remote(actor) => PingActorClient::new(actor).reset_stats().await.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+// Join the swarm through `server` and wait until its PingActor is found;
+// the handle publishes broadcasts into the swarm
+pub async fn connect(
+    server: Multiaddr,
+    actor_name: &str,
+) -> Result<(RemoteActorRef<PingActor>, Gossip), Box<dyn std::error::Error>> {
+    let mut swarm = libp2p::SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)?
+        .with_behaviour(|key| {
+            let peer_id = key.public().to_peer_id();
+            let messaging_config = remote::messaging::Config::default()
+                .with_request_timeout(Duration::from_secs(120));
+            let kameo = remote::Behaviour::new(peer_id, messaging_config);
+            Ok(GatewayBehaviour { kameo, gossipsub: gossip::behaviour(key)? })
+        })?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(600)))
+        .build();
+
+    // Initialize Kameo's global actor registry
+    swarm.behaviour().kameo.init_global();
+    info!("Gateway Peer ID: {}", swarm.local_peer_id());
+
+    swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
+    swarm.dial(server.clone())?;
+
+    // The swarm has to keep being polled for as long as the server runs
+    let (gossip, mut broadcasts) = Gossip::channel();
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = swarm.select_next_some() => event,
+                Some(frame) = broadcasts.recv() => {
+                    gossip::publish(&mut swarm.behaviour_mut().gossipsub, frame);
+                    continue;
+                }
+            };
+            match event {
+                SwarmEvent::Behaviour(GatewayBehaviourEvent::Kameo(event)) => {
+                    debug!("Kameo event: {:?}", event);
+                }
+                SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                    info!("Gateway connected to {} via {}", peer_id, endpoint.get_remote_address());
+                }
+                SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
+                    warn!("Gateway connection to {} closed: {:?}", peer_id, cause);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    info!("Looking up {} via {}...", actor_name, server);
+    let actor = retry::retry(
+        &LOOKUP_RETRY,
+        std::future::pending(),
+        |_| async {
+            match RemoteActorRef::<PingActor>::lookup(actor_name).await {
+                Ok(Some(actor)) => Ok(actor),
+                Ok(None) => Err(format!("{} not found yet", actor_name)),
+                Err(e) => Err(format!("lookup failed ({})", e)),
+            }
+        },
+        |e, _, delay| warn!("{}, retrying in {:?}...", e, delay),
+    )
+    .await?;
+    info!("Found remote PingActor");
+    Ok((actor, gossip))
+}
@@ -0,0 +1,47 @@
+// Gossipsub broadcasts
+//
+// When the server is part of a libp2p swarm (`--libp2p-port`, or gateway
+// mode) its swarm runs gossipsub next to kameo, and `POST /admin/broadcast`
+// publishes the `Broadcast` there too, on `BROADCAST_TOPIC`, so libp2p peers
+// subscribed to it get the same notices as the WebSocket clients. The swarm
+// is owned by its own task, so `Gossip` hands it the frames over a channel
+// and the task publishes them between swarm events.
+
+use libp2p::{gossipsub, identity::Keypair};
+use ping_common::BROADCAST_TOPIC;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Frames for the swarm task to publish
+#[derive(Clone)]
+pub struct Gossip(mpsc::UnboundedSender<String>);
+
+impl Gossip {
+    /// A handle and the receiving end for the swarm task
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), rx)
+    }
+
+    /// Publish `frame` on the broadcast topic; false once the swarm is gone
+    pub fn publish(&self, frame: &str) -> bool {
+        self.0.send(frame.to_string()).is_ok()
+    }
+}
+
+/// A gossipsub behaviour signing its messages with the swarm's key
+pub fn behaviour(key: &Keypair) -> Result<gossipsub::Behaviour, Box<dyn std::error::Error + Send + Sync>> {
+    let config = gossipsub::Config::default();
+    Ok(gossipsub::Behaviour::new(gossipsub::MessageAuthenticity::Signed(key.clone()), config)?)
+}
+
+/// Publish `frame` from the swarm task; no subscriber yet is no error
+pub fn publish(gossipsub: &mut gossipsub::Behaviour, frame: String) {
+    match gossipsub.publish(gossipsub::IdentTopic::new(BROADCAST_TOPIC), frame) {
+        Ok(_) => debug!("Broadcast published on gossipsub topic {}", BROADCAST_TOPIC),
+        Err(gossipsub::PublishError::NoPeersSubscribedToTopic) => {
+            debug!("No libp2p peer subscribed to {}", BROADCAST_TOPIC)
+        }
+        Err(e) => warn!("Failed to publish the broadcast on gossipsub: {}", e),
+    }
+}
@@ -0,0 +1,45 @@
+// Server-initiated heartbeat
+//
+// Periodically pushes the PingActor's counter to every connected client so
+// dashboards can watch the actor without sending pings of their own.
+// Heartbeats are not queued for disconnected sessions; the next one after a
+// resume carries the current value anyway. The other way round, clients may
+// send keepalives to check the connection, which are answered here rather
+// than by an actor.
+
+use crate::{unix_millis, AppState};
+use ping_common::{Heartbeat, KeepAlive};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+pub fn spawn(state: AppState, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let total_pings = match state.ping.stats().await {
+                Ok(stats) => stats.total_pings,
+                Err(e) => {
+                    warn!("Heartbeat skipped, actor error: {}", e);
+                    continue;
+                }
+            };
+            let heartbeat = Heartbeat { total_pings, sent_at_ms: unix_millis() };
+            let frame = serde_json::to_string(&heartbeat).unwrap();
+            let delivered = state.connections.broadcast(&frame);
+            debug!("Heartbeat sent to {} client(s)", delivered);
+        }
+    });
+}
+
+/// The answer to `text` if it is a client's keepalive: the keepalive itself
+pub fn answer_keepalive(text: &str) -> Option<String> {
+    // Cheap enough to try on every frame
+    if !text.contains("\"keepalive\"") {
+        return None;
+    }
+    serde_json::from_str::<KeepAlive>(text).ok()?;
+    Some(serde_json::to_string(&KeepAlive::KeepAlive).unwrap())
+}
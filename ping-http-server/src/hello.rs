@@ -0,0 +1,34 @@
+// Protocol handshake
+//
+// A client may open with a `Hello` naming its protocol version and codecs.
+// The server answers with a `HelloAck` for the codec the connection already
+// uses (MessagePack when the WebSocket subprotocol asked for it, JSON
+// otherwise), or refuses a version it doesn't speak with an
+// `unsupported_version` error and closes the connection. Clients that never
+// send a hello are served as the current version.
+
+use crate::routes;
+use ping_common::Hello;
+
+// Sent in every HelloAck
+const SERVER_INFO: &str = concat!("ping-http-server/", env!("CARGO_PKG_VERSION"));
+
+/// The answer to `text` if it is a client's hello: the ack to send, or the
+/// error frame to send before closing
+pub fn answer(text: &str, codec: &str) -> Option<Result<String, String>> {
+    // Cheap enough to try on every frame, like keepalives
+    if !text.contains("\"hello\"") {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    if value.get("type")? != "hello" {
+        return None;
+    }
+    let answer = serde_json::from_value::<Hello>(value)
+        .map_err(|e| ping_common::Error::Codec(e.to_string()))
+        .and_then(|hello| hello.negotiate(&[codec], SERVER_INFO));
+    Some(match answer {
+        Ok(ack) => Ok(serde_json::to_string(&ack).unwrap()),
+        Err(e) => Err(routes::error_frame(&e, text)),
+    })
+}
@@ -0,0 +1,430 @@
+// HTTP server
+//
+// The whole server as a library: `Args` is its command line, and a `Server`
+// built from them has its actors spawned and its routes ready. The binary
+// binds the ports the command line names and runs it (`Server::run`);
+// ping-testkit binds ephemeral ports instead and serves on those
+// (`Server::serve`, `Server::serve_tcp`), so tests run it in-process.
+
+mod admin;
+mod assets;
+mod audit;
+mod auth;
+mod backpressure;
+mod client;
+mod compression;
+mod config;
+mod connections;
+mod dashboard;
+mod gateway;
+mod gossip;
+mod heartbeat;
+mod hello;
+mod lines;
+mod metrics;
+mod msgpack;
+mod p2p;
+mod routes;
+mod sessions;
+mod slow;
+mod tcp;
+mod webtransport;
+mod ws;
+
+use axum::{
+    extract::State,
+    routing::get,
+    Json, Router,
+};
+use audit::AuditLog;
+use axum_server::tls_rustls::RustlsConfig;
+use clap::Parser;
+use backpressure::{Backpressure, BackpressureStats};
+use client::ForwardedHeader;
+use compression::{Compression, CompressionStats};
+use config::{Config, SettingsArgs};
+use connections::ConnectionManager;
+use dashboard::RecentLatency;
+use gateway::PingHandle;
+use gossip::Gossip;
+use kameo::prelude::*;
+use libp2p::Multiaddr;
+use metrics::Metrics;
+use ping_common::latency::LatencyStats;
+use ping_common::log_file::{LogFile, Rotation};
+use ping_common::logging::LogFormat;
+use ping_common::{Echo, EchoActor, KvActor, KvRequest, Ping, PingActor, StatsActor};
+use routes::ActorRegistry;
+use sessions::SessionStore;
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+};
+use tracing::{info, Level};
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[command(name = "ping-http-server")]
+pub struct Args {
+    #[command(flatten)]
+    settings: SettingsArgs,
+
+    /// Seconds a disconnected session is kept for resumption
+    #[arg(long, default_value_t = 300)]
+    session_ttl: u64,
+
+    /// Maximum server-pushed frames queued for a disconnected session
+    #[arg(long, default_value_t = 100)]
+    session_queue: usize,
+
+    /// Number of reverse proxies in front of the server whose
+    /// Forwarded/X-Forwarded-For headers identify the client (0 = ignore them)
+    #[arg(long, default_value_t = 0)]
+    trusted_proxies: usize,
+
+    /// The header the trusted proxies write the client address to; the
+    /// other one is ignored
+    #[arg(long, value_enum, default_value_t = ForwardedHeader::XForwardedFor)]
+    forwarded_header: ForwardedHeader,
+
+    /// Forward pings to the PingActor of a ping-cli-server at this libp2p
+    /// address instead of spawning one locally
+    /// (e.g. /ip4/127.0.0.1/tcp/36341/p2p/PEER_ID)
+    #[arg(long)]
+    gateway: Option<Multiaddr>,
+
+    /// Also accept libp2p clients on this TCP port, publishing this server's
+    /// PingActor to them as ping-cli-server does
+    #[arg(long, conflicts_with = "gateway")]
+    libp2p_port: Option<u16>,
+
+    /// Registry name of the remote PingActor in gateway mode
+    #[arg(long, default_value = "ping_actor", requires = "gateway")]
+    gateway_actor: String,
+
+    /// PEM certificate chain; serves HTTPS/WSS with ALPN (h2, http/1.1)
+    /// instead of cleartext HTTP/1.1 and h2c
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Also accept WebTransport sessions over HTTP/3 on the UDP port of the
+    /// same number, at /wt and /wt/:actor
+    #[arg(long, requires = "tls_cert")]
+    webtransport: bool,
+
+    /// Also accept plain TCP connections to the PingActor on this port,
+    /// exchanging one JSON frame per line
+    #[arg(long)]
+    tcp_port: Option<u16>,
+
+    /// Push a heartbeat with the current ping count to every client at this
+    /// interval in seconds (disabled when unset)
+    #[arg(long)]
+    heartbeat_interval: Option<u64>,
+
+    /// Append a JSON line for every Ping answered to this file
+    #[arg(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Rotate the audit log once it reaches this many bytes
+    #[arg(long, default_value_t = 10 * 1024 * 1024, requires = "audit_log")]
+    audit_max_bytes: u64,
+
+    /// Rotated audit logs to keep (<file>.1 being the newest)
+    #[arg(long, default_value_t = 5, requires = "audit_log")]
+    audit_keep: usize,
+
+    /// Log format: text, or json for one JSON object per line
+    #[arg(long, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Also write the logs to this file, rotated daily unless --log-rotation
+    /// or --log-max-bytes say otherwise
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// When the log file starts afresh: minutely, hourly, daily or never
+    #[arg(long, default_value_t = Rotation::Daily, requires = "log_file")]
+    log_rotation: Rotation,
+
+    /// Rotate the log file once it reaches this many bytes instead
+    #[arg(long, requires = "log_file", conflicts_with = "log_rotation")]
+    log_max_bytes: Option<u64>,
+
+    /// Rotated log files to keep
+    #[arg(long, default_value_t = 7, requires = "log_file")]
+    log_keep: usize,
+
+    /// Export tracing spans to the OpenTelemetry collector at this URL over
+    /// OTLP/HTTP (e.g. http://localhost:4318)
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Serve the runtime's tasks to tokio-console on 127.0.0.1:6669
+    /// (TOKIO_CONSOLE_BIND picks another address); needs a build with
+    /// `--cfg tokio_unstable` and the tokio-console feature
+    #[arg(long)]
+    pub tokio_console: bool,
+}
+
+impl Args {
+    /// The log file the arguments ask for, if any
+    pub fn log_file(&self) -> Option<LogFile> {
+        let path = self.log_file.clone()?;
+        Some(LogFile { path, rotation: self.log_rotation, max_bytes: self.log_max_bytes, keep: self.log_keep })
+    }
+}
+
+// State shared by all HTTP and WebSocket handlers
+#[derive(Clone)]
+struct AppState {
+    ping: PingHandle,
+    actors: Arc<ActorRegistry>,
+    compression: Arc<Compression>,
+    sessions: Arc<SessionStore>,
+    connections: Arc<ConnectionManager>,
+    config: Arc<Config>,
+    backpressure: Arc<Backpressure>,
+    audit: Option<Arc<AuditLog>>,
+    trusted_proxies: usize,
+    forwarded_header: ForwardedHeader,
+    /// How long the actors took to answer bridged frames, on any transport
+    latency: Arc<Mutex<LatencyStats>>,
+    /// The same for the last few seconds, for the dashboard
+    recent_latency: Arc<Mutex<RecentLatency>>,
+    metrics: Arc<Metrics>,
+    /// Publishes admin broadcasts into the libp2p swarm, when there is one
+    gossip: Option<Gossip>,
+    /// Requests and bytes by client, for the dashboard
+    stats: ActorRef<StatsActor>,
+}
+
+impl AppState {
+    // Count a bridged frame's round trip, since the start and for the
+    // dashboard
+    fn record_latency(&self, rtt: Duration) {
+        self.latency.lock().unwrap().record(rtt);
+        self.recent_latency.lock().unwrap().record(rtt);
+    }
+}
+
+/// The actors, shared state and routes of a server, not yet listening
+pub struct Server {
+    state: AppState,
+    app: Router,
+    tcp_port: Option<u16>,
+    tls: Option<(PathBuf, PathBuf)>,
+    webtransport: bool,
+}
+
+impl Server {
+    /// Spawn the actors (or connect to the gateway's), the background tasks
+    /// and the libp2p listener the arguments ask for, and build the routes
+    pub async fn new(args: Args) -> Result<Self, Box<dyn std::error::Error>> {
+        info!("Starting HTTP Server with WebSocket support...");
+
+        let config = Arc::new(Config::load(args.settings)?);
+        config.clone().watch();
+        let settings = config.current();
+
+        // Spawn the PingActor (same actor used in CLI version), or find the CLI
+        // server's in gateway mode, plus the other actors served under /ws/:actor
+        let mut actors = ActorRegistry::default();
+        let mut gossip = None;
+        let ping = match args.gateway {
+            Some(server) => {
+                info!("Gateway mode: forwarding pings to {}", server);
+                let (remote, broadcasts) = gateway::connect(server, &args.gateway_actor).await?;
+                gossip = Some(broadcasts);
+                actors.register_remote::<_, Ping>("ping", remote.clone());
+                PingHandle::Remote(remote)
+            }
+            None => {
+                let local = PingActor::spawn(PingActor { ping_count: 0 });
+                actors.register::<_, Ping>("ping", local.clone());
+                PingHandle::Local(local)
+            }
+        };
+        let echo_actor = EchoActor::spawn(EchoActor { echo_count: 0 });
+        if let (Some(port), PingHandle::Local(local)) = (args.libp2p_port, &ping) {
+            gossip = Some(p2p::serve(port, local.clone(), echo_actor.clone()).await?);
+        }
+        let kv_actor = KvActor::spawn(KvActor::default());
+
+        actors.register::<_, Echo>("echo", echo_actor);
+        actors.register::<_, KvRequest>("kv", kv_actor);
+
+        info!("Actors spawned successfully: {}", actors.names().collect::<Vec<_>>().join(", "));
+
+        if settings.compression {
+            info!("WebSocket compression enabled (threshold: {} bytes)", settings.compression_threshold);
+        }
+
+        let sessions = Arc::new(SessionStore::new(
+            Duration::from_secs(args.session_ttl),
+            args.session_queue,
+        ));
+
+        // Periodically drop sessions whose TTL has elapsed
+        let expiring = sessions.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(expiring.ttl().max(Duration::from_secs(1)) / 2);
+            loop {
+                interval.tick().await;
+                let expired = expiring.expire();
+                if expired > 0 {
+                    info!("Expired {} idle session(s)", expired);
+                }
+            }
+        });
+
+        if settings.admin_token.is_none() {
+            info!("Admin API disabled (set --admin-token to enable)");
+        }
+
+        let audit = match args.audit_log {
+            Some(path) => {
+                info!("Auditing pings to {}", path.display());
+                Some(Arc::new(AuditLog::open(path, args.audit_max_bytes, args.audit_keep)?))
+            }
+            None => None,
+        };
+
+        let state = AppState {
+            ping,
+            actors: Arc::new(actors),
+            compression: Arc::new(Compression::default()),
+            sessions,
+            connections: Arc::new(ConnectionManager::default()),
+            config: config.clone(),
+            backpressure: Arc::new(Backpressure::default()),
+            audit,
+            trusted_proxies: args.trusted_proxies,
+            forwarded_header: args.forwarded_header,
+            latency: Arc::default(),
+            recent_latency: Arc::default(),
+            metrics: Arc::new(Metrics::new()),
+            gossip,
+            // Unbounded, so the bridges never wait on it or drop an update
+            stats: StatsActor::spawn_with_mailbox(StatsActor::default(), kameo::mailbox::unbounded()),
+        };
+
+        if let Some(secs) = args.heartbeat_interval.filter(|&secs| secs > 0) {
+            info!("Heartbeat enabled every {}s", secs);
+            heartbeat::spawn(state.clone(), Duration::from_secs(secs));
+        }
+
+        // Cross-origin HTTP requests are checked against the current allow-list
+        let cors_config = config.clone();
+        let cors = CorsLayer::new().allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin.to_str().is_ok_and(|origin| cors_config.current().allows_origin(origin))
+        }));
+
+        // Build router with HTTP and WebSocket endpoints
+        let app = Router::new()
+            .route("/", get(assets::serve_index))
+            .route("/ws", get(ws::websocket_handler))
+            .route("/ws/:actor", get(ws::actor_websocket_handler))
+            .route("/stats/compression", get(compression_stats))
+            .route("/stats/backpressure", get(backpressure_stats))
+            .route("/metrics", get(metrics::scrape))
+            .route("/dashboard", get(assets::serve_dashboard))
+            .route("/dashboard/stream", get(dashboard::stream))
+            .nest("/admin", admin::router(state.clone()))
+            .route("/wasm", get(assets::serve_wasm_index))
+            .route("/wasm/", get(assets::serve_wasm_index))
+            .route("/wasm/*path", get(assets::serve_wasm))
+            .route("/static/*path", get(assets::serve_static))
+            .layer(cors)
+            // One `request` span and completion line per HTTP request
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .with_state(state.clone());
+
+        Ok(Server {
+            state,
+            app,
+            tcp_port: args.tcp_port,
+            tls: args.tls_cert.zip(args.tls_key),
+            webtransport: args.webtransport,
+        })
+    }
+
+    /// Accept plain TCP connections to the PingActor on `listener`, as
+    /// `--tcp-port` does, in the background
+    pub fn serve_tcp(&self, listener: TcpListener) -> JoinHandle<()> {
+        tcp::serve(listener, self.state.clone())
+    }
+
+    /// Serve HTTP and WebSockets in cleartext on `listener` until it fails
+    pub async fn serve(self, listener: TcpListener) -> std::io::Result<()> {
+        let service = self.app.into_make_service_with_connect_info::<SocketAddr>();
+        axum::serve(listener, service).await
+    }
+
+    /// Listen on 127.0.0.1:8080, and on the TCP port and over TLS and
+    /// WebTransport if the arguments asked for them, until the process exits
+    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+        if let Some(port) = self.tcp_port {
+            self.serve_tcp(TcpListener::bind(SocketAddr::new(addr.ip(), port)).await?);
+        }
+
+        // Both listeners speak HTTP/1.1 and HTTP/2: over TLS the protocol is
+        // picked by ALPN, in cleartext HTTP/2 needs prior knowledge (h2c).
+        // WebSocket upgrades always use HTTP/1.1.
+        if let Some((cert, key)) = self.tls {
+            // libp2p links rustls too, so pick the crypto provider explicitly
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            if self.webtransport {
+                webtransport::serve(addr, &cert, &key, self.state).await?;
+            }
+            let tls = RustlsConfig::from_pem_file(cert, key).await?;
+            info!("HTTPS Server listening on: https://{} (HTTP/1.1, HTTP/2)", addr);
+            info!("WebSocket endpoint available at: wss://{}/ws", addr);
+            info!("Per-actor endpoints available at: wss://{}/ws/:actor", addr);
+            let service = self.app.into_make_service_with_connect_info::<SocketAddr>();
+            axum_server::bind_rustls(addr, tls).serve(service).await?;
+        } else {
+            info!("HTTP Server listening on: http://{} (HTTP/1.1, h2c)", addr);
+            info!("WebSocket endpoint available at: ws://{}/ws", addr);
+            info!("Per-actor endpoints available at: ws://{}/ws/:actor", addr);
+            self.serve(TcpListener::bind(addr).await?).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Report compression counters
+async fn compression_stats(State(state): State<AppState>) -> Json<CompressionStats> {
+    let settings = state.config.current();
+    Json(state.compression.stats(settings.compression, settings.compression_threshold))
+}
+
+// Report backpressure counters
+async fn backpressure_stats(State(state): State<AppState>) -> Json<BackpressureStats> {
+    let settings = state.config.current();
+    Json(state.backpressure.stats(settings.overflow_policy, settings.queue_size))
+}
+
+// Milliseconds since the Unix epoch, for timestamps on server-pushed frames
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
@@ -0,0 +1,216 @@
+// Line-delimited JSON connections
+//
+// WebTransport streams and plain TCP connections (`--tcp-port`) carry the
+// frames a JSON WebSocket client would exchange, one per line. Once the
+// transport has accepted a connection and picked its actor, it is served
+// here: the token is checked unless it came with the request, then every
+// line is answered in its own task with at most `max_inflight` in progress,
+// while a writer task sends the replies and the server's pushes. Keepalives
+// and hellos (see `hello`) are answered straight away. A client that fails
+// to authenticate gets an `unauthorized` error line before the connection
+// ends.
+
+use crate::auth::AUTH_TIMEOUT;
+use crate::config::Settings;
+use crate::connections::{ConnectionId, Outbound};
+use crate::dashboard::Traffic;
+use crate::heartbeat;
+use crate::hello;
+use crate::routes::{self, ActorRoute, RouteError};
+use crate::slow;
+use crate::AppState;
+use ping_common::{Auth, Error, Ping, MAX_FRAME_BYTES};
+use ping_metrics::{Codec, RequestLabels, Transport};
+use std::{net::IpAddr, sync::Arc, time::Instant};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::{mpsc, Semaphore},
+};
+use tracing::{error, info, warn, Instrument};
+
+// Longest line accepted from a client, in bytes
+const MAX_LINE: usize = MAX_FRAME_BYTES;
+
+/// A connection to an actor, whatever carries its lines
+pub struct LineConnection {
+    pub id: ConnectionId,
+    pub client: IpAddr,
+    pub name: String,
+    pub route: Arc<dyn ActorRoute>,
+    pub settings: Arc<Settings>,
+    pub state: AppState,
+    /// How the client is connected, for the logs and the audit log
+    pub transport: Transport,
+    pub traffic: Traffic,
+}
+
+/// Same checks as `auth::authenticate`, on the first line instead of the
+/// first frame
+pub async fn authenticate<R>(lines: &mut R, settings: &Settings, url_token: Option<&str>) -> Result<(), &'static str>
+where
+    R: AsyncBufRead + Unpin,
+{
+    if settings.ws_tokens.is_empty() {
+        return Ok(());
+    }
+    let token = match url_token {
+        Some(token) => token.to_string(),
+        None => match tokio::time::timeout(AUTH_TIMEOUT, read_line(lines)).await {
+            Ok(Some(text)) => serde_json::from_str::<Auth>(&text).map_err(|_| "token required")?.token,
+            Ok(None) => return Err("closed before authenticating"),
+            Err(_) => return Err("no token in time"),
+        },
+    };
+    if settings.accepts_token(&token) {
+        Ok(())
+    } else {
+        Err("invalid token")
+    }
+}
+
+/// Tell a client that failed `authenticate` why, and end its side of the
+/// connection
+pub async fn refuse<W: AsyncWrite + Unpin>(send: &mut W, reason: &str) {
+    let error = Error::Auth(reason.to_string()).to_reply();
+    let _ = send.write_all(line(serde_json::to_string(&error).unwrap()).as_bytes()).await;
+    let _ = send.shutdown().await;
+}
+
+/// Read lines until the client leaves, then unregister the connection.
+/// Returns the reason if the server closed it.
+pub async fn serve<R, W>(
+    conn: Arc<LineConnection>,
+    mut lines: R,
+    send: W,
+    outbound: mpsc::UnboundedReceiver<Outbound>,
+) -> Option<String>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    info!("Client #{} ({}) connected to {} over {}", conn.id, conn.client, conn.name, conn.transport);
+    let (replies_tx, replies_rx) = mpsc::channel(conn.settings.max_inflight);
+    let writer = tokio::spawn(write_lines(send, conn.id, replies_rx, outbound).in_current_span());
+    let limit = Arc::new(Semaphore::new(conn.settings.max_inflight));
+    let mut received = 0u64;
+
+    loop {
+        let permit = limit.clone().acquire_owned().await.expect("semaphore is never closed");
+        let text = tokio::select! {
+            text = read_line(&mut lines) => text,
+            // The writer stopped: server-side close or a dead stream
+            _ = replies_tx.closed() => None,
+        };
+        let Some(text) = text else { break };
+        if let Some(reply) = heartbeat::answer_keepalive(&text) {
+            let _ = replies_tx.try_send(reply);
+            continue;
+        }
+        match hello::answer(&text, "json") {
+            Some(Ok(ack)) => {
+                let _ = replies_tx.try_send(ack);
+                continue;
+            }
+            Some(Err(error)) => {
+                warn!("Client #{} ({}) refused: incompatible hello", conn.id, conn.client);
+                let _ = replies_tx.try_send(error);
+                break;
+            }
+            None => {}
+        }
+        received += 1;
+        let conn = conn.clone();
+        let replies = replies_tx.clone();
+        tokio::spawn(
+            async move {
+                let _ = replies.send(process_line(&conn, &text).await).await;
+                drop(permit);
+            }
+            .in_current_span(),
+        );
+    }
+
+    drop(replies_tx);
+    let reason = writer.await.ok().flatten();
+    info!("Client #{} disconnected after {} message(s)", conn.id, received);
+    conn.state.connections.unregister(conn.id);
+    conn.traffic.closed(&conn.state.stats);
+    reason
+}
+
+// Writer task: sends replies and pushed frames, returning the reason if the
+// server closed the connection
+async fn write_lines<W: AsyncWrite + Unpin>(
+    mut send: W,
+    id: ConnectionId,
+    mut replies: mpsc::Receiver<String>,
+    mut outbound: mpsc::UnboundedReceiver<Outbound>,
+) -> Option<String> {
+    loop {
+        let frame = tokio::select! {
+            reply = replies.recv() => reply?,
+            Some(push) = outbound.recv() => match push {
+                Outbound::Frame(frame) => frame,
+                Outbound::Close(reason) => {
+                    info!("Closing client #{}: {}", id, reason);
+                    let _ = send.shutdown().await;
+                    return Some(reason);
+                }
+            },
+        };
+        if send.write_all(line(frame).as_bytes()).await.is_err() {
+            return None;
+        }
+    }
+}
+
+// Forward a line to the actor, returning the reply or an error line
+async fn process_line(conn: &LineConnection, text: &str) -> String {
+    let started = Instant::now();
+    let handled = conn.route.handle(text).instrument(routes::frame_span(&conn.name, text)).await;
+    let labels = RequestLabels { transport: conn.transport, codec: Codec::Json, actor: conn.name.clone() };
+    slow::check(&conn.state, &conn.settings, &labels, text, started.elapsed());
+    let json = match handled {
+        Ok(json) => json,
+        Err(e) => {
+            match &e {
+                RouteError::Parse(e) => warn!("Parse error: {}", e),
+                RouteError::Invalid(e) => warn!("Invalid message: {}", e),
+                e => error!("{}", e),
+            }
+            return routes::error_frame(&e.into(), text);
+        }
+    };
+    let handled_in = started.elapsed();
+    conn.state.record_latency(handled_in);
+    conn.traffic.reply(&conn.state.stats, text.len(), json.len(), handled_in);
+    info!("Replied to {} message", conn.name);
+    if let Some(audit) = conn.state.audit.as_ref().filter(|_| conn.name == "ping") {
+        if let Ok(ping) = serde_json::from_str::<Ping>(text) {
+            audit.record(&ping, conn.transport.as_str(), conn.client, conn.id, None);
+        }
+    }
+    json
+}
+
+// The next non-empty line, or None once the stream ends, fails or carries a
+// line longer than MAX_LINE
+async fn read_line<R: AsyncBufRead + Unpin>(lines: &mut R) -> Option<String> {
+    loop {
+        let mut buf = Vec::new();
+        let read = (&mut *lines).take(MAX_LINE as u64 + 1).read_until(b'\n', &mut buf).await.ok()?;
+        if read == 0 || (buf.len() > MAX_LINE && !buf.ends_with(b"\n")) {
+            return None;
+        }
+        let text = String::from_utf8(buf).ok()?;
+        let text = text.trim_end();
+        if !text.is_empty() {
+            return Some(text.to_string());
+        }
+    }
+}
+
+fn line(mut json: String) -> String {
+    json.push('\n');
+    json
+}
@@ -0,0 +1,71 @@
+// Prometheus metrics
+//
+// /metrics serves the server's counters in the Prometheus text format, on the
+// same listener as the rest of the HTTP API: the requests every bridge hands
+// to an actor and the slow ones among them (see `slow`), by transport, codec
+// and actor; the connections each transport accepted; the mean time the
+// actors took to answer each connected client; and the PingActor's own
+// counter. The last two are read from the actors on every scrape, the means
+// from the StatsActor. Names and labels come from ping-metrics, shared with
+// ping-cli-server.
+
+use crate::AppState;
+use axum::{extract::State, http::header, response::IntoResponse};
+use ping_common::GetPeerStats;
+use ping_metrics::prometheus_client::encoding::text::encode;
+use ping_metrics::prometheus_client::metrics::{counter::Counter, family::Family, gauge::Gauge};
+use ping_metrics::prometheus_client::registry::Registry;
+use ping_metrics::{ClientLabels, RequestLabels, Transport, TransportLabels};
+use std::sync::atomic::AtomicU64;
+
+pub struct Metrics {
+    registry: Registry,
+    requests: Family<RequestLabels, Counter>,
+    slow_requests: Family<RequestLabels, Counter>,
+    connections: Family<TransportLabels, Counter>,
+    handling: Family<ClientLabels, Gauge<f64, AtomicU64>>,
+    actor_pings: Gauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = ping_metrics::registry("ping-http-server");
+        let requests = ping_metrics::REQUESTS.register(&mut registry);
+        let slow_requests = ping_metrics::SLOW_REQUESTS.register(&mut registry);
+        let connections = ping_metrics::CONNECTIONS.register(&mut registry);
+        let handling = ping_metrics::CLIENT_HANDLING_TIME.register(&mut registry);
+        let actor_pings = ping_metrics::ACTOR_PINGS.register(&mut registry);
+        Self { registry, requests, slow_requests, connections, handling, actor_pings }
+    }
+
+    pub fn request(&self, labels: &RequestLabels) {
+        self.requests.get_or_create(labels).inc();
+    }
+
+    pub fn slow_request(&self, labels: &RequestLabels) {
+        self.slow_requests.get_or_create(labels).inc();
+    }
+
+    pub fn connection(&self, transport: Transport) {
+        self.connections.get_or_create(&TransportLabels { transport }).inc();
+    }
+}
+
+pub async fn scrape(State(state): State<AppState>) -> impl IntoResponse {
+    if let Ok(stats) = state.ping.stats().await {
+        state.metrics.actor_pings.set(stats.total_pings as i64);
+    }
+    // Rebuilt on every scrape, so the clients that left drop out
+    if let Ok(clients) = state.stats.ask(GetPeerStats).await {
+        state.metrics.handling.clear();
+        for client in clients.0 {
+            if let Some(us) = client.avg_handling_us {
+                let label = ClientLabels { client: client.peer };
+                state.metrics.handling.get_or_create(&label).set(us as f64 / 1e6);
+            }
+        }
+    }
+    let mut body = String::new();
+    let _ = encode(&mut body, &state.metrics.registry);
+    ([(header::CONTENT_TYPE, "application/openmetrics-text; version=1.0.0; charset=utf-8")], body)
+}
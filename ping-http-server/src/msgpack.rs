@@ -0,0 +1,24 @@
+// MessagePack frames
+//
+// A client that offers the `ping.msgpack` WebSocket subprotocol exchanges
+// MessagePack in binary frames instead of JSON text, which is smaller for
+// pings and pongs. Routes and actors keep dealing in JSON: frames are
+// transcoded here at the edge, so every actor and every server-pushed frame
+// (session info, broadcasts, errors) works unchanged; the transcoding
+// itself is ping-common's `codec::msgpack`.
+
+use ping_common::codec::msgpack;
+use ping_common::Error;
+
+/// Subprotocol a client offers to get MessagePack frames
+pub const PROTOCOL: &str = "ping.msgpack";
+
+/// JSON for an inbound MessagePack frame
+pub fn decode(data: &[u8]) -> Result<String, Error> {
+    msgpack::to_json(data)
+}
+
+/// MessagePack frame for an outbound JSON reply, maps keyed by field name
+pub fn encode(json: &str) -> Result<Vec<u8>, Error> {
+    msgpack::from_json(json)
+}
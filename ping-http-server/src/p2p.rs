@@ -0,0 +1,103 @@
+// libp2p listener
+//
+// With `--libp2p-port <port>` the HTTP server also does what ping-cli-server
+// does: it listens for libp2p peers and publishes its own PingActor (and
+// EchoActor) in the registry under the CLI server's names. ping-cli-client
+// and the browser then talk to one and the same actor in one process, the
+// ping count going up from both sides. The opposite of gateway mode, where
+// this server is the libp2p client. Admin broadcasts are published to the
+// peers over gossipsub (see `gossip`).
+
+use crate::gossip::{self, Gossip};
+use futures::StreamExt;
+use kameo::prelude::*;
+use kameo::remote;
+use libp2p::{
+    noise, tcp, yamux,
+    swarm::{NetworkBehaviour, SwarmEvent},
+};
+use ping_common::{EchoActor, PingActor};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Registry names, the same as ping-cli-server's defaults
+const PING_ACTOR_NAME: &str = "ping_actor";
+const ECHO_ACTOR_NAME: &str = "echo_actor";
+
+// Network behaviour wrapping Kameo's remote messaging, as in the CLI crates
+#[derive(NetworkBehaviour)]
+struct ListenerBehaviour {
+    kameo: remote::Behaviour,
+    gossipsub: libp2p::gossipsub::Behaviour,
+}
+
+// Listen on `port` and publish the actors; the swarm runs in the background
+// for as long as the server does, publishing what the returned handle is given
+pub async fn serve(
+    port: u16,
+    ping_actor: ActorRef<PingActor>,
+    echo_actor: ActorRef<EchoActor>,
+) -> Result<Gossip, Box<dyn std::error::Error>> {
+    let mut swarm = libp2p::SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)?
+        .with_behaviour(|key| {
+            let peer_id = key.public().to_peer_id();
+            let messaging_config = remote::messaging::Config::default()
+                .with_request_timeout(Duration::from_secs(120));
+            let kameo = remote::Behaviour::new(peer_id, messaging_config);
+            Ok(ListenerBehaviour { kameo, gossipsub: gossip::behaviour(key)? })
+        })?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(600)))
+        .build();
+
+    // Initialize Kameo's global actor registry
+    swarm.behaviour().kameo.init_global();
+    let peer_id = *swarm.local_peer_id();
+    info!("libp2p Peer ID: {}", peer_id);
+    swarm.listen_on(format!("/ip4/0.0.0.0/tcp/{}", port).parse()?)?;
+
+    let (gossip, mut broadcasts) = Gossip::channel();
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = swarm.select_next_some() => event,
+                Some(frame) = broadcasts.recv() => {
+                    gossip::publish(&mut swarm.behaviour_mut().gossipsub, frame);
+                    continue;
+                }
+            };
+            match event {
+                SwarmEvent::Behaviour(ListenerBehaviourEvent::Kameo(event)) => {
+                    debug!("Kameo event: {:?}", event);
+                }
+                SwarmEvent::NewListenAddr { address, .. } => {
+                    info!("libp2p connection string: {}/p2p/{}", address, peer_id);
+                }
+                SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                    info!("libp2p client connected: {}", peer_id);
+                    swarm.add_peer_address(peer_id, endpoint.get_remote_address().clone());
+                }
+                SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                    info!("libp2p client disconnected: {}", peer_id);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    // Registration goes through the swarm task, so it has to be running
+    tokio::spawn(async move {
+        register(&ping_actor, PING_ACTOR_NAME).await;
+        register(&echo_actor, ECHO_ACTOR_NAME).await;
+    });
+    Ok(gossip)
+}
+
+// Publish `actor` in the registry as `name`
+async fn register<A: Actor + RemoteActor>(actor: &ActorRef<A>, name: &str) {
+    match actor.register(name).await {
+        Ok(_) => info!("{} registered as '{}' for libp2p clients", A::name(), name),
+        Err(e) => warn!("Failed to register {} as '{}': {}", A::name(), name, e),
+    }
+}
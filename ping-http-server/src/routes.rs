@@ -0,0 +1,216 @@
+// Actor registry for the `/ws/:actor` endpoint
+//
+// Each entry bridges JSON text frames to one actor type: the frame is
+// deserialized into the actor's message, forwarded with `ask`, and the reply
+// serialized back. The message must pass its `Validate` rules first. The
+// socket handler only ever sees text in, text out.
+// In gateway mode an entry may point at an actor on another libp2p peer.
+// A frame that fails is answered with an error frame rather than nothing.
+// Each phase runs in a span of its own (`decode`, `ask`, `encode`) inside the
+// frame's, so an exported trace shows where the time went. A frame carrying a
+// trace context in its metadata has its span continue the client's trace.
+
+use futures::future::BoxFuture;
+use kameo::prelude::*;
+use kameo::remote::{RemoteActor, RemoteMessage};
+use kameo::reply::Reply;
+use ping_common::builder::TRACEPARENT;
+use ping_common::{telemetry, Error, Uuid, Validate, ValidationError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, fmt, future::IntoFuture, marker::PhantomData, sync::Arc};
+use tracing::{info_span, Instrument, Span};
+
+/// Why a frame could not be turned into a reply
+#[derive(Debug)]
+pub enum RouteError {
+    Parse(serde_json::Error),
+    Invalid(ValidationError),
+    Actor(String),
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Parse(e) => write!(f, "parse error: {}", e),
+            RouteError::Invalid(e) => write!(f, "invalid message: {}", e),
+            RouteError::Actor(e) => write!(f, "actor error: {}", e),
+        }
+    }
+}
+
+impl From<RouteError> for Error {
+    fn from(e: RouteError) -> Self {
+        match e {
+            RouteError::Parse(e) => Error::Codec(e.to_string()),
+            RouteError::Invalid(e) => Error::from(e),
+            RouteError::Actor(e) => Error::Actor(e),
+        }
+    }
+}
+
+/// What identifies a Ping, read from frames that failed or took long
+#[derive(Deserialize, Default)]
+pub struct Ids {
+    pub sequence: Option<u64>,
+    pub correlation_id: Option<Uuid>,
+    pub client_id: Option<String>,
+}
+
+impl Ids {
+    /// The ids in `text`, those it has
+    pub fn of(text: &str) -> Self {
+        serde_json::from_str(text).unwrap_or_default()
+    }
+}
+
+/// The error frame answering `text`, carrying the Ping's sequence number and
+/// correlation id when the frame has them, so the client can tell which of
+/// its pings failed
+pub fn error_frame(error: &Error, text: &str) -> String {
+    let mut reply = error.to_reply();
+    let ids = Ids::of(text);
+    reply.sequence = ids.sequence;
+    reply.correlation_id = ids.correlation_id;
+    serde_json::to_string(&reply).expect("ErrorReply serializes")
+}
+
+// The metadata of any message, read from frames before they are routed
+#[derive(Deserialize)]
+struct Metadata {
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+/// The span to route `text` to `actor` in, a child of the client's span when
+/// the frame carries its trace context
+pub fn frame_span(actor: &str, text: &str) -> Span {
+    let span = info_span!("frame", actor = %actor);
+    // Most frames carry none, and aren't parsed twice for it
+    if text.contains(TRACEPARENT) {
+        if let Some(traceparent) = serde_json::from_str::<Metadata>(text).ok().and_then(|m| m.metadata.get(TRACEPARENT).cloned()) {
+            telemetry::set_parent(&span, &traceparent);
+        }
+    }
+    span
+}
+
+// The message in a frame, if it passes its `Validate` rules
+fn decode<M: DeserializeOwned + Validate>(text: &str) -> Result<M, RouteError> {
+    info_span!("decode", bytes = text.len()).in_scope(|| {
+        let msg: M = serde_json::from_str(text).map_err(RouteError::Parse)?;
+        msg.validate().map_err(RouteError::Invalid)?;
+        Ok(msg)
+    })
+}
+
+// The frame carrying an actor's reply
+fn encode<R: Serialize>(reply: &R) -> Result<String, RouteError> {
+    info_span!("encode").in_scope(|| serde_json::to_string(reply).map_err(RouteError::Parse))
+}
+
+/// Type-erased bridge from a JSON frame to one actor
+pub trait ActorRoute: Send + Sync {
+    fn handle<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<String, RouteError>>;
+}
+
+/// Routes frames of message type `M` to an `ActorRef<A>`
+pub struct JsonRoute<A: Actor, M> {
+    actor: ActorRef<A>,
+    _message: PhantomData<fn(M)>,
+}
+
+impl<A: Actor, M> JsonRoute<A, M> {
+    pub fn new(actor: ActorRef<A>) -> Self {
+        Self { actor, _message: PhantomData }
+    }
+}
+
+impl<A, M> ActorRoute for JsonRoute<A, M>
+where
+    A: Actor + Message<M>,
+    M: DeserializeOwned + Validate + Send + 'static,
+    <A::Reply as Reply>::Ok: Serialize,
+    <A::Reply as Reply>::Error: fmt::Display,
+{
+    fn handle<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<String, RouteError>> {
+        Box::pin(async move {
+            let msg: M = decode(text)?;
+            let reply = self
+                .actor
+                .ask(msg)
+                .into_future()
+                .instrument(info_span!("ask", actor = A::name()))
+                .await
+                .map_err(|e| RouteError::Actor(e.to_string()))?;
+            encode(&reply)
+        })
+    }
+}
+
+/// Routes frames of message type `M` to a `RemoteActorRef<A>` over libp2p
+pub struct RemoteJsonRoute<A: Actor, M> {
+    actor: RemoteActorRef<A>,
+    _message: PhantomData<fn(M)>,
+}
+
+impl<A: Actor, M> RemoteJsonRoute<A, M> {
+    pub fn new(actor: RemoteActorRef<A>) -> Self {
+        Self { actor, _message: PhantomData }
+    }
+}
+
+impl<A, M> ActorRoute for RemoteJsonRoute<A, M>
+where
+    A: RemoteActor + Message<M> + RemoteMessage<M>,
+    M: DeserializeOwned + Serialize + Validate + Send + Sync + 'static,
+    <A::Reply as Reply>::Ok: Serialize + DeserializeOwned,
+    <A::Reply as Reply>::Error: fmt::Display + DeserializeOwned,
+{
+    fn handle<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<String, RouteError>> {
+        Box::pin(async move {
+            let msg: M = decode(text)?;
+            let reply = self
+                .actor
+                .ask(&msg)
+                .into_future()
+                .instrument(info_span!("ask", actor = A::name(), remote = true))
+                .await
+                .map_err(|e| RouteError::Actor(e.to_string()))?;
+            encode(&reply)
+        })
+    }
+}
+
+/// Actor handles keyed by the path segment they are served under
+#[derive(Clone, Default)]
+pub struct ActorRegistry {
+    routes: HashMap<String, Arc<dyn ActorRoute>>,
+}
+
+impl ActorRegistry {
+    /// Serve `actor` at `/ws/{name}`, accepting messages of type `M`
+    pub fn register<A, M>(&mut self, name: &str, actor: ActorRef<A>)
+    where
+        JsonRoute<A, M>: ActorRoute + 'static,
+        A: Actor,
+    {
+        self.routes.insert(name.to_string(), Arc::new(JsonRoute::<A, M>::new(actor)));
+    }
+
+    /// Serve a remote `actor` at `/ws/{name}`, accepting messages of type `M`
+    pub fn register_remote<A, M>(&mut self, name: &str, actor: RemoteActorRef<A>)
+    where
+        RemoteJsonRoute<A, M>: ActorRoute + 'static,
+        A: Actor,
+    {
+        self.routes.insert(name.to_string(), Arc::new(RemoteJsonRoute::<A, M>::new(actor)));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ActorRoute>> {
+        self.routes.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+}
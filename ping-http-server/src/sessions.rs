@@ -0,0 +1,153 @@
+// Resumable WebSocket sessions
+//
+// A client that connects with `?session=<id>` gets its per-session counters
+// back on reconnect, along with any server-pushed frames it missed while
+// disconnected. Disconnected sessions are dropped once the TTL elapses.
+
+use crate::connections::ConnectionId;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest session id a client may present
+const MAX_SESSION_ID_LEN: usize = 64;
+
+struct Session {
+    messages: u64,
+    connection: Option<ConnectionId>,
+    disconnected_at: Option<Instant>,
+    missed: VecDeque<String>,
+}
+
+/// Result of attaching a connection to a session
+pub struct Attached {
+    pub resumed: bool,
+    pub messages: u64,
+    pub missed: Vec<String>,
+    /// Connection that held the session before this one, if still attached
+    pub displaced: Option<ConnectionId>,
+}
+
+/// Description of a session, served by the admin API
+#[derive(Serialize, Debug)]
+pub struct SessionSummary {
+    pub id: String,
+    pub connection: Option<ConnectionId>,
+    pub messages: u64,
+    pub missed: usize,
+    /// Seconds since the session disconnected, if it is not connected
+    pub idle_secs: Option<u64>,
+}
+
+/// All known sessions, live or waiting to be resumed
+pub struct SessionStore {
+    ttl: Duration,
+    max_missed: usize,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration, max_missed: usize) -> Self {
+        Self {
+            ttl,
+            max_missed,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Session ids are short opaque tokens: ASCII letters, digits, `-` and `_`
+    pub fn is_valid_id(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= MAX_SESSION_ID_LEN
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// Attach `connection` to session `id`, creating the session if needed
+    pub fn attach(&self, id: &str, connection: ConnectionId) -> Attached {
+        let mut sessions = self.sessions.lock().unwrap();
+        let resumed = sessions.contains_key(id);
+        let session = sessions.entry(id.to_string()).or_insert_with(|| Session {
+            messages: 0,
+            connection: None,
+            disconnected_at: None,
+            missed: VecDeque::new(),
+        });
+        let displaced = session.connection.replace(connection);
+        session.disconnected_at = None;
+
+        Attached {
+            resumed,
+            messages: session.messages,
+            missed: session.missed.drain(..).collect(),
+            displaced,
+        }
+    }
+
+    /// Mark the session disconnected, unless a newer connection took it over
+    pub fn detach(&self, id: &str, connection: ConnectionId) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(session) = sessions.get_mut(id) {
+            if session.connection == Some(connection) {
+                session.connection = None;
+                session.disconnected_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Count a message handled on behalf of the session
+    pub fn record_message(&self, id: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(id) {
+            session.messages += 1;
+        }
+    }
+
+    /// Queue a server-originated frame for every disconnected session,
+    /// returning how many sessions it was queued for
+    pub fn queue_missed(&self, frame: &str) -> usize {
+        if self.max_missed == 0 {
+            return 0;
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut queued = 0;
+        for session in sessions.values_mut().filter(|s| s.connection.is_none()) {
+            if session.missed.len() == self.max_missed {
+                session.missed.pop_front();
+            }
+            session.missed.push_back(frame.to_string());
+            queued += 1;
+        }
+        queued
+    }
+
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut list: Vec<_> = sessions
+            .iter()
+            .map(|(id, session)| SessionSummary {
+                id: id.clone(),
+                connection: session.connection,
+                messages: session.messages,
+                missed: session.missed.len(),
+                idle_secs: session.disconnected_at.map(|at| at.elapsed().as_secs()),
+            })
+            .collect();
+        list.sort_by(|a, b| a.id.cmp(&b.id));
+        list
+    }
+
+    /// Drop sessions that have been disconnected for longer than the TTL
+    pub fn expire(&self) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, session| match session.disconnected_at {
+            Some(at) => at.elapsed() < self.ttl,
+            None => true,
+        });
+        before - sessions.len()
+    }
+}
@@ -0,0 +1,38 @@
+// Slow requests
+//
+// The bridges time every message they hand to an actor, from the frame
+// arriving to the reply (or error) being ready. One taking longer than
+// `--slow-request-ms` (500 ms by default, 0 turns it off) is logged as a
+// warning with the client id, sequence number and duration, and counted on
+// /metrics by transport, codec and actor, so a regression of the tail latency
+// shows without tracing every request. The frame is only parsed for its ids
+// once it turned out slow.
+
+use crate::config::Settings;
+use crate::routes::Ids;
+use crate::AppState;
+use ping_metrics::RequestLabels;
+use std::time::Duration;
+use tracing::warn;
+
+/// Count the request in `text`, and log and count it as slow if it took
+/// longer than `settings` allow
+pub fn check(state: &AppState, settings: &Settings, labels: &RequestLabels, text: &str, elapsed: Duration) {
+    state.metrics.request(labels);
+    if settings.slow_request.is_zero() || elapsed <= settings.slow_request {
+        return;
+    }
+    let ids = Ids::of(text);
+    warn!(
+        actor = labels.actor,
+        transport = labels.transport.as_str(),
+        codec = labels.codec.as_str(),
+        client_id = ids.client_id.as_deref(),
+        sequence = ids.sequence,
+        duration_ms = elapsed.as_millis() as u64,
+        "Slow request: {} answered after {:?}",
+        labels.actor,
+        elapsed
+    );
+    state.metrics.slow_request(labels);
+}
@@ -0,0 +1,67 @@
+// Plain TCP endpoint
+//
+// With `--tcp-port <port>` the server also accepts plain TCP connections to
+// the PingActor, for clients that have neither a WebSocket library nor
+// libp2p: each side writes one JSON frame per line, as on a WebTransport
+// stream, server pushes included. There is no URL to carry a token, so with
+// tokens configured the first line has to be the auth frame.
+
+use crate::dashboard::Traffic;
+use crate::lines::{self, LineConnection};
+use crate::AppState;
+use ping_metrics::Transport;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{
+    io::BufReader,
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tracing::{info, info_span, warn, Instrument};
+
+/// Accept connections on `listener` in the background, until the process
+/// exits or the task is aborted
+pub fn serve(listener: TcpListener, state: AppState) -> JoinHandle<()> {
+    if let Ok(addr) = listener.local_addr() {
+        info!("TCP endpoint available at: {} (newline-delimited JSON)", addr);
+    }
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    tokio::spawn(handle_connection(stream, peer, state.clone()));
+                }
+                Err(e) => warn!("TCP accept failed: {}", e),
+            }
+        }
+    })
+}
+
+async fn handle_connection(stream: TcpStream, peer: SocketAddr, state: AppState) {
+    let client = peer.ip();
+    let _ = stream.set_nodelay(true);
+    // The connection keeps these settings even if the config is reloaded
+    let settings = state.config.current();
+    let route = state.actors.get("ping").expect("the PingActor is always registered");
+    let (recv, mut send) = stream.into_split();
+    let mut lines = BufReader::new(recv);
+    if let Err(reason) = lines::authenticate(&mut lines, &settings, None).await {
+        warn!("Rejected TCP client {}: {}", client, reason);
+        lines::refuse(&mut send, reason).await;
+        return;
+    }
+
+    let (id, outbound) = state.connections.register(client, "ping", None);
+    state.metrics.connection(Transport::Tcp);
+    let span = info_span!("tcp", conn = id, %client);
+    let conn = LineConnection {
+        id,
+        client,
+        name: "ping".to_string(),
+        route,
+        settings,
+        state,
+        transport: Transport::Tcp,
+        traffic: Traffic::new(id, client),
+    };
+    lines::serve(Arc::new(conn), lines, send, outbound).instrument(span).await;
+}
@@ -0,0 +1,114 @@
+// WebTransport endpoint
+//
+// With `--webtransport` the server also accepts WebTransport sessions over
+// HTTP/3, on the UDP port with the same number as the HTTPS listener and
+// with the same certificate. The session's path picks the actor the way the
+// WebSocket endpoints do (`/wt` for the PingActor, `/wt/:actor` for the
+// others). The client opens one bidirectional stream and both sides write
+// newline-delimited JSON on it (see `lines`): the frames a JSON WebSocket
+// client would exchange, server pushes included. Tokens are checked as for
+// WebSockets, from `?token=` or an auth line sent first; a client that fails
+// gets an `unauthorized` error line, then the session is closed with code
+// 4401. Sessions, compression and MessagePack stay WebSocket-only.
+
+use crate::auth::{AUTH_TIMEOUT, UNAUTHORIZED};
+use crate::dashboard::Traffic;
+use crate::lines::{self, LineConnection};
+use crate::AppState;
+use ping_metrics::Transport;
+use serde::Deserialize;
+use std::{net::SocketAddr, path::Path, sync::Arc};
+use tokio::io::BufReader;
+use tracing::{debug, info, info_span, warn, Instrument};
+use wtransport::{endpoint::IncomingSession, Endpoint, Identity, ServerConfig, VarInt};
+
+// Query parameters accepted on the session's URL
+#[derive(Deserialize, Default)]
+struct WtParams {
+    token: Option<String>,
+}
+
+/// Accept WebTransport sessions on UDP `addr` until the process exits
+pub async fn serve(addr: SocketAddr, cert: &Path, key: &Path, state: AppState) -> Result<(), Box<dyn std::error::Error>> {
+    let identity = Identity::load_pemfiles(cert, key).await?;
+    let config = ServerConfig::builder()
+        .with_bind_address(addr)
+        .with_identity(identity)
+        .build();
+    let endpoint = Endpoint::server(config)?;
+    info!("WebTransport endpoint available at: https://{}/wt (HTTP/3)", addr);
+    tokio::spawn(async move {
+        loop {
+            let incoming = endpoint.accept().await;
+            tokio::spawn(handle_session(incoming, state.clone()));
+        }
+    });
+    Ok(())
+}
+
+// Check the session request, accept it and serve its stream
+async fn handle_session(incoming: IncomingSession, state: AppState) {
+    let request = match incoming.await {
+        Ok(request) => request,
+        Err(e) => {
+            debug!("WebTransport handshake failed: {}", e);
+            return;
+        }
+    };
+    let client = request.remote_address().ip();
+    // The connection keeps these settings even if the config is reloaded
+    let settings = state.config.current();
+    if let Some(origin) = request.origin() {
+        if !settings.allows_origin(origin) {
+            warn!("Rejected WebTransport session from origin {} ({})", origin, client);
+            request.forbidden().await;
+            return;
+        }
+    }
+    let (path, query) = request.path().split_once('?').unwrap_or((request.path(), ""));
+    let name = match path {
+        "/wt" => "ping".to_string(),
+        _ => path.strip_prefix("/wt/").unwrap_or_default().to_string(),
+    };
+    let Some(route) = state.actors.get(&name) else {
+        request.not_found().await;
+        return;
+    };
+    let params: WtParams = serde_urlencoded::from_str(query).unwrap_or_default();
+
+    let connection = match request.accept().await {
+        Ok(connection) => connection,
+        Err(e) => {
+            debug!("WebTransport session from {} failed: {}", client, e);
+            return;
+        }
+    };
+    let (mut send, recv) = match tokio::time::timeout(AUTH_TIMEOUT, connection.accept_bi()).await {
+        Ok(Ok(stream)) => stream,
+        _ => return,
+    };
+    let mut lines = BufReader::new(recv);
+    if let Err(reason) = lines::authenticate(&mut lines, &settings, params.token.as_deref()).await {
+        warn!("Rejected WebTransport client {} for {}: {}", client, name, reason);
+        lines::refuse(&mut send, reason).await;
+        connection.close(VarInt::from_u32(UNAUTHORIZED.into()), reason.as_bytes());
+        return;
+    }
+
+    let (id, outbound) = state.connections.register(client, &name, None);
+    state.metrics.connection(Transport::WebTransport);
+    let span = info_span!("wt", conn = id, %client, actor = %name);
+    let conn = LineConnection {
+        id,
+        client,
+        name,
+        route,
+        settings,
+        state,
+        transport: Transport::WebTransport,
+        traffic: Traffic::new(id, client),
+    };
+    if let Some(reason) = lines::serve(Arc::new(conn), lines, send, outbound).instrument(span).await {
+        connection.close(VarInt::from_u32(0), reason.as_bytes());
+    }
+}
@@ -0,0 +1,465 @@
+// WebSocket endpoints
+//
+// Bridges WebSocket frames to the actors in the registry. Everything
+// transport-specific (compression, sessions, framing) lives here; the actors
+// never know a WebSocket was involved.
+//
+// Every connection runs inside its own `ws` tracing span carrying the
+// connection id, client address, actor and codec, so the logs of one client
+// can be filtered out of the rest. Message counters are attached to the
+// per-message events and recorded on the span when the connection ends.
+// Each message gets a `frame` span inside it, holding the route's decode,
+// ask and encode spans.
+
+use crate::auth;
+use crate::backpressure::{Admission, Inbox};
+use crate::client::ClientIp;
+use crate::compression::Compression;
+use crate::config::Settings;
+use crate::connections::{ConnectionId, Outbound};
+use crate::dashboard::Traffic;
+use crate::heartbeat;
+use crate::hello;
+use crate::msgpack;
+use crate::routes::{self, ActorRoute, RouteError};
+use crate::sessions::SessionStore;
+use crate::slow;
+use crate::AppState;
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use ping_common::{Error, Ping, SessionInfo, MAX_FRAME_BYTES};
+use ping_metrics::{Codec, RequestLabels, Transport};
+use serde::Deserialize;
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tokio::{
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+    task::JoinSet,
+};
+use tracing::{debug, field, info, info_span, warn, error, Instrument, Span};
+
+// Query parameters accepted on the WebSocket endpoint
+#[derive(Deserialize)]
+pub struct WsParams {
+    compression: Option<String>,
+    session: Option<String>,
+    token: Option<String>,
+}
+
+// Handle WebSocket upgrade requests on /ws, kept as an alias for /ws/ping
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    client: ClientIp,
+    headers: HeaderMap,
+    query: Query<WsParams>,
+    state: State<AppState>,
+) -> Response {
+    actor_websocket_handler(ws, client, headers, Path("ping".to_string()), query, state).await
+}
+
+// Handle WebSocket upgrade requests for a named actor
+pub async fn actor_websocket_handler(
+    ws: WebSocketUpgrade,
+    ClientIp(client): ClientIp,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(params): Query<WsParams>,
+    State(state): State<AppState>,
+) -> Response {
+    // The connection keeps these settings even if the config is reloaded
+    let settings = state.config.current();
+    // Browsers always send Origin on WebSocket upgrades; CORS doesn't apply
+    // to them, so the allow-list is enforced here
+    if let Some(origin) = headers.get(header::ORIGIN).and_then(|o| o.to_str().ok()) {
+        if !settings.allows_origin(origin) {
+            warn!("Rejected WebSocket upgrade from origin {} ({})", origin, client);
+            return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+        }
+    }
+    let Some(route) = state.actors.get(&name) else {
+        return (StatusCode::NOT_FOUND, format!("Unknown actor: {}", name)).into_response();
+    };
+    if let Some(id) = &params.session {
+        if !SessionStore::is_valid_id(id) {
+            return (StatusCode::BAD_REQUEST, "Invalid session id").into_response();
+        }
+    }
+    let compress = Compression::negotiate(settings.compression, params.compression.as_deref());
+    // Without the subprotocol on offer a MessagePack client is refused
+    let ws = if settings.msgpack { ws.protocols([msgpack::PROTOCOL]) } else { ws };
+    ws.max_message_size(MAX_FRAME_BYTES).on_upgrade(move |mut socket| async move {
+        // MessagePack frames are binary already and aren't deflated
+        let msgpack = socket.protocol().is_some_and(|p| p == msgpack::PROTOCOL);
+        let compress = compress && !msgpack;
+        if let Err(reason) = auth::authenticate(&mut socket, &settings, params.token.as_deref(), msgpack).await {
+            warn!("Rejected WebSocket client {} for {}: {}", client, name, reason);
+            return;
+        }
+        let (id, outbound) = state.connections.register(client, &name, params.session.as_deref());
+        state.metrics.connection(Transport::WebSocket);
+        let span = info_span!(
+            "ws",
+            conn = id,
+            %client,
+            actor = %name,
+            codec = if msgpack { "msgpack" } else if compress { "deflate" } else { "json" },
+            session = field::Empty,
+            received = field::Empty,
+            replied = field::Empty,
+        );
+        if let Some(session) = &params.session {
+            span.record("session", session.as_str());
+        }
+        let conn = Connection {
+            id,
+            client,
+            name,
+            route,
+            compress,
+            msgpack,
+            session_id: params.session,
+            settings,
+            state,
+            span: span.clone(),
+            received: AtomicU64::new(0),
+            replied: AtomicU64::new(0),
+            traffic: Traffic::new(id, client),
+        };
+        handle_socket(socket, conn, outbound).instrument(span).await
+    })
+}
+
+// Everything the reader, writer and processing tasks need to know about
+// their connection
+struct Connection {
+    id: ConnectionId,
+    client: IpAddr,
+    name: String,
+    route: Arc<dyn ActorRoute>,
+    compress: bool,
+    msgpack: bool,
+    session_id: Option<String>,
+    settings: Arc<Settings>,
+    state: AppState,
+    span: Span,
+    received: AtomicU64,
+    replied: AtomicU64,
+    traffic: Traffic,
+}
+
+// Handle individual WebSocket connections
+// Bridges WebSocket messages to Kameo actor messages. The socket is split so
+// a writer task owns the sending half while the reader keeps reading (Close
+// frames included) as replies are produced by concurrent processing tasks.
+async fn handle_socket(
+    socket: WebSocket,
+    conn: Connection,
+    outbound: mpsc::UnboundedReceiver<Outbound>,
+) {
+    let conn = Arc::new(conn);
+    info!(
+        "WebSocket client #{} ({}) connected to {} (compression: {}, msgpack: {})",
+        conn.id, conn.client, conn.name, conn.compress, conn.msgpack
+    );
+
+    let initial = restore_session(&conn);
+    let (sink, stream) = socket.split();
+    // Room for every in-flight reply plus an error notice per queued frame
+    let capacity = conn.settings.max_inflight + conn.settings.queue_size;
+    let (replies_tx, replies_rx) = mpsc::channel(capacity);
+
+    let writer = write_frames(sink, conn.clone(), initial, replies_rx, outbound);
+    let writer = tokio::spawn(writer.instrument(conn.span.clone()));
+    read_frames(stream, &conn, replies_tx).await;
+    let _ = writer.await;
+
+    let received = conn.received.load(Ordering::Relaxed);
+    let replied = conn.replied.load(Ordering::Relaxed);
+    conn.span.record("received", received);
+    conn.span.record("replied", replied);
+    info!("Client #{} disconnected after {} message(s), {} replied", conn.id, received, replied);
+
+    conn.state.connections.unregister(conn.id);
+    conn.traffic.closed(&conn.state.stats);
+    if let Some(session_id) = &conn.session_id {
+        conn.state.sessions.detach(session_id, conn.id);
+    }
+}
+
+// Restore the session and return the frames to send before anything else:
+// the SessionInfo followed by whatever was pushed while it was away
+fn restore_session(conn: &Connection) -> Vec<String> {
+    let Some(id) = conn.session_id.as_deref() else {
+        return Vec::new();
+    };
+    let state = &conn.state;
+    let mut attached = state.sessions.attach(id, conn.id);
+    if let Some(previous) = attached.displaced {
+        state.connections.close(previous, "session taken over by a newer connection");
+    }
+    info!(
+        "Session {} {} ({} messages, {} missed)",
+        id,
+        if attached.resumed { "resumed" } else { "started" },
+        attached.messages,
+        attached.missed.len()
+    );
+    let welcome = SessionInfo {
+        session_id: id.to_string(),
+        resumed: attached.resumed,
+        messages: attached.messages,
+        missed: attached.missed.len(),
+    };
+    let mut frames = vec![serde_json::to_string(&welcome).unwrap()];
+    frames.append(&mut attached.missed);
+    frames
+}
+
+// Writer task: sends replies and server-pushed frames until the reader is
+// done, the server closes the connection, or the socket fails
+async fn write_frames(
+    mut sink: SplitSink<WebSocket, Message>,
+    conn: Arc<Connection>,
+    initial: Vec<String>,
+    mut replies: mpsc::Receiver<String>,
+    mut outbound: mpsc::UnboundedReceiver<Outbound>,
+) {
+    for frame in initial {
+        if sink.send(encode(&conn, frame)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let frame = tokio::select! {
+            reply = replies.recv() => match reply {
+                Some(json) => json,
+                // The reader is done; if it gave up on the client (a refused
+                // hello, a full inbox) the client learns it from a clean
+                // close, with the reason the reader left behind if any
+                None => {
+                    let reason = std::iter::from_fn(|| outbound.try_recv().ok()).find_map(|push| match push {
+                        Outbound::Close(reason) => Some(reason),
+                        Outbound::Frame(_) => None,
+                    });
+                    let _ = sink.send(Message::Close(reason.map(policy_close))).await;
+                    return;
+                }
+            },
+            Some(push) = outbound.recv() => match push {
+                Outbound::Frame(frame) => frame,
+                Outbound::Close(reason) => {
+                    info!("Closing client #{}: {}", conn.id, reason);
+                    let _ = sink.send(Message::Close(Some(policy_close(reason)))).await;
+                    return;
+                }
+            },
+        };
+        if sink.send(encode(&conn, frame)).await.is_err() {
+            return;
+        }
+    }
+}
+
+// A close with code 1008 (policy violation) and `reason`, so a kicked or
+// overflowing browser client learns why
+fn policy_close(reason: String) -> CloseFrame<'static> {
+    CloseFrame { code: close_code::POLICY, reason: reason.into() }
+}
+
+// Reader loop: decodes inbound frames into the connection's bounded inbox,
+// applying the overflow policy when the actor falls behind
+async fn read_frames(
+    mut stream: SplitStream<WebSocket>,
+    conn: &Arc<Connection>,
+    replies: mpsc::Sender<String>,
+) {
+    let backpressure = &conn.state.backpressure;
+    let inbox = Arc::new(Inbox::new(conn.settings.overflow_policy, conn.settings.queue_size));
+    let dispatcher = dispatch(conn.clone(), inbox.clone(), replies.clone());
+    let dispatcher = tokio::spawn(dispatcher.instrument(conn.span.clone()));
+
+    loop {
+        let msg = tokio::select! {
+            msg = stream.next() => msg,
+            // The writer stopped: server-side close or a dead socket
+            _ = replies.closed() => break,
+        };
+
+        // Compressed clients may send deflated pings as binary frames, and
+        // MessagePack clients send nothing else
+        let text = match msg {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Binary(data))) if conn.msgpack => match msgpack::decode(&data) {
+                Ok(text) => text,
+                Err(e) => {
+                    warn!("MessagePack decode error: {}", e);
+                    let _ = replies.try_send(routes::error_frame(&e, ""));
+                    continue;
+                }
+            },
+            Some(Ok(Message::Binary(data))) if conn.compress => {
+                match conn.state.compression.decompress(&data).map(String::from_utf8) {
+                    Ok(Ok(text)) => text,
+                    Ok(Err(e)) => {
+                        warn!("Parse error: {}", e);
+                        let _ = replies.try_send(routes::error_frame(&Error::Codec(e.to_string()), ""));
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Decompression error: {}", e);
+                        let _ = replies.try_send(routes::error_frame(&Error::Codec(e.to_string()), ""));
+                        continue;
+                    }
+                }
+            }
+            Some(Ok(Message::Close(_))) | None => {
+                info!("Client #{} ({}) closed connection", conn.id, conn.client);
+                break;
+            }
+            Some(Err(e)) => {
+                error!("WebSocket error: {}", e);
+                break;
+            }
+            Some(Ok(_)) => continue,
+        };
+
+        // Answered ahead of the inbox, so a busy actor doesn't make the
+        // connection look dead
+        if let Some(reply) = heartbeat::answer_keepalive(&text) {
+            let _ = replies.try_send(reply);
+            continue;
+        }
+        let codec = if conn.msgpack { "msgpack" } else { "json" };
+        match hello::answer(&text, codec) {
+            Some(Ok(ack)) => {
+                let _ = replies.try_send(ack);
+                continue;
+            }
+            Some(Err(error)) => {
+                warn!("Client #{} ({}) refused: incompatible hello", conn.id, conn.client);
+                let _ = replies.try_send(error);
+                break;
+            }
+            None => {}
+        }
+
+        let received = conn.received.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!(received, "Frame received");
+        match backpressure.admit(&inbox, text) {
+            Admission::Queued => {}
+            Admission::DroppedOldest => debug!("Client #{} inbox full, dropped oldest frame", conn.id),
+            Admission::Rejected => {
+                debug!("Client #{} inbox full, rejected frame", conn.id);
+                let error = Error::Overloaded("Server is overloaded, message dropped".to_string()).to_reply();
+                // Never wait on the writer here, that would stall reading again
+                let _ = replies.try_send(serde_json::to_string(&error).unwrap());
+            }
+            Admission::Close => {
+                warn!("Client #{} ({}) inbox full, closing connection", conn.id, conn.client);
+                conn.state.connections.close(conn.id, "inbox full");
+                break;
+            }
+        }
+    }
+
+    // Aborting the dispatcher drops anything still in flight
+    dispatcher.abort();
+}
+
+// Dispatcher: feeds queued frames to processing tasks, with at most
+// `max_inflight` in progress per connection
+async fn dispatch(conn: Arc<Connection>, inbox: Arc<Inbox>, replies: mpsc::Sender<String>) {
+    let limit = Arc::new(Semaphore::new(conn.settings.max_inflight));
+    let mut tasks = JoinSet::new();
+    loop {
+        let permit = limit.clone().acquire_owned().await.expect("semaphore is never closed");
+        let text = inbox.next().await;
+        let task = process_frame(conn.clone(), text, replies.clone(), permit);
+        tasks.spawn(task.instrument(conn.span.clone()));
+        while tasks.try_join_next().is_some() {}
+    }
+}
+
+// Deserialize, forward to the actor (same actor as CLI uses!) and queue the
+// serialized reply, or an error frame, for the writer
+async fn process_frame(
+    conn: Arc<Connection>,
+    text: String,
+    replies: mpsc::Sender<String>,
+    _permit: OwnedSemaphorePermit,
+) {
+    let started = Instant::now();
+    let handled = conn.route.handle(&text).instrument(routes::frame_span(&conn.name, &text)).await;
+    let codec = if conn.msgpack { Codec::Msgpack } else { Codec::Json };
+    let labels = RequestLabels { transport: Transport::WebSocket, codec, actor: conn.name.clone() };
+    slow::check(&conn.state, &conn.settings, &labels, &text, started.elapsed());
+    let json = match handled {
+        Ok(json) => json,
+        Err(e) => {
+            match &e {
+                RouteError::Parse(e) => warn!("Parse error: {}", e),
+                RouteError::Invalid(e) => warn!("Invalid message: {}", e),
+                e => error!("{}", e),
+            }
+            let _ = replies.send(routes::error_frame(&e.into(), &text)).await;
+            return;
+        }
+    };
+    let handled_in = started.elapsed();
+    conn.state.record_latency(handled_in);
+    let replied = conn.replied.fetch_add(1, Ordering::Relaxed) + 1;
+    conn.traffic.reply(&conn.state.stats, text.len(), json.len(), handled_in);
+    info!(replied, "Replied to {} message", conn.name);
+    // Routes only deal in text, so the audit log reads the Ping again
+    if let Some(audit) = conn.state.audit.as_ref().filter(|_| conn.name == "ping") {
+        if let Ok(ping) = serde_json::from_str::<Ping>(&text) {
+            audit.record(&ping, "websocket", conn.client, conn.id, conn.session_id.as_deref());
+        }
+    }
+    if let Some(id) = &conn.session_id {
+        conn.state.sessions.record_message(id);
+    }
+    let _ = replies.send(json).await;
+}
+
+// Build the outgoing frame: MessagePack for clients that chose it, deflating
+// large payloads for compressed clients
+fn encode(conn: &Connection, json: String) -> Message {
+    if conn.msgpack {
+        return match msgpack::encode(&json) {
+            Ok(data) => Message::Binary(data),
+            Err(e) => {
+                warn!("MessagePack encode error: {}", e);
+                Message::Text(json)
+            }
+        };
+    }
+    if !conn.compress {
+        return Message::Text(json);
+    }
+    match conn.state.compression.compress(json.as_bytes(), conn.settings.compression_threshold) {
+        Ok(Some(data)) => Message::Binary(data),
+        Ok(None) => Message::Text(json),
+        Err(e) => {
+            warn!("Compression error: {}", e);
+            Message::Text(json)
+        }
+    }
+}
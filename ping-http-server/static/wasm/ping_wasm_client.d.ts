@@ -0,0 +1,380 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/** A ping as sent to the server */
+export interface Ping {
+    message: string;
+    sequence: number;
+    /** The `client_id` option, if set */
+    client_id?: string;
+    /** A UUID unique to this ping, echoed in its Pong */
+    correlation_id?: string;
+    /** Unix time in milliseconds when the client sent it */
+    sent_at_ms?: number;
+    /** Free-form entries, such as a W3C `traceparent` */
+    metadata?: Record<string, string>;
+}
+
+/** The server's answer to a ping */
+export interface Pong {
+    message: string;
+    sequence: number;
+    total_pings: number;
+    /** The ping's `client_id`, `correlation_id`, `sent_at_ms` and
+     * `metadata`, when it had them and the server echoes them */
+    client_id?: string;
+    correlation_id?: string;
+    sent_at_ms?: number;
+    metadata?: Record<string, string>;
+    /** Milliseconds from sending the ping to this Pong; missing if the ping had timed out */
+    rtt_ms?: number;
+}
+
+/** A notice the server pushed to every client */
+export interface Broadcast {
+    type: "broadcast";
+    message: string;
+    /** Unix time in milliseconds when the server sent it */
+    sent_at_ms: number;
+}
+
+/** The PingActor's counter, pushed periodically when the server has heartbeats on */
+export interface Heartbeat {
+    type: "heartbeat";
+    total_pings: number;
+    /** Unix time in milliseconds when the server sent it */
+    sent_at_ms: number;
+}
+
+/** Round trips of the pings answered so far; the times are null until the first Pong */
+export interface RttStats {
+    count: number;
+    min_ms: number | null;
+    avg_ms: number | null;
+    max_ms: number | null;
+    last_ms: number | null;
+}
+
+/** Totals of `stats()`; the times are null until the first Pong */
+export interface Stats {
+    sent: number;
+    received: number;
+    errors: number;
+    min_ms: number | null;
+    avg_ms: number | null;
+    p95_ms: number | null;
+    max_ms: number | null;
+}
+
+/** Settings for a new WasmPingClient, all optional */
+export interface ClientOptions {
+    /** Encoding of the frames; "msgpack" needs a server that offers it */
+    codec?: "json" | "msgpack";
+    /** How many pings may wait for the connection while it is down (default 100); 0 rejects them right away */
+    queue_limit?: number;
+    /** Token for a server started with --ws-token */
+    auth_token?: string;
+    /** How the token is sent: in an auth frame once connected (default), or as ?token= on the URL */
+    auth_via?: "frame" | "query";
+    /** "webtransport" tries the server's HTTP/3 endpoint first, falling back to a WebSocket; needs the `webtransport` feature and JSON */
+    transport?: "websocket" | "webtransport";
+    /** WebTransport endpoint; by default the WebSocket URL as https: with /ws turned into /wt */
+    webtransport_url?: string;
+    /** Largest encoded ping to send (default 1 MiB, the server's limit); larger ones are rejected */
+    max_bytes?: number;
+    /** Send a keepalive after this many milliseconds without a frame from the server (default 0, off) */
+    keepalive_ms?: number;
+    /** How long the server may take to answer a keepalive before `on_stale` is called (default 5000) */
+    stale_ms?: number;
+    /** Keep the stats and a server session id in localStorage under this key, and carry on from them */
+    persist_key?: string;
+    /** Sent with every ping, for the server to recognise this client by */
+    client_id?: string;
+    /** Metadata entries sent with every ping, and echoed in its Pong */
+    metadata?: Record<string, string>;
+    /** Sequence number of the first ping (default 1), to carry on from an earlier client */
+    first_sequence?: number;
+}
+
+
+
+/**
+ * WebAssembly ping client
+ * Uses the same Ping/Pong message format as the CLI client
+ */
+export class WasmPingClient {
+    free(): void;
+    [Symbol.dispose](): void;
+    /**
+     * Close the connection for good: no reconnection follows, and pings
+     * still waiting for their Pong are rejected. `on_close` is called once
+     * the socket has closed. `free()` does the same and releases the client.
+     */
+    close(): void;
+    /**
+     * Create new WebSocket connection to the Kameo server
+     * `url` is the server's WebSocket endpoint, e.g. `wss://example.com/ws`;
+     * a path such as `/ws/ping` is taken relative to the page, and without
+     * one the client connects to `/ws` on the host that served the page,
+     * over `wss:` when the page came over `https:`
+     * A dropped connection is reopened with backoff (0.5s doubling up to
+     * 10s, jittered), and pings keep their sequence numbers across it
+     * `options.codec: "msgpack"` exchanges MessagePack frames with the
+     * server instead of JSON; a server that doesn't offer them refuses the
+     * connection
+     * `options.client_id` goes out with every Ping, and
+     * `options.first_sequence` numbers the first one, so a returning page
+     * can carry on as the same client
+     */
+    constructor(url?: string | null, options?: ClientOptions | null);
+    /**
+     * Call `callback(event)` with the CloseEvent when the server refuses
+     * the token (close code 4401, the reason says why). The client doesn't
+     * reconnect after that, as the same token would be refused again.
+     */
+    on_auth_error(callback: (event: CloseEvent) => void): void;
+    /**
+     * Call `callback(push)` with every frame the server pushes on its own:
+     * `{type: "broadcast", message, sent_at_ms}` from the admin API, and
+     * `{type: "heartbeat", total_pings, sent_at_ms}` if heartbeats are on.
+     * They never reach `on_pong`.
+     */
+    on_broadcast(callback: (push: Broadcast | Heartbeat) => void): void;
+    /**
+     * Call `callback(event)` with the CloseEvent each time the connection
+     * closes; the client then reconnects, unless it was closed by `close()`
+     */
+    on_close(callback: (event: CloseEvent) => void): void;
+    /**
+     * Call `callback(error)` on WebSocket errors and failed reconnections
+     */
+    on_error(callback: (error: Event | Error) => void): void;
+    /**
+     * Call `callback()` each time the connection opens, reconnections
+     * included
+     */
+    on_open(callback: () => void): void;
+    /**
+     * Call `callback(pong)` with every Pong received, whether or not its
+     * ping is still waiting for it
+     */
+    on_pong(callback: (pong: Pong) => void): void;
+    /**
+     * Call `callback()` each time a dropped connection has been reopened
+     */
+    on_reconnect(callback: () => void): void;
+    /**
+     * Call `callback()` when the server leaves a keepalive unanswered for
+     * `stale_ms`, a connection that looks open but carries nothing; with
+     * `keepalive_ms` set only. The client keeps the connection, so the page
+     * decides what to do.
+     */
+    on_stale(callback: () => void): void;
+    /**
+     * State of the current socket, as WebSocket.readyState: 0 connecting,
+     * 1 open, 2 closing, 3 closed
+     */
+    readyState(): number;
+    /**
+     * Send a ping message to the server
+     * Uses the same message format as the CLI client. The returned Promise
+     * resolves with the Pong to this ping (`{message, sequence, total_pings,
+     * rtt_ms}`), or rejects if it can't be sent, the server answers it with
+     * an error, or no Pong comes within `timeout_ms`. While the connection
+     * is down the ping waits in the queue and goes out once it is back; with
+     * `queue_limit` pings already waiting, it is rejected instead. The
+     * timeout counts from the call either way.
+     * `message` replaces the default "Hello from Wasm #n". A blank one, or
+     * one that makes the frame larger than `max_bytes`, is rejected without
+     * using up a sequence number.
+     */
+    send_ping(message?: string | null): Promise<Pong>;
+    /**
+     * `send_ping()` for a ping in the trace of the page's own span:
+     * `traceparent` is that span's W3C trace context
+     * (`00-<trace id>-<span id>-<flags>`, as OpenTelemetry JS's
+     * `propagation.inject` writes it), and the servers' spans for the ping
+     * become its children
+     */
+    send_traced_ping(message: string | null | undefined, traceparent: string): Promise<Pong>;
+    /**
+     * Also post every event to `port` as a message, for a client running
+     * in a Web Worker: `{type: "open"}`, `{type: "close", code, reason,
+     * was_clean}`, `{type: "error", message}`, `{type: "pong", pong}`,
+     * `{type: "broadcast", push}`, `{type: "reconnect"}`,
+     * `{type: "auth_error", code, reason}` and `{type: "stale"}`. Pass
+     * the worker's `self` to reach the page that started it, or null to
+     * stop.
+     */
+    set_event_port(port?: MessagePort | DedicatedWorkerGlobalScope | null): void;
+    /**
+     * Send a ping every `interval_ms` milliseconds until `stop_auto_ping()`
+     * or `close()`, replacing any auto-ping already running. Nothing waits
+     * for these pings; their Pongs go to `on_pong` and into `rtt_stats`.
+     */
+    start_auto_ping(interval_ms: number): void;
+    /**
+     * Totals since the client was created: `{sent, received, errors,
+     * min_ms, avg_ms, p95_ms, max_ms}`. `errors` counts the pings that
+     * couldn't be sent or queued and those that got no Pong in time; the
+     * 95th percentile is over every round trip, to within 1.6%.
+     */
+    stats(): Stats;
+    /**
+     * Stop the pings of `start_auto_ping()`; those already sent still
+     * get their Pongs
+     */
+    stop_auto_ping(): void;
+    /**
+     * Whether `start_auto_ping()` is sending pings
+     */
+    readonly is_auto_pinging: boolean;
+    /**
+     * Whether the socket is open, so pings can be sent right away
+     */
+    readonly is_connected: boolean;
+    /**
+     * Number of pings waiting for the connection to be sent
+     */
+    readonly queue_length: number;
+    /**
+     * Round trips of the pings answered so far: `{count, min_ms, avg_ms,
+     * max_ms, last_ms}`
+     */
+    readonly rtt_stats: RttStats;
+    /**
+     * The server session the WebSocket resumes, kept with the stats under
+     * `persist_key`; undefined without one, or without localStorage
+     */
+    readonly session_id: string | undefined;
+    /**
+     * How long a ping waits for its Pong, in milliseconds (default 10000)
+     */
+    timeout_ms: number;
+    /**
+     * The transport in use: "webtransport" while the client connects over
+     * WebTransport, "websocket" otherwise, including after falling back
+     */
+    readonly transport: "websocket" | "webtransport";
+    /**
+     * The URL the client connected to
+     */
+    readonly url: string;
+}
+
+/**
+ * `size` WasmPingClients to one server, sharing out the pings
+ */
+export class WasmPingClientPool {
+    free(): void;
+    [Symbol.dispose](): void;
+    /**
+     * Close every connection for good, as `close()` does for one client
+     */
+    close(): void;
+    /**
+     * Open `size` connections to `url`, each as `new WasmPingClient(url,
+     * options)` would
+     */
+    constructor(size: number, url?: string | null, options?: ClientOptions | null);
+    /**
+     * Call `callback(pong)` with every Pong any of the connections receives
+     */
+    on_pong(callback: (pong: Pong) => void): void;
+    /**
+     * Send a ping over the next connection in turn, as `send_ping()` of a
+     * single client does. Connections that are down are passed over while
+     * any is open; with none open, the ping waits in the next one's queue.
+     */
+    send_ping(message?: string | null): Promise<Pong>;
+    /**
+     * `stats()` over all connections together: the counts are added up,
+     * and the round trips pooled
+     */
+    stats(): Stats;
+    /**
+     * Number of connections open right now
+     */
+    readonly connected: number;
+    /**
+     * Number of connections in the pool
+     */
+    readonly size: number;
+}
+
+export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
+
+export interface InitOutput {
+    readonly memory: WebAssembly.Memory;
+    readonly __wbg_wasmpingclient_free: (a: number, b: number) => void;
+    readonly wasmpingclient_close: (a: number) => void;
+    readonly wasmpingclient_is_auto_pinging: (a: number) => number;
+    readonly wasmpingclient_is_connected: (a: number) => number;
+    readonly wasmpingclient_new: (a: number, b: number, c: number) => [number, number, number];
+    readonly wasmpingclient_on_auth_error: (a: number, b: any) => void;
+    readonly wasmpingclient_on_broadcast: (a: number, b: any) => void;
+    readonly wasmpingclient_on_close: (a: number, b: any) => void;
+    readonly wasmpingclient_on_error: (a: number, b: any) => void;
+    readonly wasmpingclient_on_open: (a: number, b: any) => void;
+    readonly wasmpingclient_on_pong: (a: number, b: any) => void;
+    readonly wasmpingclient_on_reconnect: (a: number, b: any) => void;
+    readonly wasmpingclient_on_stale: (a: number, b: any) => void;
+    readonly wasmpingclient_queue_length: (a: number) => number;
+    readonly wasmpingclient_readyState: (a: number) => number;
+    readonly wasmpingclient_rtt_stats: (a: number) => any;
+    readonly wasmpingclient_send_ping: (a: number, b: number, c: number) => any;
+    readonly wasmpingclient_send_traced_ping: (a: number, b: number, c: number, d: number, e: number) => any;
+    readonly wasmpingclient_session_id: (a: number) => [number, number];
+    readonly wasmpingclient_set_event_port: (a: number, b: number) => void;
+    readonly wasmpingclient_set_timeout_ms: (a: number, b: number) => void;
+    readonly wasmpingclient_start_auto_ping: (a: number, b: number) => [number, number];
+    readonly wasmpingclient_stats: (a: number) => any;
+    readonly wasmpingclient_stop_auto_ping: (a: number) => void;
+    readonly wasmpingclient_timeout_ms: (a: number) => number;
+    readonly wasmpingclient_transport: (a: number) => [number, number];
+    readonly wasmpingclient_url: (a: number) => [number, number];
+    readonly __wbg_wasmpingclientpool_free: (a: number, b: number) => void;
+    readonly wasmpingclientpool_close: (a: number) => void;
+    readonly wasmpingclientpool_connected: (a: number) => number;
+    readonly wasmpingclientpool_new: (a: number, b: number, c: number, d: number) => [number, number, number];
+    readonly wasmpingclientpool_on_pong: (a: number, b: any) => void;
+    readonly wasmpingclientpool_send_ping: (a: number, b: number, c: number) => any;
+    readonly wasmpingclientpool_size: (a: number) => number;
+    readonly wasmpingclientpool_stats: (a: number) => any;
+    readonly wasm_bindgen__closure__destroy__h6bbaa58cb3cf32ee: (a: number, b: number) => void;
+    readonly wasm_bindgen__closure__destroy__h4412a969fbca343f: (a: number, b: number) => void;
+    readonly wasm_bindgen__convert__closures_____invoke__h1f5861940453616d: (a: number, b: number, c: any, d: any) => void;
+    readonly wasm_bindgen__convert__closures_____invoke__hd1db2d7013ce88d5: (a: number, b: number, c: any) => void;
+    readonly wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222: (a: number, b: number, c: any) => void;
+    readonly wasm_bindgen__convert__closures_____invoke__hadea7c59506573ff: (a: number, b: number) => void;
+    readonly __wbindgen_malloc: (a: number, b: number) => number;
+    readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
+    readonly __wbindgen_exn_store: (a: number) => void;
+    readonly __externref_table_alloc: () => number;
+    readonly __wbindgen_externrefs: WebAssembly.Table;
+    readonly __wbindgen_free: (a: number, b: number, c: number) => void;
+    readonly __externref_table_dealloc: (a: number) => void;
+    readonly __wbindgen_start: () => void;
+}
+
+export type SyncInitInput = BufferSource | WebAssembly.Module;
+
+/**
+ * Instantiates the given `module`, which can either be bytes or
+ * a precompiled `WebAssembly.Module`.
+ *
+ * @param {{ module: SyncInitInput }} module - Passing `SyncInitInput` directly is deprecated.
+ *
+ * @returns {InitOutput}
+ */
+export function initSync(module: { module: SyncInitInput } | SyncInitInput): InitOutput;
+
+/**
+ * If `module_or_path` is {RequestInfo} or {URL}, makes a request and
+ * for everything else, calls `WebAssembly.instantiate` directly.
+ *
+ * @param {{ module_or_path: InitInput | Promise<InitInput> }} module_or_path - Passing `InitInput` directly is deprecated.
+ *
+ * @returns {Promise<InitOutput>}
+ */
+export default function __wbg_init (module_or_path?: { module_or_path: InitInput | Promise<InitInput> } | InitInput | Promise<InitInput>): Promise<InitOutput>;
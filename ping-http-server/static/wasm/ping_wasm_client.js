@@ -0,0 +1,1239 @@
+/* @ts-self-types="./ping_wasm_client.d.ts" */
+
+/**
+ * WebAssembly ping client
+ * Uses the same Ping/Pong message format as the CLI client
+ */
+export class WasmPingClient {
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        this.__wbg_ptr = 0;
+        WasmPingClientFinalization.unregister(this);
+        return ptr;
+    }
+    free() {
+        const ptr = this.__destroy_into_raw();
+        wasm.__wbg_wasmpingclient_free(ptr, 0);
+    }
+    /**
+     * Close the connection for good: no reconnection follows, and pings
+     * still waiting for their Pong are rejected. `on_close` is called once
+     * the socket has closed. `free()` does the same and releases the client.
+     */
+    close() {
+        wasm.wasmpingclient_close(this.__wbg_ptr);
+    }
+    /**
+     * Whether `start_auto_ping()` is sending pings
+     * @returns {boolean}
+     */
+    get is_auto_pinging() {
+        const ret = wasm.wasmpingclient_is_auto_pinging(this.__wbg_ptr);
+        return ret !== 0;
+    }
+    /**
+     * Whether the socket is open, so pings can be sent right away
+     * @returns {boolean}
+     */
+    get is_connected() {
+        const ret = wasm.wasmpingclient_is_connected(this.__wbg_ptr);
+        return ret !== 0;
+    }
+    /**
+     * Create new WebSocket connection to the Kameo server
+     * `url` is the server's WebSocket endpoint, e.g. `wss://example.com/ws`;
+     * a path such as `/ws/ping` is taken relative to the page, and without
+     * one the client connects to `/ws` on the host that served the page,
+     * over `wss:` when the page came over `https:`
+     * A dropped connection is reopened with backoff (0.5s doubling up to
+     * 10s, jittered), and pings keep their sequence numbers across it
+     * `options.codec: "msgpack"` exchanges MessagePack frames with the
+     * server instead of JSON; a server that doesn't offer them refuses the
+     * connection
+     * `options.client_id` goes out with every Ping, and
+     * `options.first_sequence` numbers the first one, so a returning page
+     * can carry on as the same client
+     * @param {string | null} [url]
+     * @param {ClientOptions | null} [options]
+     */
+    constructor(url, options) {
+        var ptr0 = isLikeNone(url) ? 0 : passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmpingclient_new(ptr0, len0, isLikeNone(options) ? 0 : addToExternrefTable0(options));
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        this.__wbg_ptr = ret[0] >>> 0;
+        WasmPingClientFinalization.register(this, this.__wbg_ptr, this);
+        return this;
+    }
+    /**
+     * Call `callback(event)` with the CloseEvent when the server refuses
+     * the token (close code 4401, the reason says why). The client doesn't
+     * reconnect after that, as the same token would be refused again.
+     * @param {(event: CloseEvent) => void} callback
+     */
+    on_auth_error(callback) {
+        wasm.wasmpingclient_on_auth_error(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback(push)` with every frame the server pushes on its own:
+     * `{type: "broadcast", message, sent_at_ms}` from the admin API, and
+     * `{type: "heartbeat", total_pings, sent_at_ms}` if heartbeats are on.
+     * They never reach `on_pong`.
+     * @param {(push: Broadcast | Heartbeat) => void} callback
+     */
+    on_broadcast(callback) {
+        wasm.wasmpingclient_on_broadcast(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback(event)` with the CloseEvent each time the connection
+     * closes; the client then reconnects, unless it was closed by `close()`
+     * @param {(event: CloseEvent) => void} callback
+     */
+    on_close(callback) {
+        wasm.wasmpingclient_on_close(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback(error)` on WebSocket errors and failed reconnections
+     * @param {(error: Event | Error) => void} callback
+     */
+    on_error(callback) {
+        wasm.wasmpingclient_on_error(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback()` each time the connection opens, reconnections
+     * included
+     * @param {() => void} callback
+     */
+    on_open(callback) {
+        wasm.wasmpingclient_on_open(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback(pong)` with every Pong received, whether or not its
+     * ping is still waiting for it
+     * @param {(pong: Pong) => void} callback
+     */
+    on_pong(callback) {
+        wasm.wasmpingclient_on_pong(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback()` each time a dropped connection has been reopened
+     * @param {() => void} callback
+     */
+    on_reconnect(callback) {
+        wasm.wasmpingclient_on_reconnect(this.__wbg_ptr, callback);
+    }
+    /**
+     * Call `callback()` when the server leaves a keepalive unanswered for
+     * `stale_ms`, a connection that looks open but carries nothing; with
+     * `keepalive_ms` set only. The client keeps the connection, so the page
+     * decides what to do.
+     * @param {() => void} callback
+     */
+    on_stale(callback) {
+        wasm.wasmpingclient_on_stale(this.__wbg_ptr, callback);
+    }
+    /**
+     * Number of pings waiting for the connection to be sent
+     * @returns {number}
+     */
+    get queue_length() {
+        const ret = wasm.wasmpingclient_queue_length(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * State of the current socket, as WebSocket.readyState: 0 connecting,
+     * 1 open, 2 closing, 3 closed
+     * @returns {number}
+     */
+    readyState() {
+        const ret = wasm.wasmpingclient_readyState(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+     * Round trips of the pings answered so far: `{count, min_ms, avg_ms,
+     * max_ms, last_ms}`
+     * @returns {RttStats}
+     */
+    get rtt_stats() {
+        const ret = wasm.wasmpingclient_rtt_stats(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+     * Send a ping message to the server
+     * Uses the same message format as the CLI client. The returned Promise
+     * resolves with the Pong to this ping (`{message, sequence, total_pings,
+     * rtt_ms}`), or rejects if it can't be sent, the server answers it with
+     * an error, or no Pong comes within `timeout_ms`. While the connection
+     * is down the ping waits in the queue and goes out once it is back; with
+     * `queue_limit` pings already waiting, it is rejected instead. The
+     * timeout counts from the call either way.
+     * `message` replaces the default "Hello from Wasm #n". A blank one, or
+     * one that makes the frame larger than `max_bytes`, is rejected without
+     * using up a sequence number.
+     * @param {string | null} [message]
+     * @returns {Promise<Pong>}
+     */
+    send_ping(message) {
+        var ptr0 = isLikeNone(message) ? 0 : passStringToWasm0(message, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmpingclient_send_ping(this.__wbg_ptr, ptr0, len0);
+        return ret;
+    }
+    /**
+     * `send_ping()` for a ping in the trace of the page's own span:
+     * `traceparent` is that span's W3C trace context
+     * (`00-<trace id>-<span id>-<flags>`, as OpenTelemetry JS's
+     * `propagation.inject` writes it), and the servers' spans for the ping
+     * become its children
+     * @param {string | null | undefined} message
+     * @param {string} traceparent
+     * @returns {Promise<Pong>}
+     */
+    send_traced_ping(message, traceparent) {
+        var ptr0 = isLikeNone(message) ? 0 : passStringToWasm0(message, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ptr1 = passStringToWasm0(traceparent, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len1 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmpingclient_send_traced_ping(this.__wbg_ptr, ptr0, len0, ptr1, len1);
+        return ret;
+    }
+    /**
+     * The server session the WebSocket resumes, kept with the stats under
+     * `persist_key`; undefined without one, or without localStorage
+     * @returns {string | undefined}
+     */
+    get session_id() {
+        const ret = wasm.wasmpingclient_session_id(this.__wbg_ptr);
+        let v1;
+        if (ret[0] !== 0) {
+            v1 = getStringFromWasm0(ret[0], ret[1]).slice();
+            wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
+        }
+        return v1;
+    }
+    /**
+     * Also post every event to `port` as a message, for a client running
+     * in a Web Worker: `{type: "open"}`, `{type: "close", code, reason,
+     * was_clean}`, `{type: "error", message}`, `{type: "pong", pong}`,
+     * `{type: "broadcast", push}`, `{type: "reconnect"}`,
+     * `{type: "auth_error", code, reason}` and `{type: "stale"}`. Pass
+     * the worker's `self` to reach the page that started it, or null to
+     * stop.
+     * @param {MessagePort | DedicatedWorkerGlobalScope | null} [port]
+     */
+    set_event_port(port) {
+        wasm.wasmpingclient_set_event_port(this.__wbg_ptr, isLikeNone(port) ? 0 : addToExternrefTable0(port));
+    }
+    /**
+     * @param {number} timeout_ms
+     */
+    set timeout_ms(timeout_ms) {
+        wasm.wasmpingclient_set_timeout_ms(this.__wbg_ptr, timeout_ms);
+    }
+    /**
+     * Send a ping every `interval_ms` milliseconds until `stop_auto_ping()`
+     * or `close()`, replacing any auto-ping already running. Nothing waits
+     * for these pings; their Pongs go to `on_pong` and into `rtt_stats`.
+     * @param {number} interval_ms
+     */
+    start_auto_ping(interval_ms) {
+        const ret = wasm.wasmpingclient_start_auto_ping(this.__wbg_ptr, interval_ms);
+        if (ret[1]) {
+            throw takeFromExternrefTable0(ret[0]);
+        }
+    }
+    /**
+     * Totals since the client was created: `{sent, received, errors,
+     * min_ms, avg_ms, p95_ms, max_ms}`. `errors` counts the pings that
+     * couldn't be sent or queued and those that got no Pong in time; the
+     * 95th percentile is over every round trip, to within 1.6%.
+     * @returns {Stats}
+     */
+    stats() {
+        const ret = wasm.wasmpingclient_stats(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+     * Stop the pings of `start_auto_ping()`; those already sent still
+     * get their Pongs
+     */
+    stop_auto_ping() {
+        wasm.wasmpingclient_stop_auto_ping(this.__wbg_ptr);
+    }
+    /**
+     * How long a ping waits for its Pong, in milliseconds (default 10000)
+     * @returns {number}
+     */
+    get timeout_ms() {
+        const ret = wasm.wasmpingclient_timeout_ms(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * The transport in use: "webtransport" while the client connects over
+     * WebTransport, "websocket" otherwise, including after falling back
+     * @returns {"websocket" | "webtransport"}
+     */
+    get transport() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.wasmpingclient_transport(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+    /**
+     * The URL the client connected to
+     * @returns {string}
+     */
+    get url() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.wasmpingclient_url(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+}
+if (Symbol.dispose) WasmPingClient.prototype[Symbol.dispose] = WasmPingClient.prototype.free;
+
+/**
+ * `size` WasmPingClients to one server, sharing out the pings
+ */
+export class WasmPingClientPool {
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        this.__wbg_ptr = 0;
+        WasmPingClientPoolFinalization.unregister(this);
+        return ptr;
+    }
+    free() {
+        const ptr = this.__destroy_into_raw();
+        wasm.__wbg_wasmpingclientpool_free(ptr, 0);
+    }
+    /**
+     * Close every connection for good, as `close()` does for one client
+     */
+    close() {
+        wasm.wasmpingclientpool_close(this.__wbg_ptr);
+    }
+    /**
+     * Number of connections open right now
+     * @returns {number}
+     */
+    get connected() {
+        const ret = wasm.wasmpingclientpool_connected(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * Open `size` connections to `url`, each as `new WasmPingClient(url,
+     * options)` would
+     * @param {number} size
+     * @param {string | null} [url]
+     * @param {ClientOptions | null} [options]
+     */
+    constructor(size, url, options) {
+        var ptr0 = isLikeNone(url) ? 0 : passStringToWasm0(url, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmpingclientpool_new(size, ptr0, len0, isLikeNone(options) ? 0 : addToExternrefTable0(options));
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        this.__wbg_ptr = ret[0] >>> 0;
+        WasmPingClientPoolFinalization.register(this, this.__wbg_ptr, this);
+        return this;
+    }
+    /**
+     * Call `callback(pong)` with every Pong any of the connections receives
+     * @param {(pong: Pong) => void} callback
+     */
+    on_pong(callback) {
+        wasm.wasmpingclientpool_on_pong(this.__wbg_ptr, callback);
+    }
+    /**
+     * Send a ping over the next connection in turn, as `send_ping()` of a
+     * single client does. Connections that are down are passed over while
+     * any is open; with none open, the ping waits in the next one's queue.
+     * @param {string | null} [message]
+     * @returns {Promise<Pong>}
+     */
+    send_ping(message) {
+        var ptr0 = isLikeNone(message) ? 0 : passStringToWasm0(message, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        var len0 = WASM_VECTOR_LEN;
+        const ret = wasm.wasmpingclientpool_send_ping(this.__wbg_ptr, ptr0, len0);
+        return ret;
+    }
+    /**
+     * Number of connections in the pool
+     * @returns {number}
+     */
+    get size() {
+        const ret = wasm.wasmpingclientpool_size(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+     * `stats()` over all connections together: the counts are added up,
+     * and the round trips pooled
+     * @returns {Stats}
+     */
+    stats() {
+        const ret = wasm.wasmpingclientpool_stats(this.__wbg_ptr);
+        return ret;
+    }
+}
+if (Symbol.dispose) WasmPingClientPool.prototype[Symbol.dispose] = WasmPingClientPool.prototype.free;
+
+function __wbg_get_imports() {
+    const import0 = {
+        __proto__: null,
+        __wbg_Error_8c4e43fe74559d73: function(arg0, arg1) {
+            const ret = Error(getStringFromWasm0(arg0, arg1));
+            return ret;
+        },
+        __wbg_Number_04624de7d0e8332d: function(arg0) {
+            const ret = Number(arg0);
+            return ret;
+        },
+        __wbg___wbindgen_bigint_get_as_i64_8fcf4ce7f1ca72a2: function(arg0, arg1) {
+            const v = arg1;
+            const ret = typeof(v) === 'bigint' ? v : undefined;
+            getDataViewMemory0().setBigInt64(arg0 + 8 * 1, isLikeNone(ret) ? BigInt(0) : ret, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, !isLikeNone(ret), true);
+        },
+        __wbg___wbindgen_boolean_get_bbbb1c18aa2f5e25: function(arg0) {
+            const v = arg0;
+            const ret = typeof(v) === 'boolean' ? v : undefined;
+            return isLikeNone(ret) ? 0xFFFFFF : ret ? 1 : 0;
+        },
+        __wbg___wbindgen_debug_string_0bc8482c6e3508ae: function(arg0, arg1) {
+            const ret = debugString(arg1);
+            const ptr1 = passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
+        },
+        __wbg___wbindgen_in_47fa6863be6f2f25: function(arg0, arg1) {
+            const ret = arg0 in arg1;
+            return ret;
+        },
+        __wbg___wbindgen_is_bigint_31b12575b56f32fc: function(arg0) {
+            const ret = typeof(arg0) === 'bigint';
+            return ret;
+        },
+        __wbg___wbindgen_is_falsy_e623e5b815413d00: function(arg0) {
+            const ret = !arg0;
+            return ret;
+        },
+        __wbg___wbindgen_is_function_0095a73b8b156f76: function(arg0) {
+            const ret = typeof(arg0) === 'function';
+            return ret;
+        },
+        __wbg___wbindgen_is_object_5ae8e5880f2c1fbd: function(arg0) {
+            const val = arg0;
+            const ret = typeof(val) === 'object' && val !== null;
+            return ret;
+        },
+        __wbg___wbindgen_is_string_cd444516edc5b180: function(arg0) {
+            const ret = typeof(arg0) === 'string';
+            return ret;
+        },
+        __wbg___wbindgen_is_undefined_9e4d92534c42d778: function(arg0) {
+            const ret = arg0 === undefined;
+            return ret;
+        },
+        __wbg___wbindgen_jsval_eq_11888390b0186270: function(arg0, arg1) {
+            const ret = arg0 === arg1;
+            return ret;
+        },
+        __wbg___wbindgen_jsval_loose_eq_9dd77d8cd6671811: function(arg0, arg1) {
+            const ret = arg0 == arg1;
+            return ret;
+        },
+        __wbg___wbindgen_number_get_8ff4255516ccad3e: function(arg0, arg1) {
+            const obj = arg1;
+            const ret = typeof(obj) === 'number' ? obj : undefined;
+            getDataViewMemory0().setFloat64(arg0 + 8 * 1, isLikeNone(ret) ? 0 : ret, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, !isLikeNone(ret), true);
+        },
+        __wbg___wbindgen_string_get_72fb696202c56729: function(arg0, arg1) {
+            const obj = arg1;
+            const ret = typeof(obj) === 'string' ? obj : undefined;
+            var ptr1 = isLikeNone(ret) ? 0 : passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            var len1 = WASM_VECTOR_LEN;
+            getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
+        },
+        __wbg___wbindgen_throw_be289d5034ed271b: function(arg0, arg1) {
+            throw new Error(getStringFromWasm0(arg0, arg1));
+        },
+        __wbg__wbg_cb_unref_d9b87ff7982e3b21: function(arg0) {
+            arg0._wbg_cb_unref();
+        },
+        __wbg_call_389efe28435a9388: function() { return handleError(function (arg0, arg1) {
+            const ret = arg0.call(arg1);
+            return ret;
+        }, arguments); },
+        __wbg_call_4708e0c13bdc8e95: function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = arg0.call(arg1, arg2);
+            return ret;
+        }, arguments); },
+        __wbg_catch_c1f8c7623b458214: function(arg0, arg1) {
+            const ret = arg0.catch(arg1);
+            return ret;
+        },
+        __wbg_clearInterval_dd1e598f425db353: function(arg0) {
+            const ret = clearInterval(arg0);
+            return ret;
+        },
+        __wbg_clearTimeout_5a54f8841c30079a: function(arg0) {
+            const ret = clearTimeout(arg0);
+            return ret;
+        },
+        __wbg_close_1d08eaf57ed325c0: function() { return handleError(function (arg0) {
+            arg0.close();
+        }, arguments); },
+        __wbg_close_5175261287d1970d: function(arg0) {
+            arg0.close();
+        },
+        __wbg_closed_bae05d173573c05f: function(arg0) {
+            const ret = arg0.closed;
+            return ret;
+        },
+        __wbg_code_a552f1e91eda69b7: function(arg0) {
+            const ret = arg0.code;
+            return ret;
+        },
+        __wbg_createBidirectionalStream_0fb0fb2fb0ae59b9: function(arg0) {
+            const ret = arg0.createBidirectionalStream();
+            return ret;
+        },
+        __wbg_data_5330da50312d0bc1: function(arg0) {
+            const ret = arg0.data;
+            return ret;
+        },
+        __wbg_done_57b39ecd9addfe81: function(arg0) {
+            const ret = arg0.done;
+            return ret;
+        },
+        __wbg_encodeURIComponent_46e9eafddd38cb39: function(arg0, arg1) {
+            const ret = encodeURIComponent(getStringFromWasm0(arg0, arg1));
+            return ret;
+        },
+        __wbg_entries_58c7934c745daac7: function(arg0) {
+            const ret = Object.entries(arg0);
+            return ret;
+        },
+        __wbg_error_7534b8e9a36f1ab4: function(arg0, arg1) {
+            let deferred0_0;
+            let deferred0_1;
+            try {
+                deferred0_0 = arg0;
+                deferred0_1 = arg1;
+                console.error(getStringFromWasm0(arg0, arg1));
+            } finally {
+                wasm.__wbindgen_free(deferred0_0, deferred0_1, 1);
+            }
+        },
+        __wbg_getItem_0c792d344808dcf5: function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = arg1.getItem(getStringFromWasm0(arg2, arg3));
+            var ptr1 = isLikeNone(ret) ? 0 : passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            var len1 = WASM_VECTOR_LEN;
+            getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
+        }, arguments); },
+        __wbg_getRandomValues_71d446877d8b0ad4: function() { return handleError(function (arg0, arg1) {
+            globalThis.crypto.getRandomValues(getArrayU8FromWasm0(arg0, arg1));
+        }, arguments); },
+        __wbg_getReader_804829cfb24eb4dd: function(arg0) {
+            const ret = arg0.getReader();
+            return ret;
+        },
+        __wbg_getWriter_4bd085da387cdc1a: function() { return handleError(function (arg0) {
+            const ret = arg0.getWriter();
+            return ret;
+        }, arguments); },
+        __wbg_get_9b94d73e6221f75c: function(arg0, arg1) {
+            const ret = arg0[arg1 >>> 0];
+            return ret;
+        },
+        __wbg_get_b3ed3ad4be2bc8ac: function() { return handleError(function (arg0, arg1) {
+            const ret = Reflect.get(arg0, arg1);
+            return ret;
+        }, arguments); },
+        __wbg_get_with_ref_key_1dc361bd10053bfe: function(arg0, arg1) {
+            const ret = arg0[arg1];
+            return ret;
+        },
+        __wbg_instanceof_ArrayBuffer_c367199e2fa2aa04: function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof ArrayBuffer;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        },
+        __wbg_instanceof_Performance_194d24c143832ec1: function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof Performance;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        },
+        __wbg_instanceof_Storage_216167cf341e91d2: function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof Storage;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        },
+        __wbg_instanceof_Uint8Array_9b9075935c74707c: function(arg0) {
+            let result;
+            try {
+                result = arg0 instanceof Uint8Array;
+            } catch (_) {
+                result = false;
+            }
+            const ret = result;
+            return ret;
+        },
+        __wbg_isSafeInteger_bfbc7332a9768d2a: function(arg0) {
+            const ret = Number.isSafeInteger(arg0);
+            return ret;
+        },
+        __wbg_iterator_6ff6560ca1568e55: function() {
+            const ret = Symbol.iterator;
+            return ret;
+        },
+        __wbg_length_32ed9a279acd054c: function(arg0) {
+            const ret = arg0.length;
+            return ret;
+        },
+        __wbg_length_35a7bace40f36eac: function(arg0) {
+            const ret = arg0.length;
+            return ret;
+        },
+        __wbg_new_057993d5b5e07835: function() { return handleError(function (arg0, arg1) {
+            const ret = new WebSocket(getStringFromWasm0(arg0, arg1));
+            return ret;
+        }, arguments); },
+        __wbg_new_361308b2356cecd0: function() {
+            const ret = new Object();
+            return ret;
+        },
+        __wbg_new_72b49615380db768: function(arg0, arg1) {
+            const ret = new Error(getStringFromWasm0(arg0, arg1));
+            return ret;
+        },
+        __wbg_new_8a6f238a6ece86ea: function() {
+            const ret = new Error();
+            return ret;
+        },
+        __wbg_new_93f7a443c6a40648: function() { return handleError(function (arg0, arg1) {
+            const ret = new WebTransport(getStringFromWasm0(arg0, arg1));
+            return ret;
+        }, arguments); },
+        __wbg_new_b5d9e2fb389fef91: function(arg0, arg1) {
+            try {
+                var state0 = {a: arg0, b: arg1};
+                var cb0 = (arg0, arg1) => {
+                    const a = state0.a;
+                    state0.a = 0;
+                    try {
+                        return wasm_bindgen__convert__closures_____invoke__h1f5861940453616d(a, state0.b, arg0, arg1);
+                    } finally {
+                        state0.a = a;
+                    }
+                };
+                const ret = new Promise(cb0);
+                return ret;
+            } finally {
+                state0.a = state0.b = 0;
+            }
+        },
+        __wbg_new_dca287b076112a51: function() {
+            const ret = new Map();
+            return ret;
+        },
+        __wbg_new_dd2b680c8bf6ae29: function(arg0) {
+            const ret = new Uint8Array(arg0);
+            return ret;
+        },
+        __wbg_new_from_slice_a3d2629dc1826784: function(arg0, arg1) {
+            const ret = new Uint8Array(getArrayU8FromWasm0(arg0, arg1));
+            return ret;
+        },
+        __wbg_new_no_args_1c7c842f08d00ebb: function(arg0, arg1) {
+            const ret = new Function(getStringFromWasm0(arg0, arg1));
+            return ret;
+        },
+        __wbg_new_with_event_init_dict_7721feeda3e1e6fa: function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = new CloseEvent(getStringFromWasm0(arg0, arg1), arg2);
+            return ret;
+        }, arguments); },
+        __wbg_new_with_str_8406051fb31dddaa: function() { return handleError(function (arg0, arg1, arg2, arg3) {
+            const ret = new WebSocket(getStringFromWasm0(arg0, arg1), getStringFromWasm0(arg2, arg3));
+            return ret;
+        }, arguments); },
+        __wbg_next_3482f54c49e8af19: function() { return handleError(function (arg0) {
+            const ret = arg0.next();
+            return ret;
+        }, arguments); },
+        __wbg_next_418f80d8f5303233: function(arg0) {
+            const ret = arg0.next;
+            return ret;
+        },
+        __wbg_now_a3af9a2f4bbaa4d1: function() {
+            const ret = Date.now();
+            return ret;
+        },
+        __wbg_now_ebffdf7e580f210d: function(arg0) {
+            const ret = arg0.now();
+            return ret;
+        },
+        __wbg_postMessage_df3ad0664afcd4fe: function() { return handleError(function (arg0, arg1) {
+            arg0.postMessage(arg1);
+        }, arguments); },
+        __wbg_prototypesetcall_bdcdcc5842e4d77d: function(arg0, arg1, arg2) {
+            Uint8Array.prototype.set.call(getArrayU8FromWasm0(arg0, arg1), arg2);
+        },
+        __wbg_queueMicrotask_0aa0a927f78f5d98: function(arg0) {
+            const ret = arg0.queueMicrotask;
+            return ret;
+        },
+        __wbg_queueMicrotask_5bb536982f78a56f: function(arg0) {
+            queueMicrotask(arg0);
+        },
+        __wbg_random_912284dbf636f269: function() {
+            const ret = Math.random();
+            return ret;
+        },
+        __wbg_read_68fd377df67e19b0: function(arg0) {
+            const ret = arg0.read();
+            return ret;
+        },
+        __wbg_readable_7a70f1fe55dd76d0: function(arg0) {
+            const ret = arg0.readable;
+            return ret;
+        },
+        __wbg_readyState_1bb73ec7b8a54656: function(arg0) {
+            const ret = arg0.readyState;
+            return ret;
+        },
+        __wbg_ready_5c06ff43ffd8daa9: function(arg0) {
+            const ret = arg0.ready;
+            return ret;
+        },
+        __wbg_reason_35fce8e55dd90f31: function(arg0, arg1) {
+            const ret = arg1.reason;
+            const ptr1 = passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
+        },
+        __wbg_resolve_002c4b7d9d8f6b64: function(arg0) {
+            const ret = Promise.resolve(arg0);
+            return ret;
+        },
+        __wbg_send_542f95dea2df7994: function() { return handleError(function (arg0, arg1, arg2) {
+            arg0.send(getArrayU8FromWasm0(arg1, arg2));
+        }, arguments); },
+        __wbg_send_bc0336a1b5ce4fb7: function() { return handleError(function (arg0, arg1, arg2) {
+            arg0.send(getStringFromWasm0(arg1, arg2));
+        }, arguments); },
+        __wbg_setInterval_ed3b5e3c3ebb8a6d: function() { return handleError(function (arg0, arg1) {
+            const ret = setInterval(arg0, arg1);
+            return ret;
+        }, arguments); },
+        __wbg_setItem_cf340bb2edbd3089: function() { return handleError(function (arg0, arg1, arg2, arg3, arg4) {
+            arg0.setItem(getStringFromWasm0(arg1, arg2), getStringFromWasm0(arg3, arg4));
+        }, arguments); },
+        __wbg_setTimeout_db2dbaeefb6f39c7: function() { return handleError(function (arg0, arg1) {
+            const ret = setTimeout(arg0, arg1);
+            return ret;
+        }, arguments); },
+        __wbg_set_1eb0999cf5d27fc8: function(arg0, arg1, arg2) {
+            const ret = arg0.set(arg1, arg2);
+            return ret;
+        },
+        __wbg_set_3f1d0b984ed272ed: function(arg0, arg1, arg2) {
+            arg0[arg1] = arg2;
+        },
+        __wbg_set_6cb8631f80447a67: function() { return handleError(function (arg0, arg1, arg2) {
+            const ret = Reflect.set(arg0, arg1, arg2);
+            return ret;
+        }, arguments); },
+        __wbg_set_binaryType_5bbf62e9f705dc1a: function(arg0, arg1) {
+            arg0.binaryType = __wbindgen_enum_BinaryType[arg1];
+        },
+        __wbg_set_code_86d60a9542684e59: function(arg0, arg1) {
+            arg0.code = arg1;
+        },
+        __wbg_set_onclose_d382f3e2c2b850eb: function(arg0, arg1) {
+            arg0.onclose = arg1;
+        },
+        __wbg_set_onerror_377f18bf4569bf85: function(arg0, arg1) {
+            arg0.onerror = arg1;
+        },
+        __wbg_set_onmessage_2114aa5f4f53051e: function(arg0, arg1) {
+            arg0.onmessage = arg1;
+        },
+        __wbg_set_onopen_b7b52d519d6c0f11: function(arg0, arg1) {
+            arg0.onopen = arg1;
+        },
+        __wbg_set_reason_4e11e27980c30a7f: function(arg0, arg1, arg2) {
+            arg0.reason = getStringFromWasm0(arg1, arg2);
+        },
+        __wbg_set_was_clean_636c88853940502f: function(arg0, arg1) {
+            arg0.wasClean = arg1 !== 0;
+        },
+        __wbg_stack_0ed75d68575b0f3c: function(arg0, arg1) {
+            const ret = arg1.stack;
+            const ptr1 = passStringToWasm0(ret, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len1 = WASM_VECTOR_LEN;
+            getDataViewMemory0().setInt32(arg0 + 4 * 1, len1, true);
+            getDataViewMemory0().setInt32(arg0 + 4 * 0, ptr1, true);
+        },
+        __wbg_static_accessor_GLOBAL_12837167ad935116: function() {
+            const ret = typeof global === 'undefined' ? null : global;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_GLOBAL_THIS_e628e89ab3b1c95f: function() {
+            const ret = typeof globalThis === 'undefined' ? null : globalThis;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_SELF_a621d3dfbb60d0ce: function() {
+            const ret = typeof self === 'undefined' ? null : self;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_static_accessor_WINDOW_f8727f0cf888e0bd: function() {
+            const ret = typeof window === 'undefined' ? null : window;
+            return isLikeNone(ret) ? 0 : addToExternrefTable0(ret);
+        },
+        __wbg_then_0d9fe2c7b1857d32: function(arg0, arg1, arg2) {
+            const ret = arg0.then(arg1, arg2);
+            return ret;
+        },
+        __wbg_then_b9e7b3b5f1a9e1b5: function(arg0, arg1) {
+            const ret = arg0.then(arg1);
+            return ret;
+        },
+        __wbg_value_0546255b415e96c1: function(arg0) {
+            const ret = arg0.value;
+            return ret;
+        },
+        __wbg_wasClean_a9c77a7100d8534f: function(arg0) {
+            const ret = arg0.wasClean;
+            return ret;
+        },
+        __wbg_writable_7d26748e9ea02427: function(arg0) {
+            const ret = arg0.writable;
+            return ret;
+        },
+        __wbg_write_4dbba5e5426abaf4: function(arg0, arg1) {
+            const ret = arg0.write(arg1);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000001: function(arg0, arg1) {
+            // Cast intrinsic for `Closure(Closure { dtor_idx: 114, function: Function { arguments: [Externref], shim_idx: 115, ret: Unit, inner_ret: Some(Unit) }, mutable: true }) -> Externref`.
+            const ret = makeMutClosure(arg0, arg1, wasm.wasm_bindgen__closure__destroy__h6bbaa58cb3cf32ee, wasm_bindgen__convert__closures_____invoke__hd1db2d7013ce88d5);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000002: function(arg0, arg1) {
+            // Cast intrinsic for `Closure(Closure { dtor_idx: 74, function: Function { arguments: [NamedExternref("CloseEvent")], shim_idx: 75, ret: Unit, inner_ret: Some(Unit) }, mutable: true }) -> Externref`.
+            const ret = makeMutClosure(arg0, arg1, wasm.wasm_bindgen__closure__destroy__h4412a969fbca343f, wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000003: function(arg0, arg1) {
+            // Cast intrinsic for `Closure(Closure { dtor_idx: 74, function: Function { arguments: [NamedExternref("ErrorEvent")], shim_idx: 75, ret: Unit, inner_ret: Some(Unit) }, mutable: true }) -> Externref`.
+            const ret = makeMutClosure(arg0, arg1, wasm.wasm_bindgen__closure__destroy__h4412a969fbca343f, wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000004: function(arg0, arg1) {
+            // Cast intrinsic for `Closure(Closure { dtor_idx: 74, function: Function { arguments: [NamedExternref("MessageEvent")], shim_idx: 75, ret: Unit, inner_ret: Some(Unit) }, mutable: true }) -> Externref`.
+            const ret = makeMutClosure(arg0, arg1, wasm.wasm_bindgen__closure__destroy__h4412a969fbca343f, wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000005: function(arg0, arg1) {
+            // Cast intrinsic for `Closure(Closure { dtor_idx: 74, function: Function { arguments: [], shim_idx: 79, ret: Unit, inner_ret: Some(Unit) }, mutable: true }) -> Externref`.
+            const ret = makeMutClosure(arg0, arg1, wasm.wasm_bindgen__closure__destroy__h4412a969fbca343f, wasm_bindgen__convert__closures_____invoke__hadea7c59506573ff);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000006: function(arg0) {
+            // Cast intrinsic for `F64 -> Externref`.
+            const ret = arg0;
+            return ret;
+        },
+        __wbindgen_cast_0000000000000007: function(arg0, arg1) {
+            // Cast intrinsic for `Ref(String) -> Externref`.
+            const ret = getStringFromWasm0(arg0, arg1);
+            return ret;
+        },
+        __wbindgen_cast_0000000000000008: function(arg0) {
+            // Cast intrinsic for `U64 -> Externref`.
+            const ret = BigInt.asUintN(64, arg0);
+            return ret;
+        },
+        __wbindgen_init_externref_table: function() {
+            const table = wasm.__wbindgen_externrefs;
+            const offset = table.grow(4);
+            table.set(0, undefined);
+            table.set(offset + 0, undefined);
+            table.set(offset + 1, null);
+            table.set(offset + 2, true);
+            table.set(offset + 3, false);
+        },
+    };
+    return {
+        __proto__: null,
+        "./ping_wasm_client_bg.js": import0,
+    };
+}
+
+function wasm_bindgen__convert__closures_____invoke__hadea7c59506573ff(arg0, arg1) {
+    wasm.wasm_bindgen__convert__closures_____invoke__hadea7c59506573ff(arg0, arg1);
+}
+
+function wasm_bindgen__convert__closures_____invoke__hd1db2d7013ce88d5(arg0, arg1, arg2) {
+    wasm.wasm_bindgen__convert__closures_____invoke__hd1db2d7013ce88d5(arg0, arg1, arg2);
+}
+
+function wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222(arg0, arg1, arg2) {
+    wasm.wasm_bindgen__convert__closures_____invoke__h204fba9e6710f222(arg0, arg1, arg2);
+}
+
+function wasm_bindgen__convert__closures_____invoke__h1f5861940453616d(arg0, arg1, arg2, arg3) {
+    wasm.wasm_bindgen__convert__closures_____invoke__h1f5861940453616d(arg0, arg1, arg2, arg3);
+}
+
+
+const __wbindgen_enum_BinaryType = ["blob", "arraybuffer"];
+const WasmPingClientFinalization = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(ptr => wasm.__wbg_wasmpingclient_free(ptr >>> 0, 1));
+const WasmPingClientPoolFinalization = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(ptr => wasm.__wbg_wasmpingclientpool_free(ptr >>> 0, 1));
+
+function addToExternrefTable0(obj) {
+    const idx = wasm.__externref_table_alloc();
+    wasm.__wbindgen_externrefs.set(idx, obj);
+    return idx;
+}
+
+const CLOSURE_DTORS = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(state => state.dtor(state.a, state.b));
+
+function debugString(val) {
+    // primitive types
+    const type = typeof val;
+    if (type == 'number' || type == 'boolean' || val == null) {
+        return  `${val}`;
+    }
+    if (type == 'string') {
+        return `"${val}"`;
+    }
+    if (type == 'symbol') {
+        const description = val.description;
+        if (description == null) {
+            return 'Symbol';
+        } else {
+            return `Symbol(${description})`;
+        }
+    }
+    if (type == 'function') {
+        const name = val.name;
+        if (typeof name == 'string' && name.length > 0) {
+            return `Function(${name})`;
+        } else {
+            return 'Function';
+        }
+    }
+    // objects
+    if (Array.isArray(val)) {
+        const length = val.length;
+        let debug = '[';
+        if (length > 0) {
+            debug += debugString(val[0]);
+        }
+        for(let i = 1; i < length; i++) {
+            debug += ', ' + debugString(val[i]);
+        }
+        debug += ']';
+        return debug;
+    }
+    // Test for built-in
+    const builtInMatches = /\[object ([^\]]+)\]/.exec(toString.call(val));
+    let className;
+    if (builtInMatches && builtInMatches.length > 1) {
+        className = builtInMatches[1];
+    } else {
+        // Failed to match the standard '[object ClassName]'
+        return toString.call(val);
+    }
+    if (className == 'Object') {
+        // we're a user defined class or Object
+        // JSON.stringify avoids problems with cycles, and is generally much
+        // easier than looping through ownProperties of `val`.
+        try {
+            return 'Object(' + JSON.stringify(val) + ')';
+        } catch (_) {
+            return 'Object';
+        }
+    }
+    // errors
+    if (val instanceof Error) {
+        return `${val.name}: ${val.message}\n${val.stack}`;
+    }
+    // TODO we could test for more things here, like `Set`s and `Map`s.
+    return className;
+}
+
+function getArrayU8FromWasm0(ptr, len) {
+    ptr = ptr >>> 0;
+    return getUint8ArrayMemory0().subarray(ptr / 1, ptr / 1 + len);
+}
+
+let cachedDataViewMemory0 = null;
+function getDataViewMemory0() {
+    if (cachedDataViewMemory0 === null || cachedDataViewMemory0.buffer.detached === true || (cachedDataViewMemory0.buffer.detached === undefined && cachedDataViewMemory0.buffer !== wasm.memory.buffer)) {
+        cachedDataViewMemory0 = new DataView(wasm.memory.buffer);
+    }
+    return cachedDataViewMemory0;
+}
+
+function getStringFromWasm0(ptr, len) {
+    ptr = ptr >>> 0;
+    return decodeText(ptr, len);
+}
+
+let cachedUint8ArrayMemory0 = null;
+function getUint8ArrayMemory0() {
+    if (cachedUint8ArrayMemory0 === null || cachedUint8ArrayMemory0.byteLength === 0) {
+        cachedUint8ArrayMemory0 = new Uint8Array(wasm.memory.buffer);
+    }
+    return cachedUint8ArrayMemory0;
+}
+
+function handleError(f, args) {
+    try {
+        return f.apply(this, args);
+    } catch (e) {
+        const idx = addToExternrefTable0(e);
+        wasm.__wbindgen_exn_store(idx);
+    }
+}
+
+function isLikeNone(x) {
+    return x === undefined || x === null;
+}
+
+function makeMutClosure(arg0, arg1, dtor, f) {
+    const state = { a: arg0, b: arg1, cnt: 1, dtor };
+    const real = (...args) => {
+
+        // First up with a closure we increment the internal reference
+        // count. This ensures that the Rust closure environment won't
+        // be deallocated while we're invoking it.
+        state.cnt++;
+        const a = state.a;
+        state.a = 0;
+        try {
+            return f(a, state.b, ...args);
+        } finally {
+            state.a = a;
+            real._wbg_cb_unref();
+        }
+    };
+    real._wbg_cb_unref = () => {
+        if (--state.cnt === 0) {
+            state.dtor(state.a, state.b);
+            state.a = 0;
+            CLOSURE_DTORS.unregister(state);
+        }
+    };
+    CLOSURE_DTORS.register(real, state, state);
+    return real;
+}
+
+function passStringToWasm0(arg, malloc, realloc) {
+    if (realloc === undefined) {
+        const buf = cachedTextEncoder.encode(arg);
+        const ptr = malloc(buf.length, 1) >>> 0;
+        getUint8ArrayMemory0().subarray(ptr, ptr + buf.length).set(buf);
+        WASM_VECTOR_LEN = buf.length;
+        return ptr;
+    }
+
+    let len = arg.length;
+    let ptr = malloc(len, 1) >>> 0;
+
+    const mem = getUint8ArrayMemory0();
+
+    let offset = 0;
+
+    for (; offset < len; offset++) {
+        const code = arg.charCodeAt(offset);
+        if (code > 0x7F) break;
+        mem[ptr + offset] = code;
+    }
+    if (offset !== len) {
+        if (offset !== 0) {
+            arg = arg.slice(offset);
+        }
+        ptr = realloc(ptr, len, len = offset + arg.length * 3, 1) >>> 0;
+        const view = getUint8ArrayMemory0().subarray(ptr + offset, ptr + len);
+        const ret = cachedTextEncoder.encodeInto(arg, view);
+
+        offset += ret.written;
+        ptr = realloc(ptr, len, offset, 1) >>> 0;
+    }
+
+    WASM_VECTOR_LEN = offset;
+    return ptr;
+}
+
+function takeFromExternrefTable0(idx) {
+    const value = wasm.__wbindgen_externrefs.get(idx);
+    wasm.__externref_table_dealloc(idx);
+    return value;
+}
+
+let cachedTextDecoder = new TextDecoder('utf-8', { ignoreBOM: true, fatal: true });
+cachedTextDecoder.decode();
+const MAX_SAFARI_DECODE_BYTES = 2146435072;
+let numBytesDecoded = 0;
+function decodeText(ptr, len) {
+    numBytesDecoded += len;
+    if (numBytesDecoded >= MAX_SAFARI_DECODE_BYTES) {
+        cachedTextDecoder = new TextDecoder('utf-8', { ignoreBOM: true, fatal: true });
+        cachedTextDecoder.decode();
+        numBytesDecoded = len;
+    }
+    return cachedTextDecoder.decode(getUint8ArrayMemory0().subarray(ptr, ptr + len));
+}
+
+const cachedTextEncoder = new TextEncoder();
+
+if (!('encodeInto' in cachedTextEncoder)) {
+    cachedTextEncoder.encodeInto = function (arg, view) {
+        const buf = cachedTextEncoder.encode(arg);
+        view.set(buf);
+        return {
+            read: arg.length,
+            written: buf.length
+        };
+    };
+}
+
+let WASM_VECTOR_LEN = 0;
+
+let wasmModule, wasm;
+function __wbg_finalize_init(instance, module) {
+    wasm = instance.exports;
+    wasmModule = module;
+    cachedDataViewMemory0 = null;
+    cachedUint8ArrayMemory0 = null;
+    wasm.__wbindgen_start();
+    return wasm;
+}
+
+async function __wbg_load(module, imports) {
+    if (typeof Response === 'function' && module instanceof Response) {
+        if (typeof WebAssembly.instantiateStreaming === 'function') {
+            try {
+                return await WebAssembly.instantiateStreaming(module, imports);
+            } catch (e) {
+                const validResponse = module.ok && expectedResponseType(module.type);
+
+                if (validResponse && module.headers.get('Content-Type') !== 'application/wasm') {
+                    console.warn("`WebAssembly.instantiateStreaming` failed because your server does not serve Wasm with `application/wasm` MIME type. Falling back to `WebAssembly.instantiate` which is slower. Original error:\n", e);
+
+                } else { throw e; }
+            }
+        }
+
+        const bytes = await module.arrayBuffer();
+        return await WebAssembly.instantiate(bytes, imports);
+    } else {
+        const instance = await WebAssembly.instantiate(module, imports);
+
+        if (instance instanceof WebAssembly.Instance) {
+            return { instance, module };
+        } else {
+            return instance;
+        }
+    }
+
+    function expectedResponseType(type) {
+        switch (type) {
+            case 'basic': case 'cors': case 'default': return true;
+        }
+        return false;
+    }
+}
+
+function initSync(module) {
+    if (wasm !== undefined) return wasm;
+
+
+    if (module !== undefined) {
+        if (Object.getPrototypeOf(module) === Object.prototype) {
+            ({module} = module)
+        } else {
+            console.warn('using deprecated parameters for `initSync()`; pass a single object instead')
+        }
+    }
+
+    const imports = __wbg_get_imports();
+    if (!(module instanceof WebAssembly.Module)) {
+        module = new WebAssembly.Module(module);
+    }
+    const instance = new WebAssembly.Instance(module, imports);
+    return __wbg_finalize_init(instance, module);
+}
+
+async function __wbg_init(module_or_path) {
+    if (wasm !== undefined) return wasm;
+
+
+    if (module_or_path !== undefined) {
+        if (Object.getPrototypeOf(module_or_path) === Object.prototype) {
+            ({module_or_path} = module_or_path)
+        } else {
+            console.warn('using deprecated parameters for the initialization function; pass a single object instead')
+        }
+    }
+
+    if (module_or_path === undefined) {
+        module_or_path = new URL('ping_wasm_client_bg.wasm', import.meta.url);
+    }
+    const imports = __wbg_get_imports();
+
+    if (typeof module_or_path === 'string' || (typeof Request === 'function' && module_or_path instanceof Request) || (typeof URL === 'function' && module_or_path instanceof URL)) {
+        module_or_path = fetch(module_or_path);
+    }
+
+    const { instance, module } = await __wbg_load(await module_or_path, imports);
+
+    return __wbg_finalize_init(instance, module);
+}
+
+export { initSync, __wbg_init as default };
@@ -0,0 +1,172 @@
+// Metric names and labels
+//
+// Every binary serving Prometheus metrics takes their names, help texts and
+// labels from here, so a dashboard or alert written against one works against
+// all of them: `ping_requests_total{transport="tcp"}` means the same whether
+// ping-http-server or a later binary exports it. A registry from `registry`
+// prefixes every name with `ping_` and labels every series with the binary's
+// `service`; each binary registers the metrics that apply to it, as in
+//
+//     let mut registry = ping_metrics::registry("ping-http-server");
+//     let requests: Family<RequestLabels, Counter> = REQUESTS.register(&mut registry);
+//
+// Label values are enums where the set is closed (`Transport`, `Codec`), so a
+// binary can't spell one differently. prometheus-client is re-exported for
+// the metric types themselves.
+
+pub use prometheus_client;
+
+use prometheus_client::encoding::{EncodeLabelSet, EncodeLabelValue, LabelValueEncoder};
+use prometheus_client::registry::{Metric as PrometheusMetric, Registry};
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+/// A registry for `service` (say "ping-cli-server"), prefixing names with
+/// `ping_` and labelling every series with the service
+pub fn registry(service: &'static str) -> Registry {
+    Registry::with_prefix_and_labels("ping", std::iter::once((Cow::Borrowed("service"), Cow::Borrowed(service))))
+}
+
+/// The name and help text of a metric
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+}
+
+impl Metric {
+    /// A new metric of type `M`, registered under this name
+    pub fn register<M: PrometheusMetric + Clone + Default>(&self, registry: &mut Registry) -> M {
+        let metric = M::default();
+        registry.register(self.name, self.help, metric.clone());
+        metric
+    }
+}
+
+/// Counter by `RequestLabels`
+pub const REQUESTS: Metric = Metric { name: "requests", help: "Requests answered by the actors" };
+
+/// Counter by `RequestLabels`
+pub const SLOW_REQUESTS: Metric =
+    Metric { name: "slow_requests", help: "Requests the actors took longer than --slow-request-ms to answer" };
+
+/// Counter by `TransportLabels`
+pub const CONNECTIONS: Metric = Metric { name: "connections", help: "Connections established" };
+
+/// Gauge by `TransportLabels`
+pub const CONNECTED_CLIENTS: Metric = Metric { name: "connected_clients", help: "Clients currently connected" };
+
+/// Counter by `ClientLabels`
+pub const CLIENT_REQUESTS: Metric = Metric { name: "client_requests", help: "Actor replies sent, by connected client" };
+
+/// Counter by `ClientLabels`
+pub const RECEIVED_BYTES: Metric = Metric { name: "received_bytes", help: "Bytes received, by connected client" };
+
+/// Counter by `ClientLabels`
+pub const SENT_BYTES: Metric = Metric { name: "sent_bytes", help: "Bytes sent, by connected client" };
+
+/// Gauge by `ClientLabels`, in seconds
+pub const CLIENT_HANDLING_TIME: Metric = Metric {
+    name: "client_avg_handling_seconds",
+    help: "Mean time the actors took to answer, by connected client",
+};
+
+/// Counter by `ReasonLabels`
+pub const REFUSED_CONNECTIONS: Metric =
+    Metric { name: "refused_connections", help: "Connections refused by the access lists" };
+
+/// Counter by `ReasonLabels`
+pub const EVICTED_CLIENTS: Metric = Metric {
+    name: "evicted_clients",
+    help: "Clients disconnected by the client limit, idle timeout or a ban",
+};
+
+/// Gauge
+pub const BANNED_CLIENTS: Metric = Metric { name: "banned_clients", help: "Clients currently banned for misbehaving" };
+
+/// Gauge, in Unix seconds
+pub const REGISTRY_REFRESHED: Metric = Metric {
+    name: "registry_refreshed_timestamp_seconds",
+    help: "When the actors were last registered in the DHT",
+};
+
+/// Gauge
+pub const ACTOR_PINGS: Metric =
+    Metric { name: "actor_pings", help: "Pings handled by the PingActor since its last reset" };
+
+/// How a client reaches the actors
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Transport {
+    WebSocket,
+    WebTransport,
+    Tcp,
+    Libp2p,
+}
+
+impl Transport {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Transport::WebSocket => "websocket",
+            Transport::WebTransport => "webtransport",
+            Transport::Tcp => "tcp",
+            Transport::Libp2p => "libp2p",
+        }
+    }
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl EncodeLabelValue for Transport {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> Result<(), fmt::Error> {
+        encoder.write_str(self.as_str())
+    }
+}
+
+/// The wire format of a client's frames
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Codec {
+    Json,
+    Msgpack,
+}
+
+impl Codec {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::Msgpack => "msgpack",
+        }
+    }
+}
+
+impl EncodeLabelValue for Codec {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> Result<(), fmt::Error> {
+        encoder.write_str(self.as_str())
+    }
+}
+
+/// A request, by the actor it went to and how it came
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RequestLabels {
+    pub transport: Transport,
+    pub codec: Codec,
+    pub actor: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct TransportLabels {
+    pub transport: Transport,
+}
+
+/// One client: a libp2p peer id, or an address
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ClientLabels {
+    pub client: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ReasonLabels {
+    pub reason: &'static str,
+}
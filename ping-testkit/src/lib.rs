@@ -0,0 +1,220 @@
+// Test fixtures
+//
+// Servers started in-process on ephemeral ports, handing out typed clients
+// already connected to them, so integration tests anywhere in the workspace
+// neither shell out to the binaries nor fight over fixed ports. Add
+// ping-testkit as a dev-dependency and, in a `#[tokio::test]`:
+//
+//     let server = HttpServer::start().await?;
+//     let pong = server.websocket_client().await?.ping(ping).await?;
+//
+// - `HttpServer`: ping-http-server, with its WebSocket endpoint and its
+//   plain TCP bridge, one server per call
+// - `Libp2pPair`: a PingActor published over libp2p as ping-cli-server
+//   publishes it, and the reference a client looks it up by. kameo allows
+//   one swarm per process, so there is one pair per test binary, shared by
+//   its tests, and its swarm runs on a thread of its own to outlive each
+//   test's runtime. An `HttpServer` started with `--libp2p-port` or
+//   `--gateway` would need a second swarm, and fails in a binary using the
+//   pair.
+
+use clap::Parser;
+use kameo::prelude::*;
+use kameo::remote;
+use libp2p::{
+    noise, tcp, yamux,
+    swarm::{NetworkBehaviour, SwarmEvent},
+    Multiaddr,
+};
+use futures::StreamExt;
+use ping_common::transport::{Reconnecting, Tcp, TcpTarget, TransportError, WebSocket};
+use ping_common::{PingActor, PingActorClient};
+use ping_http_server::{Args, Server};
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, Mutex, OnceCell};
+use tokio::task::JoinHandle;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// A typed client over a WebSocket connection
+pub type WebSocketClient = PingActorClient<Mutex<Reconnecting<WebSocket>>>;
+
+/// A typed client over a plain TCP connection
+pub type TcpClient = PingActorClient<Mutex<Reconnecting<Tcp>>>;
+
+/// A typed client over kameo's libp2p remoting
+pub type Libp2pClient = PingActorClient<RemoteActorRef<PingActor>>;
+
+/// ping-http-server running in the test's runtime, stopped when dropped
+pub struct HttpServer {
+    addr: SocketAddr,
+    tcp_addr: SocketAddr,
+    tasks: [JoinHandle<()>; 2],
+}
+
+impl HttpServer {
+    /// A server with the default settings
+    pub async fn start() -> Result<Self, Error> {
+        Self::with_args(Vec::<OsString>::new()).await
+    }
+
+    /// A server started with these command-line arguments (say
+    /// `["--ws-token", "s3cret"]`). Its ports are always ephemeral ones on
+    /// 127.0.0.1, whatever the arguments say, and TLS is never served.
+    pub async fn with_args<I, T>(args: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = Args::try_parse_from(
+            std::iter::once(OsString::from("ping-http-server")).chain(args.into_iter().map(Into::into)),
+        )?;
+        let server = Server::new(args).await.map_err(|e| e.to_string())?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let tcp_addr = tcp_listener.local_addr()?;
+        let tcp = server.serve_tcp(tcp_listener);
+        let http = tokio::spawn(async move {
+            let _ = server.serve(listener).await;
+        });
+        Ok(HttpServer { addr, tcp_addr, tasks: [http, tcp] })
+    }
+
+    /// Address of the HTTP and WebSocket listener
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Address of the plain TCP endpoint
+    pub fn tcp_addr(&self) -> SocketAddr {
+        self.tcp_addr
+    }
+
+    /// URL of `path` on the server, e.g. `url("/admin/latency")`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// URL of the PingActor's WebSocket endpoint, to add `?token=` to
+    pub fn websocket_url(&self) -> String {
+        format!("ws://{}/ws", self.addr)
+    }
+
+    /// A client connected to `/ws`
+    pub async fn websocket_client(&self) -> Result<WebSocketClient, TransportError> {
+        let connection = Reconnecting::connect(self.websocket_url()).await?;
+        Ok(PingActorClient::new(Mutex::new(connection)))
+    }
+
+    /// A client connected to the TCP endpoint, sending `token` first if
+    /// given
+    pub async fn tcp_client(&self, token: Option<&str>) -> Result<TcpClient, TransportError> {
+        let target = TcpTarget { addr: self.tcp_addr.to_string(), token: token.map(str::to_string) };
+        let connection = Reconnecting::connect(target).await?;
+        Ok(PingActorClient::new(Mutex::new(connection)))
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Registry name of the pair's PingActor, as ping-cli-server's default
+pub const PING_ACTOR_NAME: &str = "ping_actor";
+
+static PAIR: OnceCell<Libp2pPair> = OnceCell::const_new();
+
+/// A PingActor served over libp2p, and a client's reference to it
+pub struct Libp2pPair {
+    addr: Multiaddr,
+    actor: RemoteActorRef<PingActor>,
+}
+
+// Network behaviour wrapping Kameo's remote messaging, as in the CLI crates
+#[derive(NetworkBehaviour)]
+struct PairBehaviour {
+    kameo: remote::Behaviour,
+}
+
+impl Libp2pPair {
+    /// The test binary's pair, started on the first call. Its PingActor
+    /// counts the pings of every test, so tests shouldn't expect its
+    /// counters to start at zero.
+    pub async fn get() -> Result<&'static Libp2pPair, Error> {
+        PAIR.get_or_try_init(|| async {
+            let (started_tx, started) = oneshot::channel();
+            std::thread::Builder::new().name("ping-testkit-libp2p".to_string()).spawn(move || {
+                let runtime = match tokio::runtime::Runtime::new() {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = started_tx.send(Err(e.into()));
+                        return;
+                    }
+                };
+                runtime.block_on(async {
+                    let _ = started_tx.send(Libp2pPair::start().await);
+                    std::future::pending::<()>().await
+                });
+            })?;
+            started.await.map_err(|_| "the libp2p thread stopped")?
+        })
+        .await
+    }
+
+    // Listen on an ephemeral port, publish a PingActor and look it up
+    async fn start() -> Result<Libp2pPair, Error> {
+        let mut swarm = libp2p::SwarmBuilder::with_new_identity()
+            .with_tokio()
+            .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)?
+            .with_behaviour(|key| {
+                let peer_id = key.public().to_peer_id();
+                let messaging_config = remote::messaging::Config::default()
+                    .with_request_timeout(Duration::from_secs(30));
+                let kameo = remote::Behaviour::new(peer_id, messaging_config);
+                Ok(PairBehaviour { kameo })
+            })?
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(600)))
+            .build();
+        swarm.behaviour().kameo.try_init_global()?;
+        swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse()?)?;
+        let peer_id = *swarm.local_peer_id();
+        let addr = loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+                break address.with_p2p(peer_id).map_err(|_| "listen address has a peer id")?;
+            }
+        };
+        tokio::spawn(async move {
+            loop {
+                swarm.select_next_some().await;
+            }
+        });
+
+        // Registration and lookup go through the swarm task
+        let actor = PingActor::spawn(PingActor { ping_count: 0 });
+        actor.register(PING_ACTOR_NAME).await?;
+        let actor = RemoteActorRef::<PingActor>::lookup(PING_ACTOR_NAME)
+            .await?
+            .ok_or("the PingActor is not in the registry")?;
+        Ok(Libp2pPair { addr, actor })
+    }
+
+    /// Address of the listening swarm, `/p2p/` part included, for a
+    /// ping-cli-client to dial
+    pub fn addr(&self) -> &Multiaddr {
+        &self.addr
+    }
+
+    /// A client of the pair's PingActor
+    pub fn client(&self) -> Libp2pClient {
+        PingActorClient::new(self.actor.clone())
+    }
+}
@@ -0,0 +1,41 @@
+// Metadata end to end
+//
+// A Ping's metadata comes back in its Pong, through every bridge.
+
+use ping_common::Ping;
+use ping_testkit::{HttpServer, Libp2pPair};
+use std::collections::BTreeMap;
+
+fn ping() -> Ping {
+    Ping::builder()
+        .message("Ping with metadata")
+        .metadata("subject", "alice")
+        .metadata("experiment", "b")
+        .build()
+}
+
+#[tokio::test]
+async fn bridges_echo_metadata() {
+    let server = HttpServer::start().await.unwrap();
+    let sent = ping();
+
+    let pong = server.websocket_client().await.unwrap().ping(sent.clone()).await.unwrap();
+    assert_eq!(pong.metadata, sent.metadata);
+
+    let pong = server.tcp_client(None).await.unwrap().ping(sent.clone()).await.unwrap();
+    assert_eq!(pong.metadata, sent.metadata);
+}
+
+#[tokio::test]
+async fn libp2p_echoes_metadata() {
+    let sent = ping();
+    let pong = Libp2pPair::get().await.unwrap().client().ping(sent.clone()).await.unwrap();
+    assert_eq!(pong.metadata, sent.metadata);
+}
+
+#[tokio::test]
+async fn no_metadata_is_none_echoed() {
+    let server = HttpServer::start().await.unwrap();
+    let pong = server.websocket_client().await.unwrap().ping(Ping::builder().message("Plain ping").build()).await.unwrap();
+    assert_eq!(pong.metadata, BTreeMap::new());
+}
@@ -0,0 +1,54 @@
+// The fixtures themselves
+//
+// Every client the testkit hands out reaches a PingActor, and the clients of
+// one HttpServer reach the same one, whichever endpoint they use.
+
+use ping_common::transport::TransportError;
+use ping_common::Ping;
+use ping_testkit::{HttpServer, Libp2pPair};
+
+fn ping(sequence: u64) -> Ping {
+    Ping::builder().message("Ping from ping-testkit").sequence(sequence).new_correlation_id().build()
+}
+
+#[tokio::test]
+async fn websocket_and_tcp_reach_one_actor() {
+    let server = HttpServer::start().await.unwrap();
+
+    let pong = server.websocket_client().await.unwrap().ping(ping(1)).await.unwrap();
+    assert_eq!((pong.sequence, pong.total_pings), (1, 1));
+
+    let pong = server.tcp_client(None).await.unwrap().ping(ping(2)).await.unwrap();
+    assert_eq!((pong.sequence, pong.total_pings), (2, 2));
+}
+
+#[tokio::test]
+async fn servers_are_independent() {
+    let first = HttpServer::start().await.unwrap();
+    let second = HttpServer::start().await.unwrap();
+    assert_ne!(first.addr(), second.addr());
+
+    first.websocket_client().await.unwrap().ping(ping(1)).await.unwrap();
+    let pong = second.websocket_client().await.unwrap().ping(ping(1)).await.unwrap();
+    assert_eq!(pong.total_pings, 1);
+}
+
+#[tokio::test]
+async fn arguments_reach_the_server() {
+    let server = HttpServer::with_args(["--ws-token", "s3cret"]).await.unwrap();
+
+    assert!(matches!(server.tcp_client(None).await, Err(TransportError::Server(_))));
+    let pong = server.tcp_client(Some("s3cret")).await.unwrap().ping(ping(1)).await.unwrap();
+    assert_eq!(pong.sequence, 1);
+}
+
+#[tokio::test]
+async fn libp2p_pair_answers() {
+    let pair = Libp2pPair::get().await.unwrap();
+    let client = pair.client();
+
+    let before = client.get_stats().await.unwrap().total_pings;
+    let pong = client.ping(ping(1)).await.unwrap();
+    assert_eq!(pong.sequence, 1);
+    assert!(pong.total_pings > before);
+}
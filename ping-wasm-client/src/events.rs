@@ -0,0 +1,126 @@
+// Client events
+//
+// What the client tells the page about: the connection opening, closing,
+// failing and coming back, the server refusing the token or leaving a
+// keepalive unanswered, every Pong and every frame the server pushes. Each
+// event goes to the callback registered for it, and to the event port if one
+// is set. Callbacks can't cross from a Web Worker to the page, so there the
+// port carries the events instead, as plain `{type, ...}` objects that
+// survive postMessage.
+
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+use web_sys::CloseEvent;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Where `set_event_port` posts the events: a MessagePort, or the
+    /// worker's own global scope to reach the page that started it
+    #[wasm_bindgen(typescript_type = "MessagePort | DedicatedWorkerGlobalScope")]
+    #[derive(Clone)]
+    pub type EventPort;
+
+    #[wasm_bindgen(method, catch, js_name = postMessage)]
+    fn post_message(this: &EventPort, message: &JsValue) -> Result<(), JsValue>;
+}
+
+// Functions the page registered for the client's events, and the port
+#[derive(Default)]
+pub struct Callbacks {
+    pub open: Option<Function>,
+    pub close: Option<Function>,
+    pub error: Option<Function>,
+    pub pong: Option<Function>,
+    pub broadcast: Option<Function>,
+    pub reconnect: Option<Function>,
+    pub auth_error: Option<Function>,
+    pub stale: Option<Function>,
+    pub port: Option<EventPort>,
+}
+
+pub enum Event {
+    Open,
+    Close(CloseEvent),
+    // An error event of the socket, or the exception of a failed reconnect
+    Error(JsValue),
+    // The Pong as JavaScript sees it
+    Pong(JsValue),
+    // A broadcast or heartbeat, as JavaScript sees it
+    Broadcast(JsValue),
+    Reconnect,
+    // The server closed the connection over the token
+    AuthError(CloseEvent),
+    // A keepalive went unanswered
+    Stale,
+}
+
+impl Event {
+    // The registered callback for the event, and the port
+    pub fn receivers(&self, callbacks: &Callbacks) -> (Option<Function>, Option<EventPort>) {
+        let callback = match self {
+            Event::Open => &callbacks.open,
+            Event::Close(_) => &callbacks.close,
+            Event::Error(_) => &callbacks.error,
+            Event::Pong(_) => &callbacks.pong,
+            Event::Broadcast(_) => &callbacks.broadcast,
+            Event::Reconnect => &callbacks.reconnect,
+            Event::AuthError(_) => &callbacks.auth_error,
+            Event::Stale => &callbacks.stale,
+        };
+        (callback.clone(), callbacks.port.clone())
+    }
+
+    pub fn call(&self, callback: &Function) {
+        let _ = match self {
+            Event::Open | Event::Reconnect | Event::Stale => callback.call0(&JsValue::NULL),
+            Event::Close(event) | Event::AuthError(event) => callback.call1(&JsValue::NULL, event),
+            Event::Error(value) | Event::Pong(value) | Event::Broadcast(value) => callback.call1(&JsValue::NULL, value),
+        };
+    }
+
+    pub fn post(&self, port: &EventPort) {
+        let _ = port.post_message(&self.message());
+    }
+
+    // `{type, ...}` with nothing postMessage can't clone: the close code and
+    // reason instead of the CloseEvent, the message of an error
+    fn message(&self) -> JsValue {
+        let message = Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = Reflect::set(&message, &key.into(), &value);
+        };
+        match self {
+            Event::Open => set("type", "open".into()),
+            Event::Close(event) => {
+                set("type", "close".into());
+                set("code", event.code().into());
+                set("reason", event.reason().into());
+                set("was_clean", event.was_clean().into());
+            }
+            Event::AuthError(event) => {
+                set("type", "auth_error".into());
+                set("code", event.code().into());
+                set("reason", event.reason().into());
+            }
+            Event::Error(error) => {
+                let text = Reflect::get(error, &"message".into())
+                    .ok()
+                    .and_then(|text| text.as_string())
+                    .unwrap_or_else(|| "WebSocket error".to_string());
+                set("type", "error".into());
+                set("message", text.into());
+            }
+            Event::Pong(pong) => {
+                set("type", "pong".into());
+                set("pong", pong.clone());
+            }
+            Event::Broadcast(push) => {
+                set("type", "broadcast".into());
+                set("push", push.clone());
+            }
+            Event::Reconnect => set("type", "reconnect".into()),
+            Event::Stale => set("type", "stale".into()),
+        }
+        message.into()
+    }
+}
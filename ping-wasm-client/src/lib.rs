@@ -1,74 +1,1177 @@
+mod events;
+mod pending;
+mod persist;
+mod pool;
+mod stats;
+#[cfg(feature = "webtransport")]
+mod webtransport;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use gloo_timers::callback::{Interval, Timeout};
 use wasm_bindgen::prelude::*;
-use web_sys::{WebSocket, MessageEvent, ErrorEvent};
-use ping_common::{Ping, Pong};
+use serde::Deserialize;
+use web_sys::{BinaryType, WebSocket, MessageEvent, ErrorEvent, CloseEvent};
+use ping_common::codec::{self, json::Json, msgpack::Msgpack, Codec as _};
+use ping_common::builder::TRACEPARENT;
+use ping_common::retry::{Backoff, RetryPolicy};
+use ping_common::{Auth, Broadcast, ErrorReply, Heartbeat, KeepAlive, Ping, Pong, Validate, MAX_FRAME_BYTES};
+use events::{Callbacks, Event};
+pub use events::EventPort;
+pub use pool::WasmPingClientPool;
+use pending::{Core, Resolvers, Waiting};
+use persist::{Persist, Saved};
+
+// Endpoint of the PingActor on the server that served the page
+const DEFAULT_PATH: &str = "/ws";
+
+// Wait before the first reconnection attempt, doubled after every failed one
+// up to the cap, with jitter
+const RECONNECT: RetryPolicy = RetryPolicy::new(Duration::from_millis(500), Duration::from_secs(10));
+
+// How long a ping waits for its Pong unless `timeout_ms` is set
+const DEFAULT_TIMEOUT_MS: u32 = 10_000;
+
+// How many pings may wait for the connection unless `queue_limit` is set
+const DEFAULT_QUEUE_LIMIT: usize = 100;
+
+// How long the answer to a keepalive may take unless `stale_ms` is set
+const DEFAULT_STALE_MS: u32 = 5_000;
+
+// How often changed stats are saved with `persist_key`
+const SAVE_INTERVAL_MS: u32 = 1_000;
+
+// Subprotocol asking ping-http-server for MessagePack frames
+const MSGPACK_PROTOCOL: &str = "ping.msgpack";
+
+// Close code of ping-http-server for a missing or invalid token
+const UNAUTHORIZED: u16 = 4401;
+
+// TypeScript shapes of the messages for the generated .d.ts, matching
+// ping_common's Ping and Pong as they travel as JSON
+#[wasm_bindgen(typescript_custom_section)]
+const MESSAGE_TYPES: &str = r#"
+/** A ping as sent to the server */
+export interface Ping {
+    message: string;
+    sequence: number;
+    /** The `client_id` option, if set */
+    client_id?: string;
+    /** A UUID unique to this ping, echoed in its Pong */
+    correlation_id?: string;
+    /** Unix time in milliseconds when the client sent it */
+    sent_at_ms?: number;
+    /** Free-form entries, such as a W3C `traceparent` */
+    metadata?: Record<string, string>;
+}
+
+/** The server's answer to a ping */
+export interface Pong {
+    message: string;
+    sequence: number;
+    total_pings: number;
+    /** The ping's `client_id`, `correlation_id`, `sent_at_ms` and
+     * `metadata`, when it had them and the server echoes them */
+    client_id?: string;
+    correlation_id?: string;
+    sent_at_ms?: number;
+    metadata?: Record<string, string>;
+    /** Milliseconds from sending the ping to this Pong; missing if the ping had timed out */
+    rtt_ms?: number;
+}
+
+/** A notice the server pushed to every client */
+export interface Broadcast {
+    type: "broadcast";
+    message: string;
+    /** Unix time in milliseconds when the server sent it */
+    sent_at_ms: number;
+}
+
+/** The PingActor's counter, pushed periodically when the server has heartbeats on */
+export interface Heartbeat {
+    type: "heartbeat";
+    total_pings: number;
+    /** Unix time in milliseconds when the server sent it */
+    sent_at_ms: number;
+}
+
+/** Round trips of the pings answered so far; the times are null until the first Pong */
+export interface RttStats {
+    count: number;
+    min_ms: number | null;
+    avg_ms: number | null;
+    max_ms: number | null;
+    last_ms: number | null;
+}
+
+/** Totals of `stats()`; the times are null until the first Pong */
+export interface Stats {
+    sent: number;
+    received: number;
+    errors: number;
+    min_ms: number | null;
+    avg_ms: number | null;
+    p95_ms: number | null;
+    max_ms: number | null;
+}
+
+/** Settings for a new WasmPingClient, all optional */
+export interface ClientOptions {
+    /** Encoding of the frames; "msgpack" needs a server that offers it */
+    codec?: "json" | "msgpack";
+    /** How many pings may wait for the connection while it is down (default 100); 0 rejects them right away */
+    queue_limit?: number;
+    /** Token for a server started with --ws-token */
+    auth_token?: string;
+    /** How the token is sent: in an auth frame once connected (default), or as ?token= on the URL */
+    auth_via?: "frame" | "query";
+    /** "webtransport" tries the server's HTTP/3 endpoint first, falling back to a WebSocket; needs the `webtransport` feature and JSON */
+    transport?: "websocket" | "webtransport";
+    /** WebTransport endpoint; by default the WebSocket URL as https: with /ws turned into /wt */
+    webtransport_url?: string;
+    /** Largest encoded ping to send (default 1 MiB, the server's limit); larger ones are rejected */
+    max_bytes?: number;
+    /** Send a keepalive after this many milliseconds without a frame from the server (default 0, off) */
+    keepalive_ms?: number;
+    /** How long the server may take to answer a keepalive before `on_stale` is called (default 5000) */
+    stale_ms?: number;
+    /** Keep the stats and a server session id in localStorage under this key, and carry on from them */
+    persist_key?: string;
+    /** Sent with every ping, for the server to recognise this client by */
+    client_id?: string;
+    /** Metadata entries sent with every ping, and echoed in its Pong */
+    metadata?: Record<string, string>;
+    /** Sequence number of the first ping (default 1), to carry on from an earlier client */
+    first_sequence?: number;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    // The constructor's `options` object as JavaScript passes it
+    #[wasm_bindgen(typescript_type = "ClientOptions")]
+    pub type JsClientOptions;
+}
+
+// The constructor's `options` object
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct ClientOptions {
+    codec: Codec,
+    queue_limit: usize,
+    auth_token: Option<String>,
+    auth_via: AuthVia,
+    transport: Transport,
+    #[cfg(feature = "webtransport")]
+    webtransport_url: Option<String>,
+    max_bytes: usize,
+    keepalive_ms: u32,
+    stale_ms: u32,
+    persist_key: Option<String>,
+    client_id: Option<String>,
+    metadata: BTreeMap<String, String>,
+    first_sequence: u64,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            codec: Codec::Json,
+            queue_limit: DEFAULT_QUEUE_LIMIT,
+            auth_token: None,
+            auth_via: AuthVia::Frame,
+            transport: Transport::WebSocket,
+            #[cfg(feature = "webtransport")]
+            webtransport_url: None,
+            max_bytes: MAX_FRAME_BYTES,
+            keepalive_ms: 0,
+            stale_ms: DEFAULT_STALE_MS,
+            persist_key: None,
+            client_id: None,
+            metadata: BTreeMap::new(),
+            first_sequence: 1,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AuthVia {
+    #[default]
+    Frame,
+    Query,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Transport {
+    #[default]
+    WebSocket,
+    WebTransport,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Codec {
+    #[default]
+    Json,
+    Msgpack,
+}
 
 /// WebAssembly ping client
 /// Uses the same Ping/Pong message format as the CLI client
 #[wasm_bindgen]
 pub struct WasmPingClient {
-    ws: WebSocket,
-    ping_count: u64,
+    state: Rc<RefCell<State>>,
+}
+
+// What outlives a single WebSocket: a dropped connection is replaced by a new
+// socket, and the sequence numbers carry on where they left off
+struct State {
+    url: String,
+    endpoint: Endpoint,
+    // Token for the auth frame sent when a socket opens
+    auth_frame: Option<Auth>,
+    socket: Socket,
+    // Numbers the pings, keeps the ones awaiting their Pong and counts them
+    core: Core,
+    // Pings made while the socket wasn't open, sent once it is
+    queue: VecDeque<Ping>,
+    queue_limit: usize,
+    // Largest encoded ping sent; anything larger is refused before it goes
+    // out
+    max_bytes: usize,
+    timeout_ms: u32,
+    // Delays of the reconnection attempts since the last successful open
+    backoff: Backoff,
+    // Whether a socket has been open before, so the next open is a reconnect
+    was_open: bool,
+    // The next attempt, if one is scheduled; dropping it cancels it
+    reconnect_timer: Option<Timeout>,
+    // The timer of `start_auto_ping()`; dropping it stops the pings
+    auto_ping: Option<Interval>,
+    // Checks for an idle connection every `keepalive_ms`, if set
+    keepalive: Option<Interval>,
+    keepalive_ms: u32,
+    stale_ms: u32,
+    // When the server last sent anything, or the socket opened
+    last_received_ms: f64,
+    // The deadline of the keepalive awaiting its answer; dropping it
+    // cancels it
+    stale_timer: Option<Timeout>,
+    // Whether a keepalive went unanswered, and nothing has come since
+    stale: bool,
+    // Where the stats are kept with `persist_key`, and the timer saving them
+    persist: Option<Persist>,
+    save_timer: Option<Interval>,
+    // Whether the page closed the client, which then stays closed
+    closed: bool,
+    callbacks: Callbacks,
+    // Attached to every socket in turn; they only hold a weak reference to
+    // the state, so dropping the client frees them
+    handlers: Option<Handlers>,
+}
+
+// Where and how sockets connect
+struct Endpoint {
+    // `url` with the token added, if it goes there
+    socket_url: String,
+    // Tried before `socket_url` while set; cleared for good once WebTransport
+    // turns out not to work
+    #[cfg(feature = "webtransport")]
+    webtransport_url: Option<String>,
+    codec: Codec,
+}
+
+// The connection to the server: a WebSocket, or with the `webtransport`
+// feature a WebTransport session
+enum Socket {
+    WebSocket(WebSocket),
+    #[cfg(feature = "webtransport")]
+    WebTransport(webtransport::Session),
+}
+
+struct Handlers {
+    // For the WebTransport sessions, which report to the state directly
+    #[cfg_attr(not(feature = "webtransport"), allow(dead_code))]
+    state: Weak<RefCell<State>>,
+    onopen: Closure<dyn FnMut(JsValue)>,
+    onmessage: Closure<dyn FnMut(MessageEvent)>,
+    onerror: Closure<dyn FnMut(ErrorEvent)>,
+    onclose: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl ClientOptions {
+    fn from_js(options: Option<JsClientOptions>) -> Result<Self, JsValue> {
+        match options {
+            Some(options) => Ok(serde_wasm_bindgen::from_value(options.into())?),
+            None => Ok(ClientOptions::default()),
+        }
+    }
+}
+
+impl WasmPingClient {
+    // Connect to the server with the options parsed already; the pool opens
+    // all of its clients with the same ones
+    fn connect(url: Option<String>, options: ClientOptions) -> Result<WasmPingClient, JsValue> {
+        let url = server_url(url.as_deref())?;
+        let query_token = options.auth_token.as_deref().filter(|_| options.auth_via == AuthVia::Query);
+        let add_token = |url: &str| query_token.map_or_else(|| url.to_string(), |token| with_param(url, "token", token));
+        let auth_frame = match options.auth_token.clone() {
+            Some(token) if options.auth_via == AuthVia::Frame => Some(Auth { token }),
+            _ => None,
+        };
+        if options.transport == Transport::WebTransport && options.codec == Codec::Msgpack {
+            return Err(js_sys::Error::new("WebTransport carries JSON only").into());
+        }
+        #[cfg(not(feature = "webtransport"))]
+        if options.transport == Transport::WebTransport {
+            return Err(js_sys::Error::new("built without the webtransport feature").into());
+        }
+        if options.max_bytes == 0 {
+            return Err(js_sys::Error::new("max_bytes must be more than 0").into());
+        }
+        if options.first_sequence == 0 {
+            return Err(js_sys::Error::new("first_sequence must be more than 0").into());
+        }
+        if options.keepalive_ms > 0 && options.stale_ms == 0 {
+            return Err(js_sys::Error::new("stale_ms must be more than 0").into());
+        }
+        let (persist, saved) = match options.persist_key.as_deref().and_then(Persist::open) {
+            Some((persist, saved)) => (Some(persist), saved),
+            None => (None, Saved::default()),
+        };
+        let mut socket_url = add_token(&url);
+        if let Some(persist) = &persist {
+            socket_url = with_param(&socket_url, "session", &persist.session_id);
+        }
+        let mut endpoint = Endpoint {
+            socket_url,
+            #[cfg(feature = "webtransport")]
+            webtransport_url: match options.transport {
+                Transport::WebTransport => Some(add_token(&options.webtransport_url.unwrap_or_else(|| webtransport_url(&url)))),
+                Transport::WebSocket => None,
+            },
+            codec: options.codec,
+        };
+        let socket = endpoint.open()?;
+        let mut core = Core::new(options.first_sequence, options.client_id);
+        core.set_metadata(options.metadata);
+        core.restore(saved.counts, saved.rtt);
+        let state = Rc::new(RefCell::new(State {
+            url,
+            endpoint,
+            auth_frame,
+            socket,
+            core,
+            queue: VecDeque::new(),
+            queue_limit: options.queue_limit,
+            max_bytes: options.max_bytes,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            backoff: RECONNECT.backoff(),
+            was_open: false,
+            reconnect_timer: None,
+            auto_ping: None,
+            keepalive: None,
+            keepalive_ms: options.keepalive_ms,
+            stale_ms: options.stale_ms,
+            last_received_ms: now_ms(),
+            stale_timer: None,
+            stale: false,
+            persist,
+            save_timer: None,
+            closed: false,
+            callbacks: Callbacks::default(),
+            handlers: None,
+        }));
+        let handlers = Handlers::new(Rc::downgrade(&state));
+        let keepalive = (options.keepalive_ms > 0).then(|| {
+            let weak = Rc::downgrade(&state);
+            Interval::new(options.keepalive_ms, move || {
+                if let Some(state) = weak.upgrade() {
+                    keep_alive(&state);
+                }
+            })
+        });
+        let save_timer = options.persist_key.is_some().then(|| {
+            let weak = Rc::downgrade(&state);
+            Interval::new(SAVE_INTERVAL_MS, move || {
+                if let Some(state) = weak.upgrade() {
+                    state.borrow().save();
+                }
+            })
+        });
+        {
+            let mut state = state.borrow_mut();
+            handlers.attach(&state.socket);
+            state.handlers = Some(handlers);
+            state.keepalive = keepalive;
+            state.save_timer = save_timer;
+        }
+        Ok(WasmPingClient { state })
+    }
 }
 
 #[wasm_bindgen]
 impl WasmPingClient {
     /// Create new WebSocket connection to the Kameo server
+    /// `url` is the server's WebSocket endpoint, e.g. `wss://example.com/ws`;
+    /// a path such as `/ws/ping` is taken relative to the page, and without
+    /// one the client connects to `/ws` on the host that served the page,
+    /// over `wss:` when the page came over `https:`
+    /// A dropped connection is reopened with backoff (0.5s doubling up to
+    /// 10s, jittered), and pings keep their sequence numbers across it
+    /// `options.codec: "msgpack"` exchanges MessagePack frames with the
+    /// server instead of JSON; a server that doesn't offer them refuses the
+    /// connection
+    /// `options.client_id` goes out with every Ping, and
+    /// `options.first_sequence` numbers the first one, so a returning page
+    /// can carry on as the same client
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<WasmPingClient, JsValue> {
+    pub fn new(
+        url: Option<String>,
+        options: Option<JsClientOptions>,
+    ) -> Result<WasmPingClient, JsValue> {
         // Set up panic handler for better error messages
         console_error_panic_hook::set_once();
-        
-        // Connect to WebSocket endpoint
-        let ws = WebSocket::new("ws://localhost:8080/ws")?;
-        
+
+        let options = ClientOptions::from_js(options)?;
+        WasmPingClient::connect(url, options)
+    }
+
+    /// The URL the client connected to
+    #[wasm_bindgen(getter)]
+    pub fn url(&self) -> String {
+        self.state.borrow().url.clone()
+    }
+
+    /// State of the current socket, as WebSocket.readyState: 0 connecting,
+    /// 1 open, 2 closing, 3 closed
+    #[wasm_bindgen(js_name = readyState)]
+    pub fn ready_state(&self) -> u16 {
+        self.state.borrow().socket.ready_state()
+    }
+
+    /// Whether the socket is open, so pings can be sent right away
+    #[wasm_bindgen(getter)]
+    pub fn is_connected(&self) -> bool {
+        self.state.borrow().socket.ready_state() == WebSocket::OPEN
+    }
+
+    /// The transport in use: "webtransport" while the client connects over
+    /// WebTransport, "websocket" otherwise, including after falling back
+    #[wasm_bindgen(getter, unchecked_return_type = "\"websocket\" | \"webtransport\"")]
+    pub fn transport(&self) -> String {
+        match self.state.borrow().socket {
+            Socket::WebSocket(_) => "websocket".to_string(),
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(_) => "webtransport".to_string(),
+        }
+    }
+
+    /// The server session the WebSocket resumes, kept with the stats under
+    /// `persist_key`; undefined without one, or without localStorage
+    #[wasm_bindgen(getter)]
+    pub fn session_id(&self) -> Option<String> {
+        self.state.borrow().persist.as_ref().map(|persist| persist.session_id.clone())
+    }
+
+    /// Close the connection for good: no reconnection follows, and pings
+    /// still waiting for their Pong are rejected. `on_close` is called once
+    /// the socket has closed. `free()` does the same and releases the client.
+    pub fn close(&self) {
+        let mut state = self.state.borrow_mut();
+        if !state.closed {
+            state.shut_down("client closed");
+            state.socket.close();
+        }
+    }
+
+    /// Call `callback()` each time the connection opens, reconnections
+    /// included
+    pub fn on_open(&self, #[wasm_bindgen(unchecked_param_type = "() => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.open = Some(callback);
+    }
+
+    /// Call `callback(event)` with the CloseEvent each time the connection
+    /// closes; the client then reconnects, unless it was closed by `close()`
+    pub fn on_close(&self, #[wasm_bindgen(unchecked_param_type = "(event: CloseEvent) => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.close = Some(callback);
+    }
+
+    /// Call `callback(error)` on WebSocket errors and failed reconnections
+    pub fn on_error(&self, #[wasm_bindgen(unchecked_param_type = "(error: Event | Error) => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.error = Some(callback);
+    }
+
+    /// Call `callback(pong)` with every Pong received, whether or not its
+    /// ping is still waiting for it
+    pub fn on_pong(&self, #[wasm_bindgen(unchecked_param_type = "(pong: Pong) => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.pong = Some(callback);
+    }
+
+    /// Call `callback(event)` with the CloseEvent when the server refuses
+    /// the token (close code 4401, the reason says why). The client doesn't
+    /// reconnect after that, as the same token would be refused again.
+    pub fn on_auth_error(&self, #[wasm_bindgen(unchecked_param_type = "(event: CloseEvent) => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.auth_error = Some(callback);
+    }
+
+    /// Call `callback(push)` with every frame the server pushes on its own:
+    /// `{type: "broadcast", message, sent_at_ms}` from the admin API, and
+    /// `{type: "heartbeat", total_pings, sent_at_ms}` if heartbeats are on.
+    /// They never reach `on_pong`.
+    pub fn on_broadcast(&self, #[wasm_bindgen(unchecked_param_type = "(push: Broadcast | Heartbeat) => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.broadcast = Some(callback);
+    }
+
+    /// Call `callback()` each time a dropped connection has been reopened
+    pub fn on_reconnect(&self, #[wasm_bindgen(unchecked_param_type = "() => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.reconnect = Some(callback);
+    }
+
+    /// Call `callback()` when the server leaves a keepalive unanswered for
+    /// `stale_ms`, a connection that looks open but carries nothing; with
+    /// `keepalive_ms` set only. The client keeps the connection, so the page
+    /// decides what to do.
+    pub fn on_stale(&self, #[wasm_bindgen(unchecked_param_type = "() => void")] callback: js_sys::Function) {
+        self.state.borrow_mut().callbacks.stale = Some(callback);
+    }
+
+    /// Also post every event to `port` as a message, for a client running
+    /// in a Web Worker: `{type: "open"}`, `{type: "close", code, reason,
+    /// was_clean}`, `{type: "error", message}`, `{type: "pong", pong}`,
+    /// `{type: "broadcast", push}`, `{type: "reconnect"}`,
+    /// `{type: "auth_error", code, reason}` and `{type: "stale"}`. Pass
+    /// the worker's `self` to reach the page that started it, or null to
+    /// stop.
+    pub fn set_event_port(&self, port: Option<EventPort>) {
+        self.state.borrow_mut().callbacks.port = port;
+    }
+
+    /// How long a ping waits for its Pong, in milliseconds (default 10000)
+    #[wasm_bindgen(getter)]
+    pub fn timeout_ms(&self) -> u32 {
+        self.state.borrow().timeout_ms
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_timeout_ms(&self, timeout_ms: u32) {
+        self.state.borrow_mut().timeout_ms = timeout_ms;
+    }
+
+    /// Number of pings waiting for the connection to be sent
+    #[wasm_bindgen(getter)]
+    pub fn queue_length(&self) -> usize {
+        self.state.borrow().queue.len()
+    }
+
+    /// Round trips of the pings answered so far: `{count, min_ms, avg_ms,
+    /// max_ms, last_ms}`
+    #[wasm_bindgen(getter, unchecked_return_type = "RttStats")]
+    pub fn rtt_stats(&self) -> JsValue {
+        stats::to_js(self.state.borrow().core.rtt())
+    }
+
+    /// Totals since the client was created: `{sent, received, errors,
+    /// min_ms, avg_ms, p95_ms, max_ms}`. `errors` counts the pings that
+    /// couldn't be sent or queued and those that got no Pong in time; the
+    /// 95th percentile is over every round trip, to within 1.6%.
+    #[wasm_bindgen(unchecked_return_type = "Stats")]
+    pub fn stats(&self) -> JsValue {
+        let state = self.state.borrow();
+        stats::summary_to_js(state.core.rtt(), state.core.counts())
+    }
+
+    /// Send a ping message to the server
+    /// Uses the same message format as the CLI client. The returned Promise
+    /// resolves with the Pong to this ping (`{message, sequence, total_pings,
+    /// rtt_ms}`), or rejects if it can't be sent, the server answers it with
+    /// an error, or no Pong comes within `timeout_ms`. While the connection
+    /// is down the ping waits in the queue and goes out once it is back; with
+    /// `queue_limit` pings already waiting, it is rejected instead. The
+    /// timeout counts from the call either way.
+    /// `message` replaces the default "Hello from Wasm #n". A blank one, or
+    /// one that makes the frame larger than `max_bytes`, is rejected without
+    /// using up a sequence number.
+    #[wasm_bindgen(unchecked_return_type = "Promise<Pong>")]
+    pub fn send_ping(&mut self, message: Option<String>) -> js_sys::Promise {
+        js_sys::Promise::new(&mut |resolve, reject| {
+            ping(&self.state, message.clone(), None, Some(Resolvers { resolve, reject }));
+        })
+    }
+
+    /// `send_ping()` for a ping in the trace of the page's own span:
+    /// `traceparent` is that span's W3C trace context
+    /// (`00-<trace id>-<span id>-<flags>`, as OpenTelemetry JS's
+    /// `propagation.inject` writes it), and the servers' spans for the ping
+    /// become its children
+    #[wasm_bindgen(unchecked_return_type = "Promise<Pong>")]
+    pub fn send_traced_ping(&mut self, message: Option<String>, traceparent: String) -> js_sys::Promise {
+        js_sys::Promise::new(&mut |resolve, reject| {
+            ping(&self.state, message.clone(), Some(traceparent.clone()), Some(Resolvers { resolve, reject }));
+        })
+    }
+
+    /// Send a ping every `interval_ms` milliseconds until `stop_auto_ping()`
+    /// or `close()`, replacing any auto-ping already running. Nothing waits
+    /// for these pings; their Pongs go to `on_pong` and into `rtt_stats`.
+    pub fn start_auto_ping(&self, interval_ms: u32) -> Result<(), JsValue> {
+        if interval_ms == 0 {
+            return Err(js_sys::Error::new("interval_ms must be more than 0").into());
+        }
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            return Err(js_sys::Error::new("client closed").into());
+        }
+        let weak = Rc::downgrade(&self.state);
+        state.auto_ping = Some(Interval::new(interval_ms, move || {
+            if let Some(state) = weak.upgrade() {
+                ping(&state, None, None, None);
+            }
+        }));
+        Ok(())
+    }
+
+    /// Stop the pings of `start_auto_ping()`; those already sent still
+    /// get their Pongs
+    pub fn stop_auto_ping(&self) {
+        self.state.borrow_mut().auto_ping = None;
+    }
+
+    /// Whether `start_auto_ping()` is sending pings
+    #[wasm_bindgen(getter)]
+    pub fn is_auto_pinging(&self) -> bool {
+        self.state.borrow().auto_ping.is_some()
+    }
+}
+
+// Send the next ping, or queue it while the connection is down, and settle
+// its Promise, if it has one, with the outcome
+fn ping(shared: &Rc<RefCell<State>>, message: Option<String>, traceparent: Option<String>, resolvers: Option<Resolvers>) {
+    let fail = |resolvers: Option<Resolvers>, error: &JsValue| {
+        if let Some(resolvers) = resolvers {
+            resolvers.reject(error);
+        }
+    };
+    let mut state = shared.borrow_mut();
+    if state.closed {
+        fail(resolvers, &js_sys::Error::new("client closed"));
+        return;
+    }
+    let sequence = state.core.next_sequence();
+
+    // Create Ping message (same format as CLI)
+    let message = message.unwrap_or_else(|| format!("Hello from Wasm #{}", sequence));
+    let mut ping = state.core.next_ping(message, js_sys::Date::now() as u64);
+    if let Some(traceparent) = traceparent {
+        ping.metadata.insert(TRACEPARENT.to_string(), traceparent);
+    }
+    if let Err(error) = state.check(&ping) {
+        state.core.record_error();
+        fail(resolvers, &error);
+        return;
+    }
+
+    let online = state.socket.ready_state() == WebSocket::OPEN;
+    if !online && state.queue.len() >= state.queue_limit {
+        let error = js_sys::Error::new(&format!("not connected, and {} pings are queued already", state.queue.len()));
+        state.core.record_error();
+        fail(resolvers, &error);
+        return;
+    }
+
+    let timeout_ms = state.timeout_ms;
+    let weak = Rc::downgrade(shared);
+    let timeout = Timeout::new(timeout_ms, move || {
+        if let Some(state) = weak.upgrade() {
+            let error = js_sys::Error::new(&format!("PING #{} timed out after {}ms", sequence, timeout_ms));
+            let mut state = state.borrow_mut();
+            state.queue.retain(|ping| ping.sequence != sequence);
+            if let Some(waiting) = state.core.time_out(sequence) {
+                waiting.reject(&error);
+            }
+        }
+    });
+    state.core.track(&ping, now_ms(), Waiting::new(resolvers, timeout));
+
+    if !online {
+        state.queue.push_back(ping);
+    } else if let Err(e) = state.send(&ping) {
+        if let Some(waiting) = state.core.cancel(sequence) {
+            waiting.reject(&e);
+        }
+    }
+}
+
+impl State {
+    // Serialize `frame` in the chosen codec and send it
+    fn send_frame(&self, frame: &impl serde::Serialize) -> Result<(), JsValue> {
+        match self.endpoint.codec {
+            Codec::Json => codec::json::to_string(frame)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+                .and_then(|json| self.socket.send_str(&json)),
+            Codec::Msgpack => Msgpack::encode(frame)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+                .and_then(|data| self.socket.send_bytes(&data)),
+        }
+    }
+
+    // Refuse a blank message, one breaking a `Validate` rule, and a frame
+    // above `max_bytes`, which the server would close the connection over at
+    // its own limit
+    fn check(&self, ping: &Ping) -> Result<(), js_sys::Error> {
+        if ping.message.trim().is_empty() {
+            return Err(js_sys::Error::new("the message is empty"));
+        }
+        ping.validate().map_err(|e| js_sys::Error::new(&format!("PING #{} is invalid: {}", ping.sequence, e)))?;
+        let size = match self.endpoint.codec {
+            Codec::Json => Json::encode(ping).map(|frame| frame.len()),
+            Codec::Msgpack => Msgpack::encode(ping).map(|frame| frame.len()),
+        }
+        .map_err(|e| js_sys::Error::new(&e.to_string()))?;
+        if size > self.max_bytes {
+            return Err(js_sys::Error::new(&format!("PING #{} is {} bytes, more than max_bytes ({})", ping.sequence, size, self.max_bytes)));
+        }
+        Ok(())
+    }
+
+    // Send `ping`, counting the outcome
+    fn send(&mut self, ping: &Ping) -> Result<(), JsValue> {
+        let sent = self.send_frame(ping);
+        match sent {
+            Ok(()) => self.core.record_sent(),
+            Err(_) => self.core.record_error(),
+        }
+        sent
+    }
+
+    // Stop for good: no more reconnecting or auto-pings, and the pings
+    // still waiting are rejected with `reason`
+    fn shut_down(&mut self, reason: &str) {
+        self.closed = true;
+        self.reconnect_timer = None;
+        self.auto_ping = None;
+        self.keepalive = None;
+        self.stale_timer = None;
+        self.save_timer = None;
+        self.queue.clear();
+        let reason = js_sys::Error::new(reason).into();
+        for waiting in self.core.cancel_all() {
+            waiting.reject(&reason);
+        }
+        self.save();
+    }
+
+    // Save the stats now, with `persist_key`
+    fn save(&self) {
+        if let Some(persist) = &self.persist {
+            persist.save(self.core.counts(), self.core.rtt());
+        }
+    }
+
+    // Send the queued pings; their round trips count from now
+    fn flush(&mut self) {
+        let now = now_ms();
+        while let Some(ping) = self.queue.pop_front() {
+            self.core.restart(ping.sequence, now);
+            if let Err(e) = self.send(&ping) {
+                if let Some(waiting) = self.core.cancel(ping.sequence) {
+                    waiting.reject(&e);
+                }
+            }
+        }
+    }
+}
+
+impl Handlers {
+    fn new(state: Weak<RefCell<State>>) -> Self {
         // Set up connection handler
-        let onopen = Closure::wrap(Box::new(move |_| {
-            web_sys::console::log_1(&"Connected to Kameo server!".into());
-        }) as Box<dyn FnMut(JsValue)>);
-        ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
-        onopen.forget();
-        
-        // Set up message handler - receives Pong responses
-        let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
-            if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
-                let s = String::from(txt);
-                if let Ok(pong) = serde_json::from_str::<Pong>(&s) {
-                    let msg = format!("PONG #{}: {} (total: {})", 
-                        pong.sequence, pong.message, pong.total_pings);
-                    web_sys::console::log_1(&msg.into());
+        let onopen = {
+            let state = state.clone();
+            Closure::wrap(Box::new(move |_| {
+                if let Some(state) = state.upgrade() {
+                    opened(&state);
                 }
+            }) as Box<dyn FnMut(JsValue)>)
+        };
+
+        // Set up message handler
+        let onmessage = {
+            let state = state.clone();
+            Closure::wrap(Box::new(move |e: MessageEvent| {
+                if let Some(state) = state.upgrade() {
+                    received(&state, e.data());
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        // Set up error handler. Browsers follow an error with a close, but
+        // not every WebSocket implementation does, so either reconnects
+        let onerror = {
+            let state = state.clone();
+            Closure::wrap(Box::new(move |e: ErrorEvent| {
+                if let Some(state) = state.upgrade() {
+                    failed(&state, e.into());
+                }
+            }) as Box<dyn FnMut(ErrorEvent)>)
+        };
+
+        // Set up close handler
+        let onclose = {
+            let state = state.clone();
+            Closure::wrap(Box::new(move |e: CloseEvent| {
+                if let Some(state) = state.upgrade() {
+                    closed(&state, e);
+                }
+            }) as Box<dyn FnMut(CloseEvent)>)
+        };
+
+        Handlers { state, onopen, onmessage, onerror, onclose }
+    }
+
+    fn attach(&self, socket: &Socket) {
+        match socket {
+            Socket::WebSocket(ws) => {
+                ws.set_onopen(Some(self.onopen.as_ref().unchecked_ref()));
+                ws.set_onmessage(Some(self.onmessage.as_ref().unchecked_ref()));
+                ws.set_onerror(Some(self.onerror.as_ref().unchecked_ref()));
+                ws.set_onclose(Some(self.onclose.as_ref().unchecked_ref()));
             }
-        }) as Box<dyn FnMut(MessageEvent)>);
-        ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
-        onmessage.forget();
-        
-        // Set up error handler
-        let onerror = Closure::wrap(Box::new(move |_: ErrorEvent| {
-            web_sys::console::log_1(&"WebSocket Error".into());
-        }) as Box<dyn FnMut(ErrorEvent)>);
-        ws.set_onerror(Some(onerror.as_ref().unchecked_ref()));
-        onerror.forget();
-        
-        Ok(WasmPingClient { ws, ping_count: 0 })
-    }
-    
-    /// Send a ping message to the server
-    /// Uses the same message format as the CLI client
-    pub fn send_ping(&mut self) -> Result<(), JsValue> {
-        self.ping_count += 1;
-        
-        // Create Ping message (same format as CLI)
-        let ping = Ping {
-            message: format!("Hello from Wasm #{}", self.ping_count),
-            sequence: self.ping_count,
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(session) => session.attach(self.state.clone()),
+        }
+    }
+}
+
+// The socket opened: send the token and the queued pings
+fn opened(shared: &Rc<RefCell<State>>) {
+    let reconnected = {
+        let mut state = shared.borrow_mut();
+        state.backoff.reset();
+        state.last_received_ms = now_ms();
+        state.stale = false;
+        // The token goes before anything else
+        if let Some(auth) = &state.auth_frame {
+            let _ = state.send_frame(auth);
+        }
+        state.flush();
+        std::mem::replace(&mut state.was_open, true)
+    };
+    emit(shared, Event::Open);
+    if reconnected {
+        emit(shared, Event::Reconnect);
+    }
+}
+
+// A frame arrived: Pongs and errors settle the pings waiting for them, and
+// what the server pushes is passed on. Any frame shows the connection is alive.
+fn received(shared: &Rc<RefCell<State>>, data: JsValue) {
+    {
+        let mut state = shared.borrow_mut();
+        state.last_received_ms = now_ms();
+        state.stale_timer = None;
+        state.stale = false;
+    }
+    match decode_frame(data) {
+        Some(Frame::Pong(pong)) => {
+            let resolved = shared.borrow_mut().core.resolve(&pong, now_ms());
+            let rtt_ms = resolved.map(|(rtt_ms, waiting)| {
+                waiting.resolve(&pong, rtt_ms);
+                rtt_ms
+            });
+            emit(shared, Event::Pong(pending::to_js(&pong, rtt_ms)));
+        }
+        Some(Frame::Push(Push::Broadcast(broadcast))) => emit(shared, Event::Broadcast(to_js(&broadcast))),
+        Some(Frame::Push(Push::Heartbeat(heartbeat))) => emit(shared, Event::Broadcast(to_js(&heartbeat))),
+        Some(Frame::Push(Push::Error(reply))) => {
+            let failed = shared.borrow_mut().core.fail(&reply);
+            if let Some(waiting) = failed {
+                waiting.reject(&js_sys::Error::new(&ping_common::Error::from(reply).to_string()));
+            }
+        }
+        Some(Frame::Push(Push::KeepAlive)) | None => {}
+    }
+}
+
+// The keepalive timer fired: after `keepalive_ms` without a frame from the
+// server, ask it for one, and report the connection stale if none comes
+// within `stale_ms`. Once stale, it isn't asked again until something
+// arrives.
+fn keep_alive(shared: &Rc<RefCell<State>>) {
+    let mut state = shared.borrow_mut();
+    let idle = now_ms() - state.last_received_ms >= f64::from(state.keepalive_ms);
+    if !idle || state.stale || state.stale_timer.is_some() || state.socket.ready_state() != WebSocket::OPEN {
+        return;
+    }
+    if state.send_frame(&KeepAlive::KeepAlive).is_err() {
+        return;
+    }
+    let weak = Rc::downgrade(shared);
+    state.stale_timer = Some(Timeout::new(state.stale_ms, move || {
+        if let Some(state) = weak.upgrade() {
+            {
+                let mut state = state.borrow_mut();
+                state.stale_timer = None;
+                state.stale = true;
+            }
+            emit(&state, Event::Stale);
+        }
+    }));
+}
+
+fn failed(shared: &Rc<RefCell<State>>, error: JsValue) {
+    emit(shared, Event::Error(error));
+    schedule_reconnect(shared);
+}
+
+// The socket closed: schedule the next connection attempt, unless the
+// server refused the token
+fn closed(shared: &Rc<RefCell<State>>, event: CloseEvent) {
+    shared.borrow_mut().stale_timer = None;
+    if event.code() == UNAUTHORIZED {
+        shared.borrow_mut().shut_down(&format!("authentication failed: {}", event.reason()));
+        emit(shared, Event::AuthError(event.clone()));
+    }
+    emit(shared, Event::Close(event));
+    schedule_reconnect(shared);
+}
+
+// A WebTransport session failed before it opened. Unless the client has been
+// connected before, WebTransport isn't tried again and a WebSocket takes its
+// place right away; otherwise this is a dropped connection like any other.
+#[cfg(feature = "webtransport")]
+fn transport_failed(shared: &Rc<RefCell<State>>, error: JsValue) {
+    if shared.borrow().was_open {
+        failed(shared, error);
+        closed(shared, webtransport::close_event(webtransport::ABNORMAL_CLOSURE, ""));
+        return;
+    }
+    shared.borrow_mut().endpoint.webtransport_url = None;
+    reconnect(shared);
+}
+
+impl Socket {
+    fn ready_state(&self) -> u16 {
+        match self {
+            Socket::WebSocket(ws) => ws.ready_state(),
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(session) => session.ready_state(),
+        }
+    }
+
+    fn send_str(&self, text: &str) -> Result<(), JsValue> {
+        match self {
+            Socket::WebSocket(ws) => ws.send_with_str(text),
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(session) => session.send(text),
+        }
+    }
+
+    // Only for MessagePack, which the client never uses over WebTransport
+    fn send_bytes(&self, data: &[u8]) -> Result<(), JsValue> {
+        match self {
+            Socket::WebSocket(ws) => ws.send_with_u8_array(data),
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(_) => Err(js_sys::Error::new("WebTransport carries JSON only").into()),
+        }
+    }
+
+    fn close(&self) {
+        match self {
+            Socket::WebSocket(ws) => {
+                let _ = ws.close();
+            }
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(session) => session.close(),
+        }
+    }
+
+    // Keep the socket from calling into the client from now on
+    fn detach(&self) {
+        match self {
+            Socket::WebSocket(ws) => {
+                ws.set_onopen(None);
+                ws.set_onmessage(None);
+                ws.set_onerror(None);
+                ws.set_onclose(None);
+            }
+            #[cfg(feature = "webtransport")]
+            Socket::WebTransport(session) => session.detach(),
+        }
+    }
+}
+
+// The socket stays open on the JavaScript side after the client is freed;
+// close it, and keep it from calling the handlers freed with the client
+impl Drop for State {
+    fn drop(&mut self) {
+        self.socket.detach();
+        self.socket.close();
+        self.save();
+    }
+}
+
+// Tell the page's callback for `event`, if it registered one, and the event
+// port. The state isn't borrowed during the call, so the callback may use
+// the client.
+fn emit(state: &Rc<RefCell<State>>, event: Event) {
+    let (callback, port) = event.receivers(&state.borrow().callbacks);
+    if let Some(callback) = callback {
+        event.call(&callback);
+    }
+    if let Some(port) = port {
+        event.post(&port);
+    }
+}
+
+// Open a new socket after the backoff for the attempts made so far, unless
+// that is already on its way or the client is closed. The timer is kept in
+// the state, so it goes away with the client instead of firing later.
+fn schedule_reconnect(shared: &Rc<RefCell<State>>) {
+    let mut state = shared.borrow_mut();
+    if state.closed || state.reconnect_timer.is_some() {
+        return;
+    }
+    let delay = state.backoff.next_delay().expect("reconnecting is retried forever");
+    let weak = Rc::downgrade(shared);
+    state.reconnect_timer = Some(Timeout::new(delay.as_millis() as u32, move || {
+        if let Some(state) = weak.upgrade() {
+            reconnect(&state);
+        }
+    }));
+}
+
+// Replace the closed socket with a new one to the same URL. A refused
+// connection closes the new socket, which schedules the next attempt.
+fn reconnect(shared: &Rc<RefCell<State>>) {
+    let (socket, _fired) = {
+        let mut state = shared.borrow_mut();
+        if state.closed {
+            return;
+        }
+        (state.endpoint.open(), state.reconnect_timer.take())
+    };
+    match socket {
+        Ok(socket) => {
+            let mut state = shared.borrow_mut();
+            state.socket.detach();
+            state.socket.close();
+            if let Some(handlers) = &state.handlers {
+                handlers.attach(&socket);
+            }
+            state.socket = socket;
+        }
+        Err(e) => {
+            emit(shared, Event::Error(e));
+            schedule_reconnect(shared);
+        }
+    }
+}
+
+// `url` with the query parameter `name` set to `value`
+fn with_param(url: &str, name: &str, value: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}={}", url, separator, name, js_sys::encode_uri_component(value))
+}
+
+impl Endpoint {
+    // A new socket: a WebTransport session while that is to be tried, and
+    // the browser has WebTransport at all, otherwise a WebSocket offering the
+    // MessagePack subprotocol if asked to
+    fn open(&mut self) -> Result<Socket, JsValue> {
+        #[cfg(feature = "webtransport")]
+        if let Some(url) = &self.webtransport_url {
+            match webtransport::Session::new(url) {
+                Ok(session) => return Ok(Socket::WebTransport(session)),
+                Err(_) => self.webtransport_url = None,
+            }
+        }
+        let ws = match self.codec {
+            Codec::Json => WebSocket::new(&self.socket_url)?,
+            Codec::Msgpack => WebSocket::new_with_str(&self.socket_url, MSGPACK_PROTOCOL)?,
         };
-        
-        // Serialize to JSON and send
-        let json = serde_json::to_string(&ping)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        
-        web_sys::console::log_1(&format!("Sending PING #{}", self.ping_count).into());
-        self.ws.send_with_str(&json)?;
-        Ok(())
+        ws.set_binary_type(BinaryType::Arraybuffer);
+        Ok(Socket::WebSocket(ws))
+    }
+}
+
+// ping-http-server's WebTransport endpoint for the WebSocket one at `url`:
+// the same host and port over https:, /ws/... becoming /wt/...
+#[cfg(feature = "webtransport")]
+fn webtransport_url(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = rest.find('/').map_or((rest, ""), |slash| rest.split_at(slash));
+    let path = match path.strip_prefix("/ws") {
+        Some(actor) if actor.is_empty() || actor.starts_with(['/', '?']) => format!("/wt{}", actor),
+        _ => path.to_string(),
+    };
+    format!("https://{}{}", host, path)
+}
+
+// What a frame from the server can carry that the client acts on. The pushes
+// are tagged with their `type`, Pongs aren't, so they are tried first.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frame {
+    Push(Push),
+    Pong(Pong),
+}
+
+// The `type` is checked here rather than by the pushes' own structs, whose
+// tag serde only writes: a Heartbeat would otherwise take any Pong with a
+// `sent_at_ms`
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Push {
+    Broadcast(Broadcast),
+    Heartbeat(Heartbeat),
+    KeepAlive,
+    Error(ErrorReply),
+}
+
+// The content of a frame: JSON text, or MessagePack in a binary frame.
+// Anything else (session info) is none of the above.
+fn decode_frame(data: JsValue) -> Option<Frame> {
+    if let Some(text) = data.as_string() {
+        return codec::json::from_str(&text).ok();
+    }
+    let buffer = data.dyn_into::<js_sys::ArrayBuffer>().ok()?;
+    Msgpack::decode(&js_sys::Uint8Array::new(&buffer).to_vec()).ok()
+}
+
+fn to_js(value: &impl serde::Serialize) -> JsValue {
+    serde_wasm_bindgen::to_value(value).unwrap_or(JsValue::NULL)
+}
+
+// Milliseconds on a monotonic clock: `performance.now()`, which pages and
+// workers both have, or the wall clock without it
+fn now_ms() -> f64 {
+    js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+        .ok()
+        .and_then(|performance| performance.dyn_into::<web_sys::Performance>().ok())
+        .map_or_else(js_sys::Date::now, |performance| performance.now())
+}
+
+// WebSocket URL for `url` as given to the constructor. Paths, and no URL at
+// all, are resolved against the location of the page or worker the client
+// runs in; without one (Node) the old default of the local server is used.
+fn server_url(url: Option<&str>) -> Result<String, JsValue> {
+    let path = match url {
+        Some(url) if !url.starts_with('/') => return Ok(url.to_string()),
+        Some(path) => path,
+        None => DEFAULT_PATH,
+    };
+    // `location` is a Location in a page and a WorkerLocation in a worker;
+    // both have the protocol and host
+    let location = js_sys::Reflect::get(&js_sys::global(), &"location".into())?;
+    if location.is_undefined() {
+        return Ok(format!("ws://localhost:8080{}", path));
     }
-}
\ No newline at end of file
+    let field = |name: &str| js_sys::Reflect::get(&location, &name.into()).map(|value| value.as_string().unwrap_or_default());
+    let scheme = if field("protocol")? == "https:" { "wss" } else { "ws" };
+    Ok(format!("{}://{}{}", scheme, field("host")?, path))
+}
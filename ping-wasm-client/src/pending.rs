@@ -0,0 +1,90 @@
+// Pings awaiting their Pong
+//
+// Every Promise `send_ping` hands out is settled from here: resolved with the
+// Pong carrying its sequence number and the round trip it took, or rejected
+// when no such Pong arrives in time. ping-common's `PingClientCore` keeps the
+// waiting pings and matches the Pongs to them; what it keeps for each is a
+// `Waiting`. A Pong that comes after its ping timed out is dropped. The
+// auto-pings wait there too, only without a Promise.
+
+use gloo_timers::callback::Timeout;
+use js_sys::Function;
+use ping_common::client::PingClientCore;
+use ping_common::Pong;
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use std::collections::BTreeMap;
+use wasm_bindgen::JsValue;
+
+pub type Core = PingClientCore<Waiting>;
+
+/// The resolve and reject functions of a ping's Promise
+pub struct Resolvers {
+    pub resolve: Function,
+    pub reject: Function,
+}
+
+impl Resolvers {
+    pub fn reject(&self, reason: &JsValue) {
+        let _ = self.reject.call1(&JsValue::NULL, reason);
+    }
+}
+
+pub struct Waiting {
+    // None for the auto-pings, which have no Promise
+    resolvers: Option<Resolvers>,
+    // Dropping the timer cancels it
+    _timeout: Timeout,
+}
+
+impl Waiting {
+    /// `timeout` rejects the ping unless its Pong comes first
+    pub fn new(resolvers: Option<Resolvers>, timeout: Timeout) -> Self {
+        Waiting { resolvers, _timeout: timeout }
+    }
+
+    pub fn resolve(self, pong: &Pong, rtt_ms: f64) {
+        if let Some(resolvers) = self.resolvers {
+            let _ = resolvers.resolve.call1(&JsValue::NULL, &to_js(pong, Some(rtt_ms)));
+        }
+    }
+
+    pub fn reject(self, reason: &JsValue) {
+        if let Some(resolvers) = self.resolvers {
+            resolvers.reject(reason);
+        }
+    }
+}
+
+// A Pong as JavaScript sees it, with the round trip when it is known
+#[derive(Serialize)]
+struct JsPong<'a> {
+    message: &'a str,
+    sequence: u64,
+    total_pings: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sent_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rtt_ms: Option<f64>,
+}
+
+pub fn to_js(pong: &Pong, rtt_ms: Option<f64>) -> JsValue {
+    let pong = JsPong {
+        message: &pong.message,
+        sequence: pong.sequence,
+        total_pings: pong.total_pings,
+        client_id: pong.client_id.as_deref(),
+        correlation_id: pong.correlation_id.map(|id| id.to_string()),
+        sent_at_ms: pong.sent_at_ms,
+        metadata: &pong.metadata,
+        rtt_ms,
+    };
+    // The metadata as a plain object, as the .d.ts has it, not a Map
+    pong.serialize(&Serializer::new().serialize_maps_as_objects(true)).unwrap_or(JsValue::NULL)
+}
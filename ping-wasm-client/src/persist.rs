@@ -0,0 +1,101 @@
+// Saved stats
+//
+// With `persist_key` the client keeps its counts, its round trips and a
+// session id in localStorage under that key, and a client created with the
+// same key, as after a page reload, carries on from them. The session id goes
+// on the WebSocket URL as `?session=`, so ping-http-server resumes the session
+// and replays what it pushed in between. The stats are saved at most once a
+// second while they change, and right away when the client is closed or
+// freed. Where there is no localStorage (a worker, or storage turned off),
+// nothing is saved and the client starts afresh.
+
+use std::cell::Cell;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
+use web_sys::Storage;
+use ping_common::client::Counts;
+use ping_common::latency::LatencyStats;
+
+// What is kept under the key
+#[derive(Serialize, Deserialize, Default)]
+pub struct Saved {
+    pub session_id: String,
+    pub counts: Counts,
+    pub rtt: LatencyStats,
+}
+
+// The same, borrowed from the client for saving
+#[derive(Serialize)]
+struct Saving<'a> {
+    session_id: &'a str,
+    counts: &'a Counts,
+    rtt: &'a LatencyStats,
+}
+
+pub struct Persist {
+    storage: Storage,
+    key: String,
+    pub session_id: String,
+    // The counts when last saved; the stats only change with them
+    saved_counts: Cell<Counts>,
+}
+
+impl Persist {
+    // The store under `key` and what it holds: the saved stats, or a new
+    // session id and no stats when there are none yet. None without
+    // localStorage.
+    pub fn open(key: &str) -> Option<(Persist, Saved)> {
+        let storage = js_sys::Reflect::get(&js_sys::global(), &"localStorage".into())
+            .ok()?
+            .dyn_into::<Storage>()
+            .ok()?;
+        let saved = storage
+            .get_item(key)
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str::<Saved>(&json).ok())
+            .filter(|saved| is_valid_id(&saved.session_id));
+        let fresh = saved.is_none();
+        let saved = saved.unwrap_or_else(|| Saved { session_id: new_session_id(), ..Saved::default() });
+        let persist = Persist {
+            storage,
+            key: key.to_string(),
+            session_id: saved.session_id.clone(),
+            saved_counts: Cell::new(saved.counts),
+        };
+        // The session id is kept from the start, so a reload before the
+        // first ping still resumes the session
+        if fresh {
+            persist.write(&saved.counts, &saved.rtt);
+        }
+        Some((persist, saved))
+    }
+
+    // Save the stats if they changed since the last time
+    pub fn save(&self, counts: &Counts, rtt: &LatencyStats) {
+        if *counts != self.saved_counts.get() {
+            self.write(counts, rtt);
+        }
+    }
+
+    fn write(&self, counts: &Counts, rtt: &LatencyStats) {
+        let saving = Saving { session_id: &self.session_id, counts, rtt };
+        let Ok(json) = serde_json::to_string(&saving) else { return };
+        // Fails when the storage is full; the next save tries again
+        if self.storage.set_item(&self.key, &json).is_ok() {
+            self.saved_counts.set(*counts);
+        }
+    }
+}
+
+// As ping-http-server accepts them
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// 16 random hex digits; only this browser needs to be told apart from the
+// server's other clients
+fn new_session_id() -> String {
+    let half = || (js_sys::Math::random() * f64::from(u32::MAX)) as u32;
+    format!("{:08x}{:08x}", half(), half())
+}
@@ -0,0 +1,102 @@
+// Connection pool
+//
+// Several clients to the same server behind one object, for load testing the
+// server's connection handling from a browser tab. Each client is a full
+// WasmPingClient with its own socket, reconnection and sequence numbers; the
+// pool hands every ping to the next one in turn, skipping those that are
+// down while others are up, and adds their counts and round trips together.
+
+use std::cell::Cell;
+use wasm_bindgen::prelude::*;
+use crate::stats;
+use ping_common::client::Counts;
+use ping_common::latency::LatencyStats;
+use crate::{ClientOptions, JsClientOptions, WasmPingClient};
+
+/// `size` WasmPingClients to one server, sharing out the pings
+#[wasm_bindgen]
+pub struct WasmPingClientPool {
+    clients: Vec<WasmPingClient>,
+    // Where the search for the client of the next ping starts
+    next: Cell<usize>,
+}
+
+#[wasm_bindgen]
+impl WasmPingClientPool {
+    /// Open `size` connections to `url`, each as `new WasmPingClient(url,
+    /// options)` would
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        size: usize,
+        url: Option<String>,
+        options: Option<JsClientOptions>,
+    ) -> Result<WasmPingClientPool, JsValue> {
+        console_error_panic_hook::set_once();
+        if size == 0 {
+            return Err(js_sys::Error::new("a pool needs at least one connection").into());
+        }
+        let options = ClientOptions::from_js(options)?;
+        if options.persist_key.is_some() {
+            return Err(js_sys::Error::new("a pool's clients can't share a persist_key").into());
+        }
+        let clients = (0..size)
+            .map(|_| WasmPingClient::connect(url.clone(), options.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(WasmPingClientPool { clients, next: Cell::new(0) })
+    }
+
+    /// Number of connections in the pool
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Number of connections open right now
+    #[wasm_bindgen(getter)]
+    pub fn connected(&self) -> usize {
+        self.clients.iter().filter(|client| client.is_connected()).count()
+    }
+
+    /// Send a ping over the next connection in turn, as `send_ping()` of a
+    /// single client does. Connections that are down are passed over while
+    /// any is open; with none open, the ping waits in the next one's queue.
+    #[wasm_bindgen(unchecked_return_type = "Promise<Pong>")]
+    pub fn send_ping(&mut self, message: Option<String>) -> js_sys::Promise {
+        let size = self.clients.len();
+        let start = self.next.get();
+        let chosen = (start..start + size)
+            .map(|i| i % size)
+            .find(|&i| self.clients[i].is_connected())
+            .unwrap_or(start % size);
+        self.next.set((chosen + 1) % size);
+        self.clients[chosen].send_ping(message)
+    }
+
+    /// Call `callback(pong)` with every Pong any of the connections receives
+    pub fn on_pong(&self, #[wasm_bindgen(unchecked_param_type = "(pong: Pong) => void")] callback: js_sys::Function) {
+        for client in &self.clients {
+            client.on_pong(callback.clone());
+        }
+    }
+
+    /// `stats()` over all connections together: the counts are added up,
+    /// and the round trips pooled
+    #[wasm_bindgen(unchecked_return_type = "Stats")]
+    pub fn stats(&self) -> JsValue {
+        let mut counts = Counts::default();
+        let mut rtt = LatencyStats::default();
+        for client in &self.clients {
+            let state = client.state.borrow();
+            counts.add(state.core.counts());
+            rtt.merge(state.core.rtt());
+        }
+        stats::summary_to_js(&rtt, &counts)
+    }
+
+    /// Close every connection for good, as `close()` does for one client
+    pub fn close(&self) {
+        for client in &self.clients {
+            client.close();
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// Round-trip statistics
+//
+// The client times every ping from sending it to the Pong answering it, and
+// keeps running figures over all of them, so pages can chart latency without
+// matching pongs to pings themselves. The figures come from ping-common's
+// `LatencyStats`, the histogram the CLI and the server report from too, so a
+// page's p95 is computed like theirs, and its memory stays flat in a soak
+// test. Next to them are the counts of pings sent, Pongs received and pings
+// that failed. A pool adds up those of its clients, and `persist_key` keeps
+// them across page reloads. This module hands them to JavaScript.
+
+use ping_common::client::Counts;
+use ping_common::latency::LatencyStats;
+use std::time::Duration;
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::JsValue;
+
+// What `rtt_stats` hands to JavaScript; the times are null before the first
+// Pong
+#[derive(Serialize)]
+struct Snapshot {
+    count: u64,
+    min_ms: Option<f64>,
+    avg_ms: Option<f64>,
+    max_ms: Option<f64>,
+    last_ms: Option<f64>,
+}
+
+// What `stats()` hands to JavaScript
+#[derive(Serialize)]
+struct Summary {
+    sent: u64,
+    received: u64,
+    errors: u64,
+    min_ms: Option<f64>,
+    avg_ms: Option<f64>,
+    p95_ms: Option<f64>,
+    max_ms: Option<f64>,
+}
+
+// Times go to JavaScript in milliseconds
+fn ms(time: Option<Duration>) -> Option<f64> {
+    time.map(|time| time.as_secs_f64() * 1000.0)
+}
+
+pub fn summary_to_js(rtt: &LatencyStats, counts: &Counts) -> JsValue {
+    let summary = Summary {
+        sent: counts.sent,
+        received: counts.received,
+        errors: counts.errors,
+        min_ms: ms(rtt.min()),
+        avg_ms: ms(rtt.mean()),
+        p95_ms: ms(rtt.percentile(0.95)),
+        max_ms: ms(rtt.max()),
+    };
+    summary
+        .serialize(&Serializer::new().serialize_missing_as_null(true))
+        .unwrap_or(JsValue::NULL)
+}
+
+pub fn to_js(rtt: &LatencyStats) -> JsValue {
+    let snapshot = Snapshot {
+        count: rtt.count(),
+        min_ms: ms(rtt.min()),
+        avg_ms: ms(rtt.mean()),
+        max_ms: ms(rtt.max()),
+        last_ms: ms(rtt.last()),
+    };
+    snapshot
+        .serialize(&Serializer::new().serialize_missing_as_null(true))
+        .unwrap_or(JsValue::NULL)
+}
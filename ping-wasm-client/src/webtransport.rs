@@ -0,0 +1,207 @@
+// WebTransport sessions
+//
+// With the `webtransport` feature and `transport: "webtransport"` the client
+// talks to ping-http-server's HTTP/3 endpoint (`--webtransport`) instead of
+// its WebSocket one: a session to `/wt` with a single bidirectional stream,
+// on which each side writes one JSON frame per line. The session reports to
+// the client through the same functions as a WebSocket's handlers, with a
+// CloseEvent made up to match when it ends. web-sys only has WebTransport
+// behind `web_sys_unstable_apis`, so the few parts used are bound here.
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use js_sys::{Promise, Reflect, Uint8Array};
+use ping_common::ErrorReply;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    CloseEvent, CloseEventInit, ReadableStream, ReadableStreamDefaultReader, WebSocket, WritableStream,
+    WritableStreamDefaultWriter,
+};
+use crate::State;
+
+#[wasm_bindgen]
+extern "C" {
+    #[derive(Clone)]
+    type WebTransport;
+
+    #[wasm_bindgen(constructor, catch)]
+    fn new(url: &str) -> Result<WebTransport, JsValue>;
+
+    #[wasm_bindgen(method, getter)]
+    fn ready(this: &WebTransport) -> Promise;
+
+    #[wasm_bindgen(method, getter)]
+    fn closed(this: &WebTransport) -> Promise;
+
+    #[wasm_bindgen(method, js_name = createBidirectionalStream)]
+    fn create_bidirectional_stream(this: &WebTransport) -> Promise;
+
+    #[wasm_bindgen(method)]
+    fn close(this: &WebTransport);
+
+    type BidirectionalStream;
+
+    #[wasm_bindgen(method, getter)]
+    fn readable(this: &BidirectionalStream) -> ReadableStream;
+
+    #[wasm_bindgen(method, getter)]
+    fn writable(this: &BidirectionalStream) -> WritableStream;
+}
+
+// Close code for a session that ended without one, as for a WebSocket
+pub const ABNORMAL_CLOSURE: u16 = 1006;
+
+pub struct Session {
+    transport: WebTransport,
+    link: Rc<Link>,
+}
+
+// What the session shares with the task reading its stream
+struct Link {
+    // As WebSocket.readyState
+    ready_state: Cell<u16>,
+    // Set once the stream is open
+    writer: RefCell<Option<WritableStreamDefaultWriter>>,
+    // Cleared when the client lets go of the session, which then reports
+    // nothing more
+    attached: Cell<bool>,
+    // Swallows the rejections of writes; a broken stream ends the session,
+    // which is reported then
+    ignore: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Session {
+    /// A session to `url`; fails where the browser has no WebTransport
+    pub fn new(url: &str) -> Result<Session, JsValue> {
+        let link = Link {
+            ready_state: Cell::new(WebSocket::CONNECTING),
+            writer: RefCell::new(None),
+            attached: Cell::new(false),
+            ignore: Closure::new(|_| {}),
+        };
+        Ok(Session { transport: WebTransport::new(url)?, link: Rc::new(link) })
+    }
+
+    /// Start reporting to the client: the stream opening, every line the
+    /// server sends and the end of the session
+    pub fn attach(&self, state: Weak<RefCell<State>>) {
+        self.link.attached.set(true);
+        wasm_bindgen_futures::spawn_local(run(self.transport.clone(), self.link.clone(), state));
+    }
+
+    pub fn detach(&self) {
+        self.link.attached.set(false);
+    }
+
+    pub fn ready_state(&self) -> u16 {
+        self.link.ready_state.get()
+    }
+
+    /// Write `text` as a line on the stream
+    pub fn send(&self, text: &str) -> Result<(), JsValue> {
+        let writer = self.link.writer.borrow();
+        let Some(writer) = writer.as_ref().filter(|_| self.ready_state() == WebSocket::OPEN) else {
+            return Err(js_sys::Error::new("the WebTransport session is not open").into());
+        };
+        let line = Uint8Array::from(format!("{}\n", text).as_bytes());
+        let _ = writer.write_with_chunk(&line).catch(&self.link.ignore);
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        if self.ready_state() < WebSocket::CLOSING {
+            self.link.ready_state.set(WebSocket::CLOSING);
+            self.transport.close();
+        }
+    }
+}
+
+// Open the stream and read it until the session ends
+async fn run(transport: WebTransport, link: Rc<Link>, state: Weak<RefCell<State>>) {
+    // Listening from the start, so a failed session's rejection is handled
+    let closed = JsFuture::from(transport.closed());
+    let reader = match open_stream(&transport).await {
+        Ok((writer, reader)) => {
+            *link.writer.borrow_mut() = Some(writer);
+            link.ready_state.set(WebSocket::OPEN);
+            report(&link, &state, crate::opened);
+            reader
+        }
+        Err(error) => {
+            link.ready_state.set(WebSocket::CLOSED);
+            report(&link, &state, |state| crate::transport_failed(state, error));
+            return;
+        }
+    };
+
+    // ping-http-server sends an `unauthorized` error before closing the
+    // session over the token
+    let mut refused = None;
+    let mut buffer = Vec::new();
+    while let Ok(chunk) = JsFuture::from(reader.read()).await {
+        if Reflect::get(&chunk, &"done".into()).map_or(true, |done| done.is_truthy()) {
+            break;
+        }
+        let Ok(value) = Reflect::get(&chunk, &"value".into()) else { break };
+        buffer.extend(Uint8Array::new(&value).to_vec());
+        while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let Ok(text) = String::from_utf8(line) else { continue };
+            match serde_json::from_str::<ErrorReply>(&text) {
+                Ok(error) if error.code == "unauthorized" => refused = Some(error.message),
+                _ => report(&link, &state, |state| crate::received(state, text.trim_end().into())),
+            }
+        }
+    }
+
+    link.writer.borrow_mut().take();
+    link.ready_state.set(WebSocket::CLOSED);
+    let event = match (refused, closed.await) {
+        (Some(reason), _) => close_event(crate::UNAUTHORIZED, &reason),
+        // A clean close has code 0 here, but 1000 for a WebSocket
+        (None, Ok(info)) => {
+            let field = |name: &str| Reflect::get(&info, &name.into()).unwrap_or(JsValue::UNDEFINED);
+            let code = field("closeCode").as_f64().map_or(0, |code| code as u16);
+            close_event(if code == 0 { 1000 } else { code }, &field("reason").as_string().unwrap_or_default())
+        }
+        (None, Err(error)) => {
+            report(&link, &state, |state| crate::failed(state, error));
+            close_event(ABNORMAL_CLOSURE, "")
+        }
+    };
+    report(&link, &state, |state| crate::closed(state, event));
+}
+
+async fn open_stream(transport: &WebTransport) -> Result<(WritableStreamDefaultWriter, ReadableStreamDefaultReader), JsValue> {
+    JsFuture::from(transport.ready()).await?;
+    let stream: BidirectionalStream = JsFuture::from(transport.create_bidirectional_stream()).await?.unchecked_into();
+    let writer = stream.writable().get_writer()?;
+    let reader = stream.readable().get_reader().unchecked_into();
+    Ok((writer, reader))
+}
+
+// Hand something to the client, unless it let go of the session
+fn report(link: &Link, state: &Weak<RefCell<State>>, to_client: impl FnOnce(&Rc<RefCell<State>>)) {
+    if link.attached.get() {
+        if let Some(state) = state.upgrade() {
+            to_client(&state);
+        }
+    }
+}
+
+/// A CloseEvent as a WebSocket closed with `code` and `reason` would fire
+pub fn close_event(code: u16, reason: &str) -> CloseEvent {
+    let init = CloseEventInit::new();
+    init.set_code(code);
+    init.set_reason(reason);
+    init.set_was_clean(code == 1000);
+    CloseEvent::new_with_event_init_dict("close", &init).unwrap_or_else(|_| {
+        // No CloseEvent outside browsers (Node); an object with its fields
+        let event = js_sys::Object::new();
+        let _ = Reflect::set(&event, &"code".into(), &code.into());
+        let _ = Reflect::set(&event, &"reason".into(), &reason.into());
+        let _ = Reflect::set(&event, &"wasClean".into(), &(code == 1000).into());
+        event.unchecked_into()
+    })
+}
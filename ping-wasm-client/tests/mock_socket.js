@@ -0,0 +1,333 @@
+// Stand-in for WebSocket
+//
+// Installed in place of the global WebSocket, so the client's tests need no
+// server. Every socket opens on the next tick and answers each JSON Ping with
+// a Pong, and each keepalive with itself, the way ping-http-server does. The
+// tests steer it from there: hold the Pongs back and release them out of
+// order, stay silent, drop the connection, refuse the next one, require a
+// token, or deliver a frame of their own. `install_transport()` adds a
+// WebTransport that answers the same way over its stream, one JSON frame per
+// line, and `install_storage()` a localStorage.
+
+const OPEN = 1;
+const CLOSED = 3;
+
+let sockets = [];
+let totalPings = 0;
+let hold = false;
+let held = [];
+let silent = false;
+let refuseNext = false;
+let requiredToken = null;
+let transports = [];
+let transportLines = [];
+let refuseTransport = false;
+
+// CloseEvent isn't a global everywhere (Node has none)
+function closeEvent(code, reason = '') {
+    const event = new Event('close');
+    event.code = code;
+    event.reason = reason;
+    event.wasClean = code === 1000;
+    return event;
+}
+
+class MockSocket {
+    constructor(url, protocols) {
+        this.url = url;
+        this.protocols = protocols === undefined ? [] : [].concat(protocols);
+        this.protocol = this.protocols[0] ?? '';
+        this.readyState = 0;
+        this.binaryType = 'blob';
+        this.sent = [];
+        this.authenticated = new URL(url).searchParams.get('token') === requiredToken;
+        sockets.push(this);
+        setTimeout(() => {
+            if (refuseNext) {
+                refuseNext = false;
+                this.readyState = CLOSED;
+                this.onerror?.(new Event('error'));
+                this.onclose?.(closeEvent(1006));
+                return;
+            }
+            this.readyState = OPEN;
+            this.onopen?.(new Event('open'));
+        }, 0);
+    }
+
+    send(data) {
+        if (this.readyState !== OPEN) {
+            throw new Error('InvalidStateError: the socket is not open');
+        }
+        // A view into the wasm memory doesn't keep its contents, so copy it
+        // as a real socket would
+        this.sent.push(typeof data === 'string' ? data : data.slice());
+        if (requiredToken !== null && !this.authenticated) {
+            const auth = typeof data === 'string' ? JSON.parse(data) : {};
+            if (auth.type === 'auth' && auth.token === requiredToken) {
+                this.authenticated = true;
+            } else if (!this.refused) {
+                // The server's close frame takes a moment to arrive
+                this.refused = true;
+                setTimeout(() => {
+                    this.readyState = CLOSED;
+                    this.onclose?.(closeEvent(4401, 'invalid token'));
+                }, 0);
+            }
+            return;
+        }
+        if (silent || typeof data !== 'string') {
+            return;
+        }
+        const ping = JSON.parse(data);
+        if (ping.type === 'keepalive') {
+            setTimeout(() => this.receive(data), 0);
+            return;
+        }
+        totalPings += 1;
+        const pong = JSON.stringify({
+            message: `Pong: ${ping.message}`,
+            sequence: ping.sequence,
+            total_pings: totalPings,
+            client_id: ping.client_id,
+            correlation_id: ping.correlation_id,
+            sent_at_ms: ping.sent_at_ms,
+            metadata: ping.metadata,
+        });
+        if (hold) {
+            held.push([this, pong]);
+        } else {
+            setTimeout(() => this.receive(pong), 0);
+        }
+    }
+
+    close() {
+        if (this.readyState === CLOSED) {
+            return;
+        }
+        this.readyState = CLOSED;
+        setTimeout(() => this.onclose?.(closeEvent(1000)), 0);
+    }
+
+    receive(data) {
+        if (this.readyState === OPEN) {
+            this.onmessage?.({ data });
+        }
+    }
+}
+
+MockSocket.CONNECTING = 0;
+MockSocket.OPEN = OPEN;
+MockSocket.CLOSING = 2;
+MockSocket.CLOSED = CLOSED;
+
+// Stand-in for WebTransport, with a ReadableStream and WritableStream for
+// the bidirectional stream
+class MockTransport {
+    constructor(url) {
+        this.url = url;
+        this.authenticated = new URL(url).searchParams.get('token') === requiredToken;
+        transports.push(this);
+        this.closed = new Promise((resolve, reject) => {
+            this.resolveClosed = resolve;
+            this.rejectClosed = reject;
+        });
+        if (refuseTransport) {
+            this.ready = Promise.reject(new Error('WebTransport connection rejected'));
+            this.rejectClosed(new Error('WebTransport connection rejected'));
+        } else {
+            this.ready = Promise.resolve();
+        }
+    }
+
+    async createBidirectionalStream() {
+        const decoder = new TextDecoder();
+        const readable = new ReadableStream({ start: controller => { this.controller = controller; } });
+        const writable = new WritableStream({
+            write: chunk => {
+                for (const line of decoder.decode(chunk).split('\n').filter(line => line)) {
+                    this.receiveLine(line);
+                }
+            },
+        });
+        return { readable, writable };
+    }
+
+    receiveLine(line) {
+        transportLines.push(line);
+        if (requiredToken !== null && !this.authenticated) {
+            const auth = JSON.parse(line);
+            if (auth.type === 'auth' && auth.token === requiredToken) {
+                this.authenticated = true;
+                return;
+            }
+            // ping-http-server's error line, then the session's end
+            this.send(JSON.stringify({ type: 'error', code: 'unauthorized', message: 'invalid token' }));
+            this.controller.close();
+            this.rejectClosed(new Error('connection closed by peer'));
+            return;
+        }
+        const ping = JSON.parse(line);
+        if (ping.type === 'keepalive') {
+            this.send(line);
+            return;
+        }
+        totalPings += 1;
+        this.send(JSON.stringify({
+            message: `Pong: ${ping.message}`,
+            sequence: ping.sequence,
+            total_pings: totalPings,
+            client_id: ping.client_id,
+            correlation_id: ping.correlation_id,
+            sent_at_ms: ping.sent_at_ms,
+            metadata: ping.metadata,
+        }));
+    }
+
+    send(line) {
+        this.controller.enqueue(new TextEncoder().encode(line + '\n'));
+    }
+
+    close() {
+        this.controller?.close();
+        this.resolveClosed({ closeCode: 0, reason: '' });
+    }
+}
+
+// Stand-in for localStorage, which Node lacks
+class MockStorage {
+    constructor() {
+        this.items = new Map();
+    }
+
+    getItem(key) {
+        return this.items.get(key) ?? null;
+    }
+
+    setItem(key, value) {
+        this.items.set(key, String(value));
+    }
+}
+
+// Replace the global WebSocket and start over, without WebTransport or
+// localStorage
+export function install() {
+    globalThis.WebSocket = MockSocket;
+    delete globalThis.WebTransport;
+    delete globalThis.localStorage;
+    delete globalThis.location;
+    sockets = [];
+    transports = [];
+    transportLines = [];
+    refuseTransport = false;
+    totalPings = 0;
+    hold = false;
+    held = [];
+    silent = false;
+    refuseNext = false;
+    requiredToken = null;
+}
+
+export function socket_count() {
+    return sockets.length;
+}
+
+// URL of the latest socket
+export function socket_url() {
+    return sockets[sockets.length - 1].url;
+}
+
+// Subprotocols the latest socket was opened with
+export function protocols() {
+    return sockets[sockets.length - 1].protocols;
+}
+
+// Frames sent over all sockets so far, strings or Uint8Arrays
+export function sent() {
+    return sockets.flatMap(socket => socket.sent);
+}
+
+// How many frames each socket has sent, in the order they were opened
+export function sent_counts() {
+    return sockets.map(socket => socket.sent.length);
+}
+
+export function set_hold(value) {
+    hold = value;
+}
+
+// Deliver the held Pongs, last one first
+export function release_reversed() {
+    for (const [socket, pong] of held.reverse()) {
+        socket.receive(pong);
+    }
+    held = [];
+}
+
+export function set_silent(value) {
+    silent = value;
+}
+
+export function refuse_next() {
+    refuseNext = true;
+}
+
+// Close sockets that don't present `token`, in the URL or an auth frame,
+// with 4401 as ping-http-server does
+export function require_token(token) {
+    requiredToken = token;
+}
+
+// Close the latest socket from the server's side
+export function drop_connection() {
+    const socket = sockets[sockets.length - 1];
+    socket.readyState = CLOSED;
+    socket.onclose?.(closeEvent(1006));
+}
+
+// Deliver `data` on the latest socket
+export function deliver(data) {
+    sockets[sockets.length - 1].receive(data);
+}
+
+// Pretend to run in a page or worker at `protocol//host`
+export function set_location(protocol, host) {
+    globalThis.location = { protocol, host };
+}
+
+// Provide a global WebTransport as well
+export function install_transport() {
+    globalThis.WebTransport = MockTransport;
+}
+
+// Fail every WebTransport session before it is ready
+export function refuse_transport() {
+    refuseTransport = true;
+}
+
+// URLs of the WebTransport sessions so far
+export function transport_urls() {
+    return transports.map(transport => transport.url);
+}
+
+// Lines sent over all WebTransport streams so far
+export function transport_lines() {
+    return transportLines;
+}
+
+// End the latest WebTransport session from the server's side
+export function drop_transport() {
+    const transport = transports[transports.length - 1];
+    transport.controller.close();
+    transport.rejectClosed(new Error('connection lost'));
+}
+
+// Add an empty localStorage, kept until the next `install()`
+export function install_storage() {
+    globalThis.Storage = MockStorage;
+    globalThis.localStorage = new MockStorage();
+}
+
+export function set_stored(key, value) {
+    localStorage.setItem(key, value);
+}
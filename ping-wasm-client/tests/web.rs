@@ -0,0 +1,643 @@
+// WasmPingClient tests
+//
+// Run with `wasm-pack test --node` (or `--headless --firefox` for a browser)
+// from ping-wasm-client. The client talks to mock_socket.js instead of a
+// server, which stands in for the global WebSocket and answers Pings the way
+// ping-http-server does.
+
+#![cfg(target_arch = "wasm32")]
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gloo_timers::future::TimeoutFuture;
+use ping_common::{Error, Ping, Pong, Uuid};
+use ping_common::codec::{msgpack::Msgpack, Codec};
+use ping_wasm_client::{JsClientOptions, WasmPingClient, WasmPingClientPool};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen(module = "/tests/mock_socket.js")]
+extern "C" {
+    fn install();
+    fn socket_count() -> u32;
+    fn socket_url() -> String;
+    fn protocols() -> Vec<String>;
+    fn sent() -> js_sys::Array;
+    fn sent_counts() -> Vec<u32>;
+    fn set_hold(value: bool);
+    fn release_reversed();
+    fn set_silent(value: bool);
+    fn refuse_next();
+    fn require_token(token: &str);
+    fn drop_connection();
+    fn deliver(data: &JsValue);
+    fn set_location(protocol: &str, host: &str);
+    fn install_storage();
+    fn set_stored(key: &str, value: &str);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen(module = "/tests/mock_socket.js")]
+extern "C" {
+    fn install_transport();
+    fn refuse_transport();
+    fn transport_urls() -> Vec<String>;
+    fn transport_lines() -> Vec<String>;
+    fn drop_transport();
+}
+
+const URL: &str = "ws://mock/ws";
+
+// A client of a freshly installed mock, connected unless the mock is told
+// otherwise first
+async fn connect(options: Option<&str>) -> WasmPingClient {
+    let options = options.map(|json| js_sys::JSON::parse(json).unwrap().unchecked_into::<JsClientOptions>());
+    let client = WasmPingClient::new(Some(URL.to_string()), options).unwrap();
+    TimeoutFuture::new(10).await;
+    client
+}
+
+async fn pong(promise: js_sys::Promise) -> Result<Pong, JsValue> {
+    let pong = JsFuture::from(promise).await?;
+    Ok(serde_wasm_bindgen::from_value(pong).unwrap())
+}
+
+async fn error_message(promise: js_sys::Promise) -> String {
+    let error = JsFuture::from(promise).await.unwrap_err();
+    error.unchecked_into::<js_sys::Error>().message().into()
+}
+
+// A callback for `on_*` counting its calls; the closure has to outlive the
+// client's use of it
+fn counter() -> (Rc<Cell<u32>>, Closure<dyn FnMut()>) {
+    let count = Rc::new(Cell::new(0));
+    let callback = Closure::new({
+        let count = count.clone();
+        move || count.set(count.get() + 1)
+    });
+    (count, callback)
+}
+
+fn function(closure: &Closure<dyn FnMut()>) -> js_sys::Function {
+    closure.as_ref().unchecked_ref::<js_sys::Function>().clone()
+}
+
+#[wasm_bindgen_test]
+fn paths_resolve_against_the_location() {
+    install();
+    assert_eq!(WasmPingClient::new(None, None).unwrap().url(), "ws://localhost:8080/ws");
+    set_location("https:", "example.com:8443");
+    assert_eq!(WasmPingClient::new(None, None).unwrap().url(), "wss://example.com:8443/ws");
+    assert_eq!(WasmPingClient::new(Some("/ws/echo".to_string()), None).unwrap().url(), "wss://example.com:8443/ws/echo");
+    assert_eq!(WasmPingClient::new(Some(URL.to_string()), None).unwrap().url(), URL);
+}
+
+#[wasm_bindgen_test]
+async fn pings_go_out_as_json() {
+    install();
+    let mut client = connect(None).await;
+    assert!(client.is_connected());
+
+    let pong = pong(client.send_ping(None)).await.unwrap();
+    assert_eq!(pong.sequence, 1);
+    assert_eq!(pong.message, "Pong: Hello from Wasm #1");
+
+    let frame = sent().get(0).as_string().unwrap();
+    let ping: Ping = serde_json::from_str(&frame).unwrap();
+    assert_eq!(ping.sequence, 1);
+    assert_eq!(ping.message, "Hello from Wasm #1");
+}
+
+#[wasm_bindgen_test]
+async fn client_id_and_first_sequence_go_into_the_pings() {
+    install();
+    let mut client = connect(Some(r#"{"client_id": "tab-1", "first_sequence": 40}"#)).await;
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 40);
+    let ping: Ping = serde_json::from_str(&sent().get(0).as_string().unwrap()).unwrap();
+    assert_eq!(ping.client_id.as_deref(), Some("tab-1"));
+    assert_eq!(ping.message, "Hello from Wasm #40");
+
+    // Left out unless set
+    install();
+    let mut client = connect(None).await;
+    pong(client.send_ping(None)).await.unwrap();
+    assert!(!sent().get(0).as_string().unwrap().contains("client_id"));
+
+    let options = js_sys::JSON::parse(r#"{"first_sequence": 0}"#).unwrap().unchecked_into();
+    assert!(WasmPingClient::new(Some(URL.to_string()), Some(options)).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn metadata_goes_into_the_pings_and_comes_back() {
+    install();
+    let mut client = connect(Some(r#"{"metadata": {"experiment": "b"}}"#)).await;
+    let promise = client.send_ping(None);
+    let ping: Ping = serde_json::from_str(&sent().get(0).as_string().unwrap()).unwrap();
+    assert_eq!(ping.metadata.get("experiment").map(String::as_str), Some("b"));
+
+    // A plain object for JavaScript
+    let pong = JsFuture::from(promise).await.unwrap();
+    let metadata = js_sys::Reflect::get(&pong, &"metadata".into()).unwrap();
+    assert!(!metadata.is_instance_of::<js_sys::Map>());
+    assert_eq!(js_sys::Reflect::get(&metadata, &"experiment".into()).unwrap(), "b");
+}
+
+#[wasm_bindgen_test]
+async fn traced_pings_carry_the_trace_context() {
+    install();
+    let mut client = connect(Some(r#"{"metadata": {"experiment": "b"}}"#)).await;
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let promise = client.send_traced_ping(None, traceparent.to_string());
+    let ping: Ping = serde_json::from_str(&sent().get(0).as_string().unwrap()).unwrap();
+    assert_eq!(ping.traceparent(), Some(traceparent));
+    assert_eq!(ping.metadata.get("experiment").map(String::as_str), Some("b"));
+    pong(promise).await.unwrap();
+
+    // Only that ping
+    pong(client.send_ping(None)).await.unwrap();
+    let ping: Ping = serde_json::from_str(&sent().get(1).as_string().unwrap()).unwrap();
+    assert_eq!(ping.traceparent(), None);
+}
+
+#[wasm_bindgen_test]
+async fn msgpack_codec_uses_binary_frames() {
+    install();
+    let mut client = connect(Some(r#"{"codec": "msgpack"}"#)).await;
+    assert_eq!(protocols(), ["ping.msgpack"]);
+
+    let promise = client.send_ping(None);
+    let frame = sent().get(0).unchecked_into::<js_sys::Uint8Array>().to_vec();
+    let ping: Ping = Msgpack::decode(&frame).unwrap();
+    assert_eq!(ping.sequence, 1);
+
+    let reply = Pong::answering(&ping).message("Pong").total_pings(7).build();
+    let data = js_sys::Uint8Array::from(&Msgpack::encode(&reply).unwrap()[..]);
+    deliver(&data.buffer());
+    assert_eq!(pong(promise).await.unwrap().total_pings, 7);
+}
+
+#[wasm_bindgen_test]
+fn unknown_codec_is_refused() {
+    install();
+    let options = js_sys::JSON::parse(r#"{"codec": "xml"}"#).unwrap().unchecked_into();
+    assert!(WasmPingClient::new(Some(URL.to_string()), Some(options)).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn pongs_settle_their_own_pings() {
+    install();
+    let mut client = connect(None).await;
+    set_hold(true);
+    let promises: Vec<_> = (0..3).map(|_| client.send_ping(None)).collect();
+    release_reversed();
+
+    for (promise, sequence) in promises.into_iter().zip(1..) {
+        let pong = pong(promise).await.unwrap();
+        assert_eq!(pong.sequence, sequence);
+    }
+    let count = js_sys::Reflect::get(&client.rtt_stats(), &"count".into()).unwrap();
+    assert_eq!(count.as_f64(), Some(3.0));
+}
+
+#[wasm_bindgen_test]
+async fn pongs_are_matched_by_correlation_id() {
+    install();
+    let mut client = connect(None).await;
+    set_hold(true);
+    let promise = client.send_ping(None);
+    let ping: Ping = serde_json::from_str(&sent().get(0).as_string().unwrap()).unwrap();
+    assert!(ping.correlation_id.is_some());
+    assert!(ping.sent_at_ms.is_some());
+
+    // Another client's Pong numbered the same doesn't settle the ping
+    let mut other = Pong::answering(&ping).message("Pong").total_pings(1).build();
+    other.correlation_id = Some(Uuid::new_v4());
+    deliver(&serde_json::to_string(&other).unwrap().into());
+    release_reversed();
+    let pong = pong(promise).await.unwrap();
+    assert_eq!(pong.correlation_id, ping.correlation_id);
+    assert_eq!(pong.sent_at_ms, ping.sent_at_ms);
+}
+
+#[wasm_bindgen_test]
+async fn error_replies_reject_their_ping() {
+    install();
+    let mut client = connect(None).await;
+    set_hold(true);
+    let promise = client.send_ping(None);
+    let ping: Ping = serde_json::from_str(&sent().get(0).as_string().unwrap()).unwrap();
+
+    let mut reply = Error::Actor("mailbox closed".to_string()).to_reply();
+    reply.sequence = Some(ping.sequence);
+    reply.correlation_id = ping.correlation_id;
+    deliver(&serde_json::to_string(&reply).unwrap().into());
+    assert_eq!(error_message(promise).await, "actor error: mailbox closed");
+    let errors = js_sys::Reflect::get(&client.stats(), &"errors".into()).unwrap();
+    assert_eq!(errors.as_f64(), Some(1.0));
+}
+
+#[wasm_bindgen_test]
+async fn unanswered_ping_times_out() {
+    install();
+    let mut client = connect(None).await;
+    set_silent(true);
+    client.set_timeout_ms(20);
+    assert_eq!(error_message(client.send_ping(None)).await, "PING #1 timed out after 20ms");
+}
+
+#[wasm_bindgen_test]
+async fn dropped_connection_is_reopened() {
+    install();
+    let mut client = connect(None).await;
+    let (reconnects, on_reconnect) = counter();
+    client.on_reconnect(function(&on_reconnect));
+    pong(client.send_ping(None)).await.unwrap();
+
+    drop_connection();
+    assert!(!client.is_connected());
+    TimeoutFuture::new(600).await;
+    assert_eq!(socket_count(), 2);
+    assert_eq!(reconnects.get(), 1);
+    assert!(client.is_connected());
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 2);
+}
+
+#[wasm_bindgen_test]
+async fn refused_connection_is_reported_and_retried() {
+    install();
+    refuse_next();
+    let (errors, on_error) = counter();
+    let client = WasmPingClient::new(Some(URL.to_string()), None).unwrap();
+    client.on_error(function(&on_error));
+    TimeoutFuture::new(10).await;
+    assert_eq!(errors.get(), 1);
+    assert_eq!(client.ready_state(), 3);
+
+    TimeoutFuture::new(600).await;
+    assert!(client.is_connected());
+}
+
+#[wasm_bindgen_test]
+async fn pings_wait_for_the_connection() {
+    install();
+    let mut client = WasmPingClient::new(Some(URL.to_string()), None).unwrap();
+    let promise = client.send_ping(None);
+    assert_eq!(client.queue_length(), 1);
+    assert_eq!(pong(promise).await.unwrap().sequence, 1);
+    assert_eq!(client.queue_length(), 0);
+}
+
+#[wasm_bindgen_test]
+async fn full_queue_rejects_pings() {
+    install();
+    let options = js_sys::JSON::parse(r#"{"queue_limit": 0}"#).unwrap().unchecked_into();
+    let mut client = WasmPingClient::new(Some(URL.to_string()), Some(options)).unwrap();
+    assert_eq!(error_message(client.send_ping(None)).await, "not connected, and 0 pings are queued already");
+}
+
+#[wasm_bindgen_test]
+async fn closed_client_stays_closed() {
+    install();
+    let mut client = connect(None).await;
+    set_silent(true);
+    let promise = client.send_ping(None);
+    client.close();
+
+    assert_eq!(error_message(promise).await, "client closed");
+    assert_eq!(error_message(client.send_ping(None)).await, "client closed");
+    TimeoutFuture::new(600).await;
+    assert_eq!(client.ready_state(), 3);
+    assert_eq!(socket_count(), 1);
+}
+
+#[wasm_bindgen_test]
+async fn auto_ping_sends_until_stopped() {
+    install();
+    let client = connect(None).await;
+    let (pongs, on_pong) = counter();
+    client.on_pong(function(&on_pong));
+
+    client.start_auto_ping(10).unwrap();
+    TimeoutFuture::new(55).await;
+    client.stop_auto_ping();
+    // The last ping sent may still be waiting for its Pong
+    TimeoutFuture::new(10).await;
+    let count = pongs.get();
+    assert!(count >= 3, "{} pongs", count);
+    TimeoutFuture::new(30).await;
+    assert_eq!(pongs.get(), count);
+    assert!(client.start_auto_ping(0).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn events_reach_the_port() {
+    install();
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let types = Rc::new(RefCell::new(Vec::new()));
+    let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new({
+        let types = types.clone();
+        move |event: web_sys::MessageEvent| {
+            let kind = js_sys::Reflect::get(&event.data(), &"type".into()).unwrap();
+            types.borrow_mut().push(kind.as_string().unwrap());
+        }
+    });
+    channel.port2().set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+    let mut client = WasmPingClient::new(Some(URL.to_string()), None).unwrap();
+    client.set_event_port(Some(channel.port1().unchecked_into()));
+    pong(client.send_ping(None)).await.unwrap();
+    drop_connection();
+    TimeoutFuture::new(600).await;
+    client.close();
+    TimeoutFuture::new(20).await;
+
+    assert_eq!(*types.borrow(), ["open", "pong", "close", "open", "reconnect", "close"]);
+    channel.port2().close();
+}
+
+#[wasm_bindgen_test]
+async fn stats_count_pings_pongs_and_errors() {
+    install();
+    let mut client = connect(None).await;
+    for _ in 0..4 {
+        pong(client.send_ping(None)).await.unwrap();
+    }
+    set_silent(true);
+    client.set_timeout_ms(20);
+    error_message(client.send_ping(None)).await;
+
+    let stats = client.stats();
+    let field = |name: &str| js_sys::Reflect::get(&stats, &name.into()).unwrap();
+    assert_eq!(field("sent").as_f64(), Some(5.0));
+    assert_eq!(field("received").as_f64(), Some(4.0));
+    assert_eq!(field("errors").as_f64(), Some(1.0));
+    assert_eq!(field("p95_ms").as_f64(), field("max_ms").as_f64());
+}
+
+#[wasm_bindgen_test]
+async fn messages_are_checked_before_sending() {
+    install();
+    let mut client = connect(Some(r#"{"max_bytes": 200}"#)).await;
+    assert_eq!(pong(client.send_ping(Some("custom".to_string()))).await.unwrap().message, "Pong: custom");
+
+    assert_eq!(error_message(client.send_ping(Some(" ".to_string()))).await, "the message is empty");
+    let long = "x".repeat(100);
+    assert_eq!(error_message(client.send_ping(Some(long))).await, "PING #2 is 210 bytes, more than max_bytes (200)");
+    assert_eq!(sent().length(), 1);
+
+    // Refused pings don't use up a sequence number
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 2);
+    let errors = js_sys::Reflect::get(&client.stats(), &"errors".into()).unwrap();
+    assert_eq!(errors.as_f64(), Some(2.0));
+
+    // Past max_bytes, the message length rule still holds
+    let mut client = connect(Some(r#"{"max_bytes": 33554432}"#)).await;
+    let huge = "x".repeat(16 * 1024 * 1024 + 1);
+    assert_eq!(
+        error_message(client.send_ping(Some(huge))).await,
+        "PING #1 is invalid: message is 16777217 bytes, more than 16777216"
+    );
+
+    let options = js_sys::JSON::parse(r#"{"max_bytes": 0}"#).unwrap().unchecked_into();
+    assert!(WasmPingClient::new(Some(URL.to_string()), Some(options)).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn pool_shares_pings_and_adds_up_stats() {
+    install();
+    assert!(WasmPingClientPool::new(0, Some(URL.to_string()), None).is_err());
+    let mut pool = WasmPingClientPool::new(3, Some(URL.to_string()), None).unwrap();
+    TimeoutFuture::new(10).await;
+    assert_eq!(socket_count(), 3);
+    assert_eq!(pool.connected(), 3);
+
+    for _ in 0..7 {
+        pong(pool.send_ping(None)).await.unwrap();
+    }
+    assert_eq!(sent_counts(), [3, 2, 2]);
+
+    let stats = pool.stats();
+    let field = |name: &str| js_sys::Reflect::get(&stats, &name.into()).unwrap();
+    assert_eq!(field("sent").as_f64(), Some(7.0));
+    assert_eq!(field("received").as_f64(), Some(7.0));
+
+    // The third socket is down, so its turn passes to the first
+    drop_connection();
+    assert_eq!(pool.connected(), 2);
+    pong(pool.send_ping(None)).await.unwrap();
+    pong(pool.send_ping(None)).await.unwrap();
+    assert_eq!(sent_counts(), [4, 3, 2]);
+    pool.close();
+    assert_eq!(pool.connected(), 0);
+}
+
+#[wasm_bindgen_test]
+async fn unanswered_keepalive_makes_the_connection_stale() {
+    install();
+    let client = connect(Some(r#"{"keepalive_ms": 20, "stale_ms": 30}"#)).await;
+    let (stale, on_stale) = counter();
+    client.on_stale(function(&on_stale));
+    TimeoutFuture::new(100).await;
+    assert_eq!(sent().get(0).as_string().unwrap(), r#"{"type":"keepalive"}"#);
+    assert_eq!(stale.get(), 0);
+
+    // Reported once, not for every keepalive after it
+    set_silent(true);
+    TimeoutFuture::new(200).await;
+    assert_eq!(stale.get(), 1);
+
+    // Until the server is heard from again
+    set_silent(false);
+    deliver(&r#"{"type":"keepalive"}"#.into());
+    set_silent(true);
+    TimeoutFuture::new(200).await;
+    assert_eq!(stale.get(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn persisted_stats_and_session_survive_a_reload() {
+    install();
+    assert_eq!(connect(Some(r#"{"persist_key": "demo"}"#)).await.session_id(), None);
+
+    install_storage();
+    let mut client = connect(Some(r#"{"persist_key": "demo"}"#)).await;
+    let session = client.session_id().unwrap();
+    assert_eq!(socket_url(), format!("{}?session={}", URL, session));
+    pong(client.send_ping(None)).await.unwrap();
+    pong(client.send_ping(None)).await.unwrap();
+    drop(client);
+
+    let client = connect(Some(r#"{"persist_key": "demo"}"#)).await;
+    assert_eq!(client.session_id(), Some(session.clone()));
+    assert_eq!(socket_url(), format!("{}?session={}", URL, session));
+    let stats = client.stats();
+    let field = |name: &str| js_sys::Reflect::get(&stats, &name.into()).unwrap();
+    assert_eq!(field("sent").as_f64(), Some(2.0));
+    assert_eq!(field("received").as_f64(), Some(2.0));
+    assert!(field("p95_ms").as_f64().is_some());
+
+    // Anything unreadable under the key is started over
+    set_stored("demo", "not json");
+    let client = connect(Some(r#"{"persist_key": "demo"}"#)).await;
+    assert_ne!(client.session_id(), Some(session));
+    assert_eq!(js_sys::Reflect::get(&client.stats(), &"sent".into()).unwrap().as_f64(), Some(0.0));
+}
+
+#[wasm_bindgen_test]
+async fn token_goes_in_the_first_frame_or_the_url() {
+    install();
+    require_token("s3cret");
+    let mut client = connect(Some(r#"{"auth_token": "s3cret"}"#)).await;
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 1);
+    assert_eq!(sent().get(0).as_string().unwrap(), r#"{"type":"auth","token":"s3cret"}"#);
+
+    let mut client = connect(Some(r#"{"auth_token": "s3cret", "auth_via": "query"}"#)).await;
+    assert_eq!(client.url(), URL);
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 1);
+}
+
+#[wasm_bindgen_test]
+async fn refused_token_stops_the_client() {
+    install();
+    require_token("s3cret");
+    let (auth_errors, on_auth_error) = counter();
+    let options = js_sys::JSON::parse(r#"{"auth_token": "wrong"}"#).unwrap().unchecked_into();
+    let mut client = WasmPingClient::new(Some(URL.to_string()), Some(options)).unwrap();
+    client.on_auth_error(function(&on_auth_error));
+    let promise = client.send_ping(None);
+
+    assert_eq!(error_message(promise).await, "authentication failed: invalid token");
+    assert_eq!(auth_errors.get(), 1);
+    TimeoutFuture::new(600).await;
+    assert_eq!(socket_count(), 1);
+}
+
+#[wasm_bindgen_test]
+async fn pushes_go_to_on_broadcast() {
+    install();
+    let client = connect(None).await;
+    let types = Rc::new(RefCell::new(Vec::new()));
+    let on_broadcast = Closure::<dyn FnMut(JsValue)>::new({
+        let types = types.clone();
+        move |push: JsValue| {
+            let kind = js_sys::Reflect::get(&push, &"type".into()).unwrap();
+            types.borrow_mut().push(kind.as_string().unwrap());
+        }
+    });
+    let (pongs, on_pong) = counter();
+    client.on_broadcast(on_broadcast.as_ref().unchecked_ref::<js_sys::Function>().clone());
+    client.on_pong(function(&on_pong));
+
+    deliver(&r#"{"type":"broadcast","message":"hello","sent_at_ms":1}"#.into());
+    deliver(&r#"{"type":"heartbeat","total_pings":3,"sent_at_ms":2}"#.into());
+    deliver(&r#"{"type":"error","code":"overloaded","message":"busy"}"#.into());
+    assert_eq!(*types.borrow(), ["broadcast", "heartbeat"]);
+    assert_eq!(pongs.get(), 0);
+}
+
+#[cfg(not(feature = "webtransport"))]
+#[wasm_bindgen_test]
+fn webtransport_needs_the_feature() {
+    install();
+    let options = js_sys::JSON::parse(r#"{"transport": "webtransport"}"#).unwrap().unchecked_into();
+    assert!(WasmPingClient::new(Some(URL.to_string()), Some(options)).is_err());
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn pings_go_over_webtransport() {
+    install();
+    install_transport();
+    let mut client = connect(Some(r#"{"transport": "webtransport", "auth_token": "s3cret"}"#)).await;
+    assert_eq!(client.transport(), "webtransport");
+    assert!(client.is_connected());
+    assert_eq!(transport_urls(), ["https://mock/wt"]);
+
+    let pong = pong(client.send_ping(None)).await.unwrap();
+    assert_eq!(pong.message, "Pong: Hello from Wasm #1");
+    let lines = transport_lines();
+    assert_eq!(lines[0], r#"{"type":"auth","token":"s3cret"}"#);
+    let ping: Ping = serde_json::from_str(&lines[1]).unwrap();
+    assert_eq!(ping.sequence, 1);
+    assert_eq!(socket_count(), 0);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn webtransport_url_follows_the_websocket_one() {
+    install();
+    install_transport();
+    let options = r#"{"transport": "webtransport", "auth_token": "s3cret", "auth_via": "query"}"#;
+    let options = js_sys::JSON::parse(options).unwrap().unchecked_into::<JsClientOptions>();
+    WasmPingClient::new(Some("wss://example.com:8443/ws/echo".to_string()), Some(options)).unwrap();
+    let options = js_sys::JSON::parse(r#"{"transport": "webtransport", "webtransport_url": "https://h3.example.com/wt"}"#).unwrap();
+    WasmPingClient::new(Some(URL.to_string()), Some(options.unchecked_into())).unwrap();
+    assert_eq!(transport_urls(), ["https://example.com:8443/wt/echo?token=s3cret", "https://h3.example.com/wt"]);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn missing_webtransport_falls_back_to_websocket() {
+    install();
+    let mut client = connect(Some(r#"{"transport": "webtransport"}"#)).await;
+    assert_eq!(client.transport(), "websocket");
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 1);
+    assert_eq!(socket_count(), 1);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn refused_session_falls_back_to_websocket() {
+    install();
+    install_transport();
+    refuse_transport();
+    let (errors, on_error) = counter();
+    let options = js_sys::JSON::parse(r#"{"transport": "webtransport"}"#).unwrap().unchecked_into();
+    let mut client = WasmPingClient::new(Some(URL.to_string()), Some(options)).unwrap();
+    client.on_error(function(&on_error));
+    let promise = client.send_ping(None);
+
+    assert_eq!(pong(promise).await.unwrap().sequence, 1);
+    assert_eq!(client.transport(), "websocket");
+    assert_eq!((transport_urls().len(), socket_count()), (1, 1));
+    assert_eq!(errors.get(), 0);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn dropped_session_is_reopened_over_webtransport() {
+    install();
+    install_transport();
+    let (closes, on_close) = counter();
+    let (reconnects, on_reconnect) = counter();
+    let mut client = connect(Some(r#"{"transport": "webtransport"}"#)).await;
+    client.on_close(function(&on_close));
+    client.on_reconnect(function(&on_reconnect));
+
+    drop_transport();
+    TimeoutFuture::new(600).await;
+    assert_eq!((closes.get(), reconnects.get()), (1, 1));
+    assert_eq!(transport_urls().len(), 2);
+    assert_eq!(client.transport(), "webtransport");
+    assert_eq!(pong(client.send_ping(None)).await.unwrap().sequence, 1);
+}
+
+#[cfg(feature = "webtransport")]
+#[wasm_bindgen_test]
+async fn refused_token_over_webtransport_stops_the_client() {
+    install();
+    install_transport();
+    require_token("s3cret");
+    let (auth_errors, on_auth_error) = counter();
+    let options = js_sys::JSON::parse(r#"{"transport": "webtransport", "auth_token": "wrong"}"#).unwrap().unchecked_into();
+    let mut client = WasmPingClient::new(Some(URL.to_string()), Some(options)).unwrap();
+    client.on_auth_error(function(&on_auth_error));
+    let promise = client.send_ping(None);
+
+    assert_eq!(error_message(promise).await, "authentication failed: invalid token");
+    assert_eq!(auth_errors.get(), 1);
+    TimeoutFuture::new(600).await;
+    assert_eq!(transport_urls().len(), 1);
+}
@@ -0,0 +1,241 @@
+// Yew wrapper for WasmPingClient
+//
+// `use_ping_client` connects a WasmPingClient for as long as the component
+// using it is mounted, and turns its callbacks into component state: the
+// connection status, the latest Pongs and the round-trip stats, re-rendering
+// on each change. `PingPanel` is a ready-made component on top of it, for
+// pages that just want the demo UI.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use ping_wasm_client::WasmPingClient;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use yew::prelude::*;
+
+// How many Pongs the log keeps
+const MAX_PONGS: usize = 50;
+
+/// State of the connection
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Status {
+    #[default]
+    Connecting,
+    Connected,
+    /// The connection dropped; the client is reopening it
+    Reconnecting,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Connecting => "connecting",
+            Status::Connected => "connected",
+            Status::Reconnecting => "reconnecting",
+        })
+    }
+}
+
+/// A Pong as the client reports it
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PongEntry {
+    pub message: String,
+    pub sequence: u64,
+    pub total_pings: u64,
+    /// Milliseconds from sending the ping to this Pong; None if the ping
+    /// had timed out
+    pub rtt_ms: Option<f64>,
+}
+
+/// Round trips of the pings answered so far, as in `rtt_stats`
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct RttStats {
+    pub count: u64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub last_ms: Option<f64>,
+}
+
+/// What `use_ping_client` hands the component
+#[derive(Clone, PartialEq)]
+pub struct UsePingClient {
+    pub status: Status,
+    /// The latest Pongs, oldest first
+    pub pongs: Vec<PongEntry>,
+    pub stats: RttStats,
+    /// The last error: a failed connection or a ping without a Pong
+    pub error: Option<String>,
+    /// Send a ping; its Pong shows up in `pongs`
+    pub send_ping: Callback<()>,
+}
+
+#[derive(Clone, Default, PartialEq)]
+struct PingState {
+    status: Status,
+    pongs: Vec<PongEntry>,
+    error: Option<String>,
+}
+
+enum Action {
+    Status(Status),
+    Pong(PongEntry),
+    Failed(String),
+}
+
+impl Reducible for PingState {
+    type Action = Action;
+
+    fn reduce(self: Rc<Self>, action: Action) -> Rc<Self> {
+        let mut state = (*self).clone();
+        match action {
+            Action::Status(status) => state.status = status,
+            Action::Pong(pong) => {
+                if state.pongs.len() == MAX_PONGS {
+                    state.pongs.remove(0);
+                }
+                state.pongs.push(pong);
+            }
+            Action::Failed(error) => state.error = Some(error),
+        }
+        state.into()
+    }
+}
+
+// The client and the callbacks registered with it; dropping it drops the
+// client first, which closes the socket and stops the callbacks
+struct Connection {
+    client: WasmPingClient,
+    _callbacks: Vec<Closure<dyn FnMut(JsValue)>>,
+}
+
+/// Connect to the ping server at `url` (as for `new WasmPingClient(url)`;
+/// None for `/ws` on the page's host) while the component is mounted. A new
+/// `url` replaces the connection.
+#[hook]
+pub fn use_ping_client(url: Option<AttrValue>) -> UsePingClient {
+    let state = use_reducer(PingState::default);
+    let connection = use_mut_ref(|| None::<Connection>);
+
+    {
+        let dispatch = state.dispatcher();
+        let connection = connection.clone();
+        use_effect_with(url, move |url| {
+            dispatch.dispatch(Action::Status(Status::Connecting));
+            match connect(url.as_deref(), &dispatch) {
+                Ok(new) => *connection.borrow_mut() = Some(new),
+                Err(e) => dispatch.dispatch(Action::Failed(describe(&e))),
+            }
+            move || drop(connection.borrow_mut().take())
+        });
+    }
+
+    // Every Pong re-renders, so the client's stats are current here
+    let stats = connection
+        .borrow()
+        .as_ref()
+        .and_then(|connection| serde_wasm_bindgen::from_value(connection.client.rtt_stats()).ok())
+        .unwrap_or_default();
+    let send_ping = {
+        let dispatch = state.dispatcher();
+        Callback::from(move |()| send(&connection, &dispatch))
+    };
+    UsePingClient {
+        status: state.status,
+        pongs: state.pongs.clone(),
+        stats,
+        error: state.error.clone(),
+        send_ping,
+    }
+}
+
+fn connect(url: Option<&str>, dispatch: &UseReducerDispatcher<PingState>) -> Result<Connection, JsValue> {
+    let client = WasmPingClient::new(url.map(str::to_string), None)?;
+    let on_open = callback(dispatch, |_| Some(Action::Status(Status::Connected)));
+    let on_close = callback(dispatch, |_| Some(Action::Status(Status::Reconnecting)));
+    let on_pong = callback(dispatch, |pong| serde_wasm_bindgen::from_value(pong).ok().map(Action::Pong));
+    client.on_open(function(&on_open));
+    client.on_close(function(&on_close));
+    client.on_pong(function(&on_pong));
+    Ok(Connection { client, _callbacks: vec![on_open, on_close, on_pong] })
+}
+
+// A client callback dispatching the action `action` makes of its argument
+fn callback(
+    dispatch: &UseReducerDispatcher<PingState>,
+    action: impl Fn(JsValue) -> Option<Action> + 'static,
+) -> Closure<dyn FnMut(JsValue)> {
+    let dispatch = dispatch.clone();
+    Closure::new(move |value: JsValue| {
+        if let Some(action) = action(value) {
+            dispatch.dispatch(action);
+        }
+    })
+}
+
+fn function(closure: &Closure<dyn FnMut(JsValue)>) -> js_sys::Function {
+    closure.as_ref().unchecked_ref::<js_sys::Function>().clone()
+}
+
+// Send a ping over the current connection, reporting it if it fails
+fn send(connection: &Rc<RefCell<Option<Connection>>>, dispatch: &UseReducerDispatcher<PingState>) {
+    let Some(promise) = connection.borrow_mut().as_mut().map(|connection| connection.client.send_ping(None)) else {
+        return;
+    };
+    let dispatch = dispatch.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = JsFuture::from(promise).await {
+            dispatch.dispatch(Action::Failed(describe(&e)));
+        }
+    });
+}
+
+// The message of a JavaScript error, or whatever it says of itself
+fn describe(error: &JsValue) -> String {
+    match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => error.message().into(),
+        None => format!("{:?}", error),
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PingPanelProps {
+    /// Server endpoint, as for `use_ping_client`
+    #[prop_or_default]
+    pub url: Option<AttrValue>,
+}
+
+/// Connection status, a "Send Ping" button, the round-trip stats and the
+/// latest Pongs
+#[function_component(PingPanel)]
+pub fn ping_panel(props: &PingPanelProps) -> Html {
+    let ping = use_ping_client(props.url.clone());
+    let onclick = {
+        let send_ping = ping.send_ping.clone();
+        Callback::from(move |_| send_ping.emit(()))
+    };
+    let ms = |value: Option<f64>| value.map_or_else(|| "-".to_string(), |ms| format!("{:.1}ms", ms));
+
+    html! {
+        <div class="ping-panel">
+            <p class="ping-status">{ format!("Status: {}", ping.status) }</p>
+            <button {onclick} disabled={ping.status != Status::Connected}>{ "Send Ping" }</button>
+            <p class="ping-stats">
+                { format!("{} pongs, RTT min {} avg {} max {}", ping.stats.count, ms(ping.stats.min_ms), ms(ping.stats.avg_ms), ms(ping.stats.max_ms)) }
+            </p>
+            if let Some(error) = &ping.error {
+                <p class="ping-error">{ error }</p>
+            }
+            <ul class="ping-log">
+                { for ping.pongs.iter().rev().map(|pong| html! {
+                    <li key={pong.sequence}>
+                        { format!("PONG #{}: {} (total: {}, {})", pong.sequence, pong.message, pong.total_pings, ms(pong.rtt_ms)) }
+                    </li>
+                }) }
+            </ul>
+        </div>
+    }
+}
@@ -0,0 +1,105 @@
+// Build tasks
+//
+// `cargo xtask wasm` compiles ping-wasm-client, with the `webtransport`
+// feature, and copies the bundle into ping-http-server/static/wasm, where the
+// server embeds it on its next build. With wasm-pack on the PATH it runs
+// `wasm-pack build --target web`; otherwise it builds the crate for
+// wasm32-unknown-unknown with cargo and runs the wasm-bindgen CLI on it
+// (whose version has to match the crate's wasm-bindgen dependency). Without
+// either tool it says so and leaves the committed bundle as it is.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+/// The files of the bundle the wasm page loads
+const BUNDLE: [&str; 3] = ["ping_wasm_client_bg.wasm", "ping_wasm_client.js", "ping_wasm_client.d.ts"];
+
+const USAGE: &str = "Usage: cargo xtask wasm";
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    let task = env::args().nth(1);
+    let result = match task.as_deref() {
+        Some("wasm") => wasm(),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn wasm() -> Result<()> {
+    let root = workspace_root();
+    let pkg = if found("wasm-pack") {
+        println!("Building the WebAssembly client with wasm-pack...");
+        let crate_dir = root.join("ping-wasm-client");
+        run(Command::new("wasm-pack")
+            .current_dir(&crate_dir)
+            .args(["build", "--target", "web", "--features", "webtransport"]))?;
+        crate_dir.join("pkg")
+    } else if found("wasm-bindgen") {
+        println!("Building the WebAssembly client with cargo and wasm-bindgen...");
+        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        run(Command::new(cargo).current_dir(&root).args([
+            "build",
+            "--release",
+            "--package",
+            "ping-wasm-client",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--features",
+            "webtransport",
+        ]))?;
+        let target = env::var_os("CARGO_TARGET_DIR").map_or_else(|| root.join("target"), PathBuf::from);
+        let out = target.join("wasm-bindgen");
+        let wasm = target.join("wasm32-unknown-unknown/release/ping_wasm_client.wasm");
+        run(Command::new("wasm-bindgen")
+            .args(["--target", "web", "--out-dir"])
+            .arg(&out)
+            .arg(wasm))?;
+        out
+    } else {
+        return Err("neither wasm-pack nor wasm-bindgen is on the PATH; install one with \
+                    `cargo install wasm-pack` or `cargo install wasm-bindgen-cli` (the committed \
+                    bundle in ping-http-server/static/wasm is left as it is)"
+            .into());
+    };
+
+    let static_dir = root.join("ping-http-server/static/wasm");
+    fs::create_dir_all(&static_dir)?;
+    for file in BUNDLE {
+        fs::copy(pkg.join(file), static_dir.join(file)).map_err(|e| format!("{}: {}", pkg.join(file).display(), e))?;
+    }
+    println!("Copied the bundle to {}", static_dir.display());
+    println!("The static files are embedded into ping-http-server, so (re)build it to pick them up:");
+    println!("    cargo run -p ping-http-server");
+    println!("and visit http://localhost:8080/wasm");
+    Ok(())
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().expect("xtask is in the workspace").to_path_buf()
+}
+
+// Whether `tool` runs at all
+fn found(tool: &str) -> bool {
+    Command::new(tool).arg("--version").output().is_ok_and(|output| output.status.success())
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command.status().map_err(|e| format!("{:?}: {}", command, e))?;
+    if !status.success() {
+        return Err(format!("{:?} failed with {}", command, status).into());
+    }
+    Ok(())
+}